@@ -8,9 +8,12 @@
  *   POST /sync              - Save daily productivity data
  *   GET  /sync?date=YYYY-MM-DD     - Retrieve single day data
  *   GET  /sync?from=YYYY-MM-DD&to=YYYY-MM-DD - Retrieve date range
+ *   GET  /keyring           - Retrieve the sync encryption keyring
+ *   POST /keyring           - Save the sync encryption keyring
  * 
  * S3 STRUCTURE:
  *   s3://bucket-name/
+ *   ├── keyring.json        (only once sync encryption is turned on)
  *   └── daily-logs/
  *       ├── 2025-01-01.json
  *       ├── 2025-01-02.json
@@ -52,6 +55,11 @@ const s3 = new S3Client({ region: 'eu-central-1' });
  */
 const BUCKET = 'your-bucket-name-here';
 
+/**
+ * S3 key of the sync encryption keyring
+ */
+const KEYRING_KEY = 'keyring.json';
+
 // =============================================================================
 // MAIN HANDLER
 // =============================================================================
@@ -111,6 +119,16 @@ exports.handler = async (event) => {
       return await handleGetSync(event, headers);
     }
 
+    // ===================================================================
+    // GET/POST /keyring - Sync Encryption Keyring
+    // ===================================================================
+    if (method === 'GET' && path.endsWith('/keyring')) {
+      return await handleGetObject(KEYRING_KEY, headers);
+    }
+    if (method === 'POST' && path.endsWith('/keyring')) {
+      return await handlePostKeyring(event, headers);
+    }
+
     // ===================================================================
     // 404 - Route Not Found
     // ===================================================================
@@ -154,13 +172,26 @@ exports.handler = async (event) => {
  *       "startTime": "2025-01-15T09:00:00.000Z",
  *       "endTime": "2025-01-15T09:25:00.000Z",
  *       "duration": 1500,
- *       "type": "work"
+ *       "type": "work",
+ *       "modifiedAt": "2025-01-15T09:25:00.000Z"
  *     }
  *   ],
  *   "projects": [
- *     { "id": "proj1", "name": "My Project", "color": "#00ff88" }
- *   ]
+ *     { "id": "proj1", "name": "My Project", "color": "#00ff88",
+ *       "modifiedAt": "2025-01-10T08:00:00.000Z" }
+ *   ],
+ *   "deletedSessions": [
+ *     { "id": "xyz789", "deletedAt": "2025-01-15T18:00:00.000Z", "date": "2025-01-15" }
+ *   ],
+ *   "deletedProjects": []
  * }
+ *
+ * The app merges with the stored copy before uploading, so the body
+ * replaces the stored file as is.
+ *
+ * With sync encryption on, the arrays are empty and the day is in
+ * "encrypted": { "keyId": "...", "nonce": "...", "ciphertext": "..." }
+ * (base64), which only the app can read.
  * 
  * @param {Object} event - API Gateway event
  * @param {Object} headers - Response headers
@@ -169,16 +200,14 @@ exports.handler = async (event) => {
 async function handlePostSync(event, headers) {
   // Parse the JSON body from the request
   const body = JSON.parse(event.body);
-  const { date, sessions, projects } = body;
+  const { date } = body;
 
   // Construct the S3 key (file path)
   const key = `daily-logs/${date}.json`;
   
-  // Prepare the data object to store
+  // Prepare the data object to store, tombstones included
   const dataToStore = {
-    date,
-    sessions,
-    projects,
+    ...body,
     syncedAt: new Date().toISOString()  // Timestamp for tracking last sync
   };
 
@@ -199,6 +228,33 @@ async function handlePostSync(event, headers) {
   };
 }
 
+/**
+ * Handle POST /keyring
+ * 
+ * Saves the sync encryption keyring. It holds the encryption keys wrapped
+ * with the user's passphrase and recovery code, never in the clear.
+ * 
+ * @param {Object} event - API Gateway event
+ * @param {Object} headers - Response headers
+ * @returns {Object} HTTP response
+ */
+async function handlePostKeyring(event, headers) {
+  await s3.send(new PutObjectCommand({
+    Bucket: BUCKET,
+    Key: KEYRING_KEY,
+    Body: event.body,
+    ContentType: 'application/json'
+  }));
+
+  console.log('Keyring saved to S3');
+
+  return {
+    statusCode: 200,
+    headers,
+    body: JSON.stringify({ success: true, key: KEYRING_KEY })
+  };
+}
+
 /**
  * Handle GET /sync
  * 
@@ -251,8 +307,17 @@ async function handleGetSync(event, headers) {
  * @returns {Object} HTTP response
  */
 async function handleGetSingleDate(date, headers) {
-  const key = `daily-logs/${date}.json`;
-  
+  return await handleGetObject(`daily-logs/${date}.json`, headers);
+}
+
+/**
+ * Returns an S3 object as stored, or 404 if it does not exist.
+ * 
+ * @param {string} key - S3 key
+ * @param {Object} headers - Response headers
+ * @returns {Object} HTTP response
+ */
+async function handleGetObject(key, headers) {
   try {
     const response = await s3.send(new GetObjectCommand({
       Bucket: BUCKET,
@@ -272,7 +337,7 @@ async function handleGetSingleDate(date, headers) {
       return {
         statusCode: 404,
         headers,
-        body: JSON.stringify({ error: 'No data for this date' })
+        body: JSON.stringify({ error: `No data for ${key}` })
       };
     }
     throw e;  // Re-throw other errors to be caught by global handler
@@ -288,8 +353,9 @@ async function handleGetSingleDate(date, headers) {
  * Process:
  * 1. List all files in the daily-logs/ prefix
  * 2. Filter files within the date range
- * 3. Load and merge all sessions and projects
- * 4. Return aggregated data
+ * 3. Load and merge all sessions, projects and tombstones
+ * 4. Return aggregated data, plus every file as stored in `logs` (the app
+ *    merges those itself, and decrypts them when sync encryption is on)
  * 
  * @param {string} from - Start date in YYYY-MM-DD format
  * @param {string} to - End date in YYYY-MM-DD format
@@ -298,8 +364,11 @@ async function handleGetSingleDate(date, headers) {
  */
 async function handleGetDateRange(from, to, headers) {
   // Arrays to collect all sessions and unique projects
+  const logs = [];
   const sessions = [];
+  const deletedSessions = [];
   const projects = new Map();  // Use Map to deduplicate by project ID
+  const deletedProjects = new Map();
   
   // -----------------------------------------------------------------
   // Step 1: List all files in the daily-logs/ prefix
@@ -337,15 +406,22 @@ async function handleGetDateRange(from, to, headers) {
         Key: fileKey
       }));
       const data = JSON.parse(await response.Body.transformToString());
+      logs.push(data);
       
-      // Merge sessions (append all)
+      // Merge sessions (append all, each day holds its own)
       if (data.sessions) {
         sessions.push(...data.sessions);
       }
+      if (data.deletedSessions) {
+        deletedSessions.push(...data.deletedSessions);
+      }
       
-      // Merge projects (deduplicate by ID)
+      // Merge projects (deduplicate by ID, keeping the latest version)
       if (data.projects) {
-        data.projects.forEach(p => projects.set(p.id, p));
+        data.projects.forEach(p => keepLatest(projects, p, 'modifiedAt'));
+      }
+      if (data.deletedProjects) {
+        data.deletedProjects.forEach(t => keepLatest(deletedProjects, t, 'deletedAt'));
       }
       
     } catch (e) {
@@ -365,11 +441,30 @@ async function handleGetDateRange(from, to, headers) {
       to,
       sessions,
       projects: Array.from(projects.values()),
+      deletedSessions,
+      deletedProjects: Array.from(deletedProjects.values()),
+      logs,
       filesLoaded: files.length
     })
   };
 }
 
+/**
+ * Stores `record` in `map` unless a copy with a later `field` timestamp is
+ * already there. Records from older app versions have no timestamp and
+ * lose to any that do.
+ *
+ * @param {Map} map - Records by ID
+ * @param {Object} record - Project or tombstone
+ * @param {string} field - Name of the timestamp field
+ */
+function keepLatest(map, record, field) {
+  const existing = map.get(record.id);
+  if (!existing || (existing[field] || '') <= (record[field] || '')) {
+    map.set(record.id, record);
+  }
+}
+
 // =============================================================================
 // DATA STRUCTURES REFERENCE
 // =============================================================================
@@ -383,14 +478,23 @@ async function handleGetDateRange(from, to, headers) {
  *   "endTime": "ISO8601",        // Session end timestamp
  *   "duration": 1500,            // Duration in seconds
  *   "date": "2025-01-15",        // Date string for filtering
- *   "type": "work|break|pomodoro" // Session type
+ *   "type": "work|break|pomodoro", // Session type
+ *   "modifiedAt": "ISO8601"      // Last change, newest copy wins on sync
  * }
  * 
  * Project Object Structure:
  * {
  *   "id": "proj1",               // Unique identifier
  *   "name": "My Project",        // Display name
- *   "color": "#00ff88"           // Hex color for UI
+ *   "color": "#00ff88",          // Hex color for UI
+ *   "modifiedAt": "ISO8601"      // Last change, newest copy wins on sync
+ * }
+ *
+ * Tombstone Structure (deleted project or session):
+ * {
+ *   "id": "abc123xyz",           // ID of the deleted record
+ *   "deletedAt": "ISO8601",      // Deletion time
+ *   "date": "2025-01-15"         // Sessions only: the day it belonged to
  * }
  * 
  * Daily Log Structure (stored in S3):
@@ -398,6 +502,8 @@ async function handleGetDateRange(from, to, headers) {
  *   "date": "2025-01-15",
  *   "sessions": [...],
  *   "projects": [...],
+ *   "deletedSessions": [...],
+ *   "deletedProjects": [...],
  *   "syncedAt": "ISO8601"        // Last sync timestamp
  * }
  */
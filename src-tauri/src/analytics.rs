@@ -0,0 +1,350 @@
+// Productivity Tracker - Analytics
+// Metrics over a period of sessions: focus/rest ratio, time per project,
+// best hours and days, fragmentation, streaks, context switches. Plain
+// functions of the sessions and a timezone, so the numbers behind an AI
+// analysis can be reproduced and shown without one. Days are the ones the
+// sessions are filed under; the timezone places them in hours of the day.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+use serde::Serialize;
+
+use crate::model::{Project, Session, SessionKind};
+
+/// Work sessions shorter than this count as fragments
+pub const SHORT_SESSION: u64 = 15 * 60;
+/// How many best hours and days are picked
+const BEST: usize = 3;
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Analytics {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Days with some work
+    pub active_days: u32,
+    pub work_seconds: u64,
+    pub work_sessions: u32,
+    pub break_seconds: u64,
+    pub break_sessions: u32,
+    /// Work time per unit of break time; `None` without breaks
+    pub focus_ratio: Option<f64>,
+    /// Mean work session length in seconds
+    pub average_session: u64,
+    /// Work sessions shorter than `SHORT_SESSION`
+    pub short_sessions: u32,
+    /// Share of work sessions that are short, 0 to 1
+    pub fragmentation: f64,
+    /// Times a work session was for another project than the one before
+    /// it on the same day
+    pub context_switches: u32,
+    /// Consecutive active days up to `to`, or up to the day before while
+    /// `to` has no work yet
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    /// Most worked first
+    pub projects: Vec<ProjectTime>,
+    /// Work seconds per local hour of the day, sessions spread over the
+    /// hours they span
+    pub hours: [u64; 24],
+    /// Work seconds per weekday, Monday first
+    pub weekdays: [u64; 7],
+    /// Hours of the day with the most work, best first
+    pub best_hours: Vec<u32>,
+    pub best_days: Vec<&'static str>,
+    /// Work on `to`
+    pub last_day: DayTotal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTime {
+    pub id: String,
+    /// The id when the project no longer exists
+    pub name: String,
+    pub seconds: u64,
+    pub sessions: u32,
+    /// Share of the work time, 0 to 1
+    pub share: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DayTotal {
+    pub seconds: u64,
+    pub sessions: u32,
+}
+
+/// Metrics of the sessions filed from `from` to `to` (inclusive), with
+/// hours of the day in `tz`; sessions outside the period are ignored
+pub fn analyze<Tz: TimeZone>(
+    sessions: &[Session],
+    projects: &[Project],
+    tz: &Tz,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Analytics {
+    let mut sessions: Vec<&Session> = sessions
+        .iter()
+        .filter(|s| (from..=to).contains(&s.date))
+        .collect();
+    sessions.sort_by_key(|s| s.start_time);
+    let (breaks, work): (Vec<&Session>, Vec<&Session>) = sessions
+        .into_iter()
+        .partition(|s| s.kind == SessionKind::Break);
+
+    let work_seconds: u64 = work.iter().map(|s| s.duration).sum();
+    let break_seconds: u64 = breaks.iter().map(|s| s.duration).sum();
+    let short_sessions = work.iter().filter(|s| s.duration < SHORT_SESSION).count();
+
+    let mut days: BTreeMap<NaiveDate, Vec<&Session>> = BTreeMap::new();
+    let mut hours = [0; 24];
+    let mut weekdays = [0; 7];
+    for session in &work {
+        days.entry(session.date).or_default().push(session);
+        weekdays[session.date.weekday().num_days_from_monday() as usize] += session.duration;
+        spread_over_hours(session, tz, &mut hours);
+    }
+    let context_switches = days
+        .values()
+        .map(|day| {
+            day.windows(2)
+                .filter(|w| w[0].project_id != w[1].project_id)
+                .count()
+        })
+        .sum::<usize>();
+    let (current_streak, longest_streak) = streaks(&days.keys().copied().collect(), to);
+    let last_day = days
+        .get(&to)
+        .map_or_else(DayTotal::default, |day| DayTotal {
+            seconds: day.iter().map(|s| s.duration).sum(),
+            sessions: day.len() as u32,
+        });
+
+    Analytics {
+        from,
+        to,
+        active_days: days.len() as u32,
+        work_seconds,
+        work_sessions: work.len() as u32,
+        break_seconds,
+        break_sessions: breaks.len() as u32,
+        focus_ratio: (break_seconds > 0).then(|| work_seconds as f64 / break_seconds as f64),
+        average_session: work_seconds.checked_div(work.len() as u64).unwrap_or(0),
+        short_sessions: short_sessions as u32,
+        fragmentation: ratio(short_sessions as u64, work.len() as u64),
+        context_switches: context_switches as u32,
+        current_streak,
+        longest_streak,
+        projects: project_times(&work, projects, work_seconds),
+        hours,
+        weekdays,
+        best_hours: best(&hours).into_iter().map(|h| h as u32).collect(),
+        best_days: best(&weekdays).into_iter().map(|d| WEEKDAYS[d]).collect(),
+        last_day,
+    }
+}
+
+fn ratio(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+/// Adds the session's tracked time to the local hours it spans, in
+/// proportion to the overlap; pauses are spread evenly
+fn spread_over_hours<Tz: TimeZone>(session: &Session, tz: &Tz, hours: &mut [u64; 24]) {
+    let span = (session.end_time - session.start_time).num_seconds();
+    let hour_of = |time: DateTime<Utc>| time.with_timezone(tz).hour() as usize;
+    if span <= 0 {
+        hours[hour_of(session.start_time)] += session.duration;
+        return;
+    }
+    let mut cursor = session.start_time;
+    let mut spread = 0;
+    while cursor < session.end_time {
+        let local = cursor.with_timezone(tz);
+        let into_hour = i64::from(local.minute() * 60 + local.second());
+        let next = (cursor + Duration::seconds(3600 - into_hour)).min(session.end_time);
+        let elapsed = (next - session.start_time).num_seconds();
+        // Cumulative rounding, so the hours add up to the duration exactly
+        let upto = session.duration * elapsed as u64 / span as u64;
+        hours[local.hour() as usize] += upto - spread;
+        spread = upto;
+        cursor = next;
+    }
+}
+
+/// Current and longest run of consecutive days
+fn streaks(days: &BTreeSet<NaiveDate>, to: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        run = match previous {
+            Some(p) if p.succ_opt() == Some(day) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+    let alive = previous.is_some_and(|last| last == to || last.succ_opt() == Some(to));
+    (if alive { run } else { 0 }, longest)
+}
+
+fn project_times(work: &[&Session], projects: &[Project], total: u64) -> Vec<ProjectTime> {
+    let mut times: BTreeMap<&str, (u64, u32)> = BTreeMap::new();
+    for session in work {
+        let time = times.entry(&session.project_id).or_default();
+        time.0 += session.duration;
+        time.1 += 1;
+    }
+    let mut times: Vec<ProjectTime> = times
+        .into_iter()
+        .map(|(id, (seconds, sessions))| ProjectTime {
+            id: id.to_string(),
+            name: projects
+                .iter()
+                .find(|p| p.id == id)
+                .map_or_else(|| id.to_string(), |p| p.name.clone()),
+            seconds,
+            sessions,
+            share: ratio(seconds, total),
+        })
+        .collect();
+    times.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.name.cmp(&b.name)));
+    times
+}
+
+/// Indices of the largest non-zero values, largest first; ties go to the
+/// lower index
+fn best(values: &[u64]) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..values.len()).filter(|&i| values[i] > 0).collect();
+    indices.sort_by(|&a, &b| values[b].cmp(&values[a]).then(a.cmp(&b)));
+    indices.truncate(BEST);
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, project};
+    use chrono::FixedOffset;
+
+    fn session(id: &str, project: &str, start: &str, minutes: i64, kind: SessionKind) -> Session {
+        Session {
+            kind,
+            ..testing::session(id, project, start, minutes)
+        }
+    }
+
+    fn work(id: &str, project: &str, start: &str, minutes: i64) -> Session {
+        session(id, project, start, minutes, SessionKind::Work)
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn totals_ratio_and_projects() {
+        let sessions = [
+            work("1", "a", "2025-03-03T09:00:00Z", 60),
+            session("2", "break", "2025-03-03T10:00:00Z", 15, SessionKind::Break),
+            session("3", "b", "2025-03-03T10:15:00Z", 25, SessionKind::Pomodoro),
+            work("4", "a", "2025-03-03T11:00:00Z", 10),
+            work("5", "gone", "2025-03-03T12:00:00Z", 5),
+        ];
+        let projects = [project("a", "Website"), project("b", "Thesis")];
+        let stats = analyze(
+            &sessions,
+            &projects,
+            &Utc,
+            date("2025-03-01"),
+            date("2025-03-03"),
+        );
+
+        assert_eq!((stats.work_seconds, stats.work_sessions), (100 * 60, 4));
+        assert_eq!((stats.break_seconds, stats.break_sessions), (15 * 60, 1));
+        assert_eq!(stats.focus_ratio, Some(100.0 / 15.0));
+        assert_eq!(stats.average_session, 25 * 60);
+        assert_eq!((stats.short_sessions, stats.fragmentation), (2, 0.5));
+        // a → b → a → gone
+        assert_eq!(stats.context_switches, 3);
+        let names: Vec<_> = stats.projects.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Website", "Thesis", "gone"]);
+        assert_eq!(stats.projects[0].share, 0.7);
+        assert_eq!(
+            stats.last_day,
+            DayTotal {
+                seconds: 100 * 60,
+                sessions: 4
+            }
+        );
+    }
+
+    #[test]
+    fn hours_and_days_are_local() {
+        // 23:30 UTC on Sunday is 00:30 on Monday in UTC+1
+        let mut monday = work("1", "a", "2025-03-02T23:30:00Z", 60);
+        monday.date = date("2025-03-03");
+        let sessions = [monday, work("2", "a", "2025-03-04T08:40:00Z", 40)];
+        let tz = FixedOffset::east_opt(3600).unwrap();
+        let stats = analyze(&sessions, &[], &tz, date("2025-03-03"), date("2025-03-04"));
+
+        assert_eq!(stats.active_days, 2);
+        assert_eq!(stats.weekdays[0], 60 * 60);
+        assert_eq!(&stats.hours[..3], [30 * 60, 30 * 60, 0]);
+        assert_eq!((stats.hours[9], stats.hours[10]), (20 * 60, 20 * 60));
+        assert_eq!(stats.best_days, ["Monday", "Tuesday"]);
+        assert_eq!(stats.best_hours, [0, 1, 9]);
+        assert_eq!(stats.hours.iter().sum::<u64>(), stats.work_seconds);
+    }
+
+    #[test]
+    fn paused_time_is_spread_over_the_span() {
+        let mut paused = work("1", "a", "2025-03-03T09:30:00Z", 60);
+        paused.duration = 45 * 60;
+        let stats = analyze(&[paused], &[], &Utc, date("2025-03-03"), date("2025-03-03"));
+        assert_eq!(&stats.hours[9..11], [1350, 1350]);
+    }
+
+    #[test]
+    fn streaks_survive_until_the_day_is_over() {
+        let days: BTreeSet<NaiveDate> = [
+            "2025-03-01",
+            "2025-03-02",
+            "2025-03-03",
+            "2025-03-05",
+            "2025-03-06",
+        ]
+        .into_iter()
+        .map(date)
+        .collect();
+        assert_eq!(streaks(&days, date("2025-03-06")), (2, 3));
+        assert_eq!(streaks(&days, date("2025-03-07")), (2, 3));
+        assert_eq!(streaks(&days, date("2025-03-08")), (0, 3));
+        assert_eq!(streaks(&BTreeSet::new(), date("2025-03-08")), (0, 0));
+    }
+
+    #[test]
+    fn empty_period() {
+        let sessions = [work("1", "a", "2025-02-01T09:00:00Z", 60)];
+        let stats = analyze(&sessions, &[], &Utc, date("2025-03-01"), date("2025-03-30"));
+        assert_eq!(stats.work_sessions, 0);
+        assert_eq!(stats.focus_ratio, None);
+        assert_eq!((stats.average_session, stats.fragmentation), (0, 0.0));
+        assert!(stats.best_hours.is_empty() && stats.projects.is_empty());
+    }
+}
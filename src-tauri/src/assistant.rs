@@ -0,0 +1,84 @@
+// Productivity Tracker - AI requests
+// Runs each prompt on a thread of its own and streams the reply to the
+// webview: `ai-text` events as text arrives, then one `ai-finished`
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::insights::{AiError, AiProvider, AiRequest};
+
+pub const AI_TEXT_EVENT: &str = "ai-text";
+pub const AI_FINISHED_EVENT: &str = "ai-finished";
+
+/// Cancellation flags of the requests in flight, by id
+#[derive(Default)]
+pub struct AiRequests(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+#[derive(Clone, Serialize)]
+struct AiText<'a> {
+    id: &'a str,
+    text: &'a str,
+}
+
+#[derive(Clone, Serialize)]
+struct AiFinished {
+    id: String,
+    text: Option<String>,
+    error: Option<AiFailure>,
+}
+
+#[derive(Clone, Serialize)]
+struct AiFailure {
+    kind: &'static str,
+    message: String,
+}
+
+impl From<AiError> for AiFailure {
+    fn from(e: AiError) -> Self {
+        Self {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl AiRequests {
+    /// Starts `request` under the webview's `id`, which tags its events
+    pub fn start(
+        &self,
+        app: AppHandle,
+        id: String,
+        provider: Box<dyn AiProvider>,
+        request: AiRequest,
+    ) {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.0.lock().unwrap().insert(id.clone(), cancel.clone());
+        thread::spawn(move || {
+            let result = provider.generate(&request, &cancel, &mut |text| {
+                let _ = app.emit(AI_TEXT_EVENT, AiText { id: &id, text });
+            });
+            app.state::<AiRequests>().0.lock().unwrap().remove(&id);
+            let (text, error) = match result {
+                Ok(text) => (Some(text), None),
+                Err(e) => (None, Some(e.into())),
+            };
+            let _ = app.emit(AI_FINISHED_EVENT, AiFinished { id, text, error });
+        });
+    }
+
+    /// Returns whether the request was still running
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.0.lock().unwrap().get(id) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// Productivity Tracker - Folder sync backend
+// Day logs in a plain directory, for users who already sync a folder
+// between machines with Syncthing, Dropbox or similar
+
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::{day_path, SyncBackend};
+use crate::crypto::{Keyring, KEYRING_FILE};
+use crate::error::Result;
+use crate::store::{read_json, write_json_atomic};
+use crate::sync::DayLog;
+
+pub struct FolderBackend {
+    dir: PathBuf,
+}
+
+impl FolderBackend {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Written atomically, so the sync tool never picks up half a file
+    fn write<T: Serialize>(&self, name: &str, value: &T) -> Result<()> {
+        let path = self.dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_json_atomic(&path, value)
+    }
+}
+
+impl SyncBackend for FolderBackend {
+    fn fetch_day(&self, date: NaiveDate) -> Result<Option<DayLog>> {
+        read_json(&self.dir.join(day_path(date)))
+    }
+
+    fn push(&self, log: &DayLog) -> Result<()> {
+        self.write(&day_path(log.date), log)
+    }
+
+    fn fetch_keyring(&self) -> Result<Option<Keyring>> {
+        read_json(&self.dir.join(KEYRING_FILE))
+    }
+
+    fn push_keyring(&self, keyring: &Keyring) -> Result<()> {
+        self.write(KEYRING_FILE, keyring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge::SyncData;
+    use crate::model::Project;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn round_trips_day_logs() {
+        let dir = TempDir::new("folder");
+        let backend = FolderBackend::new(dir.path());
+        let date = "2025-01-01".parse().unwrap();
+        assert!(backend.fetch_day(date).unwrap().is_none());
+
+        let log = DayLog {
+            date,
+            data: SyncData {
+                projects: vec![Project {
+                    id: "p".into(),
+                    name: "P".into(),
+                    color: "#00ff88".into(),
+                    modified_at: "2025-01-01T10:00:00Z".parse().unwrap(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            encrypted: None,
+        };
+        backend.push(&log).unwrap();
+        assert!(dir.path().join("daily-logs/2025-01-01.json").exists());
+        assert_eq!(backend.fetch_day(date).unwrap(), Some(log.clone()));
+        assert!(backend.fetch_keyring().unwrap().is_none());
+        assert_eq!(backend.fetch_range(date, date).unwrap(), [log]);
+    }
+}
@@ -0,0 +1,155 @@
+// Productivity Tracker - Lambda sync backend
+// The API Gateway + Lambda endpoint from `aws/lambda-function.js`
+
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri_plugin_http::reqwest::blocking::Client;
+use tauri_plugin_http::reqwest::StatusCode;
+
+use super::{http_client, read_json, SyncBackend};
+use crate::crypto::Keyring;
+use crate::error::{Error, Result};
+use crate::merge::SyncData;
+use crate::sync::DayLog;
+
+pub struct LambdaBackend {
+    base_url: String,
+    http: Client,
+}
+
+/// `GET /sync?from=&to=`. Lambdas deployed before encryption only send the
+/// aggregate, which merges the same whatever day it is filed under.
+#[derive(Deserialize)]
+struct RangeResponse {
+    logs: Option<Vec<DayLog>>,
+    #[serde(flatten)]
+    data: SyncData,
+}
+
+#[derive(Deserialize)]
+struct PostResponse {
+    #[serde(default)]
+    success: bool,
+    error: Option<String>,
+}
+
+impl LambdaBackend {
+    pub fn new(base_url: &str) -> Result<Self> {
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: http_client()?,
+        })
+    }
+
+    /// `None` on 404, which the Lambda answers for missing objects
+    fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<Option<T>> {
+        let response = self
+            .http
+            .get(format!("{}/{path}", self.base_url))
+            .query(query)
+            .send()?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        read_json(response).map(Some)
+    }
+
+    fn post<T: Serialize>(&self, path: &str, body: &T) -> Result<()> {
+        let response = self
+            .http
+            .post(format!("{}/{path}", self.base_url))
+            .json(body)
+            .send()?;
+        let status = response.status();
+        match response.json::<PostResponse>().ok() {
+            Some(PostResponse { success: true, .. }) if status.is_success() => Ok(()),
+            Some(PostResponse {
+                error: Some(error), ..
+            }) => Err(Error::Sync(error)),
+            _ => Err(Error::Sync(format!("HTTP {status}"))),
+        }
+    }
+}
+
+impl SyncBackend for LambdaBackend {
+    fn fetch_day(&self, date: NaiveDate) -> Result<Option<DayLog>> {
+        self.get("sync", &[("date", date.to_string())])
+    }
+
+    /// One request for the whole range, answered by the Lambda
+    fn fetch_range(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<DayLog>> {
+        let response = self
+            .http
+            .get(format!("{}/sync", self.base_url))
+            .query(&[("from", from.to_string()), ("to", to.to_string())])
+            .send()?;
+        let response: RangeResponse = read_json(response)?;
+        Ok(response.logs.unwrap_or_else(|| {
+            vec![DayLog {
+                date: to,
+                data: response.data,
+                encrypted: None,
+            }]
+        }))
+    }
+
+    fn push(&self, log: &DayLog) -> Result<()> {
+        self.post("sync", log)
+    }
+
+    fn fetch_keyring(&self) -> Result<Option<Keyring>> {
+        self.get("keyring", &[])
+    }
+
+    fn push_keyring(&self, keyring: &Keyring) -> Result<()> {
+        self.post("keyring", keyring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::mock_server;
+
+    fn empty_log() -> DayLog {
+        DayLog {
+            date: "2025-01-01".parse().unwrap(),
+            data: SyncData::default(),
+            encrypted: None,
+        }
+    }
+
+    #[test]
+    fn push_posts_day_log() {
+        let (url, server) = mock_server(vec![(200, r#"{"success":true}"#)]);
+        LambdaBackend::new(&format!("{url}/"))
+            .unwrap()
+            .push(&empty_log())
+            .unwrap();
+
+        let request = &server.join().unwrap()[0];
+        assert!(request.starts_with("POST /sync HTTP/1.1"), "{request}");
+        assert!(request.ends_with(
+            r#"{"date":"2025-01-01","projects":[],"sessions":[],"deletedProjects":[],"deletedSessions":[]}"#
+        ));
+    }
+
+    #[test]
+    fn push_reports_server_error() {
+        let (url, _server) = mock_server(vec![(500, r#"{"error":"bucket missing"}"#)]);
+        let err = LambdaBackend::new(&url)
+            .unwrap()
+            .push(&empty_log())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "sync failed: bucket missing");
+    }
+
+    #[test]
+    fn missing_day_is_none() {
+        let (url, server) = mock_server(vec![(404, r#"{"error":"No data for this date"}"#)]);
+        let backend = LambdaBackend::new(&url).unwrap();
+        assert!(backend.fetch_day(empty_log().date).unwrap().is_none());
+        assert!(server.join().unwrap()[0].starts_with("GET /sync?date=2025-01-01 "));
+    }
+}
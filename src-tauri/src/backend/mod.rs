@@ -0,0 +1,157 @@
+// Productivity Tracker - Sync backends
+// Where day logs are kept between devices. Every backend stores one JSON
+// file per day (`daily-logs/YYYY-MM-DD.json`, see `sync::DayLog`); merging
+// happens in the app, so a backend only has to read and replace files.
+
+mod folder;
+mod lambda;
+mod s3;
+mod webdav;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tauri_plugin_http::reqwest::blocking::{Client, Response};
+
+use crate::crypto::Keyring;
+use crate::error::{Error, Result};
+use crate::sync::DayLog;
+
+pub use folder::FolderBackend;
+pub use lambda::LambdaBackend;
+pub use s3::{S3Backend, S3Config};
+pub use webdav::WebDavBackend;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub trait SyncBackend: Send + Sync {
+    /// Reads a day; `None` if nothing was stored for it yet
+    fn fetch_day(&self, date: NaiveDate) -> Result<Option<DayLog>>;
+
+    /// Stores a day, replacing whatever was there
+    fn push(&self, log: &DayLog) -> Result<()>;
+
+    /// Every day stored from `from` to `to` (inclusive), as uploaded
+    fn fetch_range(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<DayLog>> {
+        let mut logs = Vec::new();
+        for date in from.iter_days().take_while(|date| *date <= to) {
+            logs.extend(self.fetch_day(date)?);
+        }
+        Ok(logs)
+    }
+
+    /// The encryption keyring (`keyring.json`); `None` while sync data is
+    /// stored in plain text
+    fn fetch_keyring(&self) -> Result<Option<Keyring>>;
+
+    fn push_keyring(&self, keyring: &Keyring) -> Result<()>;
+}
+
+/// Backend settings as sent by the webview
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BackendConfig {
+    /// API Gateway + Lambda from `aws/lambda-function.js`
+    #[serde(rename_all = "camelCase")]
+    Lambda { api_url: String },
+    /// A bucket on AWS S3 or any S3-compatible server such as MinIO
+    S3(S3Config),
+    WebDav {
+        url: String,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    /// A local directory kept in sync by another tool (Syncthing, Dropbox...)
+    Folder { path: PathBuf },
+}
+
+impl BackendConfig {
+    pub fn connect(self) -> Result<Box<dyn SyncBackend>> {
+        Ok(match self {
+            BackendConfig::Lambda { api_url } => Box::new(LambdaBackend::new(&api_url)?),
+            BackendConfig::S3(config) => Box::new(S3Backend::new(config)?),
+            BackendConfig::WebDav {
+                url,
+                username,
+                password,
+            } => Box::new(WebDavBackend::new(&url, username, password)?),
+            BackendConfig::Folder { path } => Box::new(FolderBackend::new(path)),
+        })
+    }
+}
+
+/// S3 key, WebDAV path and folder file name of a day log. The keyring is
+/// `KEYRING_FILE` at the top level.
+fn day_path(date: NaiveDate) -> String {
+    format!("daily-logs/{date}.json")
+}
+
+fn http_client() -> Result<Client> {
+    Ok(Client::builder().timeout(REQUEST_TIMEOUT).build()?)
+}
+
+fn check_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        Err(Error::Sync(format!("HTTP {status}")))
+    }
+}
+
+fn read_json<T: DeserializeOwned>(response: Response) -> Result<T> {
+    check_status(response)?
+        .json()
+        .map_err(|e| Error::Sync(format!("invalid response: {e}")))
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Answers one connection per canned response and hands back the requests
+    pub fn mock_server(responses: Vec<(u16, &'static str)>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut payload = vec![0; length];
+                reader.read_exact(&mut payload).unwrap();
+                request.push_str(&String::from_utf8(payload).unwrap());
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        (url, handle)
+    }
+}
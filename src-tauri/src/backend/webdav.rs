@@ -0,0 +1,127 @@
+// Productivity Tracker - WebDAV sync backend
+// Day logs as files on a WebDAV share (Nextcloud, ownCloud, Apache...)
+
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use tauri_plugin_http::reqwest::blocking::{Client, RequestBuilder};
+use tauri_plugin_http::reqwest::{Method, StatusCode};
+
+use super::{check_status, day_path, http_client, read_json, SyncBackend};
+use crate::crypto::{Keyring, KEYRING_FILE};
+use crate::error::Result;
+use crate::sync::DayLog;
+
+pub struct WebDavBackend {
+    base_url: String,
+    username: Option<String>,
+    password: Option<String>,
+    http: Client,
+}
+
+impl WebDavBackend {
+    pub fn new(base_url: &str, username: Option<String>, password: Option<String>) -> Result<Self> {
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            username,
+            password,
+            http: http_client()?,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}/{path}", self.base_url));
+        match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_ref()),
+            None => request,
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>> {
+        let response = self.request(Method::GET, path).send()?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        read_json(response).map(Some)
+    }
+}
+
+impl SyncBackend for WebDavBackend {
+    fn fetch_day(&self, date: NaiveDate) -> Result<Option<DayLog>> {
+        self.get(&day_path(date))
+    }
+
+    /// Creates `daily-logs/` on the first upload, when the server answers
+    /// 409 Conflict for the missing parent collection
+    fn push(&self, log: &DayLog) -> Result<()> {
+        let body = serde_json::to_vec(log)?;
+        let put = || {
+            self.request(Method::PUT, &day_path(log.date))
+                .header("Content-Type", "application/json")
+                .body(body.clone())
+                .send()
+        };
+        let mut response = put()?;
+        if response.status() == StatusCode::CONFLICT {
+            let mkcol = Method::from_bytes(b"MKCOL").expect("valid method");
+            check_status(self.request(mkcol, "daily-logs/").send()?)?;
+            response = put()?;
+        }
+        check_status(response)?;
+        Ok(())
+    }
+
+    fn fetch_keyring(&self) -> Result<Option<Keyring>> {
+        self.get(KEYRING_FILE)
+    }
+
+    fn push_keyring(&self, keyring: &Keyring) -> Result<()> {
+        let request = self.request(Method::PUT, KEYRING_FILE).json(keyring);
+        check_status(request.send()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::mock_server;
+    use crate::merge::SyncData;
+
+    #[test]
+    fn first_push_creates_the_collection() {
+        let (url, server) = mock_server(vec![(409, ""), (201, ""), (201, "")]);
+        let backend = WebDavBackend::new(&url, Some("me".into()), Some("secret".into())).unwrap();
+        let log = DayLog {
+            date: "2025-01-01".parse().unwrap(),
+            data: SyncData::default(),
+            encrypted: None,
+        };
+        backend.push(&log).unwrap();
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("PUT /daily-logs/2025-01-01.json "));
+        // "me:secret" in base64
+        assert!(requests[0].contains("authorization: Basic bWU6c2VjcmV0"));
+        assert!(requests[1].starts_with("MKCOL /daily-logs/ "));
+        assert!(requests[2].ends_with(r#""deletedProjects":[],"deletedSessions":[]}"#));
+    }
+
+    #[test]
+    fn fetches_stored_days() {
+        let (url, _server) = mock_server(vec![
+            (404, ""),
+            (
+                200,
+                r#"{"date":"2025-01-02","sessions":[],"syncedAt":"2025-01-02T10:00:00Z"}"#,
+            ),
+        ]);
+        let backend = WebDavBackend::new(&url, None, None).unwrap();
+        let from = "2025-01-01".parse().unwrap();
+        let to = "2025-01-02".parse().unwrap();
+        let logs = backend.fetch_range(from, to).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].date, to);
+    }
+}
@@ -0,0 +1,365 @@
+// Productivity Tracker - Billing
+// Billable time and amounts per client and period, from the client, rate
+// and billable flag of each project. Amounts are never converted between
+// currencies, so every line and total has a single one. Billable projects
+// without a rate count their time but no amount. Breaks are left out.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::db::SessionQuery;
+use crate::error::{Error, Result};
+use crate::model::{Project, Session, SessionKind};
+use crate::store::Store;
+
+/// Length of the periods amounts are grouped by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Day,
+    /// Monday to Sunday
+    Week,
+    #[default]
+    Month,
+    Year,
+}
+
+impl Period {
+    /// First day of the period `date` falls in
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Month => date.with_day(1).unwrap_or(date),
+            Period::Year => date.with_ordinal(1).unwrap_or(date),
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "day" => Ok(Period::Day),
+            "week" => Ok(Period::Week),
+            "month" => Ok(Period::Month),
+            "year" => Ok(Period::Year),
+            other => Err(Error::Invalid(format!("unknown period {other:?}"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingQuery {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    #[serde(default)]
+    pub period: Period,
+    /// Only this client (case-insensitive); all of them if `None`
+    #[serde(default)]
+    pub client: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingSummary {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub period: Period,
+    /// By period, then client (projects without one last), then currency
+    pub lines: Vec<ClientPeriod>,
+    /// Billable time and amount per currency over the whole range, time
+    /// without a rate last
+    pub totals: Vec<CurrencyTotal>,
+    /// Work on projects that are not billable
+    pub unbilled_seconds: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientPeriod {
+    /// First day of the period
+    pub period: NaiveDate,
+    pub client: Option<String>,
+    /// `None` for billable projects without a rate
+    pub currency: Option<String>,
+    pub seconds: u64,
+    /// Rounded to cents; `None` without a currency
+    pub amount: Option<f64>,
+    /// Names of the projects worked on, sorted
+    pub projects: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyTotal {
+    pub currency: Option<String>,
+    pub seconds: u64,
+    pub amount: Option<f64>,
+}
+
+/// Billing summary of `query` from the store's sessions
+pub fn billing(store: &Store, query: &BillingQuery) -> Result<BillingSummary> {
+    if query.to < query.from {
+        return Err(Error::Invalid("the range ends before it starts".into()));
+    }
+    let sessions = store.sessions(&SessionQuery {
+        from: Some(query.from.to_string()),
+        to: Some(query.to.to_string()),
+        ..Default::default()
+    })?;
+    Ok(summarize(&sessions, &store.projects()?, query))
+}
+
+/// Sessions of deleted projects are left out: their rates are gone
+pub fn summarize(
+    sessions: &[Session],
+    projects: &[Project],
+    query: &BillingQuery,
+) -> BillingSummary {
+    type Key = (NaiveDate, Option<String>, Option<String>);
+    let mut lines: BTreeMap<Key, (u64, f64, Vec<String>)> = BTreeMap::new();
+    let mut unbilled_seconds = 0;
+
+    let wanted = |project: &Project| match &query.client {
+        Some(client) => project
+            .client
+            .as_ref()
+            .is_some_and(|c| c.eq_ignore_ascii_case(client.trim())),
+        None => true,
+    };
+    for session in sessions {
+        if session.kind == SessionKind::Break
+            || session.date < query.from
+            || session.date > query.to
+        {
+            continue;
+        }
+        let Some(project) = projects.iter().find(|p| p.id == session.project_id) else {
+            continue;
+        };
+        if !wanted(project) {
+            continue;
+        }
+        if !project.billable {
+            unbilled_seconds += session.duration;
+            continue;
+        }
+        let currency = project.hourly_rate.and(project.currency.clone());
+        let key = (
+            query.period.start(session.date),
+            project.client.clone(),
+            currency,
+        );
+        let (seconds, amount, names) = lines.entry(key).or_default();
+        *seconds += session.duration;
+        *amount += project.hourly_rate.unwrap_or(0.0) * session.duration as f64 / 3600.0;
+        if !names.contains(&project.name) {
+            names.push(project.name.clone());
+        }
+    }
+
+    let mut totals: BTreeMap<Option<String>, (u64, f64)> = BTreeMap::new();
+    let mut lines: Vec<ClientPeriod> = lines
+        .into_iter()
+        .map(
+            |((period, client, currency), (seconds, amount, mut projects))| {
+                let total = totals.entry(currency.clone()).or_default();
+                total.0 += seconds;
+                total.1 += amount;
+                projects.sort();
+                ClientPeriod {
+                    period,
+                    client,
+                    amount: currency.as_ref().map(|_| cents(amount)),
+                    currency,
+                    seconds,
+                    projects,
+                }
+            },
+        )
+        .collect();
+    // Clients by name, then the time of projects without one
+    lines.sort_by(|a, b| {
+        (a.period, a.client.is_none(), &a.client, &a.currency).cmp(&(
+            b.period,
+            b.client.is_none(),
+            &b.client,
+            &b.currency,
+        ))
+    });
+
+    let mut totals: Vec<CurrencyTotal> = totals
+        .into_iter()
+        .map(|(currency, (seconds, amount))| CurrencyTotal {
+            amount: currency.as_ref().map(|_| cents(amount)),
+            currency,
+            seconds,
+        })
+        .collect();
+    totals.sort_by_key(|t| t.currency.is_none());
+
+    BillingSummary {
+        from: query.from,
+        to: query.to,
+        period: query.period,
+        lines,
+        totals,
+        unbilled_seconds,
+    }
+}
+
+fn cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::day_session;
+
+    fn project(id: &str, client: Option<&str>, rate: Option<(f64, &str)>) -> Project {
+        Project {
+            id: id.into(),
+            name: id.to_uppercase(),
+            color: "#00ff88".into(),
+            client: client.map(Into::into),
+            billable: true,
+            hourly_rate: rate.map(|(rate, _)| rate),
+            currency: rate.map(|(_, currency)| currency.into()),
+            ..Default::default()
+        }
+    }
+
+    fn projects() -> Vec<Project> {
+        vec![
+            project("web", Some("Acme"), Some((80.0, "EUR"))),
+            project("app", Some("Acme"), Some((100.0, "USD"))),
+            project("docs", Some("Globex"), Some((60.0, "EUR"))),
+            project("misc", None, None),
+            Project {
+                billable: false,
+                ..project("learning", None, None)
+            },
+        ]
+    }
+
+    fn query(period: Period) -> BillingQuery {
+        BillingQuery {
+            from: "2025-01-01".parse().unwrap(),
+            to: "2025-02-28".parse().unwrap(),
+            period,
+            client: None,
+        }
+    }
+
+    fn sessions() -> Vec<Session> {
+        vec![
+            day_session("s1", "web", "2025-01-06", 90, SessionKind::Work),
+            day_session("s2", "web", "2025-01-20", 20, SessionKind::Pomodoro),
+            day_session("s3", "app", "2025-01-07", 30, SessionKind::Work),
+            day_session("s4", "docs", "2025-02-03", 60, SessionKind::Work),
+            day_session("s5", "misc", "2025-02-04", 45, SessionKind::Work),
+            day_session("s6", "learning", "2025-01-08", 120, SessionKind::Work),
+            day_session("s7", "web", "2025-01-08", 5, SessionKind::Break),
+            day_session("s8", "gone", "2025-01-08", 60, SessionKind::Work),
+            day_session("s9", "web", "2025-03-01", 60, SessionKind::Work),
+        ]
+    }
+
+    #[test]
+    fn finds_period_starts() {
+        let date: NaiveDate = "2025-01-15".parse().unwrap();
+        assert_eq!(Period::Day.start(date), date);
+        assert_eq!(Period::Week.start(date).to_string(), "2025-01-13");
+        assert_eq!(Period::Month.start(date).to_string(), "2025-01-01");
+        assert_eq!(Period::Year.start(date).to_string(), "2025-01-01");
+    }
+
+    #[test]
+    fn sums_per_client_period_and_currency() {
+        let summary = summarize(&sessions(), &projects(), &query(Period::Month));
+        let lines: Vec<_> = summary
+            .lines
+            .iter()
+            .map(|l| {
+                (
+                    l.period.to_string(),
+                    l.client.as_deref(),
+                    l.currency.as_deref(),
+                    l.seconds,
+                    l.amount,
+                )
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (
+                    "2025-01-01".into(),
+                    Some("Acme"),
+                    Some("EUR"),
+                    110 * 60,
+                    Some(146.67)
+                ),
+                (
+                    "2025-01-01".into(),
+                    Some("Acme"),
+                    Some("USD"),
+                    30 * 60,
+                    Some(50.0)
+                ),
+                (
+                    "2025-02-01".into(),
+                    Some("Globex"),
+                    Some("EUR"),
+                    60 * 60,
+                    Some(60.0)
+                ),
+                ("2025-02-01".into(), None, None, 45 * 60, None),
+            ]
+        );
+        assert_eq!(summary.lines[0].projects, ["WEB"]);
+        assert_eq!(summary.unbilled_seconds, 120 * 60);
+
+        let totals: Vec<_> = summary
+            .totals
+            .iter()
+            .map(|t| (t.currency.as_deref(), t.seconds, t.amount))
+            .collect();
+        assert_eq!(
+            totals,
+            [
+                (Some("EUR"), 170 * 60, Some(206.67)),
+                (Some("USD"), 30 * 60, Some(50.0)),
+                (None, 45 * 60, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn groups_by_week_and_filters_by_client() {
+        let mut query = query(Period::Week);
+        query.client = Some("acme".into());
+        let summary = summarize(&sessions(), &projects(), &query);
+        let lines: Vec<_> = summary
+            .lines
+            .iter()
+            .map(|l| (l.period.to_string(), l.currency.as_deref(), l.seconds))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                ("2025-01-06".into(), Some("EUR"), 90 * 60),
+                ("2025-01-06".into(), Some("USD"), 30 * 60),
+                ("2025-01-20".into(), Some("EUR"), 20 * 60),
+            ]
+        );
+        assert_eq!(summary.unbilled_seconds, 0);
+    }
+}
@@ -0,0 +1,1062 @@
+// Productivity Tracker - Command-line client
+// Works on the same data directory as the desktop app; both sides take the
+// directory lock before touching shared files, so they can run side by side.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use clap::{Parser, Subcommand};
+
+use productivity_tracker_lib::billing::{billing, BillingQuery, BillingSummary, Period};
+use productivity_tracker_lib::calendar::Calendar;
+use productivity_tracker_lib::db::{GroupBy, SessionQuery, DEFAULT_SEARCH_LIMIT};
+use productivity_tracker_lib::editing::{SessionEdit, SessionEntry, DEFAULT_EDITS_LIMIT};
+use productivity_tracker_lib::error::{Error, Result};
+use productivity_tracker_lib::export::{export_sessions, ExportFormat};
+use productivity_tracker_lib::import::{read_entries, ImportOptions, ImportPreview, ImportSource};
+use productivity_tracker_lib::model::{
+    parse_timezone, Project, Session, SessionKind, PROJECT_COLORS, TRASH_RETENTION_DAYS,
+};
+use productivity_tracker_lib::pomodoro::{self, Phase, PomodoroSettings, PomodoroStatus};
+use productivity_tracker_lib::store::{new_id, Store};
+use productivity_tracker_lib::timer::{Tick, TimerEngine, BREAK_ID, DEFAULT_MIN_DURATION};
+use productivity_tracker_lib::timesheet::{
+    timesheet, Rounding, RoundingMode, TimesheetFormat, TimesheetOptions,
+};
+
+/// Must match `identifier` in tauri.conf.json, which names the app data directory
+const APP_IDENTIFIER: &str = "com.alessioferrari.productivity-tracker";
+
+#[derive(Parser)]
+#[command(name = "pt", version, about = "Productivity Tracker from the terminal")]
+struct Cli {
+    /// Data directory (defaults to the desktop app's)
+    #[arg(long, env = "PT_DATA_DIR", global = true)]
+    data_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start timing a project (name or id)
+    Start {
+        project: String,
+        /// Pomodoro length in minutes, the configured length if no value
+        /// is given (free mode if omitted)
+        #[arg(long, num_args = 0..=1)]
+        pomodoro: Option<Option<u64>>,
+    },
+    /// Pause the active timer
+    Pause,
+    /// Resume the paused timer
+    Resume,
+    /// Stop the active timer and save the session
+    Stop {
+        /// Drop the session instead of saving it
+        #[arg(long)]
+        discard: bool,
+    },
+    /// Show the active timer
+    Status,
+    /// List sessions (today by default)
+    Log {
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Only sessions of this project (name or id)
+        #[arg(long)]
+        project: Option<String>,
+    },
+    /// Log time by hand, e.g. --start 09:00 --end "2025-12-01 17:30"
+    Add {
+        /// Name or id
+        project: String,
+        /// Local time: HH:MM (today), "YYYY-MM-DD HH:MM" or RFC 3339
+        #[arg(long)]
+        start: String,
+        #[arg(long)]
+        end: String,
+        /// work, pomodoro or break
+        #[arg(long = "type", default_value = "work")]
+        kind: SessionKind,
+        #[arg(long)]
+        notes: Option<String>,
+        #[arg(long)]
+        tags: Option<String>,
+    },
+    /// Correct a session's project, times or type (ids are listed by log)
+    Edit {
+        session: String,
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long)]
+        start: Option<String>,
+        #[arg(long)]
+        end: Option<String>,
+        #[arg(long = "type")]
+        kind: Option<SessionKind>,
+    },
+    /// Cut a session in two at a local time
+    Split { session: String, at: String },
+    /// Combine sessions of one project and day into one
+    Merge {
+        #[arg(num_args = 2.., required = true)]
+        sessions: Vec<String>,
+    },
+    /// Delete a session
+    Delete { session: String },
+    /// Manual changes to sessions, newest first
+    History {
+        /// Only this session's
+        session: Option<String>,
+        #[arg(long, default_value_t = DEFAULT_EDITS_LIMIT)]
+        limit: u32,
+    },
+    /// Set the notes or tags of a session (ids are listed by log)
+    Note {
+        session: String,
+        /// Replaces the notes; "" clears them
+        #[arg(long)]
+        notes: Option<String>,
+        /// Replaces the tags, e.g. --tags "client, review"; "" clears them
+        #[arg(long)]
+        tags: Option<String>,
+    },
+    /// Find sessions by their notes, tags (#tag) or project name
+    Search {
+        text: String,
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Only sessions of this project (name or id)
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long, default_value_t = DEFAULT_SEARCH_LIMIT)]
+        limit: u32,
+    },
+    /// Show the pomodoro cycle, start its next phase or change its lengths
+    Pomodoro {
+        #[command(subcommand)]
+        action: Option<PomodoroCommand>,
+    },
+    /// List or manage projects
+    Projects {
+        #[command(subcommand)]
+        action: Option<ProjectsCommand>,
+    },
+    /// Time per project over a date range (last 7 days by default)
+    Report {
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// Billable time and amounts per client (this year by month by default)
+    Billing {
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// day, week, month or year
+        #[arg(long, default_value = "month")]
+        by: Period,
+        /// Only projects of this client
+        #[arg(long)]
+        client: Option<String>,
+    },
+    /// Export sessions (all by default) as csv, jsonl or ics
+    Export {
+        #[arg(long, default_value = "csv")]
+        format: ExportFormat,
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Only sessions of this project (name or id)
+        #[arg(long)]
+        project: Option<String>,
+        /// Only sessions of this type: work, pomodoro or break
+        #[arg(long = "type")]
+        kind: Option<SessionKind>,
+        /// File to write (standard output if omitted)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Printable timesheet for billing (this month by default)
+    Timesheet {
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Only this project (name or id); repeat for several
+        #[arg(long)]
+        project: Vec<String>,
+        /// Round each session to this many minutes
+        #[arg(long, default_value_t = 0)]
+        round: u64,
+        /// up, nearest or down
+        #[arg(long, default_value = "up")]
+        round_mode: RoundingMode,
+        /// Hourly rate of a billable project in --currency, e.g. --rate
+        /// "Client Work=80"; the project's own rate is used otherwise
+        #[arg(long, value_name = "PROJECT=RATE")]
+        rate: Vec<String>,
+        /// Written after amounts, e.g. EUR (the projects' own by default)
+        #[arg(long)]
+        currency: Option<String>,
+        /// Heading, e.g. the client's name
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long, default_value = "html")]
+        format: TimesheetFormat,
+        /// File to write (standard output if omitted)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Import sessions from a Toggl Track, Clockify or CSV export
+    Import {
+        file: PathBuf,
+        /// toggl, clockify or csv
+        #[arg(long, default_value = "csv")]
+        source: ImportSource,
+        /// IANA timezone of times without an offset (the app's by default)
+        #[arg(long)]
+        timezone: Option<String>,
+        /// Read 03/04/2025 as 3 April rather than March 4
+        #[arg(long)]
+        day_first: bool,
+        /// CSV column of a field, e.g. --map project=Client (csv only)
+        #[arg(long = "map", value_name = "FIELD=COLUMN")]
+        columns: Vec<String>,
+        /// Show what would be imported without importing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProjectsCommand {
+    /// Add a project
+    Add {
+        name: String,
+        /// Hex color, e.g. "#00ff88"
+        #[arg(long)]
+        color: Option<String>,
+    },
+    /// Change a project's name, color or billing details
+    Set {
+        /// Name or id
+        project: String,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        color: Option<String>,
+        /// Who the work is billed to
+        #[arg(long, conflicts_with = "no_client")]
+        client: Option<String>,
+        #[arg(long)]
+        no_client: bool,
+        /// true or false
+        #[arg(long)]
+        billable: Option<bool>,
+        /// Per hour, in --currency
+        #[arg(long, conflicts_with = "no_rate")]
+        rate: Option<f64>,
+        #[arg(long)]
+        no_rate: bool,
+        /// Three-letter code such as EUR
+        #[arg(long)]
+        currency: Option<String>,
+        /// true or false
+        #[arg(long)]
+        archived: Option<bool>,
+    },
+    /// Stop offering a project for timing; its sessions stay in reports
+    Archive { project: String },
+    /// Move a project to the trash; its sessions are kept until it is purged
+    Rm { project: String },
+    /// Take a project out of the trash or the archive
+    Restore { project: String },
+    /// Projects in the trash and when they will be purged
+    Trash,
+    /// Delete a project in the trash and all of its sessions now
+    Purge { project: String },
+}
+
+#[derive(Subcommand)]
+enum PomodoroCommand {
+    /// Start the break due, or the next pomodoro
+    Next {
+        /// Project of the pomodoro (the last one's by default)
+        project: Option<String>,
+    },
+    /// Start a new set: no pomodoros done today, work next
+    Reset,
+    /// Change the lengths in minutes and what starts on its own
+    Set {
+        #[arg(long)]
+        work: Option<u32>,
+        #[arg(long)]
+        short_break: Option<u32>,
+        #[arg(long)]
+        long_break: Option<u32>,
+        /// Pomodoros before a long break
+        #[arg(long)]
+        every: Option<u32>,
+        /// true or false
+        #[arg(long)]
+        auto_breaks: Option<bool>,
+        /// true or false
+        #[arg(long)]
+        auto_work: Option<bool>,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("pt: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let dir = match cli.data_dir {
+        Some(dir) => dir,
+        None => dirs::data_dir()
+            .ok_or_else(|| Error::NotFound("user data directory".into()))?
+            .join(APP_IDENTIFIER),
+    };
+    let mut store = Store::open(&dir)?;
+    let mut timer = TimerEngine::new(&dir);
+
+    // A countdown may have run out while neither the app nor the CLI was running
+    if let Tick::Finished { session, .. } = timer.tick(Utc::now(), |s| store.add_session(s))? {
+        pomodoro::finished(&store, &session, Utc::now())?;
+    }
+    if let Err(e) = store.purge_trash(Utc::now()) {
+        eprintln!("pt: emptying the trash failed: {e}");
+    }
+
+    match cli.command {
+        Command::Start { project, pomodoro } => {
+            let project = find_project(&store, &project)?;
+            let project = store.active_project(&project.id)?;
+            let countdown = match pomodoro {
+                Some(Some(minutes)) => Some(minutes * 60),
+                Some(None) => Some(store.pomodoro_settings()?.length(Phase::Work)),
+                None => None,
+            };
+            timer.start(&project.id, countdown, Utc::now())?;
+            println!("Started {}", project.name);
+        }
+        Command::Pause => {
+            timer.pause(Utc::now())?;
+            print_status(&store, &timer)?;
+        }
+        Command::Resume => {
+            timer.resume(Utc::now())?;
+            print_status(&store, &timer)?;
+        }
+        Command::Stop { discard } => {
+            let saved = timer.stop(!discard, DEFAULT_MIN_DURATION, Utc::now(), |s| {
+                store.add_session(s)
+            })?;
+            match saved.iter().map(|s| s.duration).sum::<u64>() {
+                0 => println!("Stopped (nothing saved)"),
+                duration => println!("Saved {} session", format_time(duration)),
+            }
+        }
+        Command::Status => print_status(&store, &timer)?,
+        Command::Log { from, to, project } => {
+            let today = store.calendar()?.today();
+            let project_id = match project {
+                Some(project) => Some(find_project(&store, &project)?.id),
+                None => None,
+            };
+            let query = SessionQuery {
+                from: Some(from.unwrap_or(today).to_string()),
+                to: Some(to.unwrap_or(today).to_string()),
+                project_id,
+                ..Default::default()
+            };
+            let projects = store.projects()?;
+            for session in store.sessions(&query)? {
+                print_session(&session, &projects);
+            }
+        }
+        Command::Add {
+            project,
+            start,
+            end,
+            kind,
+            notes,
+            tags,
+        } => {
+            let calendar = store.calendar()?;
+            let project_id = match kind {
+                SessionKind::Break => BREAK_ID.to_string(),
+                _ => find_project(&store, &project)?.id,
+            };
+            let entry = SessionEntry {
+                project_id,
+                start_time: parse_local_time(&start, &calendar)?,
+                end_time: parse_local_time(&end, &calendar)?,
+                kind,
+                duration: None,
+                notes,
+                tags: tags.into_iter().collect(),
+            };
+            let projects = store.projects()?;
+            for session in store.create_session(&entry)? {
+                print_session(&session, &projects);
+            }
+        }
+        Command::Edit {
+            session,
+            project,
+            start,
+            end,
+            kind,
+        } => {
+            let calendar = store.calendar()?;
+            let current = store.session(&session)?;
+            let time = |text: Option<String>, current| match text {
+                Some(text) => parse_local_time(&text, &calendar),
+                None => Ok(current),
+            };
+            let entry = SessionEntry {
+                project_id: match project {
+                    Some(project) => find_project(&store, &project)?.id,
+                    None => current.project_id.clone(),
+                },
+                start_time: time(start, current.start_time)?,
+                end_time: time(end, current.end_time)?,
+                kind: kind.unwrap_or(current.kind),
+                duration: None,
+                notes: current.notes.clone(),
+                tags: current.tags.clone(),
+            };
+            let projects = store.projects()?;
+            for session in store.edit_session(&session, &entry)? {
+                print_session(&session, &projects);
+            }
+        }
+        Command::Split { session, at } => {
+            let at = parse_local_time(&at, &store.calendar()?)?;
+            let projects = store.projects()?;
+            for session in store.split_session(&session, at)? {
+                print_session(&session, &projects);
+            }
+        }
+        Command::Merge { sessions } => {
+            let session = store.merge_sessions(&sessions)?;
+            print_session(&session, &store.projects()?);
+        }
+        Command::Delete { session } => {
+            store.delete_session(&session)?;
+            println!("Deleted {session}");
+        }
+        Command::History { session, limit } => {
+            let projects = store.projects()?;
+            let tz = store.calendar()?.timezone;
+            for edit in store.session_edits(session.as_deref(), limit)? {
+                print_edit(&edit, &projects, tz);
+            }
+        }
+        Command::Note {
+            session,
+            notes,
+            tags,
+        } => {
+            let current = store.session(&session)?;
+            let notes = notes.or(current.notes);
+            let tags = match tags {
+                Some(tags) => vec![tags],
+                None => current.tags,
+            };
+            let session = store.annotate_session(&session, notes.as_deref(), &tags)?;
+            print_session(&session, &store.projects()?);
+        }
+        Command::Search {
+            text,
+            from,
+            to,
+            project,
+            limit,
+        } => {
+            let project_id = match project {
+                Some(project) => Some(find_project(&store, &project)?.id),
+                None => None,
+            };
+            let query = SessionQuery {
+                from: from.map(|d| d.to_string()),
+                to: to.map(|d| d.to_string()),
+                project_id,
+                ..Default::default()
+            };
+            let sessions = store.search_sessions(&text, &query, limit)?;
+            if sessions.is_empty() {
+                println!("No matching sessions");
+            }
+            let projects = store.projects()?;
+            for session in &sessions {
+                print_session(session, &projects);
+            }
+        }
+        Command::Pomodoro { action: None } => print_pomodoro(&store)?,
+        Command::Pomodoro {
+            action: Some(PomodoroCommand::Next { project }),
+        } => {
+            let project_id = match project {
+                Some(project) => Some(find_project(&store, &project)?.id),
+                None => None,
+            };
+            pomodoro::start_next(&mut store, &mut timer, project_id.as_deref(), Utc::now())?;
+            print_status(&store, &timer)?;
+        }
+        Command::Pomodoro {
+            action: Some(PomodoroCommand::Reset),
+        } => {
+            pomodoro::reset(&store)?;
+            print_pomodoro(&store)?;
+        }
+        Command::Pomodoro {
+            action:
+                Some(PomodoroCommand::Set {
+                    work,
+                    short_break,
+                    long_break,
+                    every,
+                    auto_breaks,
+                    auto_work,
+                }),
+        } => {
+            let current = store.pomodoro_settings()?;
+            store.set_pomodoro_settings(&PomodoroSettings {
+                work_minutes: work.unwrap_or(current.work_minutes),
+                short_break_minutes: short_break.unwrap_or(current.short_break_minutes),
+                long_break_minutes: long_break.unwrap_or(current.long_break_minutes),
+                long_break_every: every.unwrap_or(current.long_break_every),
+                auto_start_breaks: auto_breaks.unwrap_or(current.auto_start_breaks),
+                auto_start_work: auto_work.unwrap_or(current.auto_start_work),
+            })?;
+            print_pomodoro(&store)?;
+        }
+        Command::Projects { action: None } => {
+            for project in store
+                .projects()?
+                .into_iter()
+                .filter(|p| p.trashed_at.is_none())
+            {
+                let mut details = Vec::new();
+                details.extend(project.client.clone());
+                if let (Some(rate), Some(currency)) = (project.hourly_rate, &project.currency) {
+                    details.push(format!("{rate:.2} {currency}/h"));
+                }
+                if project.billable {
+                    details.push("billable".into());
+                }
+                if project.archived {
+                    details.push("archived".into());
+                }
+                let details = if details.is_empty() {
+                    String::new()
+                } else {
+                    format!("  ({})", details.join(", "))
+                };
+                println!(
+                    "{}  {}  {}{details}",
+                    project.id, project.color, project.name
+                );
+            }
+        }
+        Command::Projects {
+            action: Some(ProjectsCommand::Add { name, color }),
+        } => {
+            let count = store.projects()?.len();
+            let project = Project {
+                id: new_id(),
+                name: name.trim().to_string(),
+                color: color.unwrap_or_else(|| PROJECT_COLORS[count % PROJECT_COLORS.len()].into()),
+                modified_at: Utc::now(),
+                ..Default::default()
+            };
+            let (name, id) = (project.name.clone(), project.id.clone());
+            store.add_project(project)?;
+            println!("Added {name} ({id})");
+        }
+        Command::Projects {
+            action:
+                Some(ProjectsCommand::Set {
+                    project,
+                    name,
+                    color,
+                    client,
+                    no_client,
+                    billable,
+                    rate,
+                    no_rate,
+                    currency,
+                    archived,
+                }),
+        } => {
+            let mut project = find_project(&store, &project)?;
+            if let Some(name) = name {
+                project.name = name.trim().to_string();
+            }
+            if let Some(color) = color {
+                project.color = color;
+            }
+            if let Some(client) = client {
+                project.client = Some(client.trim().to_string());
+            }
+            if no_client {
+                project.client = None;
+            }
+            if let Some(rate) = rate {
+                project.hourly_rate = Some(rate);
+            }
+            if no_rate {
+                project.hourly_rate = None;
+            }
+            if let Some(currency) = currency {
+                project.currency = Some(currency.trim().to_uppercase());
+            }
+            project.billable = billable.unwrap_or(project.billable);
+            project.archived = archived.unwrap_or(project.archived);
+            let name = project.name.clone();
+            store.update_project(project)?;
+            println!("Updated {name}");
+        }
+        Command::Projects {
+            action: Some(ProjectsCommand::Archive { project }),
+        } => {
+            let project = find_project(&store, &project)?;
+            store.set_archived(&project.id, true)?;
+            println!("Archived {}", project.name);
+        }
+        Command::Projects {
+            action: Some(ProjectsCommand::Rm { project }),
+        } => {
+            let project = find_project(&store, &project)?;
+            store.trash_project(&project.id)?;
+            println!(
+                "Moved {} to the trash; it is deleted with its sessions after \
+                 {TRASH_RETENTION_DAYS} days (`pt projects restore` brings it back)",
+                project.name
+            );
+        }
+        Command::Projects {
+            action: Some(ProjectsCommand::Restore { project }),
+        } => {
+            let project = find_project(&store, &project)?;
+            if project.trashed_at.is_some() {
+                store.restore_project(&project.id)?;
+            } else if project.archived {
+                store.set_archived(&project.id, false)?;
+            } else {
+                return Err(Error::Invalid(format!(
+                    "{} is neither archived nor in the trash",
+                    project.name
+                )));
+            }
+            println!("Restored {}", project.name);
+        }
+        Command::Projects {
+            action: Some(ProjectsCommand::Trash),
+        } => {
+            for project in store.projects()? {
+                if let Some(purge_at) = project.purge_at() {
+                    println!(
+                        "{}  purged {}  {}",
+                        project.id,
+                        purge_at.date_naive(),
+                        project.name
+                    );
+                }
+            }
+        }
+        Command::Projects {
+            action: Some(ProjectsCommand::Purge { project }),
+        } => {
+            let project = find_project(&store, &project)?;
+            store.purge_project(&project.id)?;
+            println!("Deleted {} and its sessions", project.name);
+        }
+        Command::Report { from, to } => {
+            let to = match to {
+                Some(to) => to,
+                None => store.calendar()?.today(),
+            };
+            let from = from.unwrap_or(to - Duration::days(6));
+            let query = SessionQuery {
+                from: Some(from.to_string()),
+                to: Some(to.to_string()),
+                ..Default::default()
+            };
+            let projects = store.projects()?;
+            let totals = store.session_totals(&query, GroupBy::Project)?;
+
+            println!("{from} - {to}");
+            for total in &totals {
+                println!(
+                    "{:>10}  {:>4} sessions  {}",
+                    format_time(total.seconds),
+                    total.count,
+                    project_name(&projects, &total.key),
+                );
+            }
+            let sum: u64 = totals.iter().map(|t| t.seconds).sum();
+            println!("{:>10}  total", format_time(sum));
+        }
+        Command::Billing {
+            from,
+            to,
+            by,
+            client,
+        } => {
+            let to = match to {
+                Some(to) => to,
+                None => store.calendar()?.today(),
+            };
+            let query = BillingQuery {
+                from: from.unwrap_or(to.with_ordinal(1).unwrap_or(to)),
+                to,
+                period: by,
+                client,
+            };
+            print_billing(&billing(&store, &query)?);
+        }
+        Command::Export {
+            format,
+            from,
+            to,
+            project,
+            kind,
+            output,
+        } => {
+            let project_id = match project {
+                Some(project) => Some(find_project(&store, &project)?.id),
+                None => None,
+            };
+            let query = SessionQuery {
+                from: from.map(|d| d.to_string()),
+                to: to.map(|d| d.to_string()),
+                project_id,
+                kind,
+            };
+            match output {
+                Some(path) => {
+                    let mut out = BufWriter::new(File::create(&path)?);
+                    let count = export_sessions(&store, &query, format, &mut out)?;
+                    out.flush()?;
+                    eprintln!("Exported {count} sessions to {}", path.display());
+                }
+                None => {
+                    let mut out = io::stdout().lock();
+                    export_sessions(&store, &query, format, &mut out)?;
+                }
+            }
+        }
+        Command::Timesheet {
+            from,
+            to,
+            project,
+            round,
+            round_mode,
+            rate,
+            currency,
+            title,
+            format,
+            output,
+        } => {
+            let to = match to {
+                Some(to) => to,
+                None => store.calendar()?.today(),
+            };
+            let mut options = TimesheetOptions {
+                from: from.unwrap_or(to.with_day(1).unwrap_or(to)),
+                to,
+                projects: Vec::new(),
+                rounding: Rounding {
+                    minutes: round,
+                    mode: round_mode,
+                },
+                rates: Default::default(),
+                currency,
+                title,
+            };
+            for project in &project {
+                options.projects.push(find_project(&store, project)?.id);
+            }
+            for rate in &rate {
+                let (project, amount) = rate
+                    .rsplit_once('=')
+                    .and_then(|(project, amount)| Some((project, amount.parse().ok()?)))
+                    .ok_or_else(|| {
+                        Error::Invalid(format!("expected PROJECT=RATE, got {rate:?}"))
+                    })?;
+                options
+                    .rates
+                    .insert(find_project(&store, project)?.id, amount);
+            }
+            let sheet = timesheet(&store, &options)?.render(format);
+            match output {
+                Some(path) => {
+                    fs::write(&path, sheet)?;
+                    eprintln!("Timesheet written to {}", path.display());
+                }
+                None => io::stdout().lock().write_all(&sheet)?,
+            }
+        }
+        Command::Import {
+            file,
+            source,
+            timezone,
+            day_first,
+            columns,
+            dry_run,
+        } => {
+            let mut options = ImportOptions {
+                timezone: timezone.as_deref().map(parse_timezone).transpose()?,
+                day_first,
+                ..Default::default()
+            };
+            for mapping in &columns {
+                let (field, column) = mapping.split_once('=').ok_or_else(|| {
+                    Error::Invalid(format!("expected FIELD=COLUMN, got {mapping:?}"))
+                })?;
+                options.columns.set(field, column)?;
+            }
+            let input = fs::read_to_string(&file)?;
+            let entries = read_entries(&input, source, &options, store.calendar()?.timezone)?;
+            if dry_run {
+                print_import(&store.plan_import(&entries)?.preview);
+                println!("Dry run, nothing imported");
+            } else {
+                print_import(&store.import_file(&entries)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Looks a project up by id, then by case-insensitive name
+fn find_project(store: &Store, needle: &str) -> Result<Project> {
+    let projects = store.projects()?;
+    if let Some(project) = projects.iter().find(|p| p.id == needle) {
+        return Ok(project.clone());
+    }
+    let mut matches = projects
+        .into_iter()
+        .filter(|p| p.name.eq_ignore_ascii_case(needle));
+    match (matches.next(), matches.next()) {
+        (Some(project), None) => Ok(project),
+        (Some(_), Some(_)) => Err(Error::Invalid(format!(
+            "several projects are named {needle:?}, use the id"
+        ))),
+        _ => Err(Error::NotFound(format!("project {needle:?}"))),
+    }
+}
+
+fn print_status(store: &Store, timer: &TimerEngine) -> Result<()> {
+    let Some(status) = timer.status(Utc::now())? else {
+        println!("No active timer");
+        return Ok(());
+    };
+    let name = project_name(&store.projects()?, &status.project_id);
+    let paused = if status.paused { " (paused)" } else { "" };
+    match status.remaining {
+        Some(remaining) => println!(
+            "{name}  {}{paused}  {} left",
+            format_time(status.elapsed),
+            format_time(remaining)
+        ),
+        None => println!("{name}  {}{paused}", format_time(status.elapsed)),
+    }
+    Ok(())
+}
+
+fn print_pomodoro(store: &Store) -> Result<()> {
+    let PomodoroStatus {
+        settings, cycle, ..
+    } = pomodoro::status(store, Utc::now())?;
+    let next = match cycle.next {
+        Phase::Work => "pomodoro",
+        Phase::ShortBreak => "short break",
+        Phase::LongBreak => "long break",
+    };
+    println!(
+        "{} of {} before a long break, {} today",
+        cycle.in_set, settings.long_break_every, cycle.completed
+    );
+    println!("Next: {next} ({} min)", settings.length(cycle.next) / 60);
+    println!(
+        "Lengths: {} min work, {} min short break, {} min long break",
+        settings.work_minutes, settings.short_break_minutes, settings.long_break_minutes
+    );
+    let auto = |on: bool| if on { "on" } else { "off" };
+    println!(
+        "Auto-start: breaks {}, work {}",
+        auto(settings.auto_start_breaks),
+        auto(settings.auto_start_work)
+    );
+    Ok(())
+}
+
+/// One line per session, its notes indented below
+fn print_session(session: &Session, projects: &[Project]) {
+    let tags: String = session.tags.iter().map(|t| format!("  #{t}")).collect();
+    println!(
+        "{}  {:<8}  {:>8}  {}{tags}  [{}]",
+        session.date,
+        session.kind,
+        format_time(session.duration),
+        project_name(projects, &session.project_id),
+        session.id,
+    );
+    for line in session.notes.iter().flat_map(|notes| notes.lines()) {
+        println!("    {line}");
+    }
+}
+
+fn print_edit(edit: &SessionEdit, projects: &[Project], tz: Tz) {
+    let describe = |session: &Option<Session>| match session {
+        Some(s) => {
+            let tz = s.timezone.unwrap_or(Tz::UTC);
+            format!(
+                "{} {}-{} {} ({})",
+                s.date,
+                s.start_time.with_timezone(&tz).format("%H:%M"),
+                s.end_time.with_timezone(&tz).format("%H:%M"),
+                project_name(projects, &s.project_id),
+                format_time(s.duration)
+            )
+        }
+        None => "-".into(),
+    };
+    println!(
+        "{}  {:<6}  {}  {} -> {}",
+        edit.at.with_timezone(&tz).format("%Y-%m-%d %H:%M"),
+        edit.action,
+        edit.session_id,
+        describe(&edit.before),
+        describe(&edit.after),
+    );
+}
+
+/// `HH:MM` today, `YYYY-MM-DD HH:MM` (either in the app's timezone) or an
+/// RFC 3339 time with its offset
+fn parse_local_time(text: &str, calendar: &Calendar) -> Result<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let tz = calendar.timezone;
+    let today = Utc::now().with_timezone(&tz).date_naive();
+    let local = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| {
+            NaiveTime::parse_from_str(text, "%H:%M")
+                .ok()
+                .map(|time| today.and_time(time))
+        })
+        .ok_or_else(|| Error::Invalid(format!("invalid time {text:?}")))?;
+    tz.from_local_datetime(&local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| Error::Invalid(format!("{text} does not exist in {tz}")))
+}
+
+fn print_billing(summary: &BillingSummary) {
+    println!("{} - {}", summary.from, summary.to);
+    for line in &summary.lines {
+        println!(
+            "{}  {:>10}  {:>14}  {}",
+            line.period,
+            format_time(line.seconds),
+            money(line.amount, &line.currency),
+            line.client.as_deref().unwrap_or("(no client)"),
+        );
+    }
+    for total in &summary.totals {
+        println!(
+            "{:10}  {:>10}  {:>14}  total",
+            "",
+            format_time(total.seconds),
+            money(total.amount, &total.currency),
+        );
+    }
+    if summary.unbilled_seconds > 0 {
+        println!(
+            "{:10}  {:>10}  {:>14}  not billable",
+            "",
+            format_time(summary.unbilled_seconds),
+            ""
+        );
+    }
+}
+
+fn money(amount: Option<f64>, currency: &Option<String>) -> String {
+    match (amount, currency) {
+        (Some(amount), Some(currency)) => format!("{amount:.2} {currency}"),
+        _ => "no rate".into(),
+    }
+}
+
+fn print_import(preview: &ImportPreview) {
+    if let (Some(from), Some(to)) = (preview.from, preview.to) {
+        println!("{from} - {to}");
+    }
+    for project in &preview.projects {
+        let new = if project.new { "  (new project)" } else { "" };
+        println!(
+            "{:>10}  {:>4} entries  {}{new}",
+            format_time(project.seconds),
+            project.entries,
+            project.name,
+        );
+    }
+    println!(
+        "{:>10}  {} entries, {} already imported",
+        format_time(preview.seconds),
+        preview.entries,
+        preview.duplicates
+    );
+    for issue in &preview.issues {
+        eprintln!("Skipped row {}: {}", issue.row, issue.message);
+    }
+}
+
+fn project_name(projects: &[Project], id: &str) -> String {
+    if id == BREAK_ID {
+        return "Break".into();
+    }
+    projects
+        .iter()
+        .find(|p| p.id == id)
+        .map(|p| p.name.clone())
+        .unwrap_or_else(|| id.to_string())
+}
+
+fn format_time(seconds: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
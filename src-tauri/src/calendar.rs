@@ -0,0 +1,303 @@
+// Productivity Tracker - Calendar days
+// Which day a session counts for: its local date in the user's IANA
+// timezone, where a day may start a few hours after midnight so late work
+// counts for the evening before. Sessions running past the start of a day
+// are split there. The settings live in settings.json.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::model::Session;
+use crate::store::new_id;
+
+/// Latest hour a day can start at
+pub const MAX_DAY_START_HOUR: u32 = 12;
+
+/// Day settings as chosen by the user
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaySettings {
+    /// `None` follows the system timezone
+    #[serde(default)]
+    pub timezone: Option<Tz>,
+    /// Hour at which a new day starts, 0 for midnight
+    #[serde(default)]
+    pub day_start_hour: u32,
+}
+
+impl DaySettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.day_start_hour > MAX_DAY_START_HOUR {
+            return Err(Error::Invalid(format!(
+                "a day can start at {MAX_DAY_START_HOUR}:00 at the latest"
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn calendar(&self) -> Calendar {
+        Calendar {
+            timezone: self.timezone.unwrap_or_else(system_timezone),
+            day_start_hour: self.day_start_hour,
+        }
+    }
+}
+
+/// The timezone the system is set to, UTC if it cannot be told
+pub fn system_timezone() -> Tz {
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Calendar {
+    pub timezone: Tz,
+    pub day_start_hour: u32,
+}
+
+impl Calendar {
+    /// Day `time` belongs to, by the wall clock as `start_of` counts: on
+    /// a DST change day the hours before the day start are not all one hour
+    pub fn date_of(&self, time: DateTime<Utc>) -> NaiveDate {
+        (time.with_timezone(&self.timezone).naive_local()
+            - Duration::hours(self.day_start_hour.into()))
+        .date()
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.date_of(Utc::now())
+    }
+
+    /// When `date` begins. If its start hour is skipped by a DST change,
+    /// the day begins when the clocks resume.
+    pub fn start_of(&self, date: NaiveDate) -> DateTime<Utc> {
+        let start = date.and_time(NaiveTime::MIN) + Duration::hours(self.day_start_hour.into());
+        (0..=2)
+            .find_map(|skipped| {
+                self.timezone
+                    .from_local_datetime(&(start + Duration::hours(skipped)))
+                    .earliest()
+            })
+            .map(|time| time.with_timezone(&Utc))
+            // No timezone skips three hours in a row
+            .unwrap_or_else(|| start.and_utc())
+    }
+
+    /// Files `session` under its day, in the timezone it was recorded in
+    /// (this calendar's if it has none). A session running past the start
+    /// of a day is split there, sharing the tracked time in proportion to
+    /// each part's span; the first part keeps the id.
+    pub fn file(&self, mut session: Session) -> Vec<Session> {
+        let calendar = Calendar {
+            timezone: *session.timezone.get_or_insert(self.timezone),
+            ..*self
+        };
+        let span = (session.end_time - session.start_time).num_seconds();
+        let mut parts = Vec::new();
+        let mut start = session.start_time;
+        let mut spread = 0;
+        loop {
+            let date = calendar.date_of(start);
+            let next = date.succ_opt().map(|next| calendar.start_of(next));
+            let mut part = session.clone();
+            part.start_time = start;
+            part.date = date;
+            if !parts.is_empty() {
+                part.id = new_id();
+            }
+            match next {
+                // `next > start` always holds; checked so a disagreement
+                // between date_of and start_of cannot loop forever
+                Some(next) if start < next && next < session.end_time => {
+                    let elapsed = (next - session.start_time).num_seconds();
+                    // Cumulative rounding, so the parts add up to the duration
+                    let upto = session.duration * elapsed as u64 / span as u64;
+                    part.end_time = next;
+                    part.duration = upto - spread;
+                    parts.push(part);
+                    spread = upto;
+                    start = next;
+                }
+                _ => {
+                    part.duration = session.duration - spread;
+                    parts.push(part);
+                    return parts;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, at};
+    use chrono_tz::{America, Europe};
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn session(start: &str, end: &str, duration: u64) -> Session {
+        Session {
+            end_time: at(end),
+            duration,
+            ..testing::session("s1", "p1", start, 0)
+        }
+    }
+
+    #[test]
+    fn days_are_local() {
+        let rome = Calendar {
+            timezone: Europe::Rome,
+            day_start_hour: 0,
+        };
+        // 23:30 UTC in winter is 00:30 in Rome
+        assert_eq!(rome.date_of(at("2025-01-01T23:30:00Z")), date("2025-01-02"));
+        assert_eq!(rome.date_of(at("2025-01-01T22:59:59Z")), date("2025-01-01"));
+
+        let night_owl = Calendar {
+            day_start_hour: 4,
+            ..rome
+        };
+        assert_eq!(
+            night_owl.date_of(at("2025-01-02T02:30:00Z")),
+            date("2025-01-01")
+        );
+        assert_eq!(
+            night_owl.start_of(date("2025-01-02")),
+            at("2025-01-02T03:00:00Z")
+        );
+    }
+
+    #[test]
+    fn days_start_after_a_skipped_hour() {
+        // New York skips 02:00-03:00 on 9 March 2025
+        let calendar = Calendar {
+            timezone: America::New_York,
+            day_start_hour: 2,
+        };
+        assert_eq!(
+            calendar.start_of(date("2025-03-09")),
+            at("2025-03-09T07:00:00Z")
+        );
+        assert_eq!(
+            calendar.date_of(at("2025-03-09T06:59:00Z")),
+            date("2025-03-08")
+        );
+    }
+
+    #[test]
+    fn sessions_are_split_at_the_start_of_a_day() {
+        let rome = Calendar {
+            timezone: Europe::Rome,
+            day_start_hour: 0,
+        };
+        // 22:30-01:30 in Rome with 20 minutes of pause
+        let parts = rome.file(session(
+            "2025-01-01T21:30:00Z",
+            "2025-01-02T00:30:00Z",
+            9600,
+        ));
+        assert_eq!(parts.len(), 2);
+        assert_eq!(
+            (parts[0].id.as_str(), parts[0].date),
+            ("s1", date("2025-01-01"))
+        );
+        assert_eq!(parts[0].end_time, at("2025-01-01T23:00:00Z"));
+        assert_eq!(parts[1].start_time, at("2025-01-01T23:00:00Z"));
+        assert_eq!(parts[1].date, date("2025-01-02"));
+        assert_ne!(parts[1].id, "s1");
+        assert_eq!((parts[0].duration, parts[1].duration), (4800, 4800));
+        assert!(parts
+            .iter()
+            .all(|p| p.timezone == Some(Europe::Rome) && p.validate().is_ok()));
+
+        let night_owl = Calendar {
+            day_start_hour: 4,
+            ..rome
+        };
+        let parts = night_owl.file(session(
+            "2025-01-01T21:30:00Z",
+            "2025-01-02T00:30:00Z",
+            9600,
+        ));
+        assert_eq!(parts.len(), 1);
+        assert_eq!(
+            (parts[0].date, parts[0].duration),
+            (date("2025-01-01"), 9600)
+        );
+    }
+
+    #[test]
+    fn sessions_are_split_on_a_dst_day() {
+        // Rome skips 02:00-03:00 on 30 March 2025; 04:00 CEST is 02:00 UTC
+        let night_owl = Calendar {
+            timezone: Europe::Rome,
+            day_start_hour: 4,
+        };
+        assert_eq!(
+            night_owl.date_of(at("2025-03-30T01:59:00Z")),
+            date("2025-03-29")
+        );
+        assert_eq!(
+            night_owl.date_of(at("2025-03-30T02:00:00Z")),
+            date("2025-03-30")
+        );
+        // 03:00-05:00 local
+        let parts = night_owl.file(session(
+            "2025-03-30T01:00:00Z",
+            "2025-03-30T03:00:00Z",
+            7200,
+        ));
+        assert_eq!(parts.len(), 2);
+        assert_eq!(
+            (parts[0].date, parts[0].end_time, parts[0].duration),
+            (date("2025-03-29"), at("2025-03-30T02:00:00Z"), 3600)
+        );
+        assert_eq!(
+            (parts[1].date, parts[1].duration),
+            (date("2025-03-30"), 3600)
+        );
+
+        // Back to 02:00 from 03:00 on 26 October: 04:00 CET is 03:00 UTC
+        let parts = night_owl.file(session(
+            "2025-10-26T01:00:00Z",
+            "2025-10-26T04:00:00Z",
+            10800,
+        ));
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].end_time, at("2025-10-26T03:00:00Z"));
+        assert_eq!(parts[1].date, date("2025-10-26"));
+    }
+
+    #[test]
+    fn recorded_timezone_wins() {
+        let rome = Calendar {
+            timezone: Europe::Rome,
+            day_start_hour: 0,
+        };
+        let mut recorded = session("2025-01-02T02:00:00Z", "2025-01-02T03:00:00Z", 3600);
+        recorded.timezone = Some(America::New_York);
+        let parts = rome.file(recorded);
+        assert_eq!((parts.len(), parts[0].date), (1, date("2025-01-01")));
+        assert_eq!(parts[0].timezone, Some(America::New_York));
+    }
+
+    #[test]
+    fn day_start_is_bounded() {
+        let settings = DaySettings {
+            timezone: None,
+            day_start_hour: 13,
+        };
+        assert!(settings.validate().is_err());
+        let json = r#"{"timezone":"Asia/Kolkata","dayStartHour":5}"#;
+        let settings: DaySettings = serde_json::from_str(json).unwrap();
+        assert_eq!(settings.calendar().timezone, chrono_tz::Asia::Kolkata);
+    }
+}
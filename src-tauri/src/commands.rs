@@ -0,0 +1,889 @@
+// Productivity Tracker - Tauri commands exposed to the webview
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::analytics::{self, Analytics};
+use crate::assistant::AiRequests;
+use crate::backend::BackendConfig;
+use crate::billing::{self, BillingQuery, BillingSummary};
+use crate::calendar::{Calendar, DaySettings};
+use crate::crypto;
+use crate::db::{GroupBy, SessionQuery, Total, DEFAULT_SEARCH_LIMIT};
+use crate::editing::{SessionEdit, SessionEntry, DEFAULT_EDITS_LIMIT};
+use crate::error::{Error, Result};
+use crate::export::{self, ExportFormat};
+use crate::hotkeys::HotkeySettings;
+use crate::idle::{self, IdleResolution, IdleSettings, IdleStatus};
+use crate::import::{self, ImportFile, ImportOptions, ImportPreview, ImportSource};
+use crate::insights::{self, AiRequest};
+use crate::merge::{merge, SyncData};
+use crate::model::{Project, Session};
+use crate::notifications::NotificationSettings;
+use crate::pomodoro::{self, PomodoroSettings, PomodoroStatus};
+use crate::secrets::{self, SecretStore};
+use crate::shortcuts;
+use crate::store::Store;
+use crate::sync::{self, DayStatus, EncryptionStatus};
+use crate::syncer::{SyncConfig, Syncer};
+use crate::ticker::{self, IdleState, TimerState};
+use crate::timer::{TimerStatus, DEFAULT_MIN_DURATION};
+use crate::timesheet::{self, TimesheetFormat, TimesheetOptions};
+use crate::tray;
+
+pub type StoreState = Mutex<Store>;
+pub type SecretsState = Box<dyn SecretStore>;
+/// File read by `preview_import`, waiting for `commit_import`
+pub type ImportState = Mutex<Option<ImportFile>>;
+
+// ============================================
+// PROJECTS
+// ============================================
+
+#[tauri::command]
+pub fn list_projects(store: State<'_, StoreState>) -> Result<Vec<Project>> {
+    store.lock().unwrap().projects()
+}
+
+#[tauri::command]
+pub fn add_project(app: AppHandle, store: State<'_, StoreState>, project: Project) -> Result<()> {
+    store.lock().unwrap().add_project(project)?;
+    tray::rebuild_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn update_project(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    project: Project,
+) -> Result<()> {
+    store.lock().unwrap().update_project(project)?;
+    tray::rebuild_menu(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_project_archived(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    id: String,
+    archived: bool,
+) -> Result<Project> {
+    let project = store.lock().unwrap().set_archived(&id, archived)?;
+    tray::rebuild_menu(&app);
+    Ok(project)
+}
+
+/// Moves the project to the trash; its sessions are kept
+#[tauri::command]
+pub fn trash_project(app: AppHandle, store: State<'_, StoreState>, id: String) -> Result<Project> {
+    let project = store.lock().unwrap().trash_project(&id)?;
+    tray::rebuild_menu(&app);
+    Ok(project)
+}
+
+#[tauri::command]
+pub fn restore_project(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    id: String,
+) -> Result<Project> {
+    let project = store.lock().unwrap().restore_project(&id)?;
+    tray::rebuild_menu(&app);
+    Ok(project)
+}
+
+/// Deletes a project in the trash and all of its sessions for good
+#[tauri::command]
+pub fn purge_project(store: State<'_, StoreState>, id: String) -> Result<()> {
+    store.lock().unwrap().purge_project(&id)
+}
+
+// ============================================
+// SESSIONS
+// ============================================
+
+#[tauri::command]
+pub fn list_sessions(
+    store: State<'_, StoreState>,
+    query: Option<SessionQuery>,
+) -> Result<Vec<Session>> {
+    store.lock().unwrap().sessions(&query.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn session_totals(
+    store: State<'_, StoreState>,
+    query: Option<SessionQuery>,
+    group_by: GroupBy,
+) -> Result<Vec<Total>> {
+    store
+        .lock()
+        .unwrap()
+        .session_totals(&query.unwrap_or_default(), group_by)
+}
+
+/// Returns the sessions saved: more than one when it ran past the start
+/// of a day
+#[tauri::command]
+pub fn add_session(store: State<'_, StoreState>, session: Session) -> Result<Vec<Session>> {
+    store.lock().unwrap().add_session(session)
+}
+
+/// Replaces the notes and tags of a session; returns it updated
+#[tauri::command]
+pub fn annotate_session(
+    store: State<'_, StoreState>,
+    id: String,
+    notes: Option<String>,
+    tags: Vec<String>,
+) -> Result<Session> {
+    store
+        .lock()
+        .unwrap()
+        .annotate_session(&id, notes.as_deref(), &tags)
+}
+
+/// Sessions whose notes, tags or project name match `text`, best first
+#[tauri::command]
+pub fn search_sessions(
+    store: State<'_, StoreState>,
+    text: String,
+    query: Option<SessionQuery>,
+    limit: Option<u32>,
+) -> Result<Vec<Session>> {
+    store.lock().unwrap().search_sessions(
+        &text,
+        &query.unwrap_or_default(),
+        limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+    )
+}
+
+#[tauri::command]
+pub fn delete_session(store: State<'_, StoreState>, id: String) -> Result<()> {
+    store.lock().unwrap().delete_session(&id)
+}
+
+/// Adds a session entered by hand; returns the sessions saved, more than
+/// one when it runs past the start of a day
+#[tauri::command]
+pub fn create_session(store: State<'_, StoreState>, entry: SessionEntry) -> Result<Vec<Session>> {
+    store.lock().unwrap().create_session(&entry)
+}
+
+#[tauri::command]
+pub fn edit_session(
+    store: State<'_, StoreState>,
+    id: String,
+    entry: SessionEntry,
+) -> Result<Vec<Session>> {
+    store.lock().unwrap().edit_session(&id, &entry)
+}
+
+#[tauri::command]
+pub fn split_session(
+    store: State<'_, StoreState>,
+    id: String,
+    at: DateTime<Utc>,
+) -> Result<Vec<Session>> {
+    store.lock().unwrap().split_session(&id, at)
+}
+
+#[tauri::command]
+pub fn merge_sessions(store: State<'_, StoreState>, ids: Vec<String>) -> Result<Session> {
+    store.lock().unwrap().merge_sessions(&ids)
+}
+
+/// Audit trail of manual changes, newest first; of every session if
+/// `session_id` is not given
+#[tauri::command]
+pub fn session_edits(
+    store: State<'_, StoreState>,
+    session_id: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<SessionEdit>> {
+    store
+        .lock()
+        .unwrap()
+        .session_edits(session_id.as_deref(), limit.unwrap_or(DEFAULT_EDITS_LIMIT))
+}
+
+// ============================================
+// DAYS
+// ============================================
+
+#[tauri::command]
+pub fn get_day_settings(store: State<'_, StoreState>) -> Result<DaySettings> {
+    store.lock().unwrap().day_settings()
+}
+
+/// Saves the settings and returns the calendar they give
+#[tauri::command]
+pub fn set_day_settings(store: State<'_, StoreState>, settings: DaySettings) -> Result<Calendar> {
+    let store = store.lock().unwrap();
+    store.set_day_settings(&settings)?;
+    store.calendar()
+}
+
+/// Timezone and day start hour in effect, for the webview to compute days
+/// the same way
+#[tauri::command]
+pub fn get_calendar(store: State<'_, StoreState>) -> Result<Calendar> {
+    store.lock().unwrap().calendar()
+}
+
+// ============================================
+// ANALYTICS
+// ============================================
+
+/// Metrics of the last `days` days. Sessions the server
+/// holds are included when a sync backend is set, so days recorded on other
+/// devices count too. Runs off the main thread, as fetching them can
+/// take a while.
+#[tauri::command(async)]
+pub fn get_analytics(
+    store: State<'_, StoreState>,
+    syncer: State<'_, Syncer>,
+    days: u32,
+) -> Result<Analytics> {
+    let calendar = store.lock().unwrap().calendar()?;
+    let to = calendar.today();
+    let from = to - chrono::Duration::days(i64::from(days.max(1)) - 1);
+    let mut data = store.lock().unwrap().sync_data(from, to)?;
+    if let Ok(backend) = syncer.backend() {
+        match sync::history(&store, backend.as_ref(), from, to) {
+            Ok(remote) => data = merge(&data, &remote),
+            Err(e) => log::warn!("analytics without server history: {e}"),
+        }
+    }
+    let projects = store.lock().unwrap().projects()?;
+    Ok(analytics::analyze(
+        &data.sessions,
+        &projects,
+        &calendar.timezone,
+        from,
+        to,
+    ))
+}
+
+/// Billable time and amounts per client and period
+#[tauri::command]
+pub fn billing_summary(
+    store: State<'_, StoreState>,
+    query: BillingQuery,
+) -> Result<BillingSummary> {
+    billing::billing(&store.lock().unwrap(), &query)
+}
+
+// ============================================
+// EXPORT
+// ============================================
+
+/// Asks where to save, then writes the sessions matching `query`. Returns
+/// the file written, or `None` if the dialog was cancelled. Runs off the
+/// main thread, which the dialog needs.
+#[tauri::command(async)]
+pub fn export_sessions(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    query: Option<SessionQuery>,
+    format: ExportFormat,
+) -> Result<Option<String>> {
+    let query = query.unwrap_or_default();
+    let range = match (&query.from, &query.to) {
+        (Some(from), Some(to)) => format!("-{from}-{to}"),
+        (Some(from), None) => format!("-{from}"),
+        (None, Some(to)) => format!("-{to}"),
+        (None, None) => String::new(),
+    };
+    let Some(file) = app
+        .dialog()
+        .file()
+        .add_filter(format.description(), &[format.extension()])
+        .set_file_name(format!("sessions{range}.{}", format.extension()))
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = file
+        .as_path()
+        .ok_or_else(|| Error::Invalid(format!("cannot export to {file}")))?
+        .to_path_buf();
+
+    let mut out = BufWriter::new(File::create(&path)?);
+    export::export_sessions(&store.lock().unwrap(), &query, format, &mut out)?;
+    out.flush()?;
+    Ok(Some(path.display().to_string()))
+}
+
+/// Builds a timesheet and saves it where the user picks
+#[tauri::command(async)]
+pub fn save_timesheet(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    options: TimesheetOptions,
+    format: TimesheetFormat,
+) -> Result<Option<String>> {
+    options.validate()?;
+    let Some(file) = app
+        .dialog()
+        .file()
+        .add_filter(format.description(), &[format.extension()])
+        .set_file_name(format!(
+            "timesheet-{}-{}.{}",
+            options.from,
+            options.to,
+            format.extension()
+        ))
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = file
+        .as_path()
+        .ok_or_else(|| Error::Invalid(format!("cannot save to {file}")))?
+        .to_path_buf();
+
+    let sheet = timesheet::timesheet(&store.lock().unwrap(), &options)?;
+    fs::write(&path, sheet.render(format))?;
+    Ok(Some(path.display().to_string()))
+}
+
+// ============================================
+// IMPORT
+// ============================================
+
+/// Shows the open dialog and reads the chosen file, keeping it for
+/// `commit_import`. The preview tells what committing would add; `None` if
+/// the dialog was cancelled.
+#[tauri::command(async)]
+pub fn preview_import(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    pending: State<'_, ImportState>,
+    source: ImportSource,
+    options: Option<ImportOptions>,
+) -> Result<Option<ImportPreview>> {
+    let Some(file) = app
+        .dialog()
+        .file()
+        .add_filter(source.description(), source.extensions())
+        .blocking_pick_file()
+    else {
+        return Ok(None);
+    };
+    let path = file
+        .as_path()
+        .ok_or_else(|| Error::Invalid(format!("cannot import {file}")))?
+        .to_path_buf();
+
+    let input = fs::read_to_string(&path)?;
+    let zone = store.lock().unwrap().calendar()?.timezone;
+    // Parsed without the store locked, a large file holds nothing up
+    let file = import::read_entries(&input, source, &options.unwrap_or_default(), zone)?;
+    let preview = store.lock().unwrap().plan_import(&file)?.preview;
+    *pending.lock().unwrap() = Some(file);
+    Ok(Some(preview))
+}
+
+#[tauri::command]
+pub fn commit_import(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    pending: State<'_, ImportState>,
+) -> Result<ImportPreview> {
+    let file = pending
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| Error::NotFound("file to import".into()))?;
+    let imported = store.lock().unwrap().import_file(&file)?;
+    tray::rebuild_menu(&app);
+    Ok(imported)
+}
+
+#[tauri::command]
+pub fn cancel_import(pending: State<'_, ImportState>) {
+    pending.lock().unwrap().take();
+}
+
+// ============================================
+// TIMER
+// ============================================
+
+#[tauri::command]
+pub fn get_timer(timer: State<'_, TimerState>) -> Result<Option<TimerStatus>> {
+    timer.lock().unwrap().status(Utc::now())
+}
+
+#[tauri::command]
+pub fn start_timer(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    timer: State<'_, TimerState>,
+    project_id: String,
+    countdown: Option<u64>,
+) -> Result<Option<TimerStatus>> {
+    store.lock().unwrap().active_project(&project_id)?;
+    let now = Utc::now();
+    let status = {
+        let mut timer = timer.lock().unwrap();
+        timer.start(&project_id, countdown, now)?;
+        timer.status(now)?
+    };
+    ticker::publish_status(&app, status.as_ref());
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn start_break(
+    app: AppHandle,
+    timer: State<'_, TimerState>,
+    store: State<'_, StoreState>,
+    duration: u64,
+) -> Result<Option<TimerStatus>> {
+    let now = Utc::now();
+    let (saved, status) = {
+        let mut timer = timer.lock().unwrap();
+        let saved = timer.start_break(duration, now, |session| {
+            store.lock().unwrap().add_session(session)
+        })?;
+        (saved, timer.status(now)?)
+    };
+    ticker::announce_saved(&app, &saved);
+    ticker::publish_status(&app, status.as_ref());
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn pause_timer(app: AppHandle, timer: State<'_, TimerState>) -> Result<Option<TimerStatus>> {
+    let now = Utc::now();
+    let status = {
+        let mut timer = timer.lock().unwrap();
+        timer.pause(now)?;
+        timer.status(now)?
+    };
+    ticker::publish_status(&app, status.as_ref());
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn resume_timer(app: AppHandle, timer: State<'_, TimerState>) -> Result<Option<TimerStatus>> {
+    let now = Utc::now();
+    let status = {
+        let mut timer = timer.lock().unwrap();
+        timer.resume(now)?;
+        timer.status(now)?
+    };
+    ticker::publish_status(&app, status.as_ref());
+    Ok(status)
+}
+
+/// Stops the timer; the recorded session (if any) is announced through
+/// the `session-saved` event. If it cannot be saved the timer keeps
+/// running and the error is returned.
+#[tauri::command]
+pub fn stop_timer(
+    app: AppHandle,
+    timer: State<'_, TimerState>,
+    store: State<'_, StoreState>,
+    save: Option<bool>,
+    min_duration: Option<u64>,
+) -> Result<()> {
+    let saved = timer.lock().unwrap().stop(
+        save.unwrap_or(true),
+        min_duration.unwrap_or(DEFAULT_MIN_DURATION),
+        Utc::now(),
+        |session| store.lock().unwrap().add_session(session),
+    )?;
+    ticker::announce_saved(&app, &saved);
+    ticker::publish_status(&app, None);
+    Ok(())
+}
+
+// ============================================
+// IDLE
+// ============================================
+
+#[tauri::command]
+pub fn idle_status(idle: State<'_, IdleState>) -> IdleStatus {
+    idle.lock().unwrap().status()
+}
+
+#[tauri::command]
+pub fn set_idle_settings(
+    store: State<'_, StoreState>,
+    idle: State<'_, IdleState>,
+    settings: IdleSettings,
+) -> Result<IdleStatus> {
+    store.lock().unwrap().set_idle_settings(&settings)?;
+    let mut idle = idle.lock().unwrap();
+    idle.configure(settings);
+    Ok(idle.status())
+}
+
+/// Keeps, discards or reassigns the idle time the user came back from
+/// (the `idle-returned` event); sessions it records are announced through
+/// `session-saved`.
+#[tauri::command]
+pub fn resolve_idle(
+    app: AppHandle,
+    idle: State<'_, IdleState>,
+    timer: State<'_, TimerState>,
+    store: State<'_, StoreState>,
+    resolution: IdleResolution,
+) -> Result<Option<TimerStatus>> {
+    let span = idle
+        .lock()
+        .unwrap()
+        .pending()
+        .cloned()
+        .ok_or_else(|| Error::NotFound("idle time to resolve".into()))?;
+    let now = Utc::now();
+    let (saved, status) = {
+        let mut timer = timer.lock().unwrap();
+        let saved = idle::resolve(
+            &span,
+            &resolution,
+            &mut timer,
+            &mut store.lock().unwrap(),
+            now,
+        )?;
+        (saved, timer.status(now)?)
+    };
+    idle.lock().unwrap().clear_pending();
+    ticker::announce_saved(&app, &saved);
+    ticker::publish_status(&app, status.as_ref());
+    Ok(status)
+}
+
+// ============================================
+// POMODORO
+// ============================================
+
+#[tauri::command]
+pub fn pomodoro_status(store: State<'_, StoreState>) -> Result<PomodoroStatus> {
+    pomodoro::status(&store.lock().unwrap(), Utc::now())
+}
+
+#[tauri::command]
+pub fn set_pomodoro_settings(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    settings: PomodoroSettings,
+) -> Result<PomodoroStatus> {
+    let status = {
+        let store = store.lock().unwrap();
+        store.set_pomodoro_settings(&settings)?;
+        pomodoro::status(&store, Utc::now())?
+    };
+    // The tray menu shows the lengths
+    tray::rebuild_menu(&app);
+    Ok(status)
+}
+
+/// Starts the phase the cycle is at: the break due, or a pomodoro on
+/// `project_id` (by default the project of the last one)
+#[tauri::command]
+pub fn start_pomodoro(
+    app: AppHandle,
+    timer: State<'_, TimerState>,
+    store: State<'_, StoreState>,
+    project_id: Option<String>,
+) -> Result<Option<TimerStatus>> {
+    let now = Utc::now();
+    let (saved, status) = {
+        let mut timer = timer.lock().unwrap();
+        let saved = pomodoro::start_next(
+            &mut store.lock().unwrap(),
+            &mut timer,
+            project_id.as_deref(),
+            now,
+        )?;
+        (saved, timer.status(now)?)
+    };
+    ticker::announce_saved(&app, &saved);
+    ticker::publish_status(&app, status.as_ref());
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn reset_pomodoro(app: AppHandle, store: State<'_, StoreState>) -> Result<PomodoroStatus> {
+    let status = {
+        let store = store.lock().unwrap();
+        pomodoro::reset(&store)?;
+        pomodoro::status(&store, Utc::now())?
+    };
+    let _ = app.emit(ticker::POMODORO_EVENT, &status);
+    Ok(status)
+}
+
+// ============================================
+// NOTIFICATIONS
+// ============================================
+
+#[tauri::command]
+pub fn get_notification_settings(store: State<'_, StoreState>) -> Result<NotificationSettings> {
+    store.lock().unwrap().notification_settings()
+}
+
+#[tauri::command]
+pub fn set_notification_settings(
+    store: State<'_, StoreState>,
+    settings: NotificationSettings,
+) -> Result<NotificationSettings> {
+    store.lock().unwrap().set_notification_settings(&settings)?;
+    Ok(settings)
+}
+
+/// Shows the open dialog for a sound to play when a countdown ends; `None`
+/// if it was cancelled
+#[tauri::command(async)]
+pub fn choose_sound_file(app: AppHandle) -> Result<Option<PathBuf>> {
+    let Some(file) = app
+        .dialog()
+        .file()
+        .add_filter("Sounds", &["wav", "ogg", "oga", "mp3", "flac", "aiff"])
+        .blocking_pick_file()
+    else {
+        return Ok(None);
+    };
+    let path = file
+        .as_path()
+        .ok_or_else(|| Error::Invalid(format!("cannot play {file}")))?;
+    Ok(Some(path.to_path_buf()))
+}
+
+// ============================================
+// GLOBAL SHORTCUTS
+// ============================================
+
+#[tauri::command]
+pub fn get_hotkey_settings(store: State<'_, StoreState>) -> Result<HotkeySettings> {
+    store.lock().unwrap().hotkey_settings()
+}
+
+/// Registers the new shortcuts, then saves them; if the OS refuses one,
+/// the previous shortcuts stay
+#[tauri::command]
+pub fn set_hotkey_settings(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    settings: HotkeySettings,
+) -> Result<HotkeySettings> {
+    settings.validate()?;
+    if let Err(e) = shortcuts::apply(&app, &settings) {
+        let previous = store.lock().unwrap().hotkey_settings()?;
+        let _ = shortcuts::apply(&app, &previous);
+        return Err(e);
+    }
+    store.lock().unwrap().set_hotkey_settings(&settings)?;
+    Ok(settings)
+}
+
+// ============================================
+// SYNC
+// ============================================
+
+/// Sets the sync backend (`None` to disable) and the auto-sync interval in
+/// minutes (0 = only when asked)
+#[tauri::command]
+pub fn configure_sync(
+    syncer: State<'_, Syncer>,
+    backend: Option<BackendConfig>,
+    interval_minutes: u64,
+) {
+    let config = backend.map(|backend| SyncConfig {
+        backend,
+        interval: (interval_minutes > 0).then(|| Duration::from_secs(interval_minutes * 60)),
+    });
+    syncer.configure(config);
+}
+
+/// Uploads every queued day; progress arrives as `sync-status` events
+/// followed by `sync-finished`
+#[tauri::command]
+pub fn sync_now(syncer: State<'_, Syncer>) {
+    syncer.sync_now();
+}
+
+/// Days not yet uploaded, with their last error if any
+#[tauri::command]
+pub fn sync_status(store: State<'_, StoreState>) -> Result<Vec<DayStatus>> {
+    let outbox = store.lock().unwrap().sync_outbox(None)?;
+    Ok(outbox.iter().map(DayStatus::queued).collect())
+}
+
+/// What the server holds for the last `days` days, decrypted (for the AI
+/// analysis). Runs off the main thread.
+#[tauri::command(async)]
+pub fn sync_history(
+    store: State<'_, StoreState>,
+    syncer: State<'_, Syncer>,
+    days: u32,
+) -> Result<SyncData> {
+    let to = store.lock().unwrap().calendar()?.today();
+    let from = to - chrono::Duration::days(i64::from(days.max(1)) - 1);
+    sync::history(&store, syncer.backend()?.as_ref(), from, to)
+}
+
+// ============================================
+// SYNC ENCRYPTION
+// ============================================
+
+// Commands that derive keys or reach the backend run off the main thread
+
+#[tauri::command]
+pub fn encryption_status(store: State<'_, StoreState>) -> Result<EncryptionStatus> {
+    sync::encryption_status(&store)
+}
+
+/// Turns encryption on and returns the recovery code; every day is
+/// uploaded again, sealed
+#[tauri::command(async)]
+pub fn enable_encryption(
+    store: State<'_, StoreState>,
+    syncer: State<'_, Syncer>,
+    passphrase: String,
+) -> Result<String> {
+    let code = sync::enable_encryption(&store, syncer.backend()?.as_ref(), &passphrase)?;
+    syncer.sync_now();
+    Ok(code)
+}
+
+#[tauri::command(async)]
+pub fn unlock_encryption(
+    store: State<'_, StoreState>,
+    syncer: State<'_, Syncer>,
+    passphrase: String,
+) -> Result<()> {
+    sync::unlock_encryption(&store, syncer.backend()?.as_ref(), &passphrase)?;
+    syncer.sync_now();
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub fn recover_encryption(
+    store: State<'_, StoreState>,
+    syncer: State<'_, Syncer>,
+    recovery_code: String,
+    new_passphrase: String,
+) -> Result<()> {
+    let backend = syncer.backend()?;
+    sync::recover_encryption(&store, backend.as_ref(), &recovery_code, &new_passphrase)?;
+    syncer.sync_now();
+    Ok(())
+}
+
+#[tauri::command(async)]
+pub fn change_passphrase(
+    store: State<'_, StoreState>,
+    syncer: State<'_, Syncer>,
+    passphrase: String,
+) -> Result<()> {
+    sync::change_passphrase(&store, syncer.backend()?.as_ref(), &passphrase)
+}
+
+#[tauri::command(async)]
+pub fn new_recovery_code(
+    store: State<'_, StoreState>,
+    syncer: State<'_, Syncer>,
+) -> Result<String> {
+    sync::new_recovery_code(&store, syncer.backend()?.as_ref())
+}
+
+#[tauri::command(async)]
+pub fn rotate_encryption_key(
+    store: State<'_, StoreState>,
+    syncer: State<'_, Syncer>,
+) -> Result<()> {
+    sync::rotate_encryption_key(&store, syncer.backend()?.as_ref())?;
+    syncer.sync_now();
+    Ok(())
+}
+
+/// Forgets the keys on this device until the passphrase is entered again
+#[tauri::command]
+pub fn lock_encryption(store: State<'_, StoreState>) -> Result<()> {
+    crypto::forget_keys(store.lock().unwrap().dir())
+}
+
+// ============================================
+// AI API KEYS
+// ============================================
+
+/// Saves a provider's API key to the OS keychain; an empty key removes it.
+/// Runs off the main thread, as the keychain may prompt or be slow to
+/// answer.
+#[tauri::command(async)]
+pub fn set_api_key(secrets: State<'_, SecretsState>, provider: String, key: String) -> Result<()> {
+    let name = secrets::api_key(&provider);
+    match key.trim() {
+        "" => secrets.delete(&name),
+        key => secrets.set(&name, key),
+    }
+}
+
+#[tauri::command(async)]
+pub fn has_api_key(secrets: State<'_, SecretsState>, provider: String) -> Result<bool> {
+    Ok(secrets.get(&secrets::api_key(&provider))?.is_some())
+}
+
+// ============================================
+// AI INSIGHTS
+// ============================================
+
+/// Starts streaming the reply to `request` as `ai-text` events tagged
+/// with `id`, followed by `ai-finished`. The API key is read here and never
+/// sent to the webview. Runs off the main thread, as reading the key and
+/// connecting to the provider can take a while.
+#[tauri::command(async)]
+pub fn ai_generate(
+    app: AppHandle,
+    secrets: State<'_, SecretsState>,
+    requests: State<'_, AiRequests>,
+    id: String,
+    request: AiRequest,
+) -> Result<()> {
+    let api_key = if insights::needs_key(&request.provider) {
+        secrets.get(&secrets::api_key(&request.provider))?
+    } else {
+        None
+    };
+    let provider = insights::connect(&request.provider, api_key)?;
+    requests.start(app, id, provider, request);
+    Ok(())
+}
+
+/// Stops a reply; `ai-finished` follows with a `cancelled` error
+#[tauri::command]
+pub fn ai_cancel(requests: State<'_, AiRequests>, id: String) -> bool {
+    requests.cancel(&id)
+}
+
+// ============================================
+// MIGRATION
+// ============================================
+
+/// One-off import of the data the frontend used to keep in localStorage
+#[tauri::command]
+pub fn import_legacy_data(
+    app: AppHandle,
+    store: State<'_, StoreState>,
+    projects: Vec<Project>,
+    sessions: Vec<Session>,
+) -> Result<bool> {
+    let imported = store.lock().unwrap().import(projects, sessions)?;
+    if imported {
+        tray::rebuild_menu(&app);
+    }
+    Ok(imported)
+}
@@ -0,0 +1,621 @@
+// Productivity Tracker - Sync encryption
+// Day logs are encrypted on the device before they reach the sync backend.
+//
+// Keys form three layers, all kept in a keyring that is stored next to the
+// day logs so every device can read it:
+//   passphrase / recovery code --Argon2id--> wrapping key
+//   wrapping key --wraps--> master key (one per keyring, never changes)
+//   master key --wraps--> data keys (a new one on every rotation)
+// Day logs are sealed with the newest data key. Changing the passphrase or
+// the recovery code only rewraps the master key; older data keys stay in the
+// keyring so days uploaded before a rotation can still be read.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::merge::SyncData;
+use crate::store::{new_id, read_json, write_json_atomic, DirLock};
+use crate::sync::DayLog;
+
+/// Shared keyring, in the data directory and on the sync backend
+pub const KEYRING_FILE: &str = "keyring.json";
+/// The unlocked master key, so the passphrase is asked once per device
+const MASTER_KEY_FILE: &str = "sync.key";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+const RECOVERY_CODE_LEN: usize = 20;
+
+pub(crate) type Key = [u8; KEY_LEN];
+
+/// Argon2id cost, stored in the keyring so it can be raised later
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// OWASP's recommended minimum for Argon2id
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// Highest Argon2id cost a keyring may ask for. Keyrings come from the
+/// sync backend, and whoever can write there could otherwise make every
+/// unlock take gigabytes of memory or minutes of work.
+const MAX_KDF: KdfParams = KdfParams {
+    memory_kib: 1024 * 1024,
+    iterations: 16,
+    parallelism: 8,
+};
+
+impl KdfParams {
+    fn check(self) -> Result<Self> {
+        if self.memory_kib > MAX_KDF.memory_kib
+            || self.iterations > MAX_KDF.iterations
+            || self.parallelism > MAX_KDF.parallelism
+        {
+            return Err(Error::Crypto(format!(
+                "key derivation settings too costly ({} KiB, {} passes, {} lanes)",
+                self.memory_kib, self.iterations, self.parallelism
+            )));
+        }
+        Ok(self)
+    }
+}
+
+/// Ciphertext with the nonce it was sealed with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Wrapped {
+    #[serde(with = "base64_bytes")]
+    pub nonce: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub ciphertext: Vec<u8>,
+}
+
+/// An encrypted day log, sent in place of its projects and sessions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sealed {
+    /// Data key the log was sealed with
+    pub key_id: String,
+    #[serde(flatten)]
+    pub data: Wrapped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataKey {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    /// Wrapped with the master key
+    pub key: Wrapped,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Keyring {
+    pub kdf: KdfParams,
+    #[serde(with = "base64_bytes")]
+    pub passphrase_salt: Vec<u8>,
+    #[serde(with = "base64_bytes")]
+    pub recovery_salt: Vec<u8>,
+    pub master_by_passphrase: Wrapped,
+    pub master_by_recovery: Wrapped,
+    /// Oldest first; the last one seals new uploads
+    pub keys: Vec<DataKey>,
+}
+
+/// An unlocked keyring
+pub struct Vault {
+    pub keyring: Keyring,
+    master: Key,
+    keys: BTreeMap<String, Key>,
+}
+
+impl Vault {
+    /// Sets up encryption with a fresh master key and data key. Returns the
+    /// vault and the recovery code to show the user, once.
+    pub fn create(passphrase: &str, kdf: KdfParams) -> Result<(Self, String)> {
+        check_passphrase(passphrase)?;
+        let master = random_key();
+        let code = random_recovery_code();
+        let (passphrase_salt, master_by_passphrase) = wrap_master(&master, passphrase, kdf)?;
+        let (recovery_salt, master_by_recovery) =
+            wrap_master(&master, &normalize_recovery_code(&code), kdf)?;
+        let keyring = Keyring {
+            kdf,
+            passphrase_salt,
+            recovery_salt,
+            master_by_passphrase,
+            master_by_recovery,
+            keys: Vec::new(),
+        };
+        let mut vault = Self {
+            keyring,
+            master,
+            keys: BTreeMap::new(),
+        };
+        vault.rotate()?;
+        Ok((vault, code))
+    }
+
+    pub fn unlock(keyring: Keyring, passphrase: &str) -> Result<Self> {
+        let wrapping = derive_key(passphrase.as_bytes(), &keyring.passphrase_salt, keyring.kdf)?;
+        let master = unwrap_key(&wrapping, &keyring.master_by_passphrase, b"master")
+            .map_err(|_| Error::Crypto("wrong passphrase".into()))?;
+        Self::with_master(keyring, master)
+    }
+
+    /// Unlocks with the recovery code and sets a new passphrase
+    pub fn recover(mut keyring: Keyring, code: &str, new_passphrase: &str) -> Result<Self> {
+        check_passphrase(new_passphrase)?;
+        let code = normalize_recovery_code(code);
+        let wrapping = derive_key(code.as_bytes(), &keyring.recovery_salt, keyring.kdf)?;
+        let master = unwrap_key(&wrapping, &keyring.master_by_recovery, b"master")
+            .map_err(|_| Error::Crypto("wrong recovery code".into()))?;
+        set_passphrase(&mut keyring, &master, new_passphrase)?;
+        Self::with_master(keyring, master)
+    }
+
+    fn with_master(keyring: Keyring, master: Key) -> Result<Self> {
+        let mut vault = Self {
+            keyring,
+            master,
+            keys: BTreeMap::new(),
+        };
+        vault.unwrap_data_keys()?;
+        Ok(vault)
+    }
+
+    fn unwrap_data_keys(&mut self) -> Result<()> {
+        for key in &self.keyring.keys {
+            if !self.keys.contains_key(&key.id) {
+                let data_key = unwrap_key(&self.master, &key.key, key.id.as_bytes())?;
+                self.keys.insert(key.id.clone(), data_key);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn change_passphrase(&mut self, passphrase: &str) -> Result<()> {
+        check_passphrase(passphrase)?;
+        set_passphrase(&mut self.keyring, &self.master, passphrase)
+    }
+
+    /// Replaces the recovery code; the old one stops working
+    pub fn new_recovery_code(&mut self) -> Result<String> {
+        let code = random_recovery_code();
+        set_recovery_code(&mut self.keyring, &self.master, &code)?;
+        Ok(code)
+    }
+
+    /// Adds a data key that seals every upload from now on
+    pub fn rotate(&mut self) -> Result<()> {
+        let id = new_id();
+        let key = random_key();
+        self.keyring.keys.push(DataKey {
+            id: id.clone(),
+            created_at: Utc::now(),
+            key: wrap(&self.master, &key, id.as_bytes())?,
+        });
+        self.keys.insert(id, key);
+        Ok(())
+    }
+
+    /// Adopts the backend's keyring, which carries keys rotated and
+    /// passphrases changed on other devices. Kept only if it holds all of
+    /// our data keys; returns whether it was adopted.
+    pub fn adopt_keyring(&mut self, remote: Keyring) -> Result<bool> {
+        let has_ours = self
+            .keyring
+            .keys
+            .iter()
+            .all(|ours| remote.keys.iter().any(|theirs| theirs.id == ours.id));
+        if has_ours && remote != self.keyring {
+            // Same master key or it would not unwrap: reject foreign keyrings
+            for key in &remote.keys {
+                unwrap_key(&self.master, &key.key, key.id.as_bytes())?;
+            }
+            self.keyring = remote;
+            self.unwrap_data_keys()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        &self.keyring.keys.last().expect("keyring has a data key").id
+    }
+
+    /// Encrypts a day log. The date stays readable as it names the file,
+    /// and is bound to the ciphertext so logs cannot be swapped.
+    pub fn seal(&self, log: &DayLog) -> Result<DayLog> {
+        let key_id = self.current_key_id();
+        let plaintext = serde_json::to_vec(&log.data)?;
+        let aad = log.date.to_string();
+        Ok(DayLog {
+            date: log.date,
+            data: SyncData::default(),
+            encrypted: Some(Sealed {
+                key_id: key_id.to_string(),
+                data: wrap(&self.keys[key_id], &plaintext, aad.as_bytes())?,
+            }),
+        })
+    }
+
+    /// Decrypts a sealed day log; plaintext logs are returned as they are
+    pub fn open(&self, log: DayLog) -> Result<DayLog> {
+        let Some(sealed) = &log.encrypted else {
+            return Ok(log);
+        };
+        let key = self
+            .keys
+            .get(&sealed.key_id)
+            .ok_or_else(|| Error::Crypto(format!("{} was sealed with an unknown key", log.date)))?;
+        let plaintext = unwrap(key, &sealed.data, log.date.to_string().as_bytes())
+            .map_err(|_| Error::Crypto(format!("{} could not be decrypted", log.date)))?;
+        Ok(DayLog {
+            date: log.date,
+            data: serde_json::from_slice(&plaintext)?,
+            encrypted: None,
+        })
+    }
+
+    // ============================================
+    // LOCAL FILES
+    // ============================================
+
+    /// The vault saved in `dir`: `Ok(None)` if encryption is off, an
+    /// error if it is on but this device was never unlocked
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let Some(keyring) = read_keyring(dir)? else {
+            return Ok(None);
+        };
+        let master = match fs::read(dir.join(MASTER_KEY_FILE)) {
+            Ok(bytes) => Key::try_from(bytes.as_slice())
+                .map_err(|_| Error::Crypto(format!("{MASTER_KEY_FILE} is corrupt")))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(locked()),
+            Err(e) => return Err(e.into()),
+        };
+        Self::with_master(keyring, master).map(Some)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let _lock = DirLock::acquire(dir)?;
+        write_json_atomic(&dir.join(KEYRING_FILE), &self.keyring)?;
+        write_private(&dir.join(MASTER_KEY_FILE), &self.master)
+    }
+}
+
+/// Error for a device that has not been given the passphrase yet
+pub fn locked() -> Error {
+    Error::Crypto("sync data is encrypted: unlock it with your passphrase".into())
+}
+
+pub fn read_keyring(dir: &Path) -> Result<Option<Keyring>> {
+    read_json(&dir.join(KEYRING_FILE))
+}
+
+/// Keeps a keyring found on the sync backend, so this device knows
+/// encryption is on before it is unlocked
+pub fn save_keyring(dir: &Path, keyring: &Keyring) -> Result<()> {
+    let _lock = DirLock::acquire(dir)?;
+    write_json_atomic(&dir.join(KEYRING_FILE), keyring)
+}
+
+/// Locks this device: the keys are gone until the passphrase is entered
+/// again
+pub fn forget_keys(dir: &Path) -> Result<()> {
+    let _lock = DirLock::acquire(dir)?;
+    match fs::remove_file(dir.join(MASTER_KEY_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Writes a secret readable only by the current user
+pub(crate) fn write_private(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    {
+        let mut options = fs::OpenOptions::new();
+        options.create(true).truncate(true).write(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&tmp)?;
+        std::io::Write::write_all(&mut file, bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn check_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.chars().count() < 8 {
+        return Err(Error::Invalid(
+            "passphrase must be at least 8 characters".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn set_passphrase(keyring: &mut Keyring, master: &Key, passphrase: &str) -> Result<()> {
+    (keyring.passphrase_salt, keyring.master_by_passphrase) =
+        wrap_master(master, passphrase, keyring.kdf)?;
+    Ok(())
+}
+
+fn set_recovery_code(keyring: &mut Keyring, master: &Key, code: &str) -> Result<()> {
+    (keyring.recovery_salt, keyring.master_by_recovery) =
+        wrap_master(master, &normalize_recovery_code(code), keyring.kdf)?;
+    Ok(())
+}
+
+/// Wraps the master key with a key derived from `secret` under a new salt
+fn wrap_master(master: &Key, secret: &str, kdf: KdfParams) -> Result<(Vec<u8>, Wrapped)> {
+    let salt = random_bytes(16);
+    let wrapping = derive_key(secret.as_bytes(), &salt, kdf)?;
+    Ok((salt, wrap(&wrapping, master, b"master")?))
+}
+
+fn derive_key(secret: &[u8], salt: &[u8], kdf: KdfParams) -> Result<Key> {
+    let kdf = kdf.check()?;
+    let params = argon2::Params::new(
+        kdf.memory_kib,
+        kdf.iterations,
+        kdf.parallelism,
+        Some(KEY_LEN),
+    )
+    .map_err(|e| Error::Crypto(format!("invalid key derivation settings: {e}")))?;
+    let argon = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    let mut key = [0; KEY_LEN];
+    argon
+        .hash_password_into(secret, salt, &mut key)
+        .map_err(|e| Error::Crypto(format!("key derivation failed: {e}")))?;
+    Ok(key)
+}
+
+pub(crate) fn wrap(key: &Key, plaintext: &[u8], aad: &[u8]) -> Result<Wrapped> {
+    let nonce = random_bytes(NONCE_LEN);
+    let ciphertext = XChaCha20Poly1305::new(key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| Error::Crypto("encryption failed".into()))?;
+    Ok(Wrapped { nonce, ciphertext })
+}
+
+pub(crate) fn unwrap(key: &Key, wrapped: &Wrapped, aad: &[u8]) -> Result<Vec<u8>> {
+    if wrapped.nonce.len() != NONCE_LEN {
+        return Err(Error::Crypto("invalid nonce".into()));
+    }
+    XChaCha20Poly1305::new(key.into())
+        .decrypt(
+            XNonce::from_slice(&wrapped.nonce),
+            Payload {
+                msg: &wrapped.ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| Error::Crypto("decryption failed".into()))
+}
+
+fn unwrap_key(key: &Key, wrapped: &Wrapped, aad: &[u8]) -> Result<Key> {
+    Key::try_from(unwrap(key, wrapped, aad)?.as_slice())
+        .map_err(|_| Error::Crypto("invalid key length".into()))
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+pub(crate) fn random_key() -> Key {
+    let mut key = [0; KEY_LEN];
+    OsRng.fill_bytes(&mut key);
+    key
+}
+
+/// 160 random bits in base32, grouped for writing down:
+/// `ABCD-EFGH-...` (8 groups of 4)
+fn random_recovery_code() -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let bytes = random_bytes(RECOVERY_CODE_LEN);
+    let mut chars = Vec::new();
+    for chunk in bytes.chunks(5) {
+        let bits = chunk.iter().fold(0u64, |acc, b| acc << 8 | u64::from(*b));
+        for i in (0..8).rev() {
+            chars.push(ALPHABET[(bits >> (i * 5)) as usize & 31] as char);
+        }
+    }
+    chars
+        .chunks(4)
+        .map(|group| group.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Case, spaces and dashes don't matter when the code is typed back
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+mod base64_bytes {
+    use super::{Engine, BASE64};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(d)?;
+        BASE64.decode(s).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Project;
+
+    // Cheap enough for tests; real keyrings use `KdfParams::default()`
+    const FAST: KdfParams = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    fn log() -> DayLog {
+        DayLog {
+            date: "2025-01-01".parse().unwrap(),
+            data: SyncData {
+                projects: vec![Project {
+                    id: "p".into(),
+                    name: "Secret client".into(),
+                    color: "#00ff88".into(),
+                    modified_at: "2025-01-01T10:00:00Z".parse().unwrap(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            encrypted: None,
+        }
+    }
+
+    #[test]
+    fn sealed_logs_hide_their_content() {
+        let (vault, _) = Vault::create("correct horse", FAST).unwrap();
+        let sealed = vault.seal(&log()).unwrap();
+        let json = serde_json::to_string(&sealed).unwrap();
+        assert!(!json.contains("Secret client"));
+        assert_eq!(vault.open(sealed).unwrap(), log());
+    }
+
+    #[test]
+    fn tampering_and_moved_logs_are_rejected() {
+        let (vault, _) = Vault::create("correct horse", FAST).unwrap();
+        let mut sealed = vault.seal(&log()).unwrap();
+        sealed.date = "2025-01-02".parse().unwrap();
+        assert!(vault.open(sealed.clone()).is_err());
+
+        sealed.date = log().date;
+        sealed.encrypted.as_mut().unwrap().data.ciphertext[0] ^= 1;
+        assert!(vault.open(sealed).is_err());
+    }
+
+    #[test]
+    fn unlock_needs_the_passphrase() {
+        let (vault, _) = Vault::create("correct horse", FAST).unwrap();
+        let sealed = vault.seal(&log()).unwrap();
+        let keyring = vault.keyring.clone();
+
+        let err = Vault::unlock(keyring.clone(), "wrong horse").err().unwrap();
+        assert_eq!(err.to_string(), "encryption: wrong passphrase");
+        let other = Vault::unlock(keyring, "correct horse").unwrap();
+        assert_eq!(other.open(sealed).unwrap(), log());
+    }
+
+    #[test]
+    fn recovery_code_sets_a_new_passphrase() {
+        let (vault, code) = Vault::create("correct horse", FAST).unwrap();
+        let sealed = vault.seal(&log()).unwrap();
+        let typed = code.to_lowercase().replace('-', " ");
+
+        let recovered = Vault::recover(vault.keyring.clone(), &typed, "battery staple").unwrap();
+        assert_eq!(recovered.open(sealed).unwrap(), log());
+        assert!(Vault::unlock(recovered.keyring.clone(), "correct horse").is_err());
+        assert!(Vault::unlock(recovered.keyring, "battery staple").is_ok());
+        assert!(Vault::recover(vault.keyring, "AAAA-AAAA", "battery staple").is_err());
+    }
+
+    #[test]
+    fn rotation_keeps_old_logs_readable() {
+        let (mut vault, code) = Vault::create("correct horse", FAST).unwrap();
+        let old = vault.seal(&log()).unwrap();
+        let old_key = vault.current_key_id().to_string();
+
+        vault.rotate().unwrap();
+        let new = vault.seal(&log()).unwrap();
+        assert_ne!(new.encrypted.as_ref().unwrap().key_id, old_key);
+
+        // Another device picks up the new key from the shared keyring
+        let mut other = Vault::unlock(vault.keyring.clone(), "correct horse").unwrap();
+        assert_eq!(other.open(old).unwrap(), log());
+        assert_eq!(other.open(new.clone()).unwrap(), log());
+        assert!(!other.adopt_keyring(vault.keyring.clone()).unwrap());
+
+        // The recovery code still unlocks every key
+        let recovered = Vault::recover(vault.keyring.clone(), &code, "battery staple").unwrap();
+        assert_eq!(recovered.open(new).unwrap(), log());
+    }
+
+    #[test]
+    fn devices_pick_up_keys_rotated_elsewhere() {
+        let (mut a, _) = Vault::create("correct horse", FAST).unwrap();
+        let mut b = Vault::unlock(a.keyring.clone(), "correct horse").unwrap();
+        a.rotate().unwrap();
+        let sealed = a.seal(&log()).unwrap();
+        assert!(b.open(sealed.clone()).is_err());
+        assert!(b.adopt_keyring(a.keyring.clone()).unwrap());
+        assert_eq!(b.open(sealed).unwrap(), log());
+
+        // A keyring with a different master key is refused
+        let (stranger, _) = Vault::create("correct horse", FAST).unwrap();
+        let mut foreign = stranger.keyring.clone();
+        foreign.keys.splice(0..0, b.keyring.keys.clone());
+        assert!(b.adopt_keyring(foreign).is_err());
+    }
+
+    #[test]
+    fn costly_keyrings_are_refused() {
+        let (vault, _) = Vault::create("correct horse", FAST).unwrap();
+        let mut keyring = vault.keyring.clone();
+        keyring.kdf.memory_kib = u32::MAX;
+        let err = Vault::unlock(keyring.clone(), "correct horse")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("too costly"));
+        keyring.kdf = KdfParams {
+            iterations: 1000,
+            ..FAST
+        };
+        assert!(Vault::unlock(keyring, "correct horse").is_err());
+        assert!(KdfParams::default().check().is_ok());
+    }
+
+    #[test]
+    fn recovery_codes_are_grouped_base32() {
+        let code = random_recovery_code();
+        assert_eq!(code.len(), 8 * 4 + 7);
+        assert!(code
+            .split('-')
+            .all(|g| g.len() == 4 && g.chars().all(|c| c.is_ascii_alphanumeric())));
+    }
+}
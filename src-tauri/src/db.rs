@@ -0,0 +1,963 @@
+// Productivity Tracker - Session history database
+// SQLite file in the app data directory, indexed for range and aggregate queries
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::types::{Type, Value};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::editing::SessionEdit;
+use crate::error::{Error, Result};
+use crate::model::{
+    format_timestamp, normalize_tag, parse_date, parse_timestamp, parse_timezone, Project, Session,
+    SessionKind, Tombstone,
+};
+
+/// Schema changes, applied in order. `PRAGMA user_version` records how many
+/// have run; append new steps, never edit old ones.
+const MIGRATIONS: &[&str] = &[
+    "
+CREATE TABLE IF NOT EXISTS sessions (
+    id          TEXT PRIMARY KEY,
+    project_id  TEXT NOT NULL,
+    start_time  TEXT NOT NULL,
+    end_time    TEXT NOT NULL,
+    duration    INTEGER NOT NULL,
+    date        TEXT NOT NULL,
+    type        TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_sessions_date ON sessions(date);
+CREATE INDEX IF NOT EXISTS idx_sessions_project ON sessions(project_id, date);
+CREATE INDEX IF NOT EXISTS idx_sessions_type ON sessions(type, date);
+",
+    "
+-- Days whose sessions changed since they were last uploaded. `revision`
+-- bumps on every change so an upload racing with an edit stays queued.
+CREATE TABLE sync_outbox (
+    date          TEXT PRIMARY KEY,
+    revision      INTEGER NOT NULL DEFAULT 1,
+    attempts      INTEGER NOT NULL DEFAULT 0,
+    next_attempt  TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    last_error    TEXT
+);
+INSERT INTO sync_outbox (date) SELECT DISTINCT date FROM sessions;
+CREATE TRIGGER sessions_dirty_insert AFTER INSERT ON sessions BEGIN
+    INSERT INTO sync_outbox (date) VALUES (NEW.date)
+        ON CONFLICT(date) DO UPDATE SET revision = revision + 1;
+END;
+CREATE TRIGGER sessions_dirty_update AFTER UPDATE ON sessions BEGIN
+    INSERT INTO sync_outbox (date) VALUES (OLD.date)
+        ON CONFLICT(date) DO UPDATE SET revision = revision + 1;
+    INSERT INTO sync_outbox (date) VALUES (NEW.date)
+        ON CONFLICT(date) DO UPDATE SET revision = revision + 1;
+END;
+CREATE TRIGGER sessions_dirty_delete AFTER DELETE ON sessions BEGIN
+    INSERT INTO sync_outbox (date) VALUES (OLD.date)
+        ON CONFLICT(date) DO UPDATE SET revision = revision + 1;
+END;
+",
+    "
+-- Modification times and deletion records, so sync can merge per session
+ALTER TABLE sessions ADD COLUMN modified_at TEXT NOT NULL DEFAULT '1970-01-01T00:00:00.000Z';
+CREATE TABLE session_tombstones (
+    id          TEXT PRIMARY KEY,
+    date        TEXT NOT NULL,
+    deleted_at  TEXT NOT NULL
+);
+CREATE INDEX idx_session_tombstones_date ON session_tombstones(date);
+",
+    "
+-- IANA timezone a session was recorded in; `date` is its local day from now on
+ALTER TABLE sessions ADD COLUMN timezone TEXT;
+",
+    "
+-- Notes and tags (a JSON array of strings), searchable along with the name
+-- of the session's project. Project names live in projects.json and are
+-- copied into `project_names` before searching.
+ALTER TABLE sessions ADD COLUMN notes TEXT;
+ALTER TABLE sessions ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+CREATE TABLE project_names (
+    id    TEXT PRIMARY KEY,
+    name  TEXT NOT NULL
+);
+CREATE VIRTUAL TABLE session_search USING fts5(
+    id UNINDEXED, notes, tags, project,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+INSERT INTO session_search (id, notes, tags, project) SELECT id, notes, '', '' FROM sessions;
+CREATE TRIGGER sessions_search_insert AFTER INSERT ON sessions BEGIN
+    INSERT INTO session_search (id, notes, tags, project) VALUES (
+        NEW.id,
+        NEW.notes,
+        (SELECT group_concat(value, ' ') FROM json_each(NEW.tags)),
+        (SELECT name FROM project_names WHERE id = NEW.project_id)
+    );
+END;
+CREATE TRIGGER sessions_search_update AFTER UPDATE OF id, project_id, notes, tags ON sessions BEGIN
+    DELETE FROM session_search WHERE id = OLD.id;
+    INSERT INTO session_search (id, notes, tags, project) VALUES (
+        NEW.id,
+        NEW.notes,
+        (SELECT group_concat(value, ' ') FROM json_each(NEW.tags)),
+        (SELECT name FROM project_names WHERE id = NEW.project_id)
+    );
+END;
+CREATE TRIGGER sessions_search_delete AFTER DELETE ON sessions BEGIN
+    DELETE FROM session_search WHERE id = OLD.id;
+END;
+CREATE TRIGGER project_names_insert AFTER INSERT ON project_names BEGIN
+    UPDATE session_search SET project = NEW.name
+        WHERE id IN (SELECT id FROM sessions WHERE project_id = NEW.id);
+END;
+CREATE TRIGGER project_names_update AFTER UPDATE ON project_names BEGIN
+    UPDATE session_search SET project = NEW.name
+        WHERE id IN (SELECT id FROM sessions WHERE project_id = NEW.id);
+END;
+CREATE TRIGGER project_names_delete AFTER DELETE ON project_names BEGIN
+    UPDATE session_search SET project = NULL
+        WHERE id IN (SELECT id FROM sessions WHERE project_id = OLD.id);
+END;
+",
+    "
+-- Audit trail of manual changes to sessions, as JSON snapshots. Not synced.
+CREATE TABLE session_edits (
+    id          INTEGER PRIMARY KEY,
+    session_id  TEXT NOT NULL,
+    action      TEXT NOT NULL,
+    at          TEXT NOT NULL,
+    before      TEXT,
+    after       TEXT
+);
+CREATE INDEX idx_session_edits_session ON session_edits(session_id);
+",
+];
+
+const SESSION_COLUMNS: &str =
+    "id, project_id, start_time, end_time, duration, date, type, modified_at, timezone, notes, tags";
+const SESSION_PARAMS: &str = "?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11";
+const SESSION_UPDATE: &str = "project_id = ?2, start_time = ?3, end_time = ?4, duration = ?5, \
+     date = ?6, type = ?7, modified_at = ?8, timezone = ?9, notes = ?10, tags = ?11";
+
+/// Results of a search when the caller sets no limit
+pub const DEFAULT_SEARCH_LIMIT: u32 = 100;
+
+/// Filters for session queries. Dates are inclusive `YYYY-MM-DD` strings.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub project_id: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<SessionKind>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    Date,
+    Project,
+    Type,
+}
+
+impl GroupBy {
+    fn column(self) -> &'static str {
+        match self {
+            GroupBy::Date => "date",
+            GroupBy::Project => "project_id",
+            GroupBy::Type => "type",
+        }
+    }
+}
+
+/// A day waiting to be uploaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEntry {
+    pub date: NaiveDate,
+    pub revision: i64,
+    /// Failed uploads since the day was last synced
+    pub attempts: u32,
+    pub next_attempt: DateTime<Utc>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Total {
+    pub key: String,
+    /// Sum of session durations in seconds
+    pub seconds: u64,
+    pub count: u64,
+}
+
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        // The desktop app and the CLI may write at the same time
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        let mut db = Self { conn };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let version: i64 = tx.pragma_query_value(None, "user_version", |r| r.get(0))?;
+        if version as usize > MIGRATIONS.len() {
+            return Err(Error::Invalid(format!(
+                "history database schema {version} is newer than this build supports"
+            )));
+        }
+        for step in &MIGRATIONS[version as usize..] {
+            tx.execute_batch(step)?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len() as i64)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        let row: Option<i64> = self
+            .conn
+            .query_row("SELECT 1 FROM sessions LIMIT 1", [], |r| r.get(0))
+            .optional()?;
+        Ok(row.is_none())
+    }
+
+    pub fn insert_session(&self, session: &Session) -> Result<()> {
+        insert(&self.conn, session)
+    }
+
+    pub fn insert_sessions(&mut self, sessions: &[Session]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for session in sessions {
+            insert(&tx, session)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Which of `ids` belong to a stored or deleted session
+    pub fn known_ids(&self, ids: &[&str]) -> Result<HashSet<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT EXISTS (SELECT 1 FROM sessions WHERE id = ?1) \
+             OR EXISTS (SELECT 1 FROM session_tombstones WHERE id = ?1)",
+        )?;
+        let mut known = HashSet::new();
+        for id in ids {
+            if stmt.query_row([id], |row| row.get(0))? {
+                known.insert(id.to_string());
+            }
+        }
+        Ok(known)
+    }
+
+    pub fn session(&self, id: &str) -> Result<Option<Session>> {
+        Ok(self
+            .conn
+            .query_row(
+                &format!("SELECT {SESSION_COLUMNS} FROM sessions WHERE id = ?1"),
+                [id],
+                session_from_row,
+            )
+            .optional()?)
+    }
+
+    /// Replaces the stored session with the same id
+    pub fn update_session(&self, session: &Session) -> Result<()> {
+        let updated = self.conn.execute(
+            &format!("UPDATE sessions SET {SESSION_UPDATE} WHERE id = ?1"),
+            session_params(session),
+        )?;
+        if updated == 0 {
+            return Err(Error::NotFound(format!("session {}", session.id)));
+        }
+        Ok(())
+    }
+
+    /// Deletes a session, leaving a tombstone so the deletion syncs
+    pub fn delete_session(&mut self, id: &str, deleted_at: DateTime<Utc>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        if bury(&tx, "id", id, deleted_at)? == 0 {
+            return Err(Error::NotFound(format!("session {id}")));
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn delete_project_sessions(
+        &mut self,
+        project_id: &str,
+        deleted_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        bury(&tx, "project_id", project_id, deleted_at)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Tombstones of sessions that started between `from` and `to` (inclusive)
+    pub fn session_tombstones(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Tombstone>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, date, deleted_at FROM session_tombstones \
+             WHERE date >= ?1 AND date <= ?2 ORDER BY id",
+        )?;
+        let rows = stmt.query_map([from.to_string(), to.to_string()], |row| {
+            Ok(Tombstone {
+                id: row.get(0)?,
+                date: Some(parse_column(row, 1, parse_date)?),
+                deleted_at: parse_column(row, 2, parse_timestamp)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Stores the result of a sync merge: `sessions` replace any local copy
+    /// and `tombstones` delete theirs
+    pub fn apply_sessions(&mut self, sessions: &[Session], tombstones: &[Tombstone]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for session in sessions {
+            tx.execute(
+                &format!(
+                    "INSERT INTO sessions ({SESSION_COLUMNS}) VALUES ({SESSION_PARAMS}) \
+                     ON CONFLICT(id) DO UPDATE SET {SESSION_UPDATE}"
+                ),
+                session_params(session),
+            )?;
+            tx.execute(
+                "DELETE FROM session_tombstones WHERE id = ?1",
+                [&session.id],
+            )?;
+        }
+        for tombstone in tombstones {
+            // Only session tombstones carry a date; without one there is no
+            // day to file it under
+            let Some(date) = tombstone.date else {
+                continue;
+            };
+            tx.execute("DELETE FROM sessions WHERE id = ?1", [&tombstone.id])?;
+            tx.execute(
+                "INSERT INTO session_tombstones (id, date, deleted_at) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT(id) DO UPDATE SET date = ?2, deleted_at = MAX(deleted_at, ?3)",
+                params![
+                    tombstone.id,
+                    date.to_string(),
+                    format_timestamp(tombstone.deleted_at)
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    // ============================================
+    // SYNC OUTBOX
+    // ============================================
+
+    /// Queues `date` for upload. Session changes do this through triggers;
+    /// project changes call it directly.
+    pub fn mark_dirty(&self, date: NaiveDate) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO sync_outbox (date) VALUES (?1) \
+             ON CONFLICT(date) DO UPDATE SET revision = revision + 1",
+            [date.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Queues every day holding sessions or tombstones, e.g. to upload them
+    /// again under a new encryption key
+    pub fn mark_all_dirty(&self) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO sync_outbox (date) \
+             SELECT date FROM sessions UNION SELECT date FROM session_tombstones \
+             WHERE true ON CONFLICT(date) DO UPDATE SET revision = revision + 1",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Queued days, oldest first. With `due` set, only those whose next
+    /// attempt is not after it.
+    pub fn outbox(&self, due: Option<DateTime<Utc>>) -> Result<Vec<OutboxEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT date, revision, attempts, next_attempt, last_error FROM sync_outbox \
+             WHERE ?1 IS NULL OR next_attempt <= ?1 ORDER BY date",
+        )?;
+        let rows = stmt.query_map([due.map(format_timestamp)], |row| {
+            Ok(OutboxEntry {
+                date: parse_column(row, 0, parse_date)?,
+                revision: row.get(1)?,
+                attempts: row.get(2)?,
+                next_attempt: parse_column(row, 3, parse_timestamp)?,
+                last_error: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Current revision of a queued day, `None` if it is not queued
+    pub fn outbox_revision(&self, date: NaiveDate) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT revision FROM sync_outbox WHERE date = ?1",
+                [date.to_string()],
+                |r| r.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Removes `date` from the outbox unless it changed again after
+    /// `revision` was read. Returns whether the day is now clean.
+    pub fn mark_synced(&self, date: NaiveDate, revision: i64) -> Result<bool> {
+        let deleted = self.conn.execute(
+            "DELETE FROM sync_outbox WHERE date = ?1 AND revision = ?2",
+            params![date.to_string(), revision],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    pub fn mark_failed(
+        &self,
+        date: NaiveDate,
+        error: &str,
+        next_attempt: DateTime<Utc>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sync_outbox SET attempts = attempts + 1, last_error = ?2, next_attempt = ?3 \
+             WHERE date = ?1",
+            params![date.to_string(), error, format_timestamp(next_attempt)],
+        )?;
+        Ok(())
+    }
+
+    /// Sessions matching `query`, ordered by start time
+    pub fn sessions(&self, query: &SessionQuery) -> Result<Vec<Session>> {
+        let (filter, args) = where_clause(query);
+        let sql = format!("SELECT {SESSION_COLUMNS} FROM sessions{filter} ORDER BY start_time");
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), session_from_row)?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Sessions running at some point between `from` and `to`
+    pub fn overlapping(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Session>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {SESSION_COLUMNS} FROM sessions WHERE start_time < ?2 AND end_time > ?1 \
+             ORDER BY start_time"
+        ))?;
+        let rows = stmt.query_map(
+            [format_timestamp(from), format_timestamp(to)],
+            session_from_row,
+        )?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Stores a manual change in one transaction: `saved` sessions are
+    /// added or replace their stored version, `deleted` ones are removed
+    /// with a tombstone, and `edits` go to the audit trail
+    pub fn apply_edit(
+        &mut self,
+        saved: &[Session],
+        deleted: &[&str],
+        edits: &[SessionEdit],
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for session in saved {
+            tx.execute(
+                &format!(
+                    "INSERT INTO sessions ({SESSION_COLUMNS}) VALUES ({SESSION_PARAMS}) \
+                     ON CONFLICT(id) DO UPDATE SET {SESSION_UPDATE}"
+                ),
+                session_params(session),
+            )?;
+        }
+        for id in deleted {
+            if bury(&tx, "id", id, at)? == 0 {
+                return Err(Error::NotFound(format!("session {id}")));
+            }
+        }
+        for edit in edits {
+            let snapshot =
+                |session: &Option<Session>| session.as_ref().map(serde_json::to_string).transpose();
+            tx.execute(
+                "INSERT INTO session_edits (session_id, action, at, before, after) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    edit.session_id,
+                    edit.action.as_str(),
+                    format_timestamp(edit.at),
+                    snapshot(&edit.before)?,
+                    snapshot(&edit.after)?,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The audit trail, newest first: of one session, or of all of them
+    pub fn session_edits(&self, session_id: Option<&str>, limit: u32) -> Result<Vec<SessionEdit>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, session_id, action, at, before, after FROM session_edits \
+             WHERE ?1 IS NULL OR session_id = ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![session_id, limit], |row| {
+            let snapshot = |idx: usize| match row.get::<_, Option<String>>(idx)? {
+                Some(_) => parse_column(row, idx, |text| Ok(serde_json::from_str(text)?)).map(Some),
+                None => Ok(None),
+            };
+            Ok(SessionEdit {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                action: parse_column(row, 2, str::parse)?,
+                at: parse_column(row, 3, parse_timestamp)?,
+                before: snapshot(4)?,
+                after: snapshot(5)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Up to `limit` sessions matching `query` whose notes, tags or project
+    /// name match `text` (see `match_expression`), best matches first
+    pub fn search(&self, text: &str, query: &SessionQuery, limit: u32) -> Result<Vec<Session>> {
+        let Some(expression) = match_expression(text) else {
+            return Ok(Vec::new());
+        };
+        let (filter, mut args) = where_clause(query);
+        args.insert(0, Value::Text(expression));
+        args.push(Value::Integer(limit as i64));
+        let sql = format!(
+            "WITH hits AS (SELECT id, rank FROM session_search WHERE session_search MATCH ?) \
+             SELECT {SESSION_COLUMNS} FROM sessions JOIN hits USING (id){filter} \
+             ORDER BY hits.rank, start_time DESC LIMIT ?"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), session_from_row)?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Copies project names into the database for `search`
+    pub fn set_project_names(&mut self, projects: &[Project]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for project in projects {
+            tx.execute(
+                "INSERT INTO project_names (id, name) VALUES (?1, ?2) \
+                 ON CONFLICT(id) DO UPDATE SET name = ?2 WHERE name != ?2",
+                [&project.id, &project.name],
+            )?;
+        }
+        let ids: Vec<&str> = projects.iter().map(|p| p.id.as_str()).collect();
+        tx.execute(
+            "DELETE FROM project_names WHERE id NOT IN (SELECT value FROM json_each(?1))",
+            [serde_json::to_string(&ids)?],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Duration and count of the sessions matching `query`, grouped by `group_by`
+    pub fn totals(&self, query: &SessionQuery, group_by: GroupBy) -> Result<Vec<Total>> {
+        let (filter, args) = where_clause(query);
+        let column = group_by.column();
+        let sql = format!(
+            "SELECT {column}, SUM(duration), COUNT(*) FROM sessions{filter} \
+             GROUP BY {column} ORDER BY {column}"
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(args), |row| {
+            Ok(Total {
+                key: row.get(0)?,
+                seconds: row.get::<_, i64>(1)? as u64,
+                count: row.get::<_, i64>(2)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+}
+
+fn insert(conn: &Connection, session: &Session) -> Result<()> {
+    let result = conn.execute(
+        &format!("INSERT INTO sessions ({SESSION_COLUMNS}) VALUES ({SESSION_PARAMS})"),
+        session_params(session),
+    );
+    match result {
+        Ok(_) => Ok(()),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Err(Error::Invalid(format!(
+                "session {} already exists",
+                session.id
+            )))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn session_params(session: &Session) -> impl rusqlite::Params + '_ {
+    (
+        &session.id,
+        &session.project_id,
+        format_timestamp(session.start_time),
+        format_timestamp(session.end_time),
+        session.duration as i64,
+        session.date.to_string(),
+        session.kind.as_str(),
+        format_timestamp(session.modified_at),
+        session.timezone.map(|tz| tz.name()),
+        &session.notes,
+        serde_json::to_string(&session.tags).unwrap_or_else(|_| "[]".into()),
+    )
+}
+
+/// FTS5 query for what a user typed: every word must match, as a prefix
+/// of a word in the notes, tags or project name; `#word` only matches
+/// whole words of tags. Quotes keep FTS5 syntax out of the words.
+pub fn match_expression(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .filter_map(|word| match word.strip_prefix('#') {
+            Some(tag) => normalize_tag(tag).map(|tag| format!("tags : {}", quote(&tag))),
+            None => Some(format!("{}*", quote(word))),
+        })
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn quote(word: &str) -> String {
+    format!("\"{}\"", word.replace('"', "\"\""))
+}
+
+/// Deletes the sessions whose `column` equals `value`, recording a tombstone
+/// for each. Returns how many were deleted.
+fn bury(conn: &Connection, column: &str, value: &str, deleted_at: DateTime<Utc>) -> Result<usize> {
+    conn.execute(
+        &format!(
+            "INSERT INTO session_tombstones (id, date, deleted_at) \
+             SELECT id, date, ?2 FROM sessions WHERE {column} = ?1 \
+             ON CONFLICT(id) DO UPDATE SET date = excluded.date, deleted_at = excluded.deleted_at"
+        ),
+        params![value, format_timestamp(deleted_at)],
+    )?;
+    let deleted = conn.execute(
+        &format!("DELETE FROM sessions WHERE {column} = ?1"),
+        [value],
+    )?;
+    Ok(deleted)
+}
+
+fn where_clause(query: &SessionQuery) -> (String, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut args = Vec::new();
+
+    if let Some(from) = &query.from {
+        conditions.push("date >= ?");
+        args.push(Value::Text(from.clone()));
+    }
+    if let Some(to) = &query.to {
+        conditions.push("date <= ?");
+        args.push(Value::Text(to.clone()));
+    }
+    if let Some(project_id) = &query.project_id {
+        conditions.push("project_id = ?");
+        args.push(Value::Text(project_id.clone()));
+    }
+    if let Some(kind) = &query.kind {
+        conditions.push("type = ?");
+        args.push(Value::Text(kind.to_string()));
+    }
+
+    if conditions.is_empty() {
+        (String::new(), args)
+    } else {
+        (format!(" WHERE {}", conditions.join(" AND ")), args)
+    }
+}
+
+fn session_from_row(row: &Row) -> rusqlite::Result<Session> {
+    Ok(Session {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        start_time: parse_column(row, 2, parse_timestamp)?,
+        end_time: parse_column(row, 3, parse_timestamp)?,
+        duration: row.get::<_, i64>(4)? as u64,
+        kind: parse_column(row, 6, str::parse)?,
+        modified_at: parse_column(row, 7, parse_timestamp)?,
+        date: parse_column(row, 5, parse_date)?,
+        timezone: match row.get::<_, Option<String>>(8)? {
+            Some(_) => Some(parse_column(row, 8, parse_timezone)?),
+            None => None,
+        },
+        notes: row.get(9)?,
+        tags: parse_column(row, 10, |text| Ok(serde_json::from_str(text)?))?,
+    })
+}
+
+fn parse_column<T>(
+    row: &Row,
+    idx: usize,
+    parse: impl Fn(&str) -> Result<T>,
+) -> rusqlite::Result<T> {
+    let text: String = row.get(idx)?;
+    parse(&text)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editing::EditAction;
+    use crate::testing::{self, project, TempDir};
+
+    fn session(id: &str, project: &str, notes: Option<&str>, tags: &[&str]) -> Session {
+        Session {
+            notes: notes.map(Into::into),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..testing::session(id, project, "2025-03-03T09:00:00Z", 25)
+        }
+    }
+
+    fn ids(sessions: Vec<Session>) -> Vec<String> {
+        let mut ids: Vec<String> = sessions.into_iter().map(|s| s.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn builds_match_expressions() {
+        assert_eq!(match_expression("  "), None);
+        assert_eq!(
+            match_expression("Café #Review"),
+            Some(r#""Café"* tags : "review""#.into())
+        );
+        assert_eq!(
+            match_expression(r#"say"hi OR"#).unwrap(),
+            r#""say""hi"* "OR"*"#
+        );
+    }
+
+    #[test]
+    fn searches_notes_tags_and_project_names() {
+        let dir = TempDir::new("db-search");
+        let mut db = Database::open(&dir.path().join("history.db")).unwrap();
+        db.set_project_names(&[project("p1", "Website"), project("p2", "Docs")])
+            .unwrap();
+        db.insert_sessions(&[
+            session("s1", "p1", Some("Fixed the résumé upload"), &["bug"]),
+            session("s2", "p2", Some("Reviewed the bug report"), &[]),
+            session("s3", "p2", None, &["client-review"]),
+        ])
+        .unwrap();
+        let all = SessionQuery::default();
+
+        assert_eq!(ids(db.search("resume", &all, 10).unwrap()), ["s1"]);
+        assert_eq!(ids(db.search("bug", &all, 10).unwrap()), ["s1", "s2"]);
+        assert_eq!(ids(db.search("#bug", &all, 10).unwrap()), ["s1"]);
+        assert_eq!(
+            ids(db.search("doc review", &all, 10).unwrap()),
+            ["s2", "s3"]
+        );
+        let p1 = SessionQuery {
+            project_id: Some("p1".into()),
+            ..Default::default()
+        };
+        assert_eq!(ids(db.search("bug", &p1, 10).unwrap()), ["s1"]);
+        assert_eq!(db.search("bug", &all, 1).unwrap().len(), 1);
+
+        // Renames and edits are picked up
+        db.set_project_names(&[project("p1", "Landing page")])
+            .unwrap();
+        assert!(db.search("website", &all, 10).unwrap().is_empty());
+        assert_eq!(ids(db.search("landing", &all, 10).unwrap()), ["s1"]);
+        assert!(db.search("docs", &all, 10).unwrap().is_empty());
+        let mut edited = db.session("s3").unwrap().unwrap();
+        assert_eq!(edited.tags, ["client-review"]);
+        edited.notes = Some("Call with Acme".into());
+        db.update_session(&edited).unwrap();
+        assert_eq!(ids(db.search("acme", &all, 10).unwrap()), ["s3"]);
+
+        db.delete_session("s1", Utc::now()).unwrap();
+        assert!(db.search("landing", &all, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn records_manual_edits() {
+        let dir = TempDir::new("db-edits");
+        let mut db = Database::open(&dir.path().join("history.db")).unwrap();
+        let now = Utc::now();
+        let first = session("s1", "p1", None, &[]);
+        db.apply_edit(
+            std::slice::from_ref(&first),
+            &[],
+            &[SessionEdit::new(
+                EditAction::Create,
+                None,
+                Some(&first),
+                now,
+            )],
+            now,
+        )
+        .unwrap();
+        let edited = Session {
+            notes: Some("Fixed".into()),
+            ..first.clone()
+        };
+        db.apply_edit(
+            std::slice::from_ref(&edited),
+            &[],
+            &[SessionEdit::new(
+                EditAction::Edit,
+                Some(&first),
+                Some(&edited),
+                now,
+            )],
+            now,
+        )
+        .unwrap();
+        db.apply_edit(
+            &[],
+            &["s1"],
+            &[SessionEdit::new(
+                EditAction::Delete,
+                Some(&edited),
+                None,
+                now,
+            )],
+            now,
+        )
+        .unwrap();
+
+        let edits = db.session_edits(Some("s1"), 10).unwrap();
+        let actions: Vec<_> = edits.iter().map(|e| e.action).collect();
+        assert_eq!(
+            actions,
+            [EditAction::Delete, EditAction::Edit, EditAction::Create]
+        );
+        assert_eq!(edits[1].before.as_ref(), Some(&first));
+        assert_eq!(edits[1].after.as_ref(), Some(&edited));
+        assert_eq!(edits[0].after, None);
+        assert!(db.session("s1").unwrap().is_none());
+        assert!(db.session_edits(Some("s2"), 10).unwrap().is_empty());
+        assert_eq!(db.session_edits(None, 1).unwrap().len(), 1);
+
+        // Nothing is left half done
+        assert!(db.apply_edit(&[], &["s1"], &[], now).is_err());
+    }
+
+    #[test]
+    fn queries_ranges_and_totals() {
+        let dir = TempDir::new("db-range");
+        let mut db = Database::open(&dir.path().join("history.db")).unwrap();
+        let break_time = Session {
+            kind: SessionKind::Break,
+            ..testing::session("s4", "break", "2025-03-04T10:00:00Z", 5)
+        };
+        db.insert_sessions(&[
+            testing::session("s1", "p1", "2025-03-02T09:00:00Z", 30),
+            testing::session("s2", "p1", "2025-03-03T09:00:00Z", 25),
+            testing::session("s3", "p2", "2025-03-04T09:00:00Z", 50),
+            break_time,
+        ])
+        .unwrap();
+
+        // Both ends of the range are included
+        let range = SessionQuery {
+            from: Some("2025-03-03".into()),
+            to: Some("2025-03-04".into()),
+            ..Default::default()
+        };
+        assert_eq!(ids(db.sessions(&range).unwrap()), ["s2", "s3", "s4"]);
+        let work = SessionQuery {
+            kind: Some(SessionKind::Work),
+            ..range.clone()
+        };
+        assert_eq!(ids(db.sessions(&work).unwrap()), ["s2", "s3"]);
+        let p1 = SessionQuery {
+            project_id: Some("p1".into()),
+            ..Default::default()
+        };
+        assert_eq!(ids(db.sessions(&p1).unwrap()), ["s1", "s2"]);
+
+        let by_date = db.totals(&work, GroupBy::Date).unwrap();
+        assert_eq!(
+            by_date,
+            [
+                Total {
+                    key: "2025-03-03".into(),
+                    seconds: 1500,
+                    count: 1
+                },
+                Total {
+                    key: "2025-03-04".into(),
+                    seconds: 3000,
+                    count: 1
+                },
+            ]
+        );
+        let by_project = db
+            .totals(&SessionQuery::default(), GroupBy::Project)
+            .unwrap();
+        let by_project: Vec<_> = by_project
+            .iter()
+            .map(|t| (t.key.as_str(), t.seconds, t.count))
+            .collect();
+        assert_eq!(
+            by_project,
+            [("break", 300, 1), ("p1", 3300, 2), ("p2", 3000, 1)]
+        );
+        let none = SessionQuery {
+            from: Some("2025-04-01".into()),
+            ..Default::default()
+        };
+        assert!(db.totals(&none, GroupBy::Type).unwrap().is_empty());
+    }
+
+    #[test]
+    fn range_queries_use_the_indexes() {
+        let dir = TempDir::new("db-indexes");
+        let db = Database::open(&dir.path().join("history.db")).unwrap();
+        let week = SessionQuery {
+            from: Some("2025-03-03".into()),
+            to: Some("2025-03-09".into()),
+            ..Default::default()
+        };
+        let project = SessionQuery {
+            project_id: Some("p1".into()),
+            ..week.clone()
+        };
+        let breaks = SessionQuery {
+            kind: Some(SessionKind::Break),
+            ..Default::default()
+        };
+        for (query, index) in [
+            (week, "idx_sessions_date"),
+            (project, "idx_sessions_project"),
+            (breaks, "idx_sessions_type"),
+        ] {
+            let (filter, args) = where_clause(&query);
+            let sql = format!("EXPLAIN QUERY PLAN SELECT id FROM sessions{filter}");
+            let mut stmt = db.conn.prepare(&sql).unwrap();
+            let plan: Vec<String> = stmt
+                .query_map(params_from_iter(args), |row| row.get(3))
+                .unwrap()
+                .collect::<std::result::Result<_, _>>()
+                .unwrap();
+            assert!(
+                plan.iter().any(|step| step.contains(index)),
+                "{index} not in {plan:?}"
+            );
+        }
+    }
+}
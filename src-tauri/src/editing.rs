@@ -0,0 +1,392 @@
+// Productivity Tracker - Manual session editing
+// Sessions entered by hand and corrections of recorded ones: edits, splits,
+// merges and deletions. Nothing may end in the future or overlap another
+// session. Every change is recorded in the audit trail of history.db, which
+// stays on this device.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::model::{self, Session, SessionKind};
+use crate::store::new_id;
+use crate::timer::BREAK_ID;
+
+/// Entries of the audit trail returned when the caller sets no limit
+pub const DEFAULT_EDITS_LIMIT: u32 = 50;
+
+/// A session as entered or corrected by hand
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionEntry {
+    pub project_id: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    #[serde(rename = "type")]
+    pub kind: SessionKind,
+    /// Tracked seconds. Without one an edit keeps the tracked time if the
+    /// span is unchanged, and otherwise the whole span counts.
+    #[serde(default)]
+    pub duration: Option<u64>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// As typed, see `model::parse_tags`
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditAction {
+    Create,
+    Edit,
+    /// Cut in two, or an edit that ran past the start of a day
+    Split,
+    /// Combined with another session of the same project
+    Merge,
+    Delete,
+}
+
+impl EditAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EditAction::Create => "create",
+            EditAction::Edit => "edit",
+            EditAction::Split => "split",
+            EditAction::Merge => "merge",
+            EditAction::Delete => "delete",
+        }
+    }
+}
+
+impl fmt::Display for EditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EditAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "create" => Ok(EditAction::Create),
+            "edit" => Ok(EditAction::Edit),
+            "split" => Ok(EditAction::Split),
+            "merge" => Ok(EditAction::Merge),
+            "delete" => Ok(EditAction::Delete),
+            other => Err(Error::Invalid(format!("unknown edit action {other:?}"))),
+        }
+    }
+}
+
+/// One entry of the audit trail: a session before and after a change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionEdit {
+    /// Assigned by the database, 0 until saved
+    pub id: i64,
+    pub session_id: String,
+    pub action: EditAction,
+    #[serde(with = "model::timestamp")]
+    pub at: DateTime<Utc>,
+    /// `None` for a created session
+    pub before: Option<Session>,
+    /// `None` for a deleted one
+    pub after: Option<Session>,
+}
+
+impl SessionEdit {
+    pub fn new(
+        action: EditAction,
+        before: Option<&Session>,
+        after: Option<&Session>,
+        at: DateTime<Utc>,
+    ) -> Self {
+        let session_id = after
+            .or(before)
+            .map(|session| session.id.clone())
+            .unwrap_or_default();
+        SessionEdit {
+            id: 0,
+            session_id,
+            action,
+            at,
+            before: before.cloned(),
+            after: after.cloned(),
+        }
+    }
+}
+
+/// The session `entry` describes, with the id and timezone of `base` when
+/// it corrects one. Breaks belong to no project. Still to be filed under
+/// its day (see `Calendar::file`).
+pub fn build(
+    entry: &SessionEntry,
+    base: Option<&Session>,
+    timezone: Tz,
+    now: DateTime<Utc>,
+) -> Result<Session> {
+    check_span(entry.start_time, entry.end_time, now)?;
+    let span = (entry.end_time - entry.start_time).num_seconds() as u64;
+    let duration = match (entry.duration, base) {
+        (Some(duration), _) => duration,
+        (None, Some(base))
+            if base.start_time == entry.start_time && base.end_time == entry.end_time =>
+        {
+            base.duration
+        }
+        (None, _) => span,
+    };
+    let session = Session {
+        id: base.map_or_else(new_id, |base| base.id.clone()),
+        project_id: match entry.kind {
+            SessionKind::Break => BREAK_ID.to_string(),
+            _ => entry.project_id.clone(),
+        },
+        start_time: entry.start_time,
+        end_time: entry.end_time,
+        duration,
+        kind: entry.kind,
+        modified_at: now,
+        date: entry.start_time.date_naive(),
+        timezone: Some(base.and_then(|base| base.timezone).unwrap_or(timezone)),
+        notes: entry
+            .notes
+            .as_deref()
+            .map(str::trim)
+            .filter(|notes| !notes.is_empty())
+            .map(String::from),
+        tags: model::parse_tags(&entry.tags)?,
+    };
+    session.validate()?;
+    Ok(session)
+}
+
+fn check_span(start: DateTime<Utc>, end: DateTime<Utc>, now: DateTime<Utc>) -> Result<()> {
+    if end <= start {
+        return Err(Error::Invalid("a session must end after it starts".into()));
+    }
+    if end > now {
+        return Err(Error::Invalid("a session can't end in the future".into()));
+    }
+    Ok(())
+}
+
+/// Fails if one of `sessions` overlaps one of `others` that it does not
+/// replace. Sessions touching end to start don't overlap.
+pub fn check_overlaps(sessions: &[Session], others: &[Session], replaced: &[&str]) -> Result<()> {
+    for session in sessions {
+        let clash = others.iter().find(|other| {
+            !replaced.contains(&other.id.as_str())
+                && other.start_time < session.end_time
+                && other.end_time > session.start_time
+        });
+        if let Some(other) = clash {
+            let tz = other.timezone.unwrap_or(Tz::UTC);
+            return Err(Error::Invalid(format!(
+                "overlaps the {} session of {} from {} to {}",
+                other.kind,
+                other.date,
+                other.start_time.with_timezone(&tz).format("%H:%M"),
+                other.end_time.with_timezone(&tz).format("%H:%M"),
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Cuts `session` at `at`, sharing the tracked time in proportion to each
+/// part's span. The first part keeps the id; both keep notes and tags.
+pub fn split(session: &Session, at: DateTime<Utc>, now: DateTime<Utc>) -> Result<[Session; 2]> {
+    if at <= session.start_time || at >= session.end_time {
+        return Err(Error::Invalid(
+            "a session can only be split between its start and end".into(),
+        ));
+    }
+    let span = (session.end_time - session.start_time).num_seconds() as u64;
+    let elapsed = (at - session.start_time).num_seconds() as u64;
+    let first_duration = session.duration * elapsed / span;
+    let first = Session {
+        end_time: at,
+        duration: first_duration,
+        modified_at: now,
+        ..session.clone()
+    };
+    let second = Session {
+        id: new_id(),
+        start_time: at,
+        duration: session.duration - first_duration,
+        modified_at: now,
+        ..session.clone()
+    };
+    Ok([first, second])
+}
+
+/// Combines sessions of one project, type and day into one running from the
+/// first start to the last end; gaps between them count as pauses. The
+/// earliest keeps its id, notes are joined and tags combined.
+pub fn merge(mut sessions: Vec<Session>, now: DateTime<Utc>) -> Result<Session> {
+    if sessions.len() < 2 {
+        return Err(Error::Invalid("merging needs two sessions or more".into()));
+    }
+    sessions.sort_by_key(|session| session.start_time);
+    let first = &sessions[0];
+    if sessions.iter().any(|s| s.project_id != first.project_id) {
+        return Err(Error::Invalid(
+            "only sessions of the same project can be merged".into(),
+        ));
+    }
+    if sessions.iter().any(|s| s.kind != first.kind) {
+        return Err(Error::Invalid(
+            "only sessions of the same type can be merged".into(),
+        ));
+    }
+    if sessions.iter().any(|s| s.date != first.date) {
+        return Err(Error::Invalid(
+            "only sessions of the same day can be merged".into(),
+        ));
+    }
+
+    let mut notes: Vec<&str> = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
+    for session in &sessions {
+        if let Some(text) = session.notes.as_deref() {
+            if !notes.contains(&text) {
+                notes.push(text);
+            }
+        }
+        for tag in &session.tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+    }
+    let merged = Session {
+        end_time: sessions
+            .iter()
+            .map(|s| s.end_time)
+            .max()
+            .unwrap_or(first.end_time),
+        duration: sessions.iter().map(|s| s.duration).sum(),
+        modified_at: now,
+        notes: (!notes.is_empty()).then(|| notes.join("\n\n")),
+        tags,
+        ..first.clone()
+    };
+    merged.validate()?;
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, at};
+
+    fn session(id: &str, start: &str, minutes: i64) -> Session {
+        Session {
+            timezone: Some(Tz::UTC),
+            ..testing::session(id, "p1", start, minutes)
+        }
+    }
+
+    fn entry(start: &str, end: &str) -> SessionEntry {
+        SessionEntry {
+            project_id: "p1".into(),
+            start_time: at(start),
+            end_time: at(end),
+            kind: SessionKind::Work,
+            duration: None,
+            notes: Some("  ".into()),
+            tags: vec!["#Client".into()],
+        }
+    }
+
+    #[test]
+    fn builds_entries_and_corrections() {
+        let now = at("2025-01-15T18:00:00Z");
+        let new = build(
+            &entry("2025-01-15T09:00:00Z", "2025-01-15T10:30:00Z"),
+            None,
+            Tz::UTC,
+            now,
+        )
+        .unwrap();
+        assert_eq!(new.duration, 90 * 60);
+        assert_eq!((new.notes, new.tags), (None, vec!["client".to_string()]));
+
+        // A forgotten timer: the paused time is kept only while the span is
+        let base = Session {
+            duration: 8 * 3600,
+            timezone: Some(chrono_tz::Europe::Rome),
+            ..session("s1", "2025-01-15T09:00:00Z", 9 * 60)
+        };
+        let same = entry("2025-01-15T09:00:00Z", "2025-01-15T18:00:00Z");
+        let kept = build(&same, Some(&base), Tz::UTC, now).unwrap();
+        assert_eq!((kept.id.as_str(), kept.duration), ("s1", 8 * 3600));
+        assert_eq!(kept.timezone, Some(chrono_tz::Europe::Rome));
+        let shorter = entry("2025-01-15T09:00:00Z", "2025-01-15T11:00:00Z");
+        let fixed = build(&shorter, Some(&base), Tz::UTC, now).unwrap();
+        assert_eq!((fixed.duration, fixed.modified_at), (2 * 3600, now));
+
+        let future = entry("2025-01-15T17:00:00Z", "2025-01-15T18:30:00Z");
+        assert!(build(&future, None, Tz::UTC, now).is_err());
+        let backwards = entry("2025-01-15T10:00:00Z", "2025-01-15T09:00:00Z");
+        assert!(build(&backwards, None, Tz::UTC, now).is_err());
+    }
+
+    #[test]
+    fn finds_overlaps() {
+        let others = [
+            session("a", "2025-01-15T09:00:00Z", 60),
+            session("b", "2025-01-15T11:00:00Z", 60),
+        ];
+        let touching = session("new", "2025-01-15T10:00:00Z", 60);
+        assert!(check_overlaps(&[touching], &others, &[]).is_ok());
+        let clashing = session("new", "2025-01-15T09:30:00Z", 60);
+        let err = check_overlaps(std::slice::from_ref(&clashing), &others, &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid data: overlaps the work session of 2025-01-15 from 09:00 to 10:00"
+        );
+        assert!(check_overlaps(&[clashing], &others, &["a"]).is_ok());
+    }
+
+    #[test]
+    fn splits_and_merges() {
+        let now = at("2025-01-16T00:00:00Z");
+        let mut original = session("s1", "2025-01-15T09:00:00Z", 60);
+        original.duration = 50 * 60;
+        original.tags = vec!["client".into()];
+        let [first, second] = split(&original, at("2025-01-15T09:15:00Z"), now).unwrap();
+        assert_eq!((first.id.as_str(), first.duration), ("s1", 12 * 60 + 30));
+        assert_eq!(second.duration, 37 * 60 + 30);
+        assert_eq!(first.end_time, second.start_time);
+        assert_ne!(second.id, "s1");
+        assert_eq!(second.tags, ["client"]);
+        assert!(split(&original, original.end_time, now).is_err());
+
+        let later = Session {
+            notes: Some("Review".into()),
+            tags: vec!["review".into(), "client".into()],
+            ..session("s2", "2025-01-15T10:30:00Z", 30)
+        };
+        let merged = merge(vec![later.clone(), original.clone()], now).unwrap();
+        assert_eq!(merged.id, "s1");
+        assert_eq!(merged.end_time, later.end_time);
+        assert_eq!(merged.duration, 80 * 60);
+        assert_eq!(merged.notes.as_deref(), Some("Review"));
+        assert_eq!(merged.tags, ["client", "review"]);
+
+        let other_project = Session {
+            project_id: "p2".into(),
+            ..later
+        };
+        assert!(merge(vec![original.clone(), other_project], now).is_err());
+        assert!(merge(vec![original], now).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+// Productivity Tracker - Backend errors
+
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+
+    #[error("network error: {0}")]
+    Http(#[from] tauri_plugin_http::reqwest::Error),
+
+    /// The sync endpoint answered with an error
+    #[error("sync failed: {0}")]
+    Sync(String),
+
+    /// Sync encryption: wrong passphrase, locked keyring, corrupt data...
+    #[error("encryption: {0}")]
+    Crypto(String),
+
+    #[error("AI provider: {0}")]
+    Ai(#[from] crate::insights::AiError),
+
+    /// OS keychain or the encrypted secrets file
+    #[error("secret storage: {0}")]
+    Secret(String),
+
+    /// The OS refused to register a global shortcut
+    #[error("global shortcuts: {0}")]
+    Shortcut(String),
+
+    #[error("{0} not found")]
+    NotFound(String),
+
+    #[error("invalid data: {0}")]
+    Invalid(String),
+
+    /// A data file failed to parse or validate
+    #[error("{file} is malformed: {source}")]
+    Malformed {
+        file: &'static str,
+        source: Box<Error>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// Commands return errors to the webview as plain strings
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
@@ -0,0 +1,366 @@
+// Productivity Tracker - Session export
+// Writes sessions to CSV (spreadsheets, invoicing), JSON Lines (one
+// session per line, in the same shape as everywhere else) or iCalendar
+// (each session an event, for calendar apps).
+
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::db::SessionQuery;
+use crate::error::{Error, Result};
+use crate::model::{Project, Session};
+use crate::store::Store;
+use crate::timer::BREAK_ID;
+
+/// iCalendar lines are folded to this many bytes (RFC 5545, 3.1)
+const ICS_LINE_LIMIT: usize = 75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    Ics,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Ics => "ics",
+        }
+    }
+
+    /// Name for the save dialog's file type filter
+    pub fn description(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Jsonl => "JSON Lines",
+            ExportFormat::Ics => "iCalendar",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" => Ok(ExportFormat::Jsonl),
+            "ics" => Ok(ExportFormat::Ics),
+            other => Err(Error::Invalid(format!(
+                "unknown export format {other:?} (csv, jsonl or ics)"
+            ))),
+        }
+    }
+}
+
+/// Writes the sessions matching `query`; returns how many
+pub fn export_sessions(
+    store: &Store,
+    query: &SessionQuery,
+    format: ExportFormat,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    let sessions = store.sessions(query)?;
+    export(&sessions, &store.projects()?, format, out)?;
+    Ok(sessions.len())
+}
+
+/// Writes `sessions` in `format`; `projects` give their names
+pub fn export(
+    sessions: &[Session],
+    projects: &[Project],
+    format: ExportFormat,
+    out: &mut dyn Write,
+) -> Result<()> {
+    match format {
+        ExportFormat::Csv => write_csv(sessions, projects, out),
+        ExportFormat::Jsonl => write_jsonl(sessions, out),
+        ExportFormat::Ics => write_ics(sessions, projects, out),
+    }
+}
+
+fn project_name<'a>(projects: &'a [Project], id: &'a str) -> &'a str {
+    if id == BREAK_ID {
+        return "Break";
+    }
+    projects
+        .iter()
+        .find(|p| p.id == id)
+        .map_or(id, |p| p.name.as_str())
+}
+
+// ============================================
+// CSV
+// ============================================
+
+const CSV_HEADER: &str =
+    "date,start,end,duration_seconds,duration_hours,project,project_id,type,timezone,id,tags,notes";
+
+/// One row per session. Times are in the session's own timezone with
+/// their offset, so spreadsheets show the hours as they were lived.
+fn write_csv(sessions: &[Session], projects: &[Project], out: &mut dyn Write) -> Result<()> {
+    write!(out, "{CSV_HEADER}\r\n")?;
+    for session in sessions {
+        let tz = session.timezone.unwrap_or(Tz::UTC);
+        let row = [
+            session.date.to_string(),
+            local_time(session.start_time, tz),
+            local_time(session.end_time, tz),
+            session.duration.to_string(),
+            format!("{:.2}", session.duration as f64 / 3600.0),
+            project_name(projects, &session.project_id).to_string(),
+            session.project_id.clone(),
+            session.kind.to_string(),
+            tz.name().to_string(),
+            session.id.clone(),
+            session.tags.join(", "),
+            session.notes.clone().unwrap_or_default(),
+        ];
+        let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        write!(out, "{}\r\n", row.join(","))?;
+    }
+    Ok(())
+}
+
+fn local_time(time: DateTime<Utc>, tz: Tz) -> String {
+    time.with_timezone(&tz)
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Quoted when it holds a separator, quote or line break (RFC 4180).
+/// Fields starting like a formula get a leading quote, so spreadsheets show
+/// them as text instead of running them.
+fn csv_field(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@']) {
+        format!("'{field}")
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+// ============================================
+// JSON LINES
+// ============================================
+
+fn write_jsonl(sessions: &[Session], out: &mut dyn Write) -> Result<()> {
+    for session in sessions {
+        serde_json::to_writer(&mut *out, session)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+// ============================================
+// ICALENDAR
+// ============================================
+
+fn write_ics(sessions: &[Session], projects: &[Project], out: &mut dyn Write) -> Result<()> {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Productivity Tracker//Sessions//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    for session in sessions {
+        let minutes = session.duration / 60;
+        let mut description = format!(
+            "{} session, {}h {:02}m tracked",
+            session.kind,
+            minutes / 60,
+            minutes % 60
+        );
+        if let Some(notes) = &session.notes {
+            description.push_str("\n\n");
+            description.push_str(notes);
+        }
+        let categories: Vec<String> = std::iter::once(session.kind.as_str())
+            .chain(session.tags.iter().map(String::as_str))
+            .map(ics_text)
+            .collect();
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@productivity-tracker", session.id),
+            format!(
+                "DTSTAMP:{}",
+                ics_time(session.modified_at.max(session.end_time))
+            ),
+            format!("DTSTART:{}", ics_time(session.start_time)),
+            format!("DTEND:{}", ics_time(session.end_time)),
+            format!(
+                "SUMMARY:{}",
+                ics_text(project_name(projects, &session.project_id))
+            ),
+            format!("DESCRIPTION:{}", ics_text(&description)),
+            format!("CATEGORIES:{}", categories.join(",")),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    for line in lines {
+        out.write_all(fold(&line).as_bytes())?;
+    }
+    Ok(())
+}
+
+fn ics_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escapes a TEXT value (RFC 5545, 3.3.11)
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line into lines of at most `ICS_LINE_LIMIT` bytes,
+/// continuation lines starting with a space, never inside a character
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > ICS_LINE_LIMIT {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, project};
+
+    fn session(id: &str, project: &str, start: &str, minutes: i64) -> Session {
+        Session {
+            timezone: Some(chrono_tz::Europe::Rome),
+            ..testing::session(id, project, start, minutes)
+        }
+    }
+
+    fn exported(sessions: &[Session], projects: &[Project], format: ExportFormat) -> String {
+        let mut out = Vec::new();
+        export(sessions, projects, format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_rows_in_local_time() {
+        let sessions = [
+            Session {
+                notes: Some("Line one\nline two".into()),
+                tags: vec!["client".into(), "review".into()],
+                ..session("s1", "p1", "2025-01-15T08:00:00Z", 90)
+            },
+            session("s2", "gone", "2025-01-15T10:00:00Z", 25),
+        ];
+        let csv = exported(
+            &sessions,
+            &[project("p1", "Acme, Inc. \"web\"")],
+            ExportFormat::Csv,
+        );
+        let rows: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(rows[0], CSV_HEADER);
+        assert_eq!(
+            rows[1],
+            "2025-01-15,2025-01-15T09:00:00+01:00,2025-01-15T10:30:00+01:00,5400,1.50,\
+             \"Acme, Inc. \"\"web\"\"\",p1,work,Europe/Rome,s1,\"client, review\",\
+             \"Line one\nline two\""
+        );
+        assert!(rows[2].contains(",0.42,gone,gone,work,"));
+        assert!(rows[2].ends_with(",s2,,"));
+        assert_eq!(rows.len(), 4);
+    }
+
+    #[test]
+    fn csv_fields_never_run_as_formulas() {
+        assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_field("-5"), "'-5");
+        assert_eq!(csv_field("plain"), "plain");
+    }
+
+    #[test]
+    fn jsonl_round_trips() {
+        let sessions = [
+            session("s1", "p1", "2025-01-15T08:00:00Z", 90),
+            session("s2", "p1", "2025-01-15T10:00:00Z", 25),
+        ];
+        let jsonl = exported(&sessions, &[], ExportFormat::Jsonl);
+        let parsed: Vec<Session> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(parsed, sessions);
+    }
+
+    #[test]
+    fn ics_events() {
+        let sessions = [Session {
+            notes: Some("Call, then fixes".into()),
+            tags: vec!["client".into()],
+            ..session("s1", "p1", "2025-01-15T08:00:00Z", 90)
+        }];
+        let ics = exported(
+            &sessions,
+            &[project("p1", "Client; Work")],
+            ExportFormat::Ics,
+        );
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(ics.contains("\r\nUID:s1@productivity-tracker\r\n"));
+        assert!(ics.contains("\r\nDTSTART:20250115T080000Z\r\nDTEND:20250115T093000Z\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:Client\\; Work\r\n"));
+        assert!(ics.contains(
+            "\r\nDESCRIPTION:work session\\, 1h 30m tracked\\n\\nCall\\, then fixes\r\n"
+        ));
+        assert!(ics.contains("\r\nCATEGORIES:work,client\r\n"));
+    }
+
+    #[test]
+    fn long_ics_lines_are_folded() {
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+        let folded = fold(&line);
+        let lines: Vec<&str> = folded.trim_end().split("\r\n").collect();
+        assert!(lines.iter().all(|l| l.len() <= ICS_LINE_LIMIT));
+        assert!(lines[1..].iter().all(|l| l.starts_with(' ')));
+        let unfolded: String = lines
+            .iter()
+            .map(|l| l.strip_prefix(' ').unwrap_or(l))
+            .collect();
+        assert_eq!(unfolded, line);
+    }
+
+    #[test]
+    fn formats_by_name() {
+        assert_eq!("ics".parse::<ExportFormat>().unwrap(), ExportFormat::Ics);
+        assert!("pdf".parse::<ExportFormat>().is_err());
+    }
+}
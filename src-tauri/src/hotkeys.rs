@@ -0,0 +1,160 @@
+// Productivity Tracker - Shortcut settings
+// Which system-wide key combination drives which timer action, kept in
+// settings.json and checked before saving. Combinations are accelerator
+// strings such as "CommandOrControl+Alt+P"; shortcuts.rs registers them
+// with the OS.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::model::Project;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    /// Pause or resume the timer, or start the last project
+    ToggleTimer,
+    /// Start the break the pomodoro cycle is due
+    StartBreak,
+    /// Switch the timer to the next project
+    NextProject,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HotkeySettings {
+    pub enabled: bool,
+    /// `None` leaves the action without a shortcut
+    pub toggle_timer: Option<String>,
+    pub start_break: Option<String>,
+    pub next_project: Option<String>,
+}
+
+impl Default for HotkeySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            toggle_timer: Some("CommandOrControl+Alt+P".into()),
+            start_break: Some("CommandOrControl+Alt+B".into()),
+            next_project: Some("CommandOrControl+Alt+N".into()),
+        }
+    }
+}
+
+impl HotkeySettings {
+    /// Shape only; whether the OS accepts a combination shows when it is
+    /// registered
+    pub fn validate(&self) -> Result<()> {
+        let bindings = self.all_bindings();
+        for (i, (_, keys)) in bindings.iter().enumerate() {
+            if keys.trim().is_empty() || keys.split('+').any(|key| key.trim().is_empty()) {
+                return Err(Error::Invalid(format!("shortcut \"{keys}\" is incomplete")));
+            }
+            if bindings[..i]
+                .iter()
+                .any(|(_, other)| normalize(other) == normalize(keys))
+            {
+                return Err(Error::Invalid(format!("{keys} is used for two shortcuts")));
+            }
+        }
+        Ok(())
+    }
+
+    /// The shortcuts to register: none while disabled
+    pub fn bindings(&self) -> Vec<(HotkeyAction, &str)> {
+        if self.enabled {
+            self.all_bindings()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn all_bindings(&self) -> Vec<(HotkeyAction, &str)> {
+        [
+            (HotkeyAction::ToggleTimer, &self.toggle_timer),
+            (HotkeyAction::StartBreak, &self.start_break),
+            (HotkeyAction::NextProject, &self.next_project),
+        ]
+        .into_iter()
+        .filter_map(|(action, keys)| Some((action, keys.as_deref()?)))
+        .collect()
+    }
+}
+
+fn normalize(keys: &str) -> String {
+    keys.split('+')
+        .map(|key| key.trim().to_lowercase())
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// The project after `current` among those that can be timed, wrapping
+/// around; the first one if `current` is `None` or not among them
+pub fn next_project<'a>(projects: &'a [Project], current: Option<&str>) -> Option<&'a Project> {
+    let active: Vec<&Project> = projects.iter().filter(|p| p.is_active()).collect();
+    let next = current
+        .and_then(|id| active.iter().position(|p| p.id == id))
+        .map_or(0, |i| (i + 1) % active.len());
+    active.get(next).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn project(id: &str, archived: bool) -> Project {
+        Project {
+            archived,
+            ..testing::project(id, id)
+        }
+    }
+
+    #[test]
+    fn cycles_through_active_projects() {
+        let projects = [
+            project("web", false),
+            project("old", true),
+            project("api", false),
+        ];
+        let next = |current| next_project(&projects, current).map(|p| p.id.as_str());
+        assert_eq!(next(None), Some("web"));
+        assert_eq!(next(Some("web")), Some("api"));
+        assert_eq!(next(Some("api")), Some("web"));
+        // Archived since the timer started
+        assert_eq!(next(Some("old")), Some("web"));
+        assert_eq!(next_project(&projects[1..2], None), None);
+    }
+
+    #[test]
+    fn validates_bindings() {
+        let settings = HotkeySettings::default();
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.bindings().len(), 3);
+
+        let settings = HotkeySettings {
+            enabled: false,
+            next_project: None,
+            ..Default::default()
+        };
+        assert!(settings.validate().is_ok());
+        assert!(settings.bindings().is_empty());
+
+        let invalid = [
+            HotkeySettings {
+                start_break: Some("commandorcontrol + alt + p".into()),
+                ..Default::default()
+            },
+            HotkeySettings {
+                toggle_timer: Some("Alt+".into()),
+                ..Default::default()
+            },
+            HotkeySettings {
+                next_project: Some(" ".into()),
+                ..Default::default()
+            },
+        ];
+        for settings in invalid {
+            assert!(settings.validate().is_err());
+        }
+    }
+}
@@ -0,0 +1,499 @@
+// Productivity Tracker - Idle detection
+// Notices when nobody has touched the keyboard or mouse for a while during
+// a running timer. Past the threshold the timer is either paused where the
+// idle time began, or keeps running and, on return, the user is asked
+// whether to keep, discard or give the idle minutes to another project.
+// Idle time comes from an `IdleSource`: the Wayland compositor or the X
+// server on Linux, a fake one in tests.
+
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(target_os = "linux")]
+mod x11;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::editing::SessionEntry;
+use crate::error::{Error, Result};
+use crate::model::{Session, SessionKind};
+use crate::store::Store;
+use crate::timer::{TimerEngine, TimerStatus, BREAK_ID};
+
+pub const DEFAULT_IDLE_MINUTES: u32 = 5;
+pub const MAX_IDLE_MINUTES: u32 = 240;
+
+pub trait IdleSource: Send {
+    /// Seconds since the last keyboard or mouse input; `None` if that
+    /// can't be told right now
+    fn idle_seconds(&mut self) -> Option<u64>;
+}
+
+/// The idle source of the desktop session, if the platform offers one
+pub fn system_source() -> Option<Box<dyn IdleSource>> {
+    #[cfg(target_os = "linux")]
+    {
+        // Under Wayland the X server only sees XWayland windows, so its
+        // idle time would grow while the user works in native ones
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return wayland::WaylandIdle::connect().map(|s| Box::new(s) as Box<dyn IdleSource>);
+        }
+        if std::env::var_os("DISPLAY").is_some() {
+            return x11::X11Idle::connect().map(|s| Box::new(s) as Box<dyn IdleSource>);
+        }
+    }
+    None
+}
+
+/// What happens once the threshold is passed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdleAction {
+    /// Keep timing and ask on return
+    #[default]
+    Ask,
+    /// Pause the timer where the idle time began
+    Pause,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IdleSettings {
+    pub enabled: bool,
+    /// Minutes without input before the user counts as away
+    pub threshold_minutes: u32,
+    pub action: IdleAction,
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_minutes: DEFAULT_IDLE_MINUTES,
+            action: IdleAction::Ask,
+        }
+    }
+}
+
+impl IdleSettings {
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=MAX_IDLE_MINUTES).contains(&self.threshold_minutes) {
+            return Err(Error::Invalid(format!(
+                "the idle threshold must be between 1 and {MAX_IDLE_MINUTES} minutes"
+            )));
+        }
+        Ok(())
+    }
+
+    fn threshold(&self) -> u64 {
+        self.threshold_minutes as u64 * 60
+    }
+}
+
+/// Time the user was away during a timer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleSpan {
+    pub project_id: String,
+    /// Start of the timer it happened in, to tell it from a later one
+    pub timer_started_at: DateTime<Utc>,
+    pub from: DateTime<Utc>,
+    /// The last input before the user came back, or the check that
+    /// noticed they were away while they still are
+    pub to: DateTime<Utc>,
+    /// The timer was paused at `from`, so the time is already left out
+    pub paused: bool,
+}
+
+impl IdleSpan {
+    pub fn seconds(&self) -> u64 {
+        (self.to - self.from).num_seconds().max(0) as u64
+    }
+}
+
+pub enum IdleEvent {
+    /// No input for longer than the threshold; the timer should be paused
+    /// at `from` if the span says so
+    Away(IdleSpan),
+    /// Input again; the span waits for `resolve` unless it was paused
+    Back(IdleSpan),
+}
+
+/// The user's answer about the idle time of a running timer
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum IdleResolution {
+    Keep,
+    Discard,
+    /// Leave it out of the timer and record it as work on this project
+    #[serde(rename_all = "camelCase")]
+    Reassign {
+        project_id: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleStatus {
+    /// Whether idle time can be told on this system
+    pub available: bool,
+    pub settings: IdleSettings,
+    /// Idle time the user came back from and wasn't asked about yet
+    pub pending: Option<IdleSpan>,
+}
+
+/// Checked by the ticker every second, kept in Tauri state
+pub struct IdleMonitor {
+    source: Option<Box<dyn IdleSource>>,
+    settings: IdleSettings,
+    away: Option<IdleSpan>,
+    pending: Option<IdleSpan>,
+}
+
+impl IdleMonitor {
+    pub fn new(source: Option<Box<dyn IdleSource>>, settings: IdleSettings) -> Self {
+        Self {
+            source,
+            settings,
+            away: None,
+            pending: None,
+        }
+    }
+
+    pub fn status(&self) -> IdleStatus {
+        IdleStatus {
+            available: self.source.is_some(),
+            settings: self.settings.clone(),
+            pending: self.pending.clone(),
+        }
+    }
+
+    pub fn configure(&mut self, settings: IdleSettings) {
+        if !settings.enabled {
+            self.away = None;
+        }
+        self.settings = settings;
+    }
+
+    /// Compares the idle time with the threshold while a work timer runs.
+    /// Breaks are meant to be spent away, so they are left alone.
+    pub fn check(&mut self, timer: Option<&TimerStatus>, now: DateTime<Utc>) -> Option<IdleEvent> {
+        if !self.settings.enabled {
+            return None;
+        }
+        let idle = self.source.as_mut()?.idle_seconds()?;
+        let threshold = self.settings.threshold();
+
+        let Some(mut span) = self.away.take() else {
+            let timer = timer.filter(|t| !t.paused && t.project_id != BREAK_ID)?;
+            if idle < threshold {
+                return None;
+            }
+            let span = IdleSpan {
+                project_id: timer.project_id.clone(),
+                timer_started_at: timer.started_at,
+                from: (now - Duration::seconds(idle as i64)).max(timer.started_at),
+                to: now,
+                paused: self.settings.action == IdleAction::Pause,
+            };
+            self.away = Some(span.clone());
+            return Some(IdleEvent::Away(span));
+        };
+
+        // Stopped or replaced meanwhile: there is nothing left to ask about
+        if timer.map(|t| t.started_at) != Some(span.timer_started_at) {
+            return None;
+        }
+        if idle >= threshold {
+            span.to = now;
+            self.away = Some(span);
+            return None;
+        }
+        span.to = (now - Duration::seconds(idle as i64)).max(span.from);
+        self.pending = Some(span.clone());
+        Some(IdleEvent::Back(span))
+    }
+
+    pub fn pending(&self) -> Option<&IdleSpan> {
+        self.pending.as_ref()
+    }
+
+    pub fn clear_pending(&mut self) {
+        self.pending = None;
+    }
+}
+
+/// Applies the user's answer about `span`. Returns the sessions recorded
+/// on the way: a reassigned span ends the timer where it began, saves
+/// that part, and goes on timing from the end of the span. A span that
+/// can't be reassigned is refused before the timer changes.
+pub fn resolve(
+    span: &IdleSpan,
+    resolution: &IdleResolution,
+    timer: &mut TimerEngine,
+    store: &mut Store,
+    now: DateTime<Utc>,
+) -> Result<Vec<Session>> {
+    if span.paused {
+        return match resolution {
+            IdleResolution::Discard => Ok(Vec::new()),
+            _ => Err(Error::Invalid(
+                "the idle time was left out when the timer paused".into(),
+            )),
+        };
+    }
+    match resolution {
+        IdleResolution::Keep => Ok(Vec::new()),
+        IdleResolution::Discard => {
+            timer.remove_time(span.timer_started_at, span.seconds(), now)?;
+            Ok(Vec::new())
+        }
+        IdleResolution::Reassign { project_id } => {
+            let entry = SessionEntry {
+                project_id: project_id.clone(),
+                start_time: span.from,
+                end_time: span.to,
+                kind: SessionKind::Work,
+                duration: None,
+                notes: None,
+                tags: Vec::new(),
+            };
+            store.active_project(project_id)?;
+            store.check_new_session(&entry)?;
+            let mut saved =
+                timer.cut(span.timer_started_at, span.from, span.to, now, |before| {
+                    store.add_session(before)
+                })?;
+            saved.extend(store.create_session(&entry)?);
+            Ok(saved)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, time_on};
+    use std::sync::{Arc, Mutex};
+
+    /// Idle time set by the test
+    struct FakeIdle(Arc<Mutex<Option<u64>>>);
+
+    impl IdleSource for FakeIdle {
+        fn idle_seconds(&mut self) -> Option<u64> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    const DAY: &str = "2025-03-10";
+
+    fn monitor(action: IdleAction) -> (IdleMonitor, Arc<Mutex<Option<u64>>>) {
+        let idle = Arc::new(Mutex::new(Some(0)));
+        let settings = IdleSettings {
+            action,
+            ..Default::default()
+        };
+        (
+            IdleMonitor::new(Some(Box::new(FakeIdle(idle.clone()))), settings),
+            idle,
+        )
+    }
+
+    fn running(project_id: &str) -> TimerStatus {
+        TimerStatus {
+            project_id: project_id.into(),
+            started_at: time_on(DAY, "09:00"),
+            elapsed: 0,
+            paused: false,
+            countdown: None,
+            remaining: None,
+        }
+    }
+
+    #[test]
+    fn reports_time_away_past_the_threshold() {
+        let (mut monitor, idle) = monitor(IdleAction::Ask);
+        let timer = running("web");
+
+        *idle.lock().unwrap() = Some(299);
+        assert!(monitor.check(Some(&timer), time_on(DAY, "10:04")).is_none());
+        *idle.lock().unwrap() = Some(300);
+        let Some(IdleEvent::Away(span)) = monitor.check(Some(&timer), time_on(DAY, "10:05")) else {
+            panic!("expected the user to be away");
+        };
+        assert_eq!((span.from, span.paused), (time_on(DAY, "10:00"), false));
+
+        *idle.lock().unwrap() = Some(1500);
+        assert!(monitor.check(Some(&timer), time_on(DAY, "10:25")).is_none());
+        *idle.lock().unwrap() = Some(60);
+        let Some(IdleEvent::Back(span)) = monitor.check(Some(&timer), time_on(DAY, "10:31")) else {
+            panic!("expected the user to be back");
+        };
+        assert_eq!(
+            (span.from, span.to, span.seconds()),
+            (time_on(DAY, "10:00"), time_on(DAY, "10:30"), 1800)
+        );
+        assert_eq!(monitor.status().pending, Some(span));
+
+        // Only once
+        assert!(monitor.check(Some(&timer), time_on(DAY, "10:32")).is_none());
+    }
+
+    #[test]
+    fn ignores_breaks_paused_and_replaced_timers() {
+        let (mut monitor, idle) = monitor(IdleAction::Pause);
+        *idle.lock().unwrap() = Some(600);
+        assert!(monitor.check(None, time_on(DAY, "10:00")).is_none());
+        assert!(monitor
+            .check(Some(&running(BREAK_ID)), time_on(DAY, "10:00"))
+            .is_none());
+        let paused = TimerStatus {
+            paused: true,
+            ..running("web")
+        };
+        assert!(monitor
+            .check(Some(&paused), time_on(DAY, "10:00"))
+            .is_none());
+
+        // Never earlier than the timer's start
+        let Some(IdleEvent::Away(span)) =
+            monitor.check(Some(&running("web")), time_on(DAY, "09:05"))
+        else {
+            panic!("expected the user to be away");
+        };
+        assert_eq!((span.from, span.paused), (time_on(DAY, "09:00"), true));
+
+        // Paused by the monitor, still the same timer
+        let Some(IdleEvent::Back(_)) = ({
+            *idle.lock().unwrap() = Some(0);
+            monitor.check(Some(&paused), time_on(DAY, "09:30"))
+        }) else {
+            panic!("expected the user to be back");
+        };
+
+        *idle.lock().unwrap() = Some(600);
+        monitor.check(Some(&running("web")), time_on(DAY, "11:00"));
+        let other = TimerStatus {
+            started_at: time_on(DAY, "11:01"),
+            ..running("app")
+        };
+        *idle.lock().unwrap() = Some(0);
+        assert!(monitor.check(Some(&other), time_on(DAY, "11:02")).is_none());
+    }
+
+    #[test]
+    fn validates_settings() {
+        let json = r#"{"thresholdMinutes": 10}"#;
+        let settings: IdleSettings = serde_json::from_str(json).unwrap();
+        assert!(settings.enabled);
+        assert_eq!(settings.action, IdleAction::Ask);
+        assert!(settings.validate().is_ok());
+        for threshold_minutes in [0, MAX_IDLE_MINUTES + 1] {
+            let settings = IdleSettings {
+                threshold_minutes,
+                ..Default::default()
+            };
+            assert!(settings.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn reassigns_idle_time_to_another_project() {
+        let (dir, mut store) = testing::temp_store("idle");
+        for id in ["web", "call", "old"] {
+            store.add_project(testing::project(id, id)).unwrap();
+        }
+        store.set_archived("old", true).unwrap();
+        let mut timer = TimerEngine::new(dir.path());
+        timer
+            .start("web", Some(3600), time_on(DAY, "09:00"))
+            .unwrap();
+        let span = IdleSpan {
+            project_id: "web".into(),
+            timer_started_at: time_on(DAY, "09:00"),
+            from: time_on(DAY, "09:10"),
+            to: time_on(DAY, "09:30"),
+            paused: false,
+        };
+
+        // Refused before the timer is touched, so the answer can be retried
+        for project_id in ["old", "gone"] {
+            let reassign = IdleResolution::Reassign {
+                project_id: project_id.into(),
+            };
+            assert!(resolve(
+                &span,
+                &reassign,
+                &mut timer,
+                &mut store,
+                time_on(DAY, "09:40")
+            )
+            .is_err());
+        }
+        let status = timer.status(time_on(DAY, "09:40")).unwrap().unwrap();
+        assert_eq!(
+            (status.started_at, status.elapsed),
+            (time_on(DAY, "09:00"), 2400)
+        );
+        assert!(store.sessions(&Default::default()).unwrap().is_empty());
+
+        let reassign = IdleResolution::Reassign {
+            project_id: "call".into(),
+        };
+        let saved = resolve(
+            &span,
+            &reassign,
+            &mut timer,
+            &mut store,
+            time_on(DAY, "09:40"),
+        )
+        .unwrap();
+        let saved: Vec<_> = saved
+            .iter()
+            .map(|s| (s.project_id.as_str(), s.end_time, s.duration))
+            .collect();
+        assert_eq!(
+            saved,
+            [
+                ("web", time_on(DAY, "09:10"), 600),
+                ("call", time_on(DAY, "09:30"), 1200)
+            ]
+        );
+
+        // Picks up at the end of the span, with the pomodoro's time left
+        let status = timer.status(time_on(DAY, "09:40")).unwrap().unwrap();
+        assert_eq!(status.started_at, time_on(DAY, "09:30"));
+        assert_eq!((status.elapsed, status.remaining), (600, Some(2400)));
+
+        // Discarding takes the time out of the running timer
+        let span = IdleSpan {
+            timer_started_at: time_on(DAY, "09:30"),
+            from: time_on(DAY, "09:32"),
+            to: time_on(DAY, "09:37"),
+            ..span
+        };
+        resolve(
+            &span,
+            &IdleResolution::Discard,
+            &mut timer,
+            &mut store,
+            time_on(DAY, "09:40"),
+        )
+        .unwrap();
+        let status = timer.status(time_on(DAY, "09:40")).unwrap().unwrap();
+        assert_eq!((status.elapsed, status.remaining), (300, Some(2700)));
+
+        let stale = IdleSpan {
+            timer_started_at: time_on(DAY, "09:00"),
+            ..span
+        };
+        assert!(resolve(
+            &stale,
+            &IdleResolution::Discard,
+            &mut timer,
+            &mut store,
+            time_on(DAY, "09:41")
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,79 @@
+// Productivity Tracker - X11 idle source
+// Asks the X server's screen saver extension how long input has been idle.
+// Xlib and libXss are loaded at runtime, so the app still starts where
+// they are missing.
+
+use std::ptr;
+
+use x11_dl::xlib::{Display, Xlib};
+use x11_dl::xss::{XScreenSaverInfo, Xss};
+
+use super::IdleSource;
+
+pub struct X11Idle {
+    xlib: Xlib,
+    xss: Xss,
+    display: *mut Display,
+    info: *mut XScreenSaverInfo,
+}
+
+// SAFETY: the connection is only used through `&mut self`, so never from
+// two threads at once
+unsafe impl Send for X11Idle {}
+
+impl X11Idle {
+    pub fn connect() -> Option<Self> {
+        let (xlib, xss) = match (Xlib::open(), Xss::open()) {
+            (Ok(xlib), Ok(xss)) => (xlib, xss),
+            (Err(e), _) | (_, Err(e)) => {
+                log::warn!("X11 idle detection unavailable: {e}");
+                return None;
+            }
+        };
+        // SAFETY: plain Xlib calls; both pointers are checked before use
+        // and freed on drop
+        unsafe {
+            let display = (xlib.XOpenDisplay)(ptr::null());
+            if display.is_null() {
+                log::warn!("X11 idle detection unavailable: cannot open the display");
+                return None;
+            }
+            let info = (xss.XScreenSaverAllocInfo)();
+            if info.is_null() {
+                (xlib.XCloseDisplay)(display);
+                return None;
+            }
+            Some(Self {
+                xlib,
+                xss,
+                display,
+                info,
+            })
+        }
+    }
+}
+
+impl IdleSource for X11Idle {
+    fn idle_seconds(&mut self) -> Option<u64> {
+        // SAFETY: display and info are valid until drop
+        unsafe {
+            let root = (self.xlib.XDefaultRootWindow)(self.display);
+            if (self.xss.XScreenSaverQueryInfo)(self.display, root, self.info) == 0 {
+                return None;
+            }
+            // `c_ulong` is 32 bits wide on some targets
+            #[allow(clippy::unnecessary_cast)]
+            Some((*self.info).idle as u64 / 1000)
+        }
+    }
+}
+
+impl Drop for X11Idle {
+    fn drop(&mut self) {
+        // SAFETY: both were allocated in `connect` and are not used again
+        unsafe {
+            (self.xlib.XFree)(self.info.cast());
+            (self.xlib.XCloseDisplay)(self.display);
+        }
+    }
+}
@@ -0,0 +1,1220 @@
+// Productivity Tracker - Session import
+// Reads the time entries of Toggl Track and Clockify exports (CSV or JSON)
+// and of CSV files whose columns the user names. Entries are matched to
+// projects by name, creating the missing ones, and filed under their days
+// like recorded sessions. Each entry gets a stable id, so importing the
+// same file again adds nothing.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::calendar::Calendar;
+use crate::error::{Error, Result};
+use crate::model::{
+    normalize_tag, parse_timestamp, Project, Session, SessionKind, MAX_NOTES_LEN, PROJECT_COLORS,
+};
+use crate::store::new_id;
+use crate::timer::BREAK_ID;
+
+/// Project given to entries that have none
+const NO_PROJECT: &str = "No project";
+
+/// Longest entry imported; anything longer is taken to be a mistake in
+/// the file
+const MAX_ENTRY_DAYS: i64 = 31;
+
+/// Problems with a single row skip that row; the message says why
+type RowResult<T> = std::result::Result<T, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSource {
+    /// Toggl Track: detailed report CSV, or time entries as JSON
+    Toggl,
+    /// Clockify: detailed report CSV, or time entries as JSON
+    Clockify,
+    /// Any CSV, read through `CsvColumns`
+    Csv,
+}
+
+impl ImportSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            ImportSource::Toggl => "toggl",
+            ImportSource::Clockify => "clockify",
+            ImportSource::Csv => "csv",
+        }
+    }
+
+    /// Name for the open dialog's file type filter
+    pub fn description(self) -> &'static str {
+        match self {
+            ImportSource::Toggl => "Toggl Track export",
+            ImportSource::Clockify => "Clockify export",
+            ImportSource::Csv => "CSV",
+        }
+    }
+
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            ImportSource::Toggl | ImportSource::Clockify => &["csv", "json"],
+            ImportSource::Csv => &["csv"],
+        }
+    }
+}
+
+impl fmt::Display for ImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ImportSource {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "toggl" => Ok(ImportSource::Toggl),
+            "clockify" => Ok(ImportSource::Clockify),
+            "csv" => Ok(ImportSource::Csv),
+            other => Err(Error::Invalid(format!(
+                "unknown import source {other:?} (toggl, clockify or csv)"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportOptions {
+    /// Timezone of times written without an offset; the calendar's if `None`
+    #[serde(default)]
+    pub timezone: Option<Tz>,
+    /// Read 03/04/2025 as 3 April rather than March 4
+    #[serde(default)]
+    pub day_first: bool,
+    /// Columns of a generic CSV
+    #[serde(default)]
+    pub columns: CsvColumns,
+}
+
+/// Header names of the columns a generic CSV is read from. The defaults
+/// match the CSV written by `export`, so exported files import back.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CsvColumns {
+    pub project: String,
+    /// Date and time, or only the time when `date` is set
+    pub start: String,
+    /// Like `start`; at least one of `end` and `duration` is needed
+    pub end: Option<String>,
+    /// Seconds (`5400`), hours and minutes (`1:30`, `1:30:00`) or decimal
+    /// hours (`1.5`). Defaults to the time between start and end.
+    pub duration: Option<String>,
+    pub date: Option<String>,
+    /// work, pomodoro or break; work if not mapped
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// IANA timezone of each row
+    pub timezone: Option<String>,
+    /// Kept as the session id, so rows already imported are recognised
+    pub id: Option<String>,
+    /// Used when the file has such a column, like the id and timezone
+    /// are not
+    pub notes: Option<String>,
+    /// Separated by commas or spaces; used when the file has the column
+    pub tags: Option<String>,
+}
+
+impl Default for CsvColumns {
+    fn default() -> Self {
+        CsvColumns {
+            project: "project".into(),
+            start: "start".into(),
+            end: Some("end".into()),
+            duration: Some("duration_seconds".into()),
+            date: None,
+            kind: Some("type".into()),
+            timezone: Some("timezone".into()),
+            id: Some("id".into()),
+            notes: Some("notes".into()),
+            tags: Some("tags".into()),
+        }
+    }
+}
+
+impl CsvColumns {
+    /// Maps `field` (project, start, end, duration, date, type, timezone,
+    /// id, notes or tags) to `column`; an empty column unmaps an optional
+    /// field
+    pub fn set(&mut self, field: &str, column: &str) -> Result<()> {
+        let column = Some(column.to_string()).filter(|c| !c.is_empty());
+        let required = |column: Option<String>| {
+            column.ok_or_else(|| Error::Invalid(format!("the {field} column is required")))
+        };
+        match field {
+            "project" => self.project = required(column)?,
+            "start" => self.start = required(column)?,
+            "end" => self.end = column,
+            "duration" => self.duration = column,
+            "date" => self.date = column,
+            "type" => self.kind = column,
+            "timezone" => self.timezone = column,
+            "id" => self.id = column,
+            "notes" => self.notes = column,
+            "tags" => self.tags = column,
+            other => return Err(Error::Invalid(format!("unknown CSV field {other:?}"))),
+        }
+        Ok(())
+    }
+}
+
+/// A time entry read from a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Row (CSV line, or position in a JSON list) it came from
+    pub row: usize,
+    /// Stable across imports of the same file
+    pub id: String,
+    pub project: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub duration: u64,
+    pub kind: SessionKind,
+    pub timezone: Tz,
+    /// Description of the entry
+    pub notes: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// A row that could not be read, skipped
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportIssue {
+    pub row: usize,
+    pub message: String,
+}
+
+/// Everything read from one file, waiting to be imported
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportFile {
+    pub source: ImportSource,
+    pub entries: Vec<Entry>,
+    pub issues: Vec<ImportIssue>,
+}
+
+impl ImportFile {
+    fn new(source: ImportSource) -> Self {
+        ImportFile {
+            source,
+            entries: Vec::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, row: usize, entry: RowResult<Entry>) {
+        match entry {
+            Ok(entry) => self.entries.push(entry),
+            Err(message) => self.issues.push(ImportIssue { row, message }),
+        }
+    }
+}
+
+/// Reads the entries of `input`. Times without an offset are taken to be
+/// in `zone` unless the options name another timezone. Fails only if the
+/// file as a whole cannot be read; bad rows become issues.
+pub fn read_entries(
+    input: &str,
+    source: ImportSource,
+    options: &ImportOptions,
+    zone: Tz,
+) -> Result<ImportFile> {
+    let input = input.trim_start_matches('\u{feff}');
+    let zone = options.timezone.unwrap_or(zone);
+    let json = input.trim_start().starts_with(['[', '{']);
+    match source {
+        ImportSource::Toggl if json => read_toggl_json(input, zone),
+        ImportSource::Clockify if json => read_clockify_json(input, zone),
+        ImportSource::Toggl | ImportSource::Clockify => {
+            read_report_csv(input, source, options.day_first, zone)
+        }
+        ImportSource::Csv => read_mapped_csv(input, options, zone),
+    }
+}
+
+/// An entry as found in a file, checked by `entry`
+struct RawEntry<'a> {
+    row: usize,
+    id: Option<String>,
+    project: &'a str,
+    /// Fields identifying the entry as written in the file, so the same
+    /// row gets the same id whatever timezone it is read in
+    key: Vec<&'a str>,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    duration: Option<u64>,
+    kind: SessionKind,
+    notes: &'a str,
+    tags: Vec<&'a str>,
+}
+
+impl RawEntry<'_> {
+    fn entry(self, source: ImportSource, zone: Tz) -> RowResult<Entry> {
+        if self.end_time < self.start_time {
+            return Err("ends before it starts".into());
+        }
+        if self.end_time - self.start_time > Duration::days(MAX_ENTRY_DAYS) {
+            return Err(format!("lasts more than {MAX_ENTRY_DAYS} days"));
+        }
+        let span = (self.end_time - self.start_time).num_seconds() as u64;
+        let project = match self.project.trim() {
+            "" => NO_PROJECT,
+            name => name,
+        };
+        let id = self
+            .id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| stable_id(source, &self.key));
+        Ok(Entry {
+            row: self.row,
+            id,
+            project: project.to_string(),
+            start_time: self.start_time,
+            end_time: self.end_time,
+            // Rounded report durations may run a little past the span
+            duration: self.duration.map_or(span, |d| d.min(span)),
+            kind: self.kind,
+            timezone: zone,
+            notes: Some(self.notes.trim())
+                .filter(|notes| !notes.is_empty())
+                .map(|notes| notes.chars().take(MAX_NOTES_LEN).collect()),
+            tags: import_tags(&self.tags),
+        })
+    }
+}
+
+/// Tags of other trackers may hold spaces ("client work"), which become
+/// dashes; what can't be a tag here is dropped
+fn import_tags(tags: &[&str]) -> Vec<String> {
+    let mut imported: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.split_whitespace().collect::<Vec<_>>().join("-");
+        if let Some(tag) = normalize_tag(&tag) {
+            if !imported.contains(&tag) {
+                imported.push(tag);
+            }
+        }
+    }
+    imported
+}
+
+/// Id for an entry the export gave none, derived from what identifies it
+fn stable_id(source: ImportSource, fields: &[&str]) -> String {
+    let mut hash = Sha256::new();
+    for field in fields {
+        hash.update(field.as_bytes());
+        hash.update([0x1f]);
+    }
+    let digest: String = hash.finalize()[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!("{source}-{digest}")
+}
+
+// ============================================
+// CSV
+// ============================================
+
+fn csv_reader(input: &str) -> csv::Reader<&[u8]> {
+    csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(input.as_bytes())
+}
+
+struct Headers(csv::StringRecord);
+
+impl Headers {
+    fn read(reader: &mut csv::Reader<&[u8]>) -> Result<Self> {
+        Ok(Headers(reader.headers()?.clone()))
+    }
+
+    /// Column named `name`, ignoring case
+    fn position(&self, name: &str) -> Option<usize> {
+        self.0
+            .iter()
+            .position(|h| h.eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn find(&self, name: &str) -> Result<usize> {
+        self.position(name)
+            .ok_or_else(|| Error::Invalid(format!("no {name:?} column")))
+    }
+
+    fn find_optional(&self, name: Option<&str>) -> Result<Option<usize>> {
+        name.map(|name| self.find(name)).transpose()
+    }
+}
+
+fn field(record: &csv::StringRecord, column: usize) -> &str {
+    record.get(column).unwrap_or("")
+}
+
+fn line(record: &csv::StringRecord) -> usize {
+    record.position().map_or(0, |p| p.line() as usize)
+}
+
+/// Detailed reports of Toggl Track and Clockify: one row per entry, start
+/// and end split into date and time columns in the account's timezone.
+/// They carry no ids.
+fn read_report_csv(
+    input: &str,
+    source: ImportSource,
+    day_first: bool,
+    zone: Tz,
+) -> Result<ImportFile> {
+    let mut reader = csv_reader(input);
+    let headers = Headers::read(&mut reader)?;
+    let project = headers.find("Project")?;
+    let description = headers.position("Description");
+    let start_date = headers.find("Start date")?;
+    let start_time = headers.find("Start time")?;
+    let end_date = headers.find("End date")?;
+    let end_time = headers.find("End time")?;
+    // Toggl's is "Duration", Clockify's "Duration (h)"
+    let duration = headers
+        .position("Duration")
+        .or_else(|| headers.position("Duration (h)"));
+    let tags = headers.position("Tags");
+
+    let mut file = ImportFile::new(source);
+    for record in reader.records() {
+        let record = record?;
+        let row = line(&record);
+        let local = |date: usize, time: usize| {
+            let date = parse_day(field(&record, date), day_first)?;
+            to_utc(date.and_time(parse_clock(field(&record, time))?), zone)
+        };
+        let entry = (|| {
+            RawEntry {
+                row,
+                id: None,
+                project: field(&record, project),
+                key: [project, start_date, start_time, end_date, end_time]
+                    .into_iter()
+                    .chain(description)
+                    .map(|c| field(&record, c))
+                    .collect(),
+                start_time: local(start_date, start_time)?,
+                end_time: local(end_date, end_time)?,
+                duration: duration
+                    .map(|c| parse_duration(field(&record, c)))
+                    .transpose()?,
+                kind: SessionKind::Work,
+                notes: description.map_or("", |c| field(&record, c)),
+                tags: tags.map_or("", |c| field(&record, c)).split(',').collect(),
+            }
+            .entry(source, zone)
+        })();
+        file.push(row, entry);
+    }
+    Ok(file)
+}
+
+/// Any CSV, read through the columns named in `options`. With a date
+/// column, start and end hold times of that day (an end before the start
+/// is on the next day); otherwise they hold a date and a time each.
+fn read_mapped_csv(input: &str, options: &ImportOptions, zone: Tz) -> Result<ImportFile> {
+    let columns = &options.columns;
+    let mut reader = csv_reader(input);
+    let headers = Headers::read(&mut reader)?;
+    let project = headers.find(&columns.project)?;
+    let start = headers.find(&columns.start)?;
+    let end = headers.find_optional(columns.end.as_deref())?;
+    let duration = headers.find_optional(columns.duration.as_deref())?;
+    let date = headers.find_optional(columns.date.as_deref())?;
+    let kind = headers.find_optional(columns.kind.as_deref())?;
+    let timezone = headers.find_optional(columns.timezone.as_deref())?;
+    let id = headers.find_optional(columns.id.as_deref())?;
+    let notes = columns.notes.as_deref().and_then(|c| headers.position(c));
+    let tags = columns.tags.as_deref().and_then(|c| headers.position(c));
+    if end.is_none() && duration.is_none() {
+        return Err(Error::Invalid(
+            "an end or a duration column is needed".into(),
+        ));
+    }
+
+    let mut file = ImportFile::new(ImportSource::Csv);
+    for record in reader.records() {
+        let record = record?;
+        let row = line(&record);
+        let entry = (|| {
+            let zone = match timezone.map(|c| field(&record, c)) {
+                Some(name) if !name.is_empty() => name
+                    .parse::<Tz>()
+                    .map_err(|_| format!("unknown timezone {name:?}"))?,
+                _ => zone,
+            };
+            let day = date
+                .map(|c| parse_day(field(&record, c), options.day_first))
+                .transpose()?;
+            let time = |column: usize| {
+                let value = field(&record, column);
+                match day {
+                    Some(day) => to_utc(day.and_time(parse_clock(value)?), zone),
+                    None => parse_datetime(value, options.day_first, zone),
+                }
+            };
+            let start_time = time(start)?;
+            let duration = duration
+                .map(|c| parse_duration(field(&record, c)))
+                .transpose()?;
+            let end_time = match (end, duration) {
+                (Some(end), _) => {
+                    let end_time = time(end)?;
+                    if day.is_some() && end_time < start_time {
+                        end_time
+                            .checked_add_signed(Duration::days(1))
+                            .ok_or("ends too late")?
+                    } else {
+                        end_time
+                    }
+                }
+                (None, Some(duration)) => i64::try_from(duration)
+                    .ok()
+                    .and_then(Duration::try_seconds)
+                    .and_then(|duration| start_time.checked_add_signed(duration))
+                    .ok_or_else(|| format!("duration of {duration} seconds is too long"))?,
+                (None, None) => unreachable!("checked above"),
+            };
+            let kind = match kind.map(|c| field(&record, c).to_lowercase()) {
+                Some(kind) if !kind.is_empty() => kind
+                    .parse()
+                    .map_err(|_| format!("unknown session type {kind:?}"))?,
+                _ => SessionKind::Work,
+            };
+            RawEntry {
+                row,
+                id: id.map(|c| field(&record, c).to_string()),
+                project: field(&record, project),
+                key: [Some(project), Some(start), end, date]
+                    .into_iter()
+                    .flatten()
+                    .map(|c| field(&record, c))
+                    .collect(),
+                start_time,
+                end_time,
+                duration,
+                kind,
+                notes: notes.map_or("", |c| field(&record, c)),
+                tags: tags
+                    .map_or("", |c| field(&record, c))
+                    .split([',', ' '])
+                    .collect(),
+            }
+            .entry(ImportSource::Csv, zone)
+        })();
+        file.push(row, entry);
+    }
+    Ok(file)
+}
+
+fn parse_day(s: &str, day_first: bool) -> RowResult<NaiveDate> {
+    let formats: &[&str] = if day_first {
+        &["%Y-%m-%d", "%d/%m/%Y", "%d.%m.%Y", "%d-%m-%Y"]
+    } else {
+        &["%Y-%m-%d", "%m/%d/%Y", "%d.%m.%Y", "%m-%d-%Y"]
+    };
+    formats
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(s, format).ok())
+        .ok_or_else(|| format!("invalid date {s:?}"))
+}
+
+/// 24-hour or 12-hour time of day, seconds optional
+fn parse_clock(s: &str) -> RowResult<NaiveTime> {
+    ["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(s, format).ok())
+        .ok_or_else(|| format!("invalid time {s:?}"))
+}
+
+/// RFC 3339, or a date and a time of day in `zone`
+fn parse_datetime(s: &str, day_first: bool, zone: Tz) -> RowResult<DateTime<Utc>> {
+    if let Ok(time) = parse_timestamp(s) {
+        return Ok(time);
+    }
+    let (date, time) = s
+        .split_once([' ', 'T'])
+        .ok_or_else(|| format!("invalid time {s:?}"))?;
+    to_utc(
+        parse_day(date, day_first)?.and_time(parse_clock(time.trim())?),
+        zone,
+    )
+}
+
+fn to_utc(local: NaiveDateTime, zone: Tz) -> RowResult<DateTime<Utc>> {
+    zone.from_local_datetime(&local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| format!("{local} does not exist in {}", zone.name()))
+}
+
+/// Seconds, H:MM[:SS] or decimal hours, see `CsvColumns::duration`
+fn parse_duration(s: &str) -> RowResult<u64> {
+    let invalid = || format!("invalid duration {s:?}");
+    if s.contains(':') {
+        let parts = s
+            .split(':')
+            .map(str::parse)
+            .collect::<std::result::Result<Vec<u64>, _>>()
+            .map_err(|_| invalid())?;
+        let (hours, minutes, seconds) = match parts[..] {
+            [hours, minutes] if minutes < 60 => (hours, minutes, 0),
+            [hours, minutes, seconds] if minutes < 60 && seconds < 60 => (hours, minutes, seconds),
+            _ => return Err(invalid()),
+        };
+        return hours
+            .checked_mul(3600)
+            .and_then(|s| s.checked_add(minutes * 60 + seconds))
+            .ok_or_else(invalid);
+    }
+    if let Ok(seconds) = s.parse() {
+        return Ok(seconds);
+    }
+    s.parse::<f64>()
+        .ok()
+        .filter(|hours| hours.is_finite() && *hours >= 0.0)
+        .map(|hours| (hours * 3600.0).round() as u64)
+        .ok_or_else(invalid)
+}
+
+// ============================================
+// JSON
+// ============================================
+
+/// A list of entries, bare or under `data` (Toggl reports),
+/// `time_entries` or `timeentries` (Clockify reports)
+fn json_entries(input: &str) -> Result<Vec<Value>> {
+    match serde_json::from_str(input)? {
+        Value::Array(entries) => Ok(entries),
+        Value::Object(mut object) => ["data", "time_entries", "timeentries"]
+            .iter()
+            .find_map(|key| match object.remove(*key) {
+                Some(Value::Array(entries)) => Some(entries),
+                _ => None,
+            })
+            .ok_or_else(|| Error::Invalid("no list of time entries found".into())),
+        _ => Err(Error::Invalid("expected a list of time entries".into())),
+    }
+}
+
+/// Time entry of the Toggl Track API, or of a detailed report
+#[derive(Deserialize)]
+struct TogglEntry {
+    id: Option<u64>,
+    /// `project_name` in the API (with `meta`), `project` in reports
+    #[serde(default, alias = "project_name")]
+    project: Option<String>,
+    #[serde(default, alias = "pid")]
+    project_id: Option<u64>,
+    #[serde(default)]
+    description: Option<String>,
+    start: String,
+    /// Missing while the entry is running
+    #[serde(default, alias = "end")]
+    stop: Option<String>,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+}
+
+fn read_toggl_json(input: &str, zone: Tz) -> Result<ImportFile> {
+    let mut file = ImportFile::new(ImportSource::Toggl);
+    for (index, value) in json_entries(input)?.into_iter().enumerate() {
+        let row = index + 1;
+        let entry = (|| {
+            let entry: TogglEntry = serde_json::from_value(value).map_err(|e| e.to_string())?;
+            let project = match (entry.project, entry.project_id) {
+                (Some(name), _) => name,
+                (None, Some(id)) => format!("Toggl project {id}"),
+                (None, None) => String::new(),
+            };
+            RawEntry {
+                row,
+                id: entry.id.map(|id| format!("toggl-{id}")),
+                project: &project,
+                key: vec![
+                    &project,
+                    entry.description.as_deref().unwrap_or(""),
+                    &entry.start,
+                ],
+                start_time: parse_json_time(&entry.start)?,
+                end_time: parse_json_time(entry.stop.as_deref().ok_or("still running")?)?,
+                duration: None,
+                kind: SessionKind::Work,
+                notes: entry.description.as_deref().unwrap_or(""),
+                tags: entry.tags.iter().flatten().map(String::as_str).collect(),
+            }
+            .entry(ImportSource::Toggl, zone)
+        })();
+        file.push(row, entry);
+    }
+    Ok(file)
+}
+
+/// Time entry of the Clockify API, or of a detailed report
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClockifyEntry {
+    #[serde(alias = "_id")]
+    id: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    /// Reports name the project here...
+    #[serde(default)]
+    project_name: Option<String>,
+    /// ...the API with `hydrated=true` here
+    #[serde(default)]
+    project: Option<ClockifyProject>,
+    #[serde(default)]
+    project_id: Option<String>,
+    time_interval: ClockifyInterval,
+    /// With `hydrated=true`; otherwise only tag ids, which are left out
+    #[serde(default)]
+    tags: Option<Vec<ClockifyTag>>,
+}
+
+#[derive(Deserialize)]
+struct ClockifyTag {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ClockifyProject {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ClockifyInterval {
+    start: String,
+    /// Missing while the entry is running
+    #[serde(default)]
+    end: Option<String>,
+}
+
+fn read_clockify_json(input: &str, zone: Tz) -> Result<ImportFile> {
+    let mut file = ImportFile::new(ImportSource::Clockify);
+    for (index, value) in json_entries(input)?.into_iter().enumerate() {
+        let row = index + 1;
+        let entry = (|| {
+            let entry: ClockifyEntry = serde_json::from_value(value).map_err(|e| e.to_string())?;
+            let project = match (entry.project_name, entry.project, entry.project_id) {
+                (Some(name), _, _) | (None, Some(ClockifyProject { name }), _) => name,
+                (None, None, Some(id)) => format!("Clockify project {id}"),
+                (None, None, None) => String::new(),
+            };
+            let interval = entry.time_interval;
+            RawEntry {
+                row,
+                id: entry.id.map(|id| format!("clockify-{id}")),
+                project: &project,
+                key: vec![
+                    &project,
+                    entry.description.as_deref().unwrap_or(""),
+                    &interval.start,
+                ],
+                start_time: parse_json_time(&interval.start)?,
+                end_time: parse_json_time(interval.end.as_deref().ok_or("still running")?)?,
+                duration: None,
+                kind: SessionKind::Work,
+                notes: entry.description.as_deref().unwrap_or(""),
+                tags: entry
+                    .tags
+                    .iter()
+                    .flatten()
+                    .map(|t| t.name.as_str())
+                    .collect(),
+            }
+            .entry(ImportSource::Clockify, zone)
+        })();
+        file.push(row, entry);
+    }
+    Ok(file)
+}
+
+fn parse_json_time(s: &str) -> RowResult<DateTime<Utc>> {
+    parse_timestamp(s).map_err(|_| format!("invalid timestamp {s:?}"))
+}
+
+// ============================================
+// PLAN
+// ============================================
+
+/// What an import adds to the store
+#[derive(Debug, Clone)]
+pub struct ImportPlan {
+    /// Projects to create
+    pub projects: Vec<Project>,
+    /// Sessions to add, filed under their days
+    pub sessions: Vec<Session>,
+    pub preview: ImportPreview,
+}
+
+/// Summary shown before an import is committed, and after
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreview {
+    /// Entries to add (one split over two days counts once)
+    pub entries: usize,
+    pub seconds: u64,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Projects the entries go to, most time first
+    pub projects: Vec<ImportedProject>,
+    /// Entries already imported, or repeated in the file
+    pub duplicates: usize,
+    pub issues: Vec<ImportIssue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedProject {
+    pub id: String,
+    pub name: String,
+    /// Created by the import
+    pub new: bool,
+    pub entries: usize,
+    pub seconds: u64,
+}
+
+/// Matches `file` against the store's `projects` (by name, ignoring case;
+/// projects in the trash are left alone) and the session ids it already
+/// `known`s, tombstones included so deleted sessions stay deleted. Entries
+/// that would make invalid sessions become issues.
+pub fn plan(
+    file: &ImportFile,
+    projects: &[Project],
+    known: &HashSet<String>,
+    calendar: &Calendar,
+    now: DateTime<Utc>,
+) -> ImportPlan {
+    let mut created: Vec<Project> = Vec::new();
+    let mut sessions = Vec::new();
+    let mut seen = HashSet::new();
+    let mut preview = ImportPreview {
+        issues: file.issues.clone(),
+        ..Default::default()
+    };
+
+    for entry in &file.entries {
+        if known.contains(&entry.id) || !seen.insert(entry.id.as_str()) {
+            preview.duplicates += 1;
+            continue;
+        }
+        let mut new_project = None;
+        let (project_id, name) = if entry.kind == SessionKind::Break {
+            (BREAK_ID.to_string(), "Break".to_string())
+        } else {
+            let existing = projects
+                .iter()
+                .filter(|p| p.trashed_at.is_none())
+                .chain(&created)
+                .find(|p| p.name.eq_ignore_ascii_case(&entry.project));
+            match existing {
+                Some(project) => (project.id.clone(), project.name.clone()),
+                None => {
+                    let color =
+                        PROJECT_COLORS[(projects.len() + created.len()) % PROJECT_COLORS.len()];
+                    let project = new_project.insert(Project {
+                        id: new_id(),
+                        name: entry.project.clone(),
+                        color: color.into(),
+                        modified_at: now,
+                        ..Default::default()
+                    });
+                    (project.id.clone(), project.name.clone())
+                }
+            }
+        };
+
+        let parts = calendar.file(Session {
+            id: entry.id.clone(),
+            project_id: project_id.clone(),
+            start_time: entry.start_time,
+            end_time: entry.end_time,
+            duration: entry.duration,
+            kind: entry.kind,
+            modified_at: now,
+            date: entry.start_time.date_naive(),
+            timezone: Some(entry.timezone),
+            notes: entry.notes.clone(),
+            tags: entry.tags.clone(),
+        });
+        if let Err(e) = parts.iter().try_for_each(Session::validate) {
+            let message = match e {
+                Error::Invalid(message) => message,
+                e => e.to_string(),
+            };
+            preview.issues.push(ImportIssue {
+                row: entry.row,
+                message,
+            });
+            continue;
+        }
+        created.extend(new_project);
+        for part in &parts {
+            preview.from = Some(preview.from.map_or(part.date, |d| d.min(part.date)));
+            preview.to = Some(preview.to.map_or(part.date, |d| d.max(part.date)));
+        }
+        sessions.extend(parts);
+
+        preview.entries += 1;
+        preview.seconds += entry.duration;
+        match preview.projects.iter_mut().find(|p| p.id == project_id) {
+            Some(total) => {
+                total.entries += 1;
+                total.seconds += entry.duration;
+            }
+            None => preview.projects.push(ImportedProject {
+                new: created.iter().any(|p| p.id == project_id),
+                id: project_id,
+                name,
+                entries: 1,
+                seconds: entry.duration,
+            }),
+        }
+    }
+    preview
+        .projects
+        .sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.name.cmp(&b.name)));
+    preview.issues.sort_by_key(|issue| issue.row);
+
+    ImportPlan {
+        projects: created,
+        sessions,
+        preview,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::at;
+    use chrono_tz::{America, Europe};
+
+    fn rome() -> Calendar {
+        Calendar {
+            timezone: Europe::Rome,
+            day_start_hour: 0,
+        }
+    }
+
+    #[test]
+    fn toggl_detailed_report() {
+        let csv = "\u{feff}User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags,Amount ()\n\
+            Ada,ada@example.com,Acme,Website,,Landing page,Yes,2025-01-15,09:00:00,2025-01-15,10:30:00,01:30:00,,\n\
+            Ada,ada@example.com,,,,Email,No,2025-01-15,23:30:00,2025-01-16,00:15:00,00:45:00,,\n\
+            Ada,ada@example.com,Acme,Website,,Broken,Yes,2025-01-15,noon,2025-01-15,13:00:00,01:00:00,,\n";
+        let file = read_entries(
+            csv,
+            ImportSource::Toggl,
+            &ImportOptions::default(),
+            Europe::Rome,
+        )
+        .unwrap();
+        assert_eq!(file.entries.len(), 2);
+        let first = &file.entries[0];
+        assert_eq!(first.project, "Website");
+        assert_eq!(
+            (first.start_time, first.end_time),
+            (at("2025-01-15T08:00:00Z"), at("2025-01-15T09:30:00Z"))
+        );
+        assert_eq!(first.duration, 5400);
+        assert!(first.id.starts_with("toggl-"));
+        assert_eq!(file.entries[1].project, NO_PROJECT);
+        assert_eq!(file.entries[1].end_time, at("2025-01-15T23:15:00Z"));
+        assert_eq!(
+            file.issues,
+            [ImportIssue {
+                row: 4,
+                message: "invalid time \"noon\"".into()
+            }]
+        );
+
+        // Ids only depend on the row, not on how its times are read
+        let again = read_entries(
+            csv,
+            ImportSource::Toggl,
+            &ImportOptions::default(),
+            America::New_York,
+        )
+        .unwrap();
+        assert_ne!(again.entries[0].start_time, first.start_time);
+        assert_eq!(again.entries[0].id, first.id);
+        assert_ne!(again.entries[1].id, first.id);
+    }
+
+    #[test]
+    fn clockify_report_with_us_dates() {
+        let csv = "Project,Client,Description,Task,User,Group,Email,Tags,Billable,Start Date,Start Time,End Date,End Time,Duration (h),Duration (decimal)\n\
+            Website,Acme,Design,,Ada,,ada@example.com,,Yes,01/02/2025,09:00 AM,01/02/2025,01:15 PM,04:15:00,4.25\n";
+        let options = ImportOptions {
+            timezone: Some(America::New_York),
+            ..Default::default()
+        };
+        let file = read_entries(csv, ImportSource::Clockify, &options, Europe::Rome).unwrap();
+        let entry = &file.entries[0];
+        assert_eq!(entry.start_time, at("2025-01-02T14:00:00Z"));
+        assert_eq!(entry.duration, 4 * 3600 + 15 * 60);
+        assert_eq!(entry.timezone, America::New_York);
+        assert_eq!(entry.notes.as_deref(), Some("Design"));
+
+        let day_first = ImportOptions {
+            day_first: true,
+            ..options
+        };
+        let file = read_entries(csv, ImportSource::Clockify, &day_first, Europe::Rome).unwrap();
+        assert_eq!(file.entries[0].start_time, at("2025-02-01T14:00:00Z"));
+    }
+
+    #[test]
+    fn toggl_and_clockify_json() {
+        let toggl = r#"[
+            {"id": 42, "project_name": "Website", "description": "x", "tags": ["Client work", "Review", "a,b"],
+             "start": "2025-01-15T08:00:00+00:00", "stop": "2025-01-15T09:00:00+00:00", "duration": 3600},
+            {"id": 43, "pid": 7, "start": "2025-01-15T10:00:00Z", "stop": null, "duration": -1736935200}
+        ]"#;
+        let file = read_entries(
+            toggl,
+            ImportSource::Toggl,
+            &ImportOptions::default(),
+            Tz::UTC,
+        )
+        .unwrap();
+        assert_eq!(file.entries[0].id, "toggl-42");
+        assert_eq!(file.entries[0].duration, 3600);
+        assert_eq!(file.entries[0].notes.as_deref(), Some("x"));
+        assert_eq!(file.entries[0].tags, ["client-work", "review"]);
+        assert_eq!(file.issues[0].message, "still running");
+
+        let clockify = r#"{"timeentries": [
+            {"_id": "abc", "description": "", "projectName": "Website", "tags": [{"name": "Deep"}],
+             "timeInterval": {"start": "2025-01-15T08:00:00Z", "end": "2025-01-15T08:30:00Z", "duration": 1800}},
+            {"id": "def", "project": {"name": "Docs"}, "projectId": "p1",
+             "timeInterval": {"start": "2025-01-15T09:00:00Z", "end": "2025-01-15T09:20:00Z", "duration": "PT20M"}}
+        ]}"#;
+        let file = read_entries(
+            clockify,
+            ImportSource::Clockify,
+            &ImportOptions::default(),
+            Tz::UTC,
+        )
+        .unwrap();
+        let read: Vec<_> = file
+            .entries
+            .iter()
+            .map(|e| (e.id.as_str(), e.project.as_str(), e.duration))
+            .collect();
+        assert_eq!(
+            read,
+            [
+                ("clockify-abc", "Website", 1800),
+                ("clockify-def", "Docs", 1200)
+            ]
+        );
+        assert_eq!(file.entries[0].notes, None);
+        assert_eq!(file.entries[0].tags, ["deep"]);
+    }
+
+    #[test]
+    fn mapped_csv_columns() {
+        let csv = "Day,From,To,Client,Kind\n\
+            15.01.2025,22:00,01:00,Acme,Work\n\
+            15.01.2025,12:00,12:15,,break\n\
+            15.01.2025,12:00,12:15,Acme,nap\n";
+        let mut columns = CsvColumns::default();
+        for (field, column) in [
+            ("project", "Client"),
+            ("start", "From"),
+            ("end", "To"),
+            ("date", "Day"),
+            ("type", "Kind"),
+            ("duration", ""),
+            ("timezone", ""),
+            ("id", ""),
+        ] {
+            columns.set(field, column).unwrap();
+        }
+        let options = ImportOptions {
+            columns,
+            ..Default::default()
+        };
+        let file = read_entries(csv, ImportSource::Csv, &options, Europe::Rome).unwrap();
+        assert_eq!(file.entries[0].end_time, at("2025-01-16T00:00:00Z"));
+        assert_eq!(file.entries[0].duration, 3 * 3600);
+        assert_eq!(file.entries[1].kind, SessionKind::Break);
+        assert_eq!(file.issues[0].message, "unknown session type \"nap\"");
+
+        let err = read_entries("Day,Client\n", ImportSource::Csv, &options, Tz::UTC)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("no \"From\" column"), "{err}");
+        assert!(CsvColumns::default().set("start", "").is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("5400"), Ok(5400));
+        assert_eq!(parse_duration("1:30"), Ok(5400));
+        assert_eq!(parse_duration("26:00:05"), Ok(93605));
+        assert_eq!(parse_duration("1.25"), Ok(4500));
+        assert!(parse_duration("1:75").is_err());
+        assert!(parse_duration("-1.5").is_err());
+        assert!(parse_duration("99999999999999999:00").is_err());
+    }
+
+    #[test]
+    fn skips_entries_too_long_to_be_real() {
+        let options = ImportOptions {
+            columns: CsvColumns {
+                project: "Project".into(),
+                start: "Start".into(),
+                end: None,
+                duration: Some("Seconds".into()),
+                date: None,
+                kind: None,
+                timezone: None,
+                id: None,
+                notes: None,
+                tags: None,
+            },
+            ..Default::default()
+        };
+        let csv = "Project,Start,Seconds\n\
+            Website,2025-01-15 09:00,3600\n\
+            Website,2025-01-15 09:00,99999999999999\n\
+            Website,2025-01-15 09:00,18446744073709551615\n\
+            Website,2025-01-15 09:00,2764800\n";
+        let file = read_entries(csv, ImportSource::Csv, &options, Tz::UTC).unwrap();
+        assert_eq!(file.entries.len(), 1);
+        let rows: Vec<_> = file.issues.iter().map(|i| i.row).collect();
+        assert_eq!(rows, [3, 4, 5]);
+        assert!(file.issues[0].message.contains("too long"));
+        assert!(file.issues[2].message.contains("more than 31 days"));
+    }
+
+    #[test]
+    fn plans_new_projects_and_skips_known_ids() {
+        let csv = "date,start,end,duration_seconds,duration_hours,project,project_id,type,timezone,id\r\n\
+            2025-01-15,2025-01-15T09:00:00+01:00,2025-01-15T10:00:00+01:00,3600,1.00,website,p1,work,Europe/Rome,s1\r\n\
+            2025-01-15,2025-01-15T23:00:00+01:00,2025-01-16T01:00:00+01:00,7200,2.00,Docs,p2,pomodoro,Europe/Rome,s2\r\n\
+            2025-01-15,2025-01-15T12:00:00+01:00,2025-01-15T12:10:00+01:00,600,0.17,Break,break,break,Europe/Rome,s3\r\n\
+            2025-01-15,2025-01-15T09:00:00+01:00,2025-01-15T10:00:00+01:00,3600,1.00,Website,p1,work,Europe/Rome,s1\r\n\
+            2025-01-14,2025-01-14T09:00:00+01:00,2025-01-14T10:00:00+01:00,3600,1.00,Website,p1,work,Europe/Rome,old\r\n";
+        let file =
+            read_entries(csv, ImportSource::Csv, &ImportOptions::default(), Tz::UTC).unwrap();
+        let website = Project {
+            id: "p1".into(),
+            name: "Website".into(),
+            color: "#00ff88".into(),
+            modified_at: DateTime::UNIX_EPOCH,
+            ..Default::default()
+        };
+        let known = HashSet::from(["old".to_string()]);
+        let plan = plan(
+            &file,
+            &[website],
+            &known,
+            &rome(),
+            at("2025-02-01T00:00:00Z"),
+        );
+
+        assert_eq!(plan.projects.len(), 1);
+        assert_eq!(plan.projects[0].name, "Docs");
+        // The late Docs session is split at midnight
+        assert_eq!(plan.sessions.len(), 4);
+        assert!(plan.sessions.iter().all(|s| s.validate().is_ok()));
+        let break_session = plan.sessions.iter().find(|s| s.id == "s3").unwrap();
+        assert_eq!(break_session.project_id, BREAK_ID);
+
+        let preview = &plan.preview;
+        assert_eq!(
+            (preview.entries, preview.seconds, preview.duplicates),
+            (3, 11400, 2)
+        );
+        assert_eq!(
+            (preview.from, preview.to),
+            (
+                Some("2025-01-15".parse().unwrap()),
+                Some("2025-01-16".parse().unwrap())
+            )
+        );
+        let totals: Vec<_> = preview
+            .projects
+            .iter()
+            .map(|p| (p.name.as_str(), p.new, p.entries))
+            .collect();
+        assert_eq!(
+            totals,
+            [
+                ("Docs", true, 1),
+                ("Website", false, 1),
+                ("Break", false, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_trashed_projects_and_invalid_sessions() {
+        let entry = |row: usize, id: &str, tags: &[&str]| Entry {
+            row,
+            id: id.into(),
+            project: "website".into(),
+            start_time: at("2025-01-15T09:00:00Z"),
+            end_time: at("2025-01-15T10:00:00Z"),
+            duration: 3600,
+            kind: SessionKind::Work,
+            timezone: Tz::UTC,
+            notes: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        };
+        let file = ImportFile {
+            source: ImportSource::Csv,
+            entries: vec![entry(2, "s1", &["client work"]), entry(3, "s2", &[])],
+            issues: vec![ImportIssue {
+                row: 4,
+                message: "invalid date \"x\"".into(),
+            }],
+        };
+        let trashed = Project {
+            id: "p1".into(),
+            name: "Website".into(),
+            trashed_at: Some(at("2025-01-20T00:00:00Z")),
+            ..Default::default()
+        };
+        let plan = plan(
+            &file,
+            &[trashed],
+            &HashSet::new(),
+            &rome(),
+            at("2025-02-01T00:00:00Z"),
+        );
+
+        // The entry goes to a new project, not the one in the trash
+        assert_eq!(plan.projects.len(), 1);
+        assert_ne!(plan.projects[0].id, "p1");
+        let ids: Vec<_> = plan.sessions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["s2"]);
+        let rows: Vec<_> = plan.preview.issues.iter().map(|i| i.row).collect();
+        assert_eq!(rows, [2, 4]);
+        assert!(plan.preview.issues[0].message.contains("invalid tag"));
+        assert_eq!(plan.preview.entries, 1);
+    }
+}
@@ -0,0 +1,174 @@
+// Productivity Tracker - Anthropic provider
+// Messages API with `stream: true`: server-sent events, text arriving in
+// `content_block_delta` events
+
+use std::sync::atomic::AtomicBool;
+
+use serde::Deserialize;
+use serde_json::json;
+use tauri_plugin_http::reqwest::blocking::Client;
+
+use super::{check_status, http_client, parse, read_lines, sse_data, user_message};
+use super::{AiError, AiProvider, AiRequest};
+
+const API_VERSION: &str = "2023-06-01";
+
+pub struct Anthropic {
+    endpoint: String,
+    api_key: String,
+    http: Client,
+}
+
+impl Anthropic {
+    pub fn new(endpoint: &str, api_key: String) -> Result<Self, AiError> {
+        Ok(Self {
+            endpoint: endpoint.into(),
+            api_key,
+            http: http_client()?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event {
+    ContentBlockDelta {
+        delta: Delta,
+    },
+    MessageStop,
+    Error {
+        error: ErrorBody,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: String,
+}
+
+impl AiProvider for Anthropic {
+    fn generate(
+        &self,
+        request: &AiRequest,
+        cancel: &AtomicBool,
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<String, AiError> {
+        let response = self
+            .http
+            .post(&self.endpoint)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&json!({
+                "model": request.model,
+                "max_tokens": request.max_tokens,
+                "stream": true,
+                "messages": user_message(&request.prompt),
+            }))
+            .send()?;
+
+        let mut reply = String::new();
+        read_lines(check_status(response)?, cancel, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(true);
+            };
+            match parse(data)? {
+                Event::ContentBlockDelta { delta } => {
+                    on_text(&delta.text);
+                    reply.push_str(&delta.text);
+                }
+                Event::MessageStop => return Ok(false),
+                // e.g. overloaded_error, sent mid-stream with a 200 status
+                Event::Error { error } => return Err(AiError::Provider(error.message)),
+                Event::Other => {}
+            }
+            Ok(true)
+        })?;
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::mock_server;
+    use std::sync::atomic::Ordering;
+
+    const STREAM: &str = "event: message_start\n\
+        data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\"}}\n\n\
+        event: content_block_delta\n\
+        data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Take \"}}\n\n\
+        event: ping\n\
+        data: {\"type\": \"ping\"}\n\n\
+        event: content_block_delta\n\
+        data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"breaks.\"}}\n\n\
+        event: message_stop\n\
+        data: {\"type\":\"message_stop\"}\n\n";
+
+    fn request() -> AiRequest {
+        AiRequest {
+            provider: "anthropic".into(),
+            model: "claude-haiku-4-5".into(),
+            prompt: "How was my week?".into(),
+            max_tokens: 256,
+        }
+    }
+
+    #[test]
+    fn streams_text_deltas() {
+        let (url, server) = mock_server(vec![(200, STREAM)]);
+        let provider = Anthropic::new(&url, "sk-ant-test".into()).unwrap();
+        let mut pieces = Vec::new();
+        let reply = provider
+            .generate(&request(), &AtomicBool::new(false), &mut |text| {
+                pieces.push(text.to_string())
+            })
+            .unwrap();
+        assert_eq!(pieces, ["Take ", "breaks."]);
+        assert_eq!(reply, "Take breaks.");
+
+        let sent = &server.join().unwrap()[0];
+        assert!(sent.contains("x-api-key: sk-ant-test"));
+        assert!(sent.contains(r#""stream":true"#) && sent.contains(r#""max_tokens":256"#));
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let (url, _server) = mock_server(vec![(200, STREAM)]);
+        let provider = Anthropic::new(&url, "sk-ant-test".into()).unwrap();
+        let cancel = AtomicBool::new(false);
+        let mut pieces = 0;
+        let result = provider.generate(&request(), &cancel, &mut |_| {
+            pieces += 1;
+            cancel.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(result, Err(AiError::Cancelled));
+        assert_eq!(pieces, 1);
+    }
+
+    #[test]
+    fn maps_error_statuses() {
+        let body = r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#;
+        let internal =
+            r#"{"type":"error","error":{"type":"api_error","message":"Internal error"}}"#;
+        let (url, _server) = mock_server(vec![(401, body), (500, internal)]);
+        let provider = Anthropic::new(&url, "wrong".into()).unwrap();
+        let cancel = AtomicBool::new(false);
+        let result = provider.generate(&request(), &cancel, &mut |_| {});
+        assert_eq!(result, Err(AiError::Unauthorized));
+        let result = provider.generate(&request(), &cancel, &mut |_| {});
+        assert_eq!(
+            result,
+            Err(AiError::Provider(
+                "HTTP 500 Internal Server Error: Internal error".into()
+            ))
+        );
+    }
+}
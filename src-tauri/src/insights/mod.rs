@@ -0,0 +1,241 @@
+// Productivity Tracker - AI insights
+// Talks to the AI providers listed in `CONFIG.AI_PROVIDERS`, so API keys
+// never reach the webview. Replies are streamed: each provider parses its
+// own wire format (server-sent events or JSON lines) and hands text to the
+// caller as it arrives. Failures are mapped to one `AiError` whatever the
+// provider, and a request can be cancelled between two pieces of text.
+
+mod anthropic;
+mod ollama;
+mod openai;
+
+use std::io::{BufRead, BufReader};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri_plugin_http::reqwest::blocking::{Client, Response};
+
+pub use anthropic::Anthropic;
+pub use ollama::Ollama;
+pub use openai::OpenAiCompatible;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Whole request, stream included; long replies from slow local models
+/// take a while
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+// Where each provider is reached. Fixed here rather than taken from the
+// webview, so a saved key only ever goes to its own provider.
+const ANTHROPIC_ENDPOINT: &str = "https://api.anthropic.com/v1/messages";
+const OPENAI_ENDPOINT: &str = "https://api.openai.com/v1/chat/completions";
+const GROQ_ENDPOINT: &str = "https://api.groq.com/openai/v1/chat/completions";
+const OLLAMA_ENDPOINT: &str = "http://localhost:11434/api/chat";
+
+pub trait AiProvider: Send + Sync {
+    /// Sends the prompt and calls `on_text` with each piece of the reply as
+    /// it streams in. Returns the whole reply, or `AiError::Cancelled` as
+    /// soon as `cancel` is set.
+    fn generate(
+        &self,
+        request: &AiRequest,
+        cancel: &AtomicBool,
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<String, AiError>;
+}
+
+/// A prompt as sent by the webview
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiRequest {
+    /// Key in `CONFIG.AI_PROVIDERS`: anthropic, openai, groq or ollama
+    pub provider: String,
+    pub model: String,
+    pub prompt: String,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+}
+
+fn default_max_tokens() -> u32 {
+    DEFAULT_MAX_TOKENS
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AiError {
+    #[error("no API key saved for {0}")]
+    MissingKey(String),
+
+    #[error("unknown AI provider {0}")]
+    UnknownProvider(String),
+
+    #[error("the API key was rejected")]
+    Unauthorized,
+
+    #[error("rate limited, try again later")]
+    RateLimited,
+
+    /// Could not connect, or the connection dropped mid-reply
+    #[error("provider unreachable: {0}")]
+    Unreachable(String),
+
+    /// The provider answered with an error
+    #[error("provider error: {0}")]
+    Provider(String),
+
+    #[error("unexpected response: {0}")]
+    InvalidResponse(String),
+
+    #[error("cancelled")]
+    Cancelled,
+}
+
+impl AiError {
+    /// Stable name for the UI to branch on
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AiError::MissingKey(_) => "missingKey",
+            AiError::UnknownProvider(_) => "unknownProvider",
+            AiError::Unauthorized => "unauthorized",
+            AiError::RateLimited => "rateLimited",
+            AiError::Unreachable(_) => "unreachable",
+            AiError::Provider(_) => "provider",
+            AiError::InvalidResponse(_) => "invalidResponse",
+            AiError::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl From<tauri_plugin_http::reqwest::Error> for AiError {
+    fn from(e: tauri_plugin_http::reqwest::Error) -> Self {
+        AiError::Unreachable(e.to_string())
+    }
+}
+
+/// Whether `provider` needs an API key (everything but local Ollama)
+pub fn needs_key(provider: &str) -> bool {
+    provider != "ollama"
+}
+
+pub fn connect(provider: &str, api_key: Option<String>) -> Result<Box<dyn AiProvider>, AiError> {
+    let key = || {
+        api_key
+            .clone()
+            .ok_or_else(|| AiError::MissingKey(provider.into()))
+    };
+    Ok(match provider {
+        "anthropic" => Box::new(Anthropic::new(ANTHROPIC_ENDPOINT, key()?)?),
+        "openai" => Box::new(OpenAiCompatible::new(OPENAI_ENDPOINT, key()?)?),
+        "groq" => Box::new(OpenAiCompatible::new(GROQ_ENDPOINT, key()?)?),
+        "ollama" => Box::new(Ollama::new(OLLAMA_ENDPOINT)?),
+        other => return Err(AiError::UnknownProvider(other.into())),
+    })
+}
+
+/// `{ role: "user", content }`, the message format all providers share
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+fn user_message(prompt: &str) -> [Message<'_>; 1] {
+    [Message {
+        role: "user",
+        content: prompt,
+    }]
+}
+
+fn http_client() -> Result<Client, AiError> {
+    Ok(Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()?)
+}
+
+/// Maps error statuses to `AiError`, with the provider's message if any
+fn check_status(response: Response) -> Result<Response, AiError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().unwrap_or_default();
+    Err(match status.as_u16() {
+        401 | 403 => AiError::Unauthorized,
+        429 => AiError::RateLimited,
+        _ => AiError::Provider(format!("HTTP {status}: {}", error_message(&body))),
+    })
+}
+
+/// `{"error": {"message": ...}}` (Anthropic, OpenAI, Groq), `{"error": ...}`
+/// (Ollama) or the body itself
+fn error_message(body: &str) -> String {
+    let json: Option<serde_json::Value> = serde_json::from_str(body).ok();
+    let error = json.as_ref().and_then(|json| json.get("error"));
+    match error.and_then(|e| e.get("message").or(Some(e))) {
+        Some(serde_json::Value::String(message)) => message.clone(),
+        _ => body.chars().take(200).collect(),
+    }
+}
+
+/// Feeds the response to `on_line` line by line until it returns false or
+/// the stream ends, checking `cancel` before each line
+fn read_lines(
+    response: Response,
+    cancel: &AtomicBool,
+    mut on_line: impl FnMut(&str) -> Result<bool, AiError>,
+) -> Result<(), AiError> {
+    for line in BufReader::new(response).lines() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(AiError::Cancelled);
+        }
+        let line = line.map_err(|e| AiError::Unreachable(e.to_string()))?;
+        if !on_line(&line)? {
+            break;
+        }
+    }
+    if cancel.load(Ordering::Relaxed) {
+        return Err(AiError::Cancelled);
+    }
+    Ok(())
+}
+
+/// Payload of a server-sent event `data:` line; `None` for other lines
+fn sse_data(line: &str) -> Option<&str> {
+    line.strip_prefix("data:").map(str::trim_start)
+}
+
+fn parse<'a, T: Deserialize<'a>>(json: &'a str) -> Result<T, AiError> {
+    serde_json::from_str(json).map_err(|e| AiError::InvalidResponse(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_messages_from_every_format() {
+        assert_eq!(
+            error_message(r#"{"type":"error","error":{"type":"x","message":"Overloaded"}}"#),
+            "Overloaded"
+        );
+        assert_eq!(
+            error_message(r#"{"error":"model not found"}"#),
+            "model not found"
+        );
+        assert_eq!(error_message("Bad Gateway"), "Bad Gateway");
+    }
+
+    #[test]
+    fn keys_are_required_except_for_ollama() {
+        assert_eq!(
+            connect("openai", None).err(),
+            Some(AiError::MissingKey("openai".into()))
+        );
+        assert!(connect("ollama", None).is_ok());
+        assert_eq!(
+            connect("gemini", Some("k".into())).err(),
+            Some(AiError::UnknownProvider("gemini".into()))
+        );
+    }
+}
@@ -0,0 +1,137 @@
+// Productivity Tracker - Ollama provider
+// Local `/api/chat` with `stream: true`: one JSON object per line, the
+// last one with `done: true`. No API key.
+
+use std::sync::atomic::AtomicBool;
+
+use serde::Deserialize;
+use serde_json::json;
+use tauri_plugin_http::reqwest::blocking::Client;
+
+use super::{check_status, http_client, parse, read_lines, user_message};
+use super::{AiError, AiProvider, AiRequest};
+
+pub struct Ollama {
+    endpoint: String,
+    http: Client,
+}
+
+impl Ollama {
+    pub fn new(endpoint: &str) -> Result<Self, AiError> {
+        Ok(Self {
+            endpoint: endpoint.into(),
+            http: http_client()?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct Line {
+    message: Option<Message>,
+    #[serde(default)]
+    done: bool,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    content: String,
+}
+
+impl AiProvider for Ollama {
+    fn generate(
+        &self,
+        request: &AiRequest,
+        cancel: &AtomicBool,
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<String, AiError> {
+        let response = self
+            .http
+            .post(&self.endpoint)
+            .json(&json!({
+                "model": request.model,
+                "stream": true,
+                "messages": user_message(&request.prompt),
+                "options": { "num_predict": request.max_tokens },
+            }))
+            .send()
+            .map_err(|e| {
+                AiError::Unreachable(format!(
+                    "{e} (is Ollama running? Start it with `ollama serve`)"
+                ))
+            })?;
+
+        let mut reply = String::new();
+        read_lines(check_status(response)?, cancel, |line| {
+            if line.trim().is_empty() {
+                return Ok(true);
+            }
+            let line: Line = parse(line)?;
+            if let Some(error) = line.error {
+                return Err(AiError::Provider(error));
+            }
+            if let Some(message) = line.message {
+                on_text(&message.content);
+                reply.push_str(&message.content);
+            }
+            Ok(!line.done)
+        })?;
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::mock_server;
+    use std::net::TcpListener;
+
+    fn request() -> AiRequest {
+        AiRequest {
+            provider: "ollama".into(),
+            model: "llama3.2".into(),
+            prompt: "Summarize".into(),
+            max_tokens: 1024,
+        }
+    }
+
+    #[test]
+    fn streams_json_lines() {
+        let stream =
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"Short \"},\"done\":false}\n\
+            {\"message\":{\"role\":\"assistant\",\"content\":\"sessions.\"},\"done\":false}\n\
+            {\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true}\n";
+        let (url, _server) = mock_server(vec![(200, stream)]);
+        let reply = Ollama::new(&url)
+            .unwrap()
+            .generate(&request(), &AtomicBool::new(false), &mut |_| {})
+            .unwrap();
+        assert_eq!(reply, "Short sessions.");
+    }
+
+    #[test]
+    fn reports_missing_models_and_servers() {
+        let (url, _server) =
+            mock_server(vec![(404, r#"{"error":"model \"llama3.2\" not found"}"#)]);
+        let result =
+            Ollama::new(&url)
+                .unwrap()
+                .generate(&request(), &AtomicBool::new(false), &mut |_| {});
+        assert_eq!(
+            result,
+            Err(AiError::Provider(
+                "HTTP 404 Not Found: model \"llama3.2\" not found".into()
+            ))
+        );
+
+        // Nothing listens on a port once its listener is dropped
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let result = Ollama::new(&format!("http://{addr}/api/chat"))
+            .unwrap()
+            .generate(&request(), &AtomicBool::new(false), &mut |_| {});
+        assert!(matches!(result, Err(AiError::Unreachable(_))));
+    }
+}
@@ -0,0 +1,137 @@
+// Productivity Tracker - OpenAI-compatible provider
+// Chat Completions with `stream: true`, as served by OpenAI and Groq:
+// server-sent events carrying `choices[0].delta.content`, ended by
+// `data: [DONE]`
+
+use std::sync::atomic::AtomicBool;
+
+use serde::Deserialize;
+use serde_json::json;
+use tauri_plugin_http::reqwest::blocking::Client;
+
+use super::{check_status, http_client, parse, read_lines, sse_data, user_message};
+use super::{AiError, AiProvider, AiRequest};
+
+pub struct OpenAiCompatible {
+    endpoint: String,
+    api_key: String,
+    http: Client,
+}
+
+impl OpenAiCompatible {
+    pub fn new(endpoint: &str, api_key: String) -> Result<Self, AiError> {
+        Ok(Self {
+            endpoint: endpoint.into(),
+            api_key,
+            http: http_client()?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct Chunk {
+    #[serde(default)]
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    delta: Delta,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+impl AiProvider for OpenAiCompatible {
+    fn generate(
+        &self,
+        request: &AiRequest,
+        cancel: &AtomicBool,
+        on_text: &mut dyn FnMut(&str),
+    ) -> Result<String, AiError> {
+        let response = self
+            .http
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "model": request.model,
+                // Newer OpenAI models reject the older `max_tokens`
+                "max_completion_tokens": request.max_tokens,
+                "stream": true,
+                "messages": user_message(&request.prompt),
+            }))
+            .send()?;
+
+        let mut reply = String::new();
+        read_lines(check_status(response)?, cancel, |line| {
+            match sse_data(line) {
+                Some("[DONE]") => return Ok(false),
+                Some(data) => {
+                    let chunk: Chunk = parse(data)?;
+                    for text in chunk.choices.into_iter().filter_map(|c| c.delta.content) {
+                        on_text(&text);
+                        reply.push_str(&text);
+                    }
+                }
+                None => {}
+            }
+            Ok(true)
+        })?;
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::mock_server;
+
+    #[test]
+    fn streams_chunks_until_done() {
+        let stream = "data: {\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+            data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Focus \"}}]}\n\n\
+            data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"mornings.\"}}]}\n\n\
+            data: [DONE]\n\n";
+        let (url, server) = mock_server(vec![(200, stream)]);
+        let request = AiRequest {
+            provider: "groq".into(),
+            model: "llama-3.1-8b-instant".into(),
+            prompt: "Summarize".into(),
+            max_tokens: 1024,
+        };
+        let mut pieces = Vec::new();
+        let reply = OpenAiCompatible::new(
+            &format!("{url}/openai/v1/chat/completions"),
+            "gsk_test".into(),
+        )
+        .unwrap()
+        .generate(&request, &AtomicBool::new(false), &mut |text| {
+            pieces.push(text.to_string())
+        })
+        .unwrap();
+        assert_eq!(pieces, ["Focus ", "mornings."]);
+        assert_eq!(reply, "Focus mornings.");
+
+        let sent = &server.join().unwrap()[0];
+        assert!(sent.starts_with("POST /openai/v1/chat/completions "));
+        assert!(sent.contains("authorization: Bearer gsk_test"));
+    }
+
+    #[test]
+    fn rate_limits_are_reported() {
+        let body = r#"{"error":{"message":"Rate limit reached","type":"tokens"}}"#;
+        let (url, _server) = mock_server(vec![(429, body)]);
+        let request = AiRequest {
+            provider: "openai".into(),
+            model: "gpt-5-mini".into(),
+            prompt: "Summarize".into(),
+            max_tokens: 1024,
+        };
+        let result = OpenAiCompatible::new(&url, "sk-test".into())
+            .unwrap()
+            .generate(&request, &AtomicBool::new(false), &mut |_| {});
+        assert_eq!(result, Err(AiError::RateLimited));
+    }
+}
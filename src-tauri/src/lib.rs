@@ -0,0 +1,156 @@
+// Productivity Tracker - Tauri Backend
+// Persistence and the timer live here; UI and charts are in the frontend.
+// Shared by the desktop app (main.rs) and the `pt` CLI (bin/pt.rs).
+
+pub mod analytics;
+pub mod backend;
+pub mod billing;
+pub mod calendar;
+pub mod crypto;
+pub mod db;
+pub mod editing;
+pub mod error;
+pub mod export;
+pub mod hotkeys;
+pub mod idle;
+pub mod import;
+pub mod insights;
+pub mod merge;
+pub mod model;
+pub mod notifications;
+pub mod pomodoro;
+pub mod secrets;
+pub mod store;
+pub mod sync;
+pub mod timer;
+pub mod timesheet;
+
+mod assistant;
+mod commands;
+mod notifier;
+mod pdf;
+mod shortcuts;
+mod syncer;
+#[cfg(test)]
+mod testing;
+mod ticker;
+mod tray;
+
+use std::sync::Mutex;
+
+use tauri::{Manager, WindowEvent};
+
+pub fn run() {
+    tauri::Builder::default()
+        // Background threads (ticker, sync, tray...) report through the log:
+        // stdout and a file in the app's log directory
+        .plugin(
+            tauri_plugin_log::Builder::new()
+                .level(log::LevelFilter::Info)
+                .build(),
+        )
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            let mut store = store::Store::open(&data_dir)?;
+            if let Err(e) = store.purge_trash(chrono::Utc::now()) {
+                log::warn!("emptying the trash failed: {e}");
+            }
+            let timer = timer::TimerEngine::new(&data_dir);
+            let idle = idle::IdleMonitor::new(
+                idle::system_source(),
+                store.idle_settings().unwrap_or_default(),
+            );
+            app.manage(Mutex::new(store));
+            app.manage(Mutex::new(timer));
+            app.manage(Mutex::new(idle));
+            app.manage(secrets::open(&data_dir));
+            app.manage(assistant::AiRequests::default());
+            app.manage(commands::ImportState::default());
+            tray::init(app.handle())?;
+            notifier::init(app.handle())?;
+            shortcuts::init(app.handle());
+            ticker::spawn(app.handle().clone());
+            app.manage(syncer::spawn(app.handle().clone()));
+            Ok(())
+        })
+        // Closing the window hides it; the timer keeps running in the tray
+        .on_window_event(|window, event| {
+            if let WindowEvent::CloseRequested { api, .. } = event {
+                api.prevent_close();
+                let _ = window.hide();
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            commands::list_projects,
+            commands::add_project,
+            commands::update_project,
+            commands::set_project_archived,
+            commands::trash_project,
+            commands::restore_project,
+            commands::purge_project,
+            commands::list_sessions,
+            commands::session_totals,
+            commands::get_day_settings,
+            commands::set_day_settings,
+            commands::get_calendar,
+            commands::get_analytics,
+            commands::billing_summary,
+            commands::export_sessions,
+            commands::save_timesheet,
+            commands::preview_import,
+            commands::commit_import,
+            commands::cancel_import,
+            commands::add_session,
+            commands::delete_session,
+            commands::create_session,
+            commands::edit_session,
+            commands::split_session,
+            commands::merge_sessions,
+            commands::session_edits,
+            commands::annotate_session,
+            commands::search_sessions,
+            commands::get_timer,
+            commands::start_timer,
+            commands::start_break,
+            commands::pause_timer,
+            commands::resume_timer,
+            commands::stop_timer,
+            commands::idle_status,
+            commands::set_idle_settings,
+            commands::resolve_idle,
+            commands::pomodoro_status,
+            commands::set_pomodoro_settings,
+            commands::start_pomodoro,
+            commands::reset_pomodoro,
+            commands::get_notification_settings,
+            commands::set_notification_settings,
+            commands::choose_sound_file,
+            commands::get_hotkey_settings,
+            commands::set_hotkey_settings,
+            commands::configure_sync,
+            commands::sync_now,
+            commands::sync_status,
+            commands::sync_history,
+            commands::encryption_status,
+            commands::enable_encryption,
+            commands::unlock_encryption,
+            commands::recover_encryption,
+            commands::change_passphrase,
+            commands::new_recovery_code,
+            commands::rotate_encryption_key,
+            commands::lock_encryption,
+            commands::set_api_key,
+            commands::has_api_key,
+            commands::ai_generate,
+            commands::ai_cancel,
+            commands::import_legacy_data,
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
@@ -1,14 +1,7 @@
-// Productivity Tracker - Tauri Backend
-// Minimal Rust code - all logic is in the frontend
+// Productivity Tracker - Desktop app entry point
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_fs::init())
-        .plugin(tauri_plugin_http::init())
-        .plugin(tauri_plugin_dialog::init())
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+    productivity_tracker_lib::run()
 }
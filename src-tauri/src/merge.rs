@@ -0,0 +1,364 @@
+// Productivity Tracker - Sync merge
+// Combines local and remote data record by record. Every project and
+// session carries a modification time and deletions leave tombstones; for
+// each id the newest version wins, so devices converge whatever order they
+// sync in.
+
+use std::cmp::Ordering;
+use std::collections::btree_map::{BTreeMap, Entry};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Project, Session, Tombstone};
+
+/// Projects and sessions exchanged with the sync server, with tombstones
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncData {
+    #[serde(default)]
+    pub projects: Vec<Project>,
+    #[serde(default)]
+    pub sessions: Vec<Session>,
+    #[serde(default)]
+    pub deleted_projects: Vec<Tombstone>,
+    #[serde(default)]
+    pub deleted_sessions: Vec<Tombstone>,
+}
+
+impl SyncData {
+    /// Same data with duplicates resolved and records sorted by id, so two
+    /// equivalent values compare equal
+    pub fn normalized(&self) -> Self {
+        merge(self, &SyncData::default())
+    }
+
+    /// Records and tombstones of `self` that `base` does not already hold
+    /// in the same version
+    pub fn changes_from(&self, base: &SyncData) -> SyncData {
+        SyncData {
+            projects: missing(&self.projects, &base.projects),
+            sessions: missing(&self.sessions, &base.sessions),
+            deleted_projects: missing(&self.deleted_projects, &base.deleted_projects),
+            deleted_sessions: missing(&self.deleted_sessions, &base.deleted_sessions),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.projects.is_empty()
+            && self.sessions.is_empty()
+            && self.deleted_projects.is_empty()
+            && self.deleted_sessions.is_empty()
+    }
+}
+
+fn missing<T: Clone + PartialEq>(records: &[T], base: &[T]) -> Vec<T> {
+    records
+        .iter()
+        .filter(|record| !base.contains(record))
+        .cloned()
+        .collect()
+}
+
+/// A record that can be merged
+pub trait Record: Clone + Serialize {
+    fn id(&self) -> &str;
+    fn modified_at(&self) -> DateTime<Utc>;
+}
+
+impl Record for Project {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn modified_at(&self) -> DateTime<Utc> {
+        self.modified_at
+    }
+}
+
+impl Record for Session {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn modified_at(&self) -> DateTime<Utc> {
+        self.modified_at
+    }
+}
+
+/// Merges two snapshots. The result does not depend on argument order,
+/// and merging it again with either input changes nothing.
+pub fn merge(a: &SyncData, b: &SyncData) -> SyncData {
+    let (projects, deleted_projects) = merge_records(
+        [&a.projects, &b.projects],
+        [&a.deleted_projects, &b.deleted_projects],
+    );
+    let (sessions, deleted_sessions) = merge_records(
+        [&a.sessions, &b.sessions],
+        [&a.deleted_sessions, &b.deleted_sessions],
+    );
+    SyncData {
+        projects,
+        sessions,
+        deleted_projects,
+        deleted_sessions,
+    }
+}
+
+enum Version<'a, T> {
+    Live(&'a T),
+    Deleted(&'a Tombstone),
+}
+
+impl<T: Record> Version<'_, T> {
+    fn id(&self) -> &str {
+        match self {
+            Version::Live(record) => record.id(),
+            Version::Deleted(tombstone) => &tombstone.id,
+        }
+    }
+
+    /// Newer wins; on a tie a deletion beats an edit, and two different
+    /// edits are ordered by content so every device picks the same one
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+
+    fn key(&self) -> (DateTime<Utc>, bool, String) {
+        match self {
+            Version::Live(record) => (record.modified_at(), false, to_json(*record)),
+            Version::Deleted(tombstone) => (tombstone.deleted_at, true, to_json(*tombstone)),
+        }
+    }
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn merge_records<T: Record>(
+    live: [&[T]; 2],
+    deleted: [&[Tombstone]; 2],
+) -> (Vec<T>, Vec<Tombstone>) {
+    let versions = live
+        .into_iter()
+        .flatten()
+        .map(Version::Live)
+        .chain(deleted.into_iter().flatten().map(Version::Deleted));
+
+    let mut winners: BTreeMap<String, Version<T>> = BTreeMap::new();
+    for version in versions {
+        match winners.entry(version.id().to_string()) {
+            Entry::Vacant(entry) => {
+                entry.insert(version);
+            }
+            Entry::Occupied(mut entry) => {
+                if version.cmp(entry.get()) == Ordering::Greater {
+                    entry.insert(version);
+                }
+            }
+        }
+    }
+
+    let mut records = Vec::new();
+    let mut tombstones = Vec::new();
+    for version in winners.into_values() {
+        match version {
+            Version::Live(record) => records.push(record.clone()),
+            Version::Deleted(tombstone) => tombstones.push(tombstone.clone()),
+        }
+    }
+    (records, tombstones)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::SessionKind;
+    use chrono::Duration;
+    use proptest::prelude::*;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        "2025-01-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::seconds(seconds)
+    }
+
+    fn project(id: &str, name: &str, modified: i64) -> Project {
+        Project {
+            id: id.into(),
+            name: name.into(),
+            color: "#00ff88".into(),
+            modified_at: at(modified),
+            ..Default::default()
+        }
+    }
+
+    fn tombstone(id: &str, deleted: i64) -> Tombstone {
+        Tombstone {
+            id: id.into(),
+            deleted_at: at(deleted),
+            date: None,
+        }
+    }
+
+    #[test]
+    fn newer_edit_wins() {
+        let local = SyncData {
+            projects: vec![project("p", "Old", 1)],
+            ..Default::default()
+        };
+        let remote = SyncData {
+            projects: vec![project("p", "New", 2)],
+            ..Default::default()
+        };
+        assert_eq!(merge(&local, &remote).projects, [project("p", "New", 2)]);
+    }
+
+    #[test]
+    fn deletion_propagates_unless_edited_later() {
+        let live = SyncData {
+            projects: vec![project("p", "P", 1)],
+            ..Default::default()
+        };
+        let deleted = SyncData {
+            deleted_projects: vec![tombstone("p", 2)],
+            ..Default::default()
+        };
+        let merged = merge(&live, &deleted);
+        assert!(merged.projects.is_empty());
+        assert_eq!(merged.deleted_projects, [tombstone("p", 2)]);
+
+        let edited = SyncData {
+            projects: vec![project("p", "P2", 3)],
+            ..Default::default()
+        };
+        assert_eq!(merge(&edited, &deleted).projects, [project("p", "P2", 3)]);
+    }
+
+    #[test]
+    fn ties_are_broken_the_same_way_on_both_sides() {
+        let a = SyncData {
+            projects: vec![project("p", "A", 1)],
+            ..Default::default()
+        };
+        let b = SyncData {
+            projects: vec![project("p", "B", 1)],
+            ..Default::default()
+        };
+        assert_eq!(merge(&a, &b), merge(&b, &a));
+
+        let deleted = SyncData {
+            deleted_projects: vec![tombstone("p", 1)],
+            ..Default::default()
+        };
+        assert!(merge(&a, &deleted).projects.is_empty());
+    }
+
+    #[test]
+    fn changes_leave_out_records_already_held() {
+        let local = SyncData {
+            projects: vec![project("p", "P", 1), project("q", "Q", 1)],
+            ..Default::default()
+        };
+        let remote = SyncData {
+            projects: vec![project("q", "Q2", 2)],
+            deleted_projects: vec![tombstone("r", 1)],
+            ..Default::default()
+        };
+        let changes = merge(&local, &remote).changes_from(&local);
+        assert_eq!(changes.projects, [project("q", "Q2", 2)]);
+        assert_eq!(changes.deleted_projects, [tombstone("r", 1)]);
+        assert!(merge(&local, &local).changes_from(&local).is_empty());
+    }
+
+    // Small id and timestamp pools make collisions, ties and deletions common
+
+    fn arb_project() -> impl Strategy<Value = Project> {
+        ("[abc]", "[XY]", 0i64..4).prop_map(|(id, name, t)| project(&id, &name, t))
+    }
+
+    fn arb_session() -> impl Strategy<Value = Session> {
+        ("[abc]", "[pq]", 0u64..3, 0i64..4).prop_map(|(id, project_id, minutes, t)| Session {
+            id,
+            project_id,
+            start_time: at(0),
+            end_time: at(0) + Duration::minutes(minutes as i64),
+            duration: minutes * 60,
+            kind: SessionKind::Work,
+            modified_at: at(t),
+            date: at(0).date_naive(),
+            timezone: None,
+            notes: None,
+            tags: Vec::new(),
+        })
+    }
+
+    fn arb_tombstone() -> impl Strategy<Value = Tombstone> {
+        ("[abc]", 0i64..4).prop_map(|(id, t)| tombstone(&id, t))
+    }
+
+    fn arb_data() -> impl Strategy<Value = SyncData> {
+        (
+            prop::collection::vec(arb_project(), 0..4),
+            prop::collection::vec(arb_session(), 0..4),
+            prop::collection::vec(arb_tombstone(), 0..3),
+            prop::collection::vec(arb_tombstone(), 0..3),
+        )
+            .prop_map(|(projects, sessions, deleted_projects, deleted_sessions)| {
+                SyncData {
+                    projects,
+                    sessions,
+                    deleted_projects,
+                    deleted_sessions,
+                }
+            })
+    }
+
+    /// A device syncing against a server that stores whatever it is sent:
+    /// pull, merge, push, then adopt the merged result locally
+    fn sync(device: &mut SyncData, server: &mut SyncData) {
+        let merged = merge(device, server);
+        *server = merged.clone();
+        *device = merged;
+    }
+
+    proptest! {
+        #[test]
+        fn merge_is_commutative(a in arb_data(), b in arb_data()) {
+            prop_assert_eq!(merge(&a, &b), merge(&b, &a));
+        }
+
+        #[test]
+        fn merge_is_associative(a in arb_data(), b in arb_data(), c in arb_data()) {
+            prop_assert_eq!(merge(&a, &merge(&b, &c)), merge(&merge(&a, &b), &c));
+        }
+
+        #[test]
+        fn merge_is_idempotent(a in arb_data(), b in arb_data()) {
+            let merged = merge(&a, &b);
+            prop_assert_eq!(merge(&merged, &a), merged.clone());
+            prop_assert_eq!(merge(&merged, &merged), merged);
+        }
+
+        #[test]
+        fn two_devices_converge_in_either_order(
+            a in arb_data(),
+            b in arb_data(),
+            server in arb_data(),
+        ) {
+            let (mut a1, mut b1, mut s1) = (a.clone(), b.clone(), server.clone());
+            sync(&mut a1, &mut s1);
+            sync(&mut b1, &mut s1);
+            sync(&mut a1, &mut s1);
+
+            let (mut a2, mut b2, mut s2) = (a, b, server);
+            sync(&mut b2, &mut s2);
+            sync(&mut a2, &mut s2);
+            sync(&mut b2, &mut s2);
+
+            prop_assert_eq!(&a1, &b1);
+            prop_assert_eq!(&a1, &s1);
+            prop_assert_eq!(&a1, &a2);
+            prop_assert_eq!(&a2, &b2);
+        }
+    }
+}
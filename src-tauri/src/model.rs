@@ -0,0 +1,763 @@
+// Productivity Tracker - Domain model
+// Typed projects and sessions, the JSON shape shared with the webview and
+// older files, and the migrations that bring old files up to date
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+/// Version of the on-disk JSON layout written by this build
+pub const SCHEMA_VERSION: u32 = 5;
+
+/// Days a project stays in the trash before it is purged
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// Colors given to projects created without one (CLI, imports)
+pub const PROJECT_COLORS: [&str; 6] = [
+    "#00ff88", "#ff6b35", "#00d4ff", "#ff3366", "#ffcc00", "#aa66ff",
+];
+
+/// Seconds of rounding tolerated between a session's duration and its span
+const DURATION_SLACK: i64 = 1;
+/// Longest notes on a session, in characters
+pub const MAX_NOTES_LEN: usize = 10_000;
+/// Longest tag, in characters
+const MAX_TAG_LEN: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionKind {
+    /// Free-mode work, stopped by hand
+    Work,
+    /// Work session that ran its full countdown
+    Pomodoro,
+    Break,
+}
+
+impl SessionKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionKind::Work => "work",
+            SessionKind::Pomodoro => "pomodoro",
+            SessionKind::Break => "break",
+        }
+    }
+}
+
+impl fmt::Display for SessionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SessionKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "work" => Ok(SessionKind::Work),
+            "pomodoro" => Ok(SessionKind::Pomodoro),
+            "break" => Ok(SessionKind::Break),
+            other => Err(Error::Invalid(format!("unknown session type {other:?}"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    /// Who the work is billed to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    #[serde(default)]
+    pub billable: bool,
+    /// Per hour, in `currency`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hourly_rate: Option<f64>,
+    /// ISO 4217 code such as "EUR"; required with a rate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// No longer worked on; its sessions still count in reports
+    #[serde(default)]
+    pub archived: bool,
+    /// When it was moved to the trash, from which it is purged together
+    /// with its sessions once the retention period is over
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_timestamp"
+    )]
+    pub trashed_at: Option<DateTime<Utc>>,
+    /// Last local change, used to resolve sync conflicts. Records from
+    /// older versions count as never modified.
+    #[serde(default = "never", with = "timestamp")]
+    pub modified_at: DateTime<Utc>,
+}
+
+impl Project {
+    /// Whether it can be timed: neither archived nor in the trash
+    pub fn is_active(&self) -> bool {
+        !self.archived && self.trashed_at.is_none()
+    }
+
+    /// When it will be purged from the trash, if it is in it
+    pub fn purge_at(&self) -> Option<DateTime<Utc>> {
+        self.trashed_at
+            .map(|at| at + Duration::days(TRASH_RETENTION_DAYS))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.id.is_empty() {
+            return Err(Error::Invalid("project id is empty".into()));
+        }
+        if self.name.trim().is_empty() {
+            return Err(Error::Invalid(format!(
+                "project {}: name is empty",
+                self.id
+            )));
+        }
+        let invalid = |msg: &str| Err(Error::Invalid(format!("project {}: {msg}", self.id)));
+        if self.client.as_ref().is_some_and(|c| c.trim().is_empty()) {
+            return invalid("client is empty");
+        }
+        if let Some(rate) = self.hourly_rate {
+            if !rate.is_finite() || rate < 0.0 {
+                return invalid("hourly rate must be zero or more");
+            }
+            if self.currency.is_none() {
+                return invalid("an hourly rate needs a currency");
+            }
+        }
+        if let Some(currency) = &self.currency {
+            if currency.len() != 3 || !currency.bytes().all(|b| b.is_ascii_uppercase()) {
+                return invalid("currency must be a three-letter code such as EUR");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "SessionRecord", try_from = "SessionRecord")]
+pub struct Session {
+    pub id: String,
+    pub project_id: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    /// Tracked seconds; shorter than the span when the timer was paused
+    pub duration: u64,
+    pub kind: SessionKind,
+    pub modified_at: DateTime<Utc>,
+    /// Day the session is filed under, in local time (see `calendar`)
+    pub date: NaiveDate,
+    /// IANA timezone it was recorded in; `None` for sessions from older
+    /// versions, which were filed under the UTC date
+    pub timezone: Option<Tz>,
+    /// What the session was about, free text
+    pub notes: Option<String>,
+    /// Normalized with `normalize_tag`
+    pub tags: Vec<String>,
+}
+
+impl Session {
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: &str| Err(Error::Invalid(format!("session {}: {msg}", self.id)));
+
+        if self.id.is_empty() {
+            return Err(Error::Invalid("session id is empty".into()));
+        }
+        if self.project_id.is_empty() {
+            return invalid("project id is empty");
+        }
+        if self.end_time < self.start_time {
+            return invalid("ends before it starts");
+        }
+        // No timezone and day start put a day more than a day away
+        if (self.date - self.start_time.date_naive()).num_days().abs() > 1 {
+            return invalid("date is too far from the start time");
+        }
+        let span = (self.end_time - self.start_time).num_seconds();
+        if self.duration as i64 > span + DURATION_SLACK {
+            return invalid("duration is longer than the time between start and end");
+        }
+        if self
+            .notes
+            .as_ref()
+            .is_some_and(|notes| notes.chars().count() > MAX_NOTES_LEN)
+        {
+            return invalid("notes are too long");
+        }
+        if let Some(tag) = self
+            .tags
+            .iter()
+            .find(|tag| normalize_tag(tag).as_deref() != Some(tag.as_str()))
+        {
+            return invalid(&format!("invalid tag {tag:?}"));
+        }
+        Ok(())
+    }
+}
+
+/// Lowercase tag without a leading `#`; `None` if nothing is left or it is
+/// too long. Tags are single words: spaces and commas separate them.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    let valid = !tag.is_empty()
+        && tag.chars().count() <= MAX_TAG_LEN
+        && !tag.chars().any(|c| c.is_whitespace() || c == ',');
+    valid.then_some(tag)
+}
+
+/// Tags from user input such as "#client, review": normalized, without
+/// duplicates, in the order given
+pub fn parse_tags<S: AsRef<str>>(input: &[S]) -> Result<Vec<String>> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in input
+        .iter()
+        .flat_map(|s| s.as_ref().split(|c: char| c.is_whitespace() || c == ','))
+        .filter(|word| !word.is_empty())
+    {
+        let tag =
+            normalize_tag(word).ok_or_else(|| Error::Invalid(format!("invalid tag {word:?}")))?;
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    Ok(tags)
+}
+
+/// Marks a deleted project or session, so the deletion wins over older
+/// copies of the record still held by other devices
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tombstone {
+    pub id: String,
+    #[serde(with = "timestamp")]
+    pub deleted_at: DateTime<Utc>,
+    /// Day of a deleted session, so day logs can carry its tombstone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<NaiveDate>,
+}
+
+/// Timestamp given to records written before modification times existed
+pub fn never() -> DateTime<Utc> {
+    DateTime::UNIX_EPOCH
+}
+
+/// JSON shape of a session as exchanged with the webview and found in
+/// older files. Without a `date`, the UTC date of `startTime` is used, as
+/// older versions did.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionRecord {
+    id: String,
+    project_id: String,
+    start_time: String,
+    end_time: String,
+    duration: u64,
+    #[serde(default)]
+    date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timezone: Option<String>,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    modified_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+impl From<Session> for SessionRecord {
+    fn from(session: Session) -> Self {
+        SessionRecord {
+            date: Some(session.date.to_string()),
+            timezone: session.timezone.map(|tz| tz.name().to_string()),
+            start_time: format_timestamp(session.start_time),
+            end_time: format_timestamp(session.end_time),
+            id: session.id,
+            project_id: session.project_id,
+            duration: session.duration,
+            kind: session.kind.to_string(),
+            modified_at: Some(format_timestamp(session.modified_at)),
+            notes: session.notes,
+            tags: session.tags,
+        }
+    }
+}
+
+impl TryFrom<SessionRecord> for Session {
+    type Error = Error;
+
+    fn try_from(record: SessionRecord) -> Result<Self> {
+        let context = |e: Error| match e {
+            Error::Invalid(msg) => Error::Invalid(format!("session {}: {msg}", record.id)),
+            e => e,
+        };
+        let start_time = parse_timestamp(&record.start_time).map_err(context)?;
+        let session = Session {
+            date: match record.date.as_deref() {
+                Some(date) if !date.is_empty() => parse_date(date).map_err(context)?,
+                _ => start_time.date_naive(),
+            },
+            timezone: match &record.timezone {
+                Some(name) => Some(parse_timezone(name).map_err(context)?),
+                None => None,
+            },
+            start_time,
+            end_time: parse_timestamp(&record.end_time).map_err(context)?,
+            kind: record.kind.parse().map_err(context)?,
+            modified_at: match &record.modified_at {
+                Some(time) => parse_timestamp(time).map_err(context)?,
+                None => never(),
+            },
+            id: record.id,
+            project_id: record.project_id,
+            duration: record.duration,
+            notes: record.notes.filter(|notes| !notes.trim().is_empty()),
+            tags: record.tags,
+        };
+        session.validate()?;
+        Ok(session)
+    }
+}
+
+/// Timestamps are stored like JavaScript's `toISOString()`
+pub fn format_timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+pub fn parse_timestamp(s: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| Error::Invalid(format!("invalid timestamp {s:?}")))
+}
+
+pub fn parse_date(s: &str) -> Result<NaiveDate> {
+    s.parse()
+        .map_err(|_| Error::Invalid(format!("invalid date {s:?}")))
+}
+
+/// IANA timezone name, e.g. `Europe/Rome`
+pub fn parse_timezone(s: &str) -> Result<Tz> {
+    s.parse()
+        .map_err(|_| Error::Invalid(format!("unknown timezone {s:?}")))
+}
+
+/// Serde adapter for timestamp fields, in the same format as sessions
+pub(crate) mod timestamp {
+    use chrono::{DateTime, Utc};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &DateTime<Utc>, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&super::format_timestamp(*time))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<DateTime<Utc>, D::Error> {
+        let s = String::deserialize(d)?;
+        super::parse_timestamp(&s).map_err(de::Error::custom)
+    }
+}
+
+/// Same for optional timestamps, left out when `None`
+mod optional_timestamp {
+    use chrono::{DateTime, Utc};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &Option<DateTime<Utc>>, s: S) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => super::timestamp::serialize(time, s),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match Option::<String>::deserialize(d)? {
+            Some(s) => super::parse_timestamp(&s)
+                .map(Some)
+                .map_err(de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+// ============================================
+// VERSIONED FILES
+// ============================================
+
+/// Contents of projects.json
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectList {
+    pub projects: Vec<Project>,
+    /// Deleted projects, kept so the deletion reaches other devices
+    pub deleted: Vec<Tombstone>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectsFile<P> {
+    schema_version: u32,
+    #[serde(flatten)]
+    list: P,
+}
+
+/// Parses projects.json, migrating older layouts. Also returns the version
+/// found on disk so callers can rewrite outdated files.
+pub fn parse_projects(bytes: &[u8]) -> Result<(ProjectList, u32)> {
+    let value: Value = serde_json::from_slice(bytes)?;
+    let version = schema_version(&value);
+    let file: ProjectsFile<ProjectList> = serde_json::from_value(migrate(value, version)?)?;
+    for project in &file.list.projects {
+        project.validate()?;
+    }
+    Ok((file.list, version))
+}
+
+/// Current projects.json layout, ready to be written
+pub fn versioned_projects(list: &ProjectList) -> impl Serialize + '_ {
+    ProjectsFile {
+        schema_version: SCHEMA_VERSION,
+        list,
+    }
+}
+
+/// Version 1 files are the bare arrays written by the first releases
+fn schema_version(value: &Value) -> u32 {
+    value
+        .get("schemaVersion")
+        .and_then(Value::as_u64)
+        .map_or(1, |v| v as u32)
+}
+
+/// `MIGRATIONS[n]` upgrades a file from version `n + 1` to `n + 2`
+const MIGRATIONS: [fn(Value) -> Result<Value>; 4] = [v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5];
+
+fn migrate(mut value: Value, version: u32) -> Result<Value> {
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(Error::Invalid(format!(
+            "unsupported schema version {version} (this build reads up to {SCHEMA_VERSION})"
+        )));
+    }
+    for step in &MIGRATIONS[version as usize - 1..] {
+        value = step(value)?;
+    }
+    Ok(value)
+}
+
+fn v1_to_v2(value: Value) -> Result<Value> {
+    match value {
+        Value::Array(projects) => Ok(serde_json::json!({
+            "schemaVersion": 2,
+            "projects": projects,
+        })),
+        _ => Err(Error::Invalid("expected a list of projects".into())),
+    }
+}
+
+/// Adds modification times and the list of deleted projects
+fn v2_to_v3(mut value: Value) -> Result<Value> {
+    let never = serde_json::to_value(never())?;
+    if let Some(projects) = value["projects"].as_array_mut() {
+        for project in projects.iter_mut().filter_map(Value::as_object_mut) {
+            project.insert("modifiedAt".into(), never.clone());
+        }
+    }
+    value["schemaVersion"] = 3.into();
+    value["deleted"] = Value::Array(Vec::new());
+    Ok(value)
+}
+
+/// Adds billing details: projects start out not billable and active
+fn v3_to_v4(mut value: Value) -> Result<Value> {
+    if let Some(projects) = value["projects"].as_array_mut() {
+        for project in projects.iter_mut().filter_map(Value::as_object_mut) {
+            project.insert("billable".into(), false.into());
+            project.insert("archived".into(), false.into());
+        }
+    }
+    value["schemaVersion"] = 4.into();
+    Ok(value)
+}
+
+/// Adds the trash, which starts out empty. Older builds would show
+/// trashed projects as active and drop the field when saving.
+fn v4_to_v5(mut value: Value) -> Result<Value> {
+    value["schemaVersion"] = 5.into();
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_json(start: &str, end: &str, duration: u64, kind: &str) -> String {
+        format!(
+            r#"{{"id":"s1","projectId":"p1","startTime":"{start}","endTime":"{end}",
+                "duration":{duration},"date":"2025-01-01","type":"{kind}"}}"#
+        )
+    }
+
+    #[test]
+    fn parses_legacy_session() {
+        let json = session_json(
+            "2025-01-01T10:00:00.000Z",
+            "2025-01-01T10:25:00.000Z",
+            1500,
+            "pomodoro",
+        );
+        let session: Session = serde_json::from_str(&json).unwrap();
+        assert_eq!(session.kind, SessionKind::Pomodoro);
+        assert_eq!(session.duration, 1500);
+        assert_eq!(session.date, NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+        assert_eq!(session.timezone, None);
+        assert_eq!(session.modified_at, never());
+    }
+
+    #[test]
+    fn session_round_trips_through_json() {
+        let json = session_json(
+            "2025-01-01T10:00:00.000Z",
+            "2025-01-01T11:00:00.000Z",
+            3600,
+            "work",
+        );
+        let session: Session = serde_json::from_str(&json).unwrap();
+        let value = serde_json::to_value(&session).unwrap();
+        assert_eq!(value["startTime"], "2025-01-01T10:00:00.000Z");
+        assert_eq!(value["date"], "2025-01-01");
+        assert_eq!(value["type"], "work");
+        assert_eq!(value["modifiedAt"], "1970-01-01T00:00:00.000Z");
+        assert_eq!(serde_json::from_value::<Session>(value).unwrap(), session);
+    }
+
+    #[test]
+    fn rejects_unknown_kind() {
+        let json = session_json("2025-01-01T10:00:00Z", "2025-01-01T11:00:00Z", 60, "nap");
+        let err = serde_json::from_str::<Session>(&json)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown session type \"nap\""), "{err}");
+    }
+
+    #[test]
+    fn rejects_bad_timestamp() {
+        let json = session_json("yesterday", "2025-01-01T11:00:00Z", 60, "work");
+        let err = serde_json::from_str::<Session>(&json)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("session s1: invalid timestamp"), "{err}");
+    }
+
+    #[test]
+    fn rejects_end_before_start() {
+        let json = session_json("2025-01-01T11:00:00Z", "2025-01-01T10:00:00Z", 60, "work");
+        let err = serde_json::from_str::<Session>(&json)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("ends before it starts"), "{err}");
+    }
+
+    #[test]
+    fn keeps_local_date_and_timezone() {
+        // 23:30 UTC is already the next day in Rome
+        let json = r#"{"id":"s1","projectId":"p1","startTime":"2025-01-01T23:30:00.000Z",
+            "endTime":"2025-01-02T00:00:00.000Z","duration":1800,"date":"2025-01-02",
+            "timezone":"Europe/Rome","type":"work"}"#;
+        let session: Session = serde_json::from_str(json).unwrap();
+        assert_eq!(session.date, NaiveDate::from_ymd_opt(2025, 1, 2).unwrap());
+        assert_eq!(session.timezone, Some(chrono_tz::Europe::Rome));
+        let value = serde_json::to_value(&session).unwrap();
+        assert_eq!(
+            (&value["date"], &value["timezone"]),
+            (&"2025-01-02".into(), &"Europe/Rome".into())
+        );
+
+        let moved = json.replace("2025-01-02\",", "2025-01-05\",");
+        assert!(serde_json::from_str::<Session>(&moved).is_err());
+        let unknown = json.replace("Europe/Rome", "Mars/Olympus");
+        let err = serde_json::from_str::<Session>(&unknown)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown timezone"), "{err}");
+    }
+
+    #[test]
+    fn reads_notes_and_tags() {
+        let json = r#"{"id":"s1","projectId":"p1","startTime":"2025-01-01T10:00:00.000Z",
+            "endTime":"2025-01-01T10:25:00.000Z","duration":1500,"type":"pomodoro",
+            "notes":"Reviewed the invoice","tags":["acme","review"]}"#;
+        let session: Session = serde_json::from_str(json).unwrap();
+        assert_eq!(session.notes.as_deref(), Some("Reviewed the invoice"));
+        assert_eq!(session.tags, ["acme", "review"]);
+        let value = serde_json::to_value(&session).unwrap();
+        assert_eq!(value["tags"], serde_json::json!(["acme", "review"]));
+
+        let bad = json.replace("\"review\"", "\"Needs Review\"");
+        let err = serde_json::from_str::<Session>(&bad)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("invalid tag"), "{err}");
+
+        // Sessions without either leave them out
+        let plain = session_json("2025-01-01T10:00:00Z", "2025-01-01T11:00:00Z", 60, "work");
+        let value = serde_json::to_value(serde_json::from_str::<Session>(&plain).unwrap()).unwrap();
+        assert!(value.get("notes").is_none() && value.get("tags").is_none());
+    }
+
+    #[test]
+    fn parses_tags() {
+        assert_eq!(
+            parse_tags(&["#Client, review", "client  Deep-Work"]).unwrap(),
+            ["client", "review", "deep-work"]
+        );
+        assert!(parse_tags(&["ok", &"x".repeat(41)]).is_err());
+        assert_eq!(normalize_tag("  #"), None);
+    }
+
+    #[test]
+    fn rejects_duration_longer_than_span() {
+        let json = session_json("2025-01-01T10:00:00Z", "2025-01-01T10:01:00Z", 3600, "work");
+        assert!(serde_json::from_str::<Session>(&json).is_err());
+    }
+
+    #[test]
+    fn migrates_v1_projects() {
+        let v1 = br##"[{"id":"proj1","name":"Work Project","color":"#00ff88"}]"##;
+        let (list, version) = parse_projects(v1).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(list.projects[0].name, "Work Project");
+        assert_eq!(list.projects[0].modified_at, never());
+        assert!(list.deleted.is_empty());
+    }
+
+    #[test]
+    fn migrates_v2_projects() {
+        let v2 = br##"{"schemaVersion":2,"projects":[{"id":"p","name":"P","color":"#fff"}]}"##;
+        let (list, version) = parse_projects(v2).unwrap();
+        assert_eq!(version, 2);
+        assert_eq!(list.projects[0].modified_at, never());
+    }
+
+    #[test]
+    fn reads_current_projects() {
+        let list = ProjectList {
+            projects: vec![Project {
+                id: "p".into(),
+                name: "P".into(),
+                color: "#fff".into(),
+                client: Some("Acme".into()),
+                billable: true,
+                hourly_rate: Some(80.0),
+                currency: Some("EUR".into()),
+                archived: false,
+                trashed_at: Some("2025-01-03T10:00:00Z".parse().unwrap()),
+                modified_at: "2025-01-01T10:00:00Z".parse().unwrap(),
+            }],
+            deleted: vec![Tombstone {
+                id: "q".into(),
+                deleted_at: "2025-01-02T10:00:00Z".parse().unwrap(),
+                date: None,
+            }],
+        };
+        let value = serde_json::to_value(&list.projects[0]).unwrap();
+        assert_eq!(value["trashedAt"], "2025-01-03T10:00:00.000Z");
+        let bytes = serde_json::to_vec(&versioned_projects(&list)).unwrap();
+        assert_eq!(parse_projects(&bytes).unwrap(), (list, SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn migrates_v3_projects() {
+        let v3 = br##"{"schemaVersion":3,"deleted":[],"projects":[
+            {"id":"p","name":"P","color":"#fff","modifiedAt":"2025-01-01T10:00:00.000Z"}]}"##;
+        let (list, version) = parse_projects(v3).unwrap();
+        assert_eq!(version, 3);
+        let project = &list.projects[0];
+        assert!(!project.billable && project.is_active());
+        assert_eq!((&project.client, project.hourly_rate), (&None, None));
+    }
+
+    #[test]
+    fn validates_billing_details() {
+        let valid = Project {
+            id: "p".into(),
+            name: "P".into(),
+            hourly_rate: Some(80.0),
+            currency: Some("EUR".into()),
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        let cases = [
+            (
+                Project {
+                    client: Some(" ".into()),
+                    ..valid.clone()
+                },
+                "client is empty",
+            ),
+            (
+                Project {
+                    hourly_rate: Some(-1.0),
+                    ..valid.clone()
+                },
+                "zero or more",
+            ),
+            (
+                Project {
+                    currency: None,
+                    ..valid.clone()
+                },
+                "needs a currency",
+            ),
+            (
+                Project {
+                    currency: Some("euro".into()),
+                    ..valid.clone()
+                },
+                "three-letter code",
+            ),
+        ];
+        for (project, message) in cases {
+            let err = project.validate().unwrap_err().to_string();
+            assert!(err.contains(message), "{err}");
+        }
+    }
+
+    #[test]
+    fn purge_date_follows_the_retention() {
+        let mut project = Project {
+            id: "p".into(),
+            name: "P".into(),
+            ..Default::default()
+        };
+        assert_eq!(project.purge_at(), None);
+        project.trashed_at = Some("2025-01-01T10:00:00Z".parse().unwrap());
+        assert!(!project.is_active());
+        assert_eq!(
+            project.purge_at(),
+            Some("2025-01-31T10:00:00Z".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_newer_schema() {
+        let err = parse_projects(br#"{"schemaVersion":99,"projects":[]}"#).unwrap_err();
+        assert!(err.to_string().contains("unsupported schema version 99"));
+    }
+
+    #[test]
+    fn rejects_unnamed_project() {
+        let err = parse_projects(br##"[{"id":"p","name":" ","color":"#fff"}]"##).unwrap_err();
+        assert!(err.to_string().contains("project p: name is empty"));
+    }
+}
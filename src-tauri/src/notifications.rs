@@ -0,0 +1,317 @@
+// Productivity Tracker - Notifications
+// What the desktop notification says when a countdown runs out, which
+// buttons it offers, and the sound played with it. Shown from Rust, so it
+// arrives whether or not the window is open.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::model::{Session, SessionKind};
+use crate::pomodoro::{Phase, PomodoroStatus};
+
+/// Minutes the "+5 min" button gives the phase that ended
+pub const EXTEND_MINUTES: u64 = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    pub sound: bool,
+    /// Played when a pomodoro ends instead of the system sound
+    pub pomodoro_sound: Option<PathBuf>,
+    /// Played when a break ends instead of the system sound
+    pub break_sound: Option<PathBuf>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sound: true,
+            pomodoro_sound: None,
+            break_sound: None,
+        }
+    }
+}
+
+impl NotificationSettings {
+    pub fn validate(&self) -> Result<()> {
+        for path in self.pomodoro_sound.iter().chain(&self.break_sound) {
+            if !path.is_file() {
+                return Err(Error::Invalid(format!(
+                    "sound file {} not found",
+                    path.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The file to play after `kind` of session, if one was chosen
+    pub fn sound_file(&self, kind: SessionKind) -> Option<&Path> {
+        match kind {
+            SessionKind::Break => self.break_sound.as_deref(),
+            _ => self.pomodoro_sound.as_deref(),
+        }
+    }
+}
+
+/// Buttons of a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertAction {
+    /// Start the break due, or the next pomodoro after a break
+    Next,
+    /// Another EXTEND_MINUTES of the phase that ended
+    Extend,
+    /// Stop the phase that started on its own
+    Stop,
+}
+
+impl AlertAction {
+    pub fn id(self) -> &'static str {
+        match self {
+            AlertAction::Next => "next",
+            AlertAction::Extend => "extend",
+            AlertAction::Stop => "stop",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        [AlertAction::Next, AlertAction::Extend, AlertAction::Stop]
+            .into_iter()
+            .find(|action| action.id() == id)
+    }
+}
+
+/// Notifications differ by the phase that ended and by whether the next
+/// one started on its own, each with its own set of buttons
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlertKind {
+    pub was_break: bool,
+    pub started: bool,
+}
+
+impl AlertKind {
+    pub const ALL: [AlertKind; 4] = [
+        AlertKind {
+            was_break: false,
+            started: false,
+        },
+        AlertKind {
+            was_break: false,
+            started: true,
+        },
+        AlertKind {
+            was_break: true,
+            started: false,
+        },
+        AlertKind {
+            was_break: true,
+            started: true,
+        },
+    ];
+
+    /// Identifies the set of buttons to the notification service
+    pub fn id(self) -> String {
+        let phase = if self.was_break { "break" } else { "pomodoro" };
+        let started = if self.started { "-started" } else { "" };
+        format!("{phase}{started}")
+    }
+
+    /// Buttons and their labels: starting the next phase unless it already
+    /// runs, or else stopping it
+    pub fn actions(self) -> Vec<(AlertAction, String)> {
+        let mut actions = Vec::new();
+        if !self.started {
+            let next = if self.was_break {
+                "Start pomodoro"
+            } else {
+                "Start break"
+            };
+            actions.push((AlertAction::Next, next.to_string()));
+        }
+        actions.push((AlertAction::Extend, format!("+{EXTEND_MINUTES} min")));
+        if self.started {
+            actions.push((AlertAction::Stop, "Stop".to_string()));
+        }
+        actions
+    }
+}
+
+pub struct Alert {
+    pub kind: AlertKind,
+    pub title: String,
+    pub body: String,
+}
+
+/// What to say when `session`'s countdown ran out; `pomodoro` is the cycle
+/// after it and `started` tells whether its next phase started on its own
+pub fn alert(session: &Session, pomodoro: &PomodoroStatus, started: bool) -> Alert {
+    let kind = AlertKind {
+        was_break: session.kind == SessionKind::Break,
+        started,
+    };
+    let next = match pomodoro.cycle.next {
+        Phase::Work => "the next pomodoro",
+        Phase::ShortBreak => "a short break",
+        Phase::LongBreak => "a long break",
+    };
+    let minutes = pomodoro.next_length / 60;
+    let next = if started {
+        format!("Started {next} ({minutes} min).")
+    } else {
+        format!("Time for {next} ({minutes} min).")
+    };
+    let (title, body) = if kind.was_break {
+        ("☕ Break finished!".to_string(), next)
+    } else {
+        let done = pomodoro.cycle.completed;
+        let today = if done == 1 { "pomodoro" } else { "pomodoros" };
+        (
+            "🍅 Pomodoro completed!".to_string(),
+            format!("{done} {today} today. {next}"),
+        )
+    };
+    Alert { kind, title, body }
+}
+
+/// Theme sound the notification service plays when no file was chosen
+pub fn system_sound(kind: SessionKind) -> &'static str {
+    let finished_break = kind == SessionKind::Break;
+    if cfg!(target_os = "macos") {
+        if finished_break {
+            "Ping"
+        } else {
+            "Glass"
+        }
+    } else if cfg!(windows) {
+        if finished_break {
+            "Default"
+        } else {
+            "Reminder"
+        }
+    } else if finished_break {
+        // freedesktop sound theme names
+        "bell"
+    } else {
+        "complete"
+    }
+}
+
+/// Plays a sound file with the system's player, without waiting for it
+pub fn play(path: &Path) {
+    let players: &[(&str, &[&str])] = if cfg!(target_os = "macos") {
+        &[("afplay", &[])]
+    } else if cfg!(windows) {
+        &[("powershell", &["-NoProfile", "-Command"])]
+    } else {
+        &[("paplay", &[]), ("pw-play", &[]), ("aplay", &["-q"])]
+    };
+    for (program, args) in players {
+        let mut command = Command::new(program);
+        command.args(*args);
+        if cfg!(windows) {
+            // Media.SoundPlayer only plays WAV files
+            let path = path.display().to_string().replace('\'', "''");
+            command.arg(format!(
+                "(New-Object Media.SoundPlayer '{path}').PlaySync()"
+            ));
+        } else {
+            command.arg(path);
+        }
+        let spawned = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(mut child) => {
+                thread::spawn(move || child.wait());
+                return;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                log::warn!("playing {} failed: {e}", path.display());
+                return;
+            }
+        }
+    }
+    log::warn!("no sound player found for {}", path.display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pomodoro::{Cycle, PomodoroSettings};
+    use crate::testing;
+
+    fn session(kind: SessionKind) -> Session {
+        Session {
+            kind,
+            ..testing::session("s1", "web", "2025-03-10T09:00:00Z", 25)
+        }
+    }
+
+    fn status(next: Phase, completed: u32) -> PomodoroStatus {
+        let settings = PomodoroSettings::default();
+        PomodoroStatus {
+            next_length: settings.length(next),
+            settings,
+            cycle: Cycle {
+                completed,
+                next,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn says_what_comes_next() {
+        let done = alert(
+            &session(SessionKind::Pomodoro),
+            &status(Phase::LongBreak, 4),
+            false,
+        );
+        assert_eq!(done.kind.id(), "pomodoro");
+        assert_eq!(
+            done.body,
+            "4 pomodoros today. Time for a long break (15 min)."
+        );
+        let actions: Vec<_> = done.kind.actions().into_iter().map(|(a, _)| a).collect();
+        assert_eq!(actions, [AlertAction::Next, AlertAction::Extend]);
+
+        let back = alert(&session(SessionKind::Break), &status(Phase::Work, 4), true);
+        assert_eq!(back.kind.id(), "break-started");
+        assert_eq!(back.body, "Started the next pomodoro (25 min).");
+        let actions: Vec<_> = back.kind.actions().into_iter().map(|(a, _)| a).collect();
+        assert_eq!(actions, [AlertAction::Extend, AlertAction::Stop]);
+
+        for action in [AlertAction::Next, AlertAction::Extend, AlertAction::Stop] {
+            assert_eq!(AlertAction::from_id(action.id()), Some(action));
+        }
+        assert_eq!(AlertAction::from_id("tap"), None);
+    }
+
+    #[test]
+    fn checks_sound_files() {
+        let settings: NotificationSettings = serde_json::from_str(r#"{"sound": false}"#).unwrap();
+        assert!(settings.enabled && !settings.sound);
+        assert!(settings.validate().is_ok());
+
+        let missing = NotificationSettings {
+            break_sound: Some(PathBuf::from("/nonexistent/bell.ogg")),
+            ..Default::default()
+        };
+        assert!(missing.validate().is_err());
+        assert_eq!(
+            missing.sound_file(SessionKind::Break),
+            Some(Path::new("/nonexistent/bell.ogg"))
+        );
+        assert_eq!(missing.sound_file(SessionKind::Pomodoro), None);
+    }
+}
@@ -0,0 +1,116 @@
+// Productivity Tracker - Desktop notifications
+// Shows the notification of a countdown that ran out through the
+// notification plugin, and carries out the buttons clicked on it
+
+use chrono::Utc;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::{Action, ActionPerformed, ActionType, NotificationExt};
+
+use crate::commands::{self, StoreState};
+use crate::error::Result;
+use crate::model::Session;
+use crate::notifications::{self, AlertAction, AlertKind, EXTEND_MINUTES};
+use crate::pomodoro::{self, PomodoroStatus};
+use crate::ticker::{self, TimerState};
+use crate::tray;
+
+/// Key of the finished session in a notification's extra data, read back
+/// when one of its buttons is clicked
+const SESSION_KEY: &str = "session";
+
+pub fn init(app: &AppHandle) -> tauri_plugin_notification::Result<()> {
+    let types = AlertKind::ALL
+        .into_iter()
+        .map(|kind| {
+            let actions = kind
+                .actions()
+                .into_iter()
+                .map(|(action, title)| Action::builder(action.id(), title).build())
+                .collect();
+            ActionType::builder(kind.id()).actions(actions).build()
+        })
+        .collect();
+    let notification = app.notification();
+    notification.register_action_types(types)?;
+    let handle = app.clone();
+    notification.on_action(move |performed| handle_action(&handle, performed))
+}
+
+/// Tells the user `session`'s countdown ran out; `pomodoro` is the cycle
+/// after it and `started` whether its next phase started on its own
+pub fn finished(app: &AppHandle, session: &Session, pomodoro: &PomodoroStatus, started: bool) {
+    let settings = app
+        .state::<StoreState>()
+        .lock()
+        .unwrap()
+        .notification_settings();
+    let settings = match settings {
+        Ok(settings) if settings.enabled => settings,
+        Ok(_) => return,
+        Err(e) => {
+            log::warn!("reading the notification settings failed: {e}");
+            return;
+        }
+    };
+
+    let alert = notifications::alert(session, pomodoro, started);
+    let mut notification = app
+        .notification()
+        .builder()
+        .title(alert.title)
+        .body(alert.body)
+        .action_type_id(alert.kind.id())
+        .extra(SESSION_KEY, session);
+    if settings.sound {
+        match settings.sound_file(session.kind) {
+            Some(path) => notifications::play(path),
+            None => notification = notification.sound(notifications::system_sound(session.kind)),
+        }
+    }
+    if let Err(e) = notification.show() {
+        log::warn!("showing the notification failed: {e}");
+    }
+}
+
+fn handle_action(app: &AppHandle, performed: &ActionPerformed) {
+    let Some(action) = AlertAction::from_id(performed.action_id()) else {
+        // A click on the notification itself
+        tray::show_main_window(app);
+        return;
+    };
+    let result = match action {
+        AlertAction::Next => {
+            commands::start_pomodoro(app.clone(), app.state(), app.state(), None).map(drop)
+        }
+        AlertAction::Extend => {
+            let session = performed
+                .notification()
+                .and_then(|notification| notification.extra().get(SESSION_KEY))
+                .and_then(|session| serde_json::from_value(session.clone()).ok());
+            match session {
+                Some(session) => extend(app, &session),
+                None => Ok(()),
+            }
+        }
+        AlertAction::Stop => {
+            commands::stop_timer(app.clone(), app.state(), app.state(), None, None)
+        }
+    };
+    if let Err(e) = result {
+        ticker::report_error(app, &format!("notification action {}", action.id()), &e);
+    }
+}
+
+/// Another few minutes of the phase `session` ended
+fn extend(app: &AppHandle, session: &Session) -> Result<()> {
+    let now = Utc::now();
+    let (saved, status) = {
+        let (timer, store) = (app.state::<TimerState>(), app.state::<StoreState>());
+        let (mut timer, mut store) = (timer.lock().unwrap(), store.lock().unwrap());
+        let saved = pomodoro::extend(&mut store, &mut timer, session, EXTEND_MINUTES * 60, now)?;
+        (saved, timer.status(now)?)
+    };
+    ticker::announce_saved(app, &saved);
+    ticker::publish_status(app, status.as_ref());
+    Ok(())
+}
@@ -0,0 +1,286 @@
+// Productivity Tracker - Minimal PDF writer
+// Just enough PDF 1.4 for printable reports: A4 pages of text in the
+// standard Helvetica fonts (which every viewer has, so nothing is
+// embedded), lines and filled rectangles. Text is WinAnsi encoded;
+// characters outside it print as "?".
+
+use std::fmt::Write as _;
+
+/// A4 in points
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+
+/// Advance widths of Helvetica, `' '..='~'`, in 1/1000 of the font size
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Same for Helvetica-Bold
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+/// Width of characters outside the tables (accented letters and such)
+const OTHER_WIDTH: u16 = 556;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+
+    /// Width of `text` in points at `size`
+    pub fn width(self, text: &str, size: f32) -> f32 {
+        let table = match self {
+            Font::Regular => &HELVETICA,
+            Font::Bold => &HELVETICA_BOLD,
+        };
+        let units: u32 = text
+            .chars()
+            .map(|c| match c {
+                ' '..='~' => table[c as usize - 32],
+                _ => OTHER_WIDTH,
+            } as u32)
+            .sum();
+        units as f32 * size / 1000.0
+    }
+}
+
+/// RGB, each 0.0 to 1.0
+pub type Color = [f32; 3];
+
+pub const BLACK: Color = [0.0, 0.0, 0.0];
+
+/// `#rrggbb` or `#rgb`
+pub fn parse_color(hex: &str) -> Option<Color> {
+    let hex = hex.strip_prefix('#')?;
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok().map(|v| v as f32 / 255.0);
+    match hex.len() {
+        6 => Some([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        ]),
+        3 => Some([
+            channel(&hex[0..1].repeat(2))?,
+            channel(&hex[1..2].repeat(2))?,
+            channel(&hex[2..3].repeat(2))?,
+        ]),
+        _ => None,
+    }
+}
+
+/// A document being drawn, page by page. Coordinates are in points from
+/// the bottom left corner of the page, as in PDF itself.
+pub struct Document {
+    title: String,
+    pages: Vec<String>,
+}
+
+impl Document {
+    pub fn new(title: &str) -> Self {
+        Document {
+            title: title.to_string(),
+            pages: vec![String::new()],
+        }
+    }
+
+    pub fn new_page(&mut self) {
+        self.pages.push(String::new());
+    }
+
+    fn page(&mut self) -> &mut String {
+        self.pages.last_mut().expect("a document has a page")
+    }
+
+    pub fn text(&mut self, x: f32, y: f32, font: Font, size: f32, color: Color, text: &str) {
+        let [r, g, b] = color;
+        let encoded = literal(text);
+        let _ = writeln!(
+            self.page(),
+            "BT /{} {size} Tf {r} {g} {b} rg {x:.2} {y:.2} Td ({encoded}) Tj ET",
+            font.resource()
+        );
+    }
+
+    /// Text ending at `right`
+    pub fn text_right(&mut self, right: f32, y: f32, font: Font, size: f32, text: &str) {
+        let x = right - font.width(text, size);
+        self.text(x, y, font, size, BLACK, text);
+    }
+
+    pub fn line(&mut self, x1: f32, x2: f32, y: f32, width: f32, gray: f32) {
+        let _ = writeln!(
+            self.page(),
+            "{gray} G {width} w {x1:.2} {y:.2} m {x2:.2} {y:.2} l S"
+        );
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let [r, g, b] = color;
+        let _ = writeln!(
+            self.page(),
+            "{r} {g} {b} rg {x:.2} {y:.2} {width:.2} {height:.2} re f"
+        );
+    }
+
+    /// The finished file
+    pub fn finish(self) -> Vec<u8> {
+        let pages = self.pages.len();
+        // 1 catalog, 2 page tree, 3-4 fonts, 5 info, then page and content
+        // objects in pairs
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {pages} >>",
+                (0..pages)
+                    .map(|i| format!("{} 0 R", 6 + 2 * i))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )
+            .into_bytes(),
+            font_object("Helvetica"),
+            font_object("Helvetica-Bold"),
+        ];
+        let mut info = b"<< /Producer (Productivity Tracker) /Title (".to_vec();
+        info.extend(encode(&literal(&self.title)));
+        info.extend_from_slice(b") >>");
+        objects.push(info);
+        for (i, content) in self.pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+                     /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                    7 + 2 * i
+                )
+                .into_bytes(),
+            );
+            let content = encode(content);
+            let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+            stream.extend(content);
+            stream.extend_from_slice(b"\nendstream");
+            objects.push(stream);
+        }
+
+        // A binary comment line tells tools the file is not plain text
+        let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+            out.extend(object);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        );
+        out.extend(table.into_bytes());
+        out
+    }
+}
+
+fn font_object(name: &str) -> Vec<u8> {
+    format!("<< /Type /Font /Subtype /Type1 /BaseFont /{name} /Encoding /WinAnsiEncoding >>")
+        .into_bytes()
+}
+
+/// Escapes `text` for a PDF string literal. Still a Rust string: content
+/// streams are built as text and encoded to WinAnsi bytes at the end.
+fn literal(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)")
+}
+
+/// WinAnsi (Windows-1252) bytes of `text`
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '\u{0}'..='\u{7f}' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_text() {
+        assert_eq!(Font::Regular.width("Hi", 10.0), (722 + 222) as f32 / 100.0);
+        assert!(Font::Bold.width("Total", 10.0) > Font::Regular.width("Total", 10.0));
+    }
+
+    #[test]
+    fn writes_a_valid_structure() {
+        let mut doc = Document::new("Timesheet (January)");
+        doc.text(50.0, 800.0, Font::Bold, 18.0, BLACK, "Café – 50 €");
+        doc.new_page();
+        doc.line(50.0, 545.0, 400.0, 0.5, 0.8);
+        let pdf = doc.finish();
+        let contains = |needle: &[u8]| pdf.windows(needle.len()).any(|w| w == needle);
+
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        assert!(contains(b"/Count 2"));
+        assert!(contains(b"/Title (Timesheet \\(January\\))"));
+        // WinAnsi bytes for é, – and €
+        assert!(contains(b"(Caf\xe9 \x96 50 \x80) Tj"));
+
+        // Every xref offset points at its object
+        let xref = pdf.windows(6).rposition(|w| w == b"\nxref\n").unwrap() + 1;
+        let table = std::str::from_utf8(&pdf[xref..]).unwrap();
+        let offsets: Vec<usize> = table
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        assert_eq!(offsets.len(), 9);
+        for (i, offset) in offsets.iter().enumerate() {
+            assert!(pdf[*offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        }
+        let startxref: usize = table.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert_eq!(startxref, xref);
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("#ff0000"), Some([1.0, 0.0, 0.0]));
+        assert_eq!(parse_color("#fff"), Some([1.0, 1.0, 1.0]));
+        assert_eq!(parse_color("red"), None);
+    }
+}
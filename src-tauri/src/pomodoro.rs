@@ -0,0 +1,430 @@
+// Productivity Tracker - Pomodoro cycles
+// Work, then a short break, with a long break once every few pomodoros.
+// The timer engine only counts down; this decides what comes after a
+// countdown runs out, and starts it when phases are set to follow on
+// their own. The cycle lives in pomodoro.json so a restart, or the `pt`
+// CLI, carries on where it was; the lengths are in settings.json.
+
+use std::fs;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::model::{Session, SessionKind};
+use crate::store::{read_json, write_json_atomic, DirLock, Store};
+use crate::timer::{TimerEngine, TimerStatus, DEFAULT_MIN_DURATION};
+
+const POMODORO_FILE: &str = "pomodoro.json";
+
+pub const MAX_WORK_MINUTES: u32 = 180;
+pub const MAX_BREAK_MINUTES: u32 = 120;
+pub const MAX_LONG_BREAK_EVERY: u32 = 12;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Phase {
+    #[default]
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PomodoroSettings {
+    pub work_minutes: u32,
+    pub short_break_minutes: u32,
+    pub long_break_minutes: u32,
+    /// Pomodoros in a set, each set ending with a long break
+    pub long_break_every: u32,
+    /// Start the break as soon as a pomodoro ends
+    pub auto_start_breaks: bool,
+    /// Start the next pomodoro, on the same project, as soon as a break ends
+    pub auto_start_work: bool,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+            auto_start_breaks: false,
+            auto_start_work: false,
+        }
+    }
+}
+
+impl PomodoroSettings {
+    pub fn validate(&self) -> Result<()> {
+        let check = |what: &str, value: u32, max: u32| {
+            if (1..=max).contains(&value) {
+                Ok(())
+            } else {
+                Err(Error::Invalid(format!(
+                    "{what} must be between 1 and {max}"
+                )))
+            }
+        };
+        check("a pomodoro's minutes", self.work_minutes, MAX_WORK_MINUTES)?;
+        check(
+            "a short break's minutes",
+            self.short_break_minutes,
+            MAX_BREAK_MINUTES,
+        )?;
+        check(
+            "a long break's minutes",
+            self.long_break_minutes,
+            MAX_BREAK_MINUTES,
+        )?;
+        check(
+            "the pomodoros before a long break",
+            self.long_break_every,
+            MAX_LONG_BREAK_EVERY,
+        )
+    }
+
+    /// Length of `phase` in seconds
+    pub fn length(&self, phase: Phase) -> u64 {
+        let minutes = match phase {
+            Phase::Work => self.work_minutes,
+            Phase::ShortBreak => self.short_break_minutes,
+            Phase::LongBreak => self.long_break_minutes,
+        };
+        minutes as u64 * 60
+    }
+}
+
+/// Where the user is in their pomodoros
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Cycle {
+    /// Day the counts are for; a new day starts a new set
+    pub date: Option<NaiveDate>,
+    /// Pomodoros completed that day
+    pub completed: u32,
+    /// Completed since the last long break
+    pub in_set: u32,
+    pub next: Phase,
+    /// Project of the last pomodoro, taken up again after the break
+    pub project_id: Option<String>,
+    /// Start of the countdown added on to the last phase, which doesn't
+    /// move the cycle on when it runs out
+    pub extension: Option<DateTime<Utc>>,
+}
+
+impl Cycle {
+    /// The counts as of `date`
+    pub fn on(mut self, date: NaiveDate) -> Self {
+        if self.date != Some(date) {
+            self.date = Some(date);
+            self.completed = 0;
+            self.in_set = 0;
+            self.next = Phase::Work;
+        }
+        self
+    }
+
+    /// The break the set calls for now
+    pub fn due_break(&self, settings: &PomodoroSettings) -> Phase {
+        if self.in_set >= settings.long_break_every {
+            Phase::LongBreak
+        } else {
+            Phase::ShortBreak
+        }
+    }
+
+    /// Moves on after a countdown ran out on `date`. Breaks follow
+    /// pomodoros; a break at least as long as a long break, however it was
+    /// started, ends the set.
+    pub fn finished(self, session: &Session, settings: &PomodoroSettings, date: NaiveDate) -> Self {
+        let mut cycle = self.on(date);
+        if cycle.extension.take() == Some(session.start_time) {
+            return cycle;
+        }
+        if session.kind == SessionKind::Break {
+            if session.duration >= settings.length(Phase::LongBreak) {
+                cycle.in_set = 0;
+            }
+            cycle.next = Phase::Work;
+        } else {
+            cycle.completed += 1;
+            cycle.in_set += 1;
+            cycle.project_id = Some(session.project_id.clone());
+            cycle.next = cycle.due_break(settings);
+        }
+        cycle
+    }
+}
+
+/// Sent to the webview with every change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PomodoroStatus {
+    pub settings: PomodoroSettings,
+    pub cycle: Cycle,
+    /// Length of the next phase in seconds
+    pub next_length: u64,
+}
+
+pub fn status(store: &Store, now: DateTime<Utc>) -> Result<PomodoroStatus> {
+    let settings = store.pomodoro_settings()?;
+    let cycle = load(store)?.on(store.calendar()?.date_of(now));
+    Ok(PomodoroStatus {
+        next_length: settings.length(cycle.next),
+        settings,
+        cycle,
+    })
+}
+
+/// Starts a new set: no pomodoros done, work next
+pub fn reset(store: &Store) -> Result<()> {
+    let _lock = DirLock::acquire(store.dir())?;
+    match fs::remove_file(store.dir().join(POMODORO_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Moves the cycle on after `session`'s countdown ran out
+pub fn finished(store: &Store, session: &Session, now: DateTime<Utc>) -> Result<Cycle> {
+    let settings = store.pomodoro_settings()?;
+    let date = store.calendar()?.date_of(now);
+    let _lock = DirLock::acquire(store.dir())?;
+    let cycle = load(store)?.finished(session, &settings, date);
+    write_json_atomic(&store.dir().join(POMODORO_FILE), &cycle)?;
+    Ok(cycle)
+}
+
+/// Starts the phase after `cycle` if the settings let it follow on its
+/// own; work goes on with the last pomodoro's project if still active
+pub fn auto_start(
+    store: &mut Store,
+    timer: &mut TimerEngine,
+    cycle: &Cycle,
+    now: DateTime<Utc>,
+) -> Result<Option<TimerStatus>> {
+    let settings = store.pomodoro_settings()?;
+    let start = match cycle.next {
+        Phase::Work => {
+            settings.auto_start_work
+                && cycle
+                    .project_id
+                    .as_ref()
+                    .is_some_and(|id| store.active_project(id).is_ok())
+        }
+        Phase::ShortBreak | Phase::LongBreak => settings.auto_start_breaks,
+    };
+    if !start {
+        return Ok(None);
+    }
+    start_next(store, timer, None, now)?;
+    timer.status(now)
+}
+
+/// Starts the next phase: the break due, or a pomodoro on `project_id`
+/// (the last pomodoro's if `None`). A work timer stopped to take the
+/// break is saved first; returns what was saved.
+pub fn start_next(
+    store: &mut Store,
+    timer: &mut TimerEngine,
+    project_id: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<Vec<Session>> {
+    let PomodoroStatus {
+        cycle, next_length, ..
+    } = status(store, now)?;
+    if cycle.next != Phase::Work {
+        return timer.start_break(next_length, now, |s| store.add_session(s));
+    }
+    let project_id = project_id
+        .map(str::to_string)
+        .or(cycle.project_id)
+        .ok_or_else(|| Error::Invalid("pick a project for the pomodoro".into()))?;
+    store.active_project(&project_id)?;
+    timer.start(&project_id, Some(next_length), now)?;
+    Ok(Vec::new())
+}
+
+/// Gives the phase `session` ended another `seconds`: a countdown on its
+/// project, or another break, that the cycle doesn't count. The timer
+/// running meanwhile is stopped and saved; returns what was saved.
+pub fn extend(
+    store: &mut Store,
+    timer: &mut TimerEngine,
+    session: &Session,
+    seconds: u64,
+    now: DateTime<Utc>,
+) -> Result<Vec<Session>> {
+    if session.kind != SessionKind::Break {
+        store.active_project(&session.project_id)?;
+    }
+    let saved = timer.stop(true, DEFAULT_MIN_DURATION, now, |s| store.add_session(s))?;
+    if session.kind == SessionKind::Break {
+        timer.start_break(seconds, now, |s| store.add_session(s))?;
+    } else {
+        timer.start(&session.project_id, Some(seconds), now)?;
+    }
+    let _lock = DirLock::acquire(store.dir())?;
+    let cycle = Cycle {
+        extension: Some(now),
+        ..load(store)?
+    };
+    write_json_atomic(&store.dir().join(POMODORO_FILE), &cycle)?;
+    Ok(saved)
+}
+
+fn load(store: &Store) -> Result<Cycle> {
+    Ok(read_json(&store.dir().join(POMODORO_FILE))?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn session(kind: SessionKind, minutes: i64) -> Session {
+        let project = if kind == SessionKind::Break {
+            "break"
+        } else {
+            "web"
+        };
+        Session {
+            kind,
+            ..testing::session("s1", project, "2025-03-10T09:00:00Z", minutes)
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    #[test]
+    fn schedules_a_long_break_every_few_pomodoros() {
+        let settings = PomodoroSettings {
+            long_break_every: 2,
+            ..Default::default()
+        };
+        let pomodoro = session(SessionKind::Pomodoro, 25);
+        let short = session(SessionKind::Break, 5);
+        let long = session(SessionKind::Break, 15);
+
+        let cycle = Cycle::default().finished(&pomodoro, &settings, date(10));
+        assert_eq!(
+            (cycle.next, cycle.completed, cycle.in_set),
+            (Phase::ShortBreak, 1, 1)
+        );
+        assert_eq!(cycle.project_id.as_deref(), Some("web"));
+
+        let cycle = cycle.finished(&short, &settings, date(10));
+        assert_eq!((cycle.next, cycle.in_set), (Phase::Work, 1));
+        let cycle = cycle.finished(&pomodoro, &settings, date(10));
+        assert_eq!(
+            (cycle.next, cycle.completed, cycle.in_set),
+            (Phase::LongBreak, 2, 2)
+        );
+
+        // A short break doesn't end the set, the long break does
+        let cycle = cycle.finished(&short, &settings, date(10));
+        assert_eq!(cycle.due_break(&settings), Phase::LongBreak);
+        let cycle = cycle.finished(&long, &settings, date(10));
+        assert_eq!(
+            (cycle.next, cycle.completed, cycle.in_set),
+            (Phase::Work, 2, 0)
+        );
+
+        // Counted per day
+        let cycle = cycle.finished(&pomodoro, &settings, date(10)).on(date(11));
+        assert_eq!(
+            (cycle.next, cycle.completed, cycle.in_set),
+            (Phase::Work, 0, 0)
+        );
+    }
+
+    #[test]
+    fn validates_settings() {
+        let settings: PomodoroSettings = serde_json::from_str(r#"{"workMinutes": 50}"#).unwrap();
+        assert_eq!((settings.work_minutes, settings.long_break_every), (50, 4));
+        assert_eq!(settings.length(Phase::LongBreak), 15 * 60);
+        assert!(settings.validate().is_ok());
+
+        let invalid = [
+            PomodoroSettings {
+                work_minutes: 0,
+                ..Default::default()
+            },
+            PomodoroSettings {
+                long_break_minutes: MAX_BREAK_MINUTES + 1,
+                ..Default::default()
+            },
+            PomodoroSettings {
+                long_break_every: 0,
+                ..Default::default()
+            },
+        ];
+        for settings in invalid {
+            assert!(settings.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn follows_on_to_the_next_phase() {
+        let (dir, mut store) = testing::temp_store("pomodoro");
+        store.add_project(testing::project("web", "Web")).unwrap();
+        let mut timer = TimerEngine::new(dir.path());
+        let now = Utc::now();
+
+        // Nothing to go back to yet
+        assert!(start_next(&mut store, &mut timer, None, now).is_err());
+        start_next(&mut store, &mut timer, Some("web"), now).unwrap();
+        assert_eq!(timer.status(now).unwrap().unwrap().countdown, Some(25 * 60));
+        timer.stop(false, 0, now, |s| store.add_session(s)).unwrap();
+
+        let cycle = finished(&store, &session(SessionKind::Pomodoro, 25), now).unwrap();
+        assert!(auto_start(&mut store, &mut timer, &cycle, now)
+            .unwrap()
+            .is_none());
+        store
+            .set_pomodoro_settings(&PomodoroSettings {
+                auto_start_breaks: true,
+                auto_start_work: true,
+                ..Default::default()
+            })
+            .unwrap();
+        let status = auto_start(&mut store, &mut timer, &cycle, now)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (status.project_id.as_str(), status.countdown),
+            ("break", Some(5 * 60))
+        );
+
+        // Back to the same project after the break, from a fresh read
+        let cycle = finished(&store, &session(SessionKind::Break, 5), now).unwrap();
+        assert_eq!(self::status(&store, now).unwrap().cycle, cycle);
+        let status = auto_start(&mut store, &mut timer, &cycle, now)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (status.project_id.as_str(), status.countdown),
+            ("web", Some(25 * 60))
+        );
+
+        // Five more minutes of the break, not counted as another one
+        let saved = extend(
+            &mut store,
+            &mut timer,
+            &session(SessionKind::Break, 5),
+            5 * 60,
+            now,
+        );
+        assert!(saved.unwrap().is_empty());
+        let extra = timer.stop(true, 0, now, |s| store.add_session(s)).unwrap();
+        assert_eq!(finished(&store, &extra[0], now).unwrap(), cycle);
+
+        reset(&store).unwrap();
+        assert_eq!(self::status(&store, now).unwrap().cycle.completed, 0);
+    }
+}
@@ -0,0 +1,251 @@
+// Productivity Tracker - Secret storage
+// API keys for the AI providers, kept out of the webview's localStorage.
+// They go to the OS keychain: Keychain on macOS, Credential Manager on
+// Windows, Secret Service (GNOME Keyring, KWallet) on Linux. Without one,
+// e.g. on a headless Linux box with no keyring daemon, they are encrypted
+// into `secrets.json` with a key of their own in `secrets.key`. Both files
+// are readable only by the user: this keeps keys out of backups and copies
+// of the data directory that leave the key file behind, not away from the
+// user's own account.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::crypto::{self, Key, Wrapped};
+use crate::error::{Error, Result};
+use crate::store::{read_json, DirLock};
+
+/// Service the entries are filed under in the OS keychain
+const SERVICE: &str = "productivity-tracker";
+const SECRETS_FILE: &str = "secrets.json";
+const SECRETS_KEY_FILE: &str = "secrets.key";
+
+pub trait SecretStore: Send + Sync {
+    fn get(&self, name: &str) -> Result<Option<String>>;
+
+    fn set(&self, name: &str, secret: &str) -> Result<()>;
+
+    /// Deleting a missing secret is not an error
+    fn delete(&self, name: &str) -> Result<()>;
+}
+
+/// Name of the API key of an AI provider (`anthropic`, `openai`...)
+pub fn api_key(provider: &str) -> String {
+    format!("ai-key/{provider}")
+}
+
+/// The OS keychain if it answers, otherwise the encrypted file in `dir`.
+/// Secrets left in the file while there was no keychain are moved to it.
+pub fn open(dir: &Path) -> Box<dyn SecretStore> {
+    let file = EncryptedFile::new(dir);
+    match Keychain.get("probe") {
+        Ok(_) => {
+            if let Err(e) = file.move_to(&Keychain) {
+                log::warn!("could not move secrets to the keychain: {e}");
+            }
+            Box::new(Keychain)
+        }
+        Err(e) => {
+            log::warn!("{e}; keeping secrets in {SECRETS_FILE}");
+            Box::new(file)
+        }
+    }
+}
+
+// ============================================
+// OS KEYCHAIN
+// ============================================
+
+pub struct Keychain;
+
+impl Keychain {
+    fn entry(name: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(SERVICE, name).map_err(keychain_error)
+    }
+}
+
+impl SecretStore for Keychain {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match Self::entry(name)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keychain_error(e)),
+        }
+    }
+
+    fn set(&self, name: &str, secret: &str) -> Result<()> {
+        Self::entry(name)?
+            .set_password(secret)
+            .map_err(keychain_error)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        match Self::entry(name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(keychain_error(e)),
+        }
+    }
+}
+
+fn keychain_error(e: keyring::Error) -> Error {
+    Error::Secret(format!("OS keychain: {e}"))
+}
+
+// ============================================
+// ENCRYPTED FILE
+// ============================================
+
+/// Secrets sealed one by one, each bound to its name
+pub struct EncryptedFile {
+    dir: PathBuf,
+}
+
+impl EncryptedFile {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, Wrapped>> {
+        Ok(read_json(&self.dir.join(SECRETS_FILE))?.unwrap_or_default())
+    }
+
+    fn write(&self, secrets: &BTreeMap<String, Wrapped>) -> Result<()> {
+        let json = serde_json::to_vec_pretty(secrets)?;
+        crypto::write_private(&self.dir.join(SECRETS_FILE), &json)
+    }
+
+    fn key(&self) -> Result<Option<Key>> {
+        match fs::read(self.dir.join(SECRETS_KEY_FILE)) {
+            Ok(bytes) => Key::try_from(bytes.as_slice())
+                .map(Some)
+                .map_err(|_| Error::Secret(format!("{SECRETS_KEY_FILE} is corrupt"))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn open_secret(key: Option<Key>, name: &str, wrapped: &Wrapped) -> Result<String> {
+        let key = key.ok_or_else(|| Error::Secret(format!("{SECRETS_KEY_FILE} is missing")))?;
+        let secret = crypto::unwrap(&key, wrapped, name.as_bytes())
+            .map_err(|_| Error::Secret(format!("{name} could not be decrypted")))?;
+        String::from_utf8(secret).map_err(|_| Error::Secret(format!("{name} is not text")))
+    }
+
+    /// Hands every secret to `target`, then deletes both files
+    fn move_to(&self, target: &dyn SecretStore) -> Result<()> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let secrets = self.read()?;
+        if secrets.is_empty() {
+            return Ok(());
+        }
+        let key = self.key()?;
+        for (name, wrapped) in &secrets {
+            target.set(name, &Self::open_secret(key, name, wrapped)?)?;
+        }
+        fs::remove_file(self.dir.join(SECRETS_FILE))?;
+        fs::remove_file(self.dir.join(SECRETS_KEY_FILE))?;
+        Ok(())
+    }
+}
+
+impl SecretStore for EncryptedFile {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        match self.read()?.get(name) {
+            Some(wrapped) => Self::open_secret(self.key()?, name, wrapped).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Creates the key with the first secret
+    fn set(&self, name: &str, secret: &str) -> Result<()> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let key = match self.key()? {
+            Some(key) => key,
+            None => {
+                let key = crypto::random_key();
+                crypto::write_private(&self.dir.join(SECRETS_KEY_FILE), &key)?;
+                key
+            }
+        };
+        let mut secrets = self.read()?;
+        secrets.insert(
+            name.to_string(),
+            crypto::wrap(&key, secret.as_bytes(), name.as_bytes())?,
+        );
+        self.write(&secrets)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let mut secrets = self.read()?;
+        if secrets.remove(name).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn file_store_keeps_keys_encrypted() {
+        let dir = TempDir::new("secrets-file");
+        let secrets = EncryptedFile::new(dir.path());
+        assert_eq!(secrets.get(&api_key("openai")).unwrap(), None);
+
+        secrets.set(&api_key("openai"), "sk-test-123456").unwrap();
+        secrets.set(&api_key("groq"), "gsk-test").unwrap();
+        let stored = fs::read_to_string(dir.path().join(SECRETS_FILE)).unwrap();
+        assert!(stored.contains("ai-key/openai") && !stored.contains("sk-test"));
+        assert_eq!(
+            secrets.get(&api_key("openai")).unwrap().as_deref(),
+            Some("sk-test-123456")
+        );
+
+        secrets.delete(&api_key("openai")).unwrap();
+        secrets.delete(&api_key("openai")).unwrap();
+        assert_eq!(secrets.get(&api_key("openai")).unwrap(), None);
+        assert_eq!(
+            secrets.get(&api_key("groq")).unwrap().as_deref(),
+            Some("gsk-test")
+        );
+    }
+
+    #[test]
+    fn entries_cannot_be_swapped() {
+        let dir = TempDir::new("secrets-swap");
+        let secrets = EncryptedFile::new(dir.path());
+        secrets.set("a", "first").unwrap();
+        secrets.set("b", "second").unwrap();
+
+        let mut stored = secrets.read().unwrap();
+        let a = stored.remove("a").unwrap();
+        stored.insert("b".into(), a);
+        secrets.write(&stored).unwrap();
+        assert!(secrets.get("b").is_err());
+    }
+
+    #[test]
+    fn secrets_move_to_another_store() {
+        let (from, to) = (TempDir::new("secrets-from"), TempDir::new("secrets-to"));
+        let source = EncryptedFile::new(from.path());
+        let target = EncryptedFile::new(to.path());
+        source.set(&api_key("anthropic"), "sk-ant-test").unwrap();
+
+        source.move_to(&target).unwrap();
+        assert!(
+            !from.path().join(SECRETS_FILE).exists()
+                && !from.path().join(SECRETS_KEY_FILE).exists()
+        );
+        assert_eq!(
+            target.get(&api_key("anthropic")).unwrap().as_deref(),
+            Some("sk-ant-test")
+        );
+    }
+}
@@ -0,0 +1,125 @@
+// Productivity Tracker - Global shortcuts
+// Registers the configured key combinations with the OS and carries out
+// their actions, wherever the focus is
+
+use chrono::Utc;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use crate::commands::{self, StoreState};
+use crate::error::{Error, Result};
+use crate::hotkeys::{self, HotkeyAction, HotkeySettings};
+use crate::pomodoro::{self, Phase};
+use crate::ticker::{self, TimerState};
+use crate::tray;
+
+/// Registers the saved shortcuts; those the OS refuses are reported and
+/// left out
+pub fn init(app: &AppHandle) {
+    let settings = app.state::<StoreState>().lock().unwrap().hotkey_settings();
+    if let Err(e) = apply(app, &settings.unwrap_or_default()) {
+        log::warn!("{e}");
+    }
+}
+
+/// Replaces the registered shortcuts with those of `settings`
+pub fn apply(app: &AppHandle, settings: &HotkeySettings) -> Result<()> {
+    let shortcuts = app.global_shortcut();
+    shortcuts
+        .unregister_all()
+        .map_err(|e| Error::Shortcut(e.to_string()))?;
+    let mut refused = Vec::new();
+    for (action, keys) in settings.bindings() {
+        let registered = shortcuts.on_shortcut(keys, move |app, _, event| {
+            if event.state == ShortcutState::Pressed {
+                run(app, action);
+            }
+        });
+        if let Err(e) = registered {
+            refused.push(format!("{keys} ({e})"));
+        }
+    }
+    if refused.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Shortcut(format!(
+            "cannot use {}",
+            refused.join(", ")
+        )))
+    }
+}
+
+fn run(app: &AppHandle, action: HotkeyAction) {
+    let result = match action {
+        HotkeyAction::ToggleTimer => toggle_timer(app),
+        HotkeyAction::StartBreak => tray::pomodoro_length(app, None).and_then(|duration| {
+            commands::start_break(app.clone(), app.state(), app.state(), duration).map(drop)
+        }),
+        HotkeyAction::NextProject => next_project(app),
+    };
+    if let Err(e) = result {
+        ticker::report_error(app, &format!("shortcut {action:?}"), &e);
+    }
+}
+
+/// Pauses or resumes the timer; with none, starts a pomodoro on the last
+/// pomodoro's project, or else the first project
+fn toggle_timer(app: &AppHandle) -> Result<()> {
+    let status = app
+        .state::<TimerState>()
+        .lock()
+        .unwrap()
+        .status(Utc::now())?;
+    match status {
+        Some(status) if status.paused => commands::resume_timer(app.clone(), app.state()).map(drop),
+        Some(_) => commands::pause_timer(app.clone(), app.state()).map(drop),
+        None => {
+            let (last, projects) = {
+                let store = app.state::<StoreState>();
+                let store = store.lock().unwrap();
+                let last = pomodoro::status(&store, Utc::now())?.cycle.project_id;
+                (last, store.projects()?)
+            };
+            let project = projects
+                .iter()
+                .find(|p| Some(&p.id) == last.as_ref() && p.is_active())
+                .or_else(|| hotkeys::next_project(&projects, None))
+                .ok_or_else(|| Error::NotFound("project to time".into()))?;
+            start(app, project.id.clone(), true)
+        }
+    }
+}
+
+/// Moves the timer on to the next project, saving the time of the one
+/// running. A countdown goes on as a new pomodoro, free mode stays free.
+fn next_project(app: &AppHandle) -> Result<()> {
+    let status = app
+        .state::<TimerState>()
+        .lock()
+        .unwrap()
+        .status(Utc::now())?;
+    let (current, projects) = {
+        let store = app.state::<StoreState>();
+        let store = store.lock().unwrap();
+        let current = match &status {
+            Some(status) => Some(status.project_id.clone()),
+            None => pomodoro::status(&store, Utc::now())?.cycle.project_id,
+        };
+        (current, store.projects()?)
+    };
+    let next = hotkeys::next_project(&projects, current.as_deref())
+        .ok_or_else(|| Error::NotFound("project to time".into()))?;
+    if status.is_some() {
+        commands::stop_timer(app.clone(), app.state(), app.state(), None, None)?;
+    }
+    let countdown = status.is_none_or(|status| status.countdown.is_some());
+    start(app, next.id.clone(), countdown)
+}
+
+fn start(app: &AppHandle, project_id: String, countdown: bool) -> Result<()> {
+    let countdown = match countdown {
+        true => Some(tray::pomodoro_length(app, Some(Phase::Work))?),
+        false => None,
+    };
+    commands::start_timer(app.clone(), app.state(), app.state(), project_id, countdown).map(drop)
+}
@@ -0,0 +1,965 @@
+// Productivity Tracker - Local data store
+// Projects persisted as JSON, sessions in the SQLite history database
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::calendar::{Calendar, DaySettings};
+use crate::db::{Database, GroupBy, OutboxEntry, SessionQuery, Total};
+use crate::editing::{self, EditAction, SessionEdit, SessionEntry};
+use crate::error::{Error, Result};
+use crate::hotkeys::HotkeySettings;
+use crate::idle::IdleSettings;
+use crate::import::{self, ImportFile, ImportPlan, ImportPreview};
+use crate::merge::SyncData;
+use crate::model::{self, Project, ProjectList, Session, SessionKind, Tombstone, SCHEMA_VERSION};
+use crate::notifications::NotificationSettings;
+use crate::pomodoro::PomodoroSettings;
+
+const PROJECTS_FILE: &str = "projects.json";
+const HISTORY_DB: &str = "history.db";
+const SETTINGS_FILE: &str = "settings.json";
+const LOCK_FILE: &str = ".lock";
+// Sessions file written by earlier versions, imported into the database once
+const LEGACY_SESSIONS_FILE: &str = "sessions.json";
+
+pub struct Store {
+    dir: PathBuf,
+    db: Database,
+}
+
+/// settings.json: the day settings at the top level, where earlier
+/// versions wrote them, then a section per feature
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Settings {
+    #[serde(flatten)]
+    day: DaySettings,
+    #[serde(default)]
+    idle: IdleSettings,
+    #[serde(default)]
+    pomodoro: PomodoroSettings,
+    #[serde(default)]
+    notifications: NotificationSettings,
+    #[serde(default)]
+    hotkeys: HotkeySettings,
+}
+
+// The desktop app and the `pt` CLI share these files, so nothing is cached
+// in memory: every operation re-reads from disk, and writes happen while
+// holding the data directory lock.
+impl Store {
+    /// Opens the store in `dir`, creating the directory if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let _lock = DirLock::acquire(&dir)?;
+        let mut db = Database::open(&dir.join(HISTORY_DB))?;
+        migrate_sessions_file(&dir, &mut db)?;
+
+        let store = Self { dir, db };
+        store.migrate_projects_file()?;
+        Ok(store)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Seeds an empty store (e.g. with data migrated from localStorage).
+    /// Returns false and leaves the store untouched if it already holds data.
+    pub fn import(&mut self, projects: Vec<Project>, sessions: Vec<Session>) -> Result<bool> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let has_projects = self.dir.join(PROJECTS_FILE).exists();
+        if has_projects || !self.db.is_empty()? {
+            return Ok(false);
+        }
+        self.db.insert_sessions(&sessions)?;
+        self.save_projects(&ProjectList {
+            projects,
+            deleted: Vec::new(),
+        })?;
+        Ok(true)
+    }
+
+    // ============================================
+    // PROJECTS
+    // ============================================
+
+    pub fn projects(&self) -> Result<Vec<Project>> {
+        Ok(self.project_list()?.projects)
+    }
+
+    pub fn add_project(&mut self, mut project: Project) -> Result<()> {
+        project.validate()?;
+        project.modified_at = Utc::now();
+        let _lock = DirLock::acquire(&self.dir)?;
+        let mut list = self.project_list()?;
+        if list.projects.iter().any(|p| p.id == project.id) {
+            return Err(Error::Invalid(format!(
+                "project {} already exists",
+                project.id
+            )));
+        }
+        list.deleted.retain(|t| t.id != project.id);
+        list.projects.push(project);
+        self.save_projects(&list)?;
+        self.projects_changed()
+    }
+
+    pub fn update_project(&mut self, mut project: Project) -> Result<()> {
+        project.validate()?;
+        project.modified_at = Utc::now();
+        let _lock = DirLock::acquire(&self.dir)?;
+        let mut list = self.project_list()?;
+        let existing = list
+            .projects
+            .iter_mut()
+            .find(|p| p.id == project.id)
+            .ok_or_else(|| Error::NotFound(format!("project {}", project.id)))?;
+        *existing = project;
+        self.save_projects(&list)?;
+        self.projects_changed()
+    }
+
+    /// The project to time, which must be neither archived nor in the trash
+    pub fn active_project(&self, id: &str) -> Result<Project> {
+        let project = self
+            .projects()?
+            .into_iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| Error::NotFound(format!("project {id}")))?;
+        match (project.archived, project.trashed_at) {
+            (_, Some(_)) => Err(Error::Invalid(format!("{} is in the trash", project.name))),
+            (true, None) => Err(Error::Invalid(format!("{} is archived", project.name))),
+            (false, None) => Ok(project),
+        }
+    }
+
+    /// Archived projects can't be timed; their sessions stay in the history
+    pub fn set_archived(&mut self, id: &str, archived: bool) -> Result<Project> {
+        self.change_project(id, |project| project.archived = archived)
+    }
+
+    /// Moves a project to the trash, which `restore_project` undoes. Its
+    /// sessions are kept until it is purged: by hand, or by `purge_trash`
+    /// once the retention period is over.
+    pub fn trash_project(&mut self, id: &str) -> Result<Project> {
+        let now = Utc::now();
+        self.change_project(id, |project| {
+            project.trashed_at.get_or_insert(now);
+        })
+    }
+
+    pub fn restore_project(&mut self, id: &str) -> Result<Project> {
+        self.change_project(id, |project| project.trashed_at = None)
+    }
+
+    /// Deletes a project in the trash together with all of its sessions
+    pub fn purge_project(&mut self, id: &str) -> Result<()> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let mut list = self.project_list()?;
+        let project = list
+            .projects
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| Error::NotFound(format!("project {id}")))?;
+        if project.trashed_at.is_none() {
+            return Err(Error::Invalid(format!(
+                "{} is not in the trash",
+                project.name
+            )));
+        }
+        self.purge(&mut list, id, Utc::now())
+    }
+
+    /// Purges the projects whose time in the trash is over, sessions and
+    /// all; returns them
+    pub fn purge_trash(&mut self, now: DateTime<Utc>) -> Result<Vec<Project>> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let mut list = self.project_list()?;
+        let expired: Vec<Project> = list
+            .projects
+            .iter()
+            .filter(|p| p.purge_at().is_some_and(|at| at <= now))
+            .cloned()
+            .collect();
+        for project in &expired {
+            self.purge(&mut list, &project.id, now)?;
+        }
+        Ok(expired)
+    }
+
+    /// Removes a project with all of its sessions, leaving tombstones so
+    /// the deletion reaches other devices. The caller holds the lock.
+    fn purge(&mut self, list: &mut ProjectList, id: &str, now: DateTime<Utc>) -> Result<()> {
+        list.projects.retain(|p| p.id != id);
+        list.deleted.push(Tombstone {
+            id: id.into(),
+            deleted_at: now,
+            date: None,
+        });
+        self.db.delete_project_sessions(id, now)?;
+        self.save_projects(list)?;
+        self.projects_changed()
+    }
+
+    fn change_project(&mut self, id: &str, change: impl FnOnce(&mut Project)) -> Result<Project> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let mut list = self.project_list()?;
+        let project = list
+            .projects
+            .iter_mut()
+            .find(|p| p.id == id)
+            .ok_or_else(|| Error::NotFound(format!("project {id}")))?;
+        change(project);
+        project.modified_at = Utc::now();
+        let project = project.clone();
+        self.save_projects(&list)?;
+        self.projects_changed()?;
+        Ok(project)
+    }
+
+    // ============================================
+    // SESSIONS
+    // ============================================
+
+    pub fn sessions(&self, query: &SessionQuery) -> Result<Vec<Session>> {
+        self.db.sessions(query)
+    }
+
+    pub fn session_totals(&self, query: &SessionQuery, group_by: GroupBy) -> Result<Vec<Total>> {
+        self.db.totals(query, group_by)
+    }
+
+    /// Files the session under its local day, split where it runs past
+    /// the start of a day; returns the sessions saved
+    pub fn add_session(&mut self, session: Session) -> Result<Vec<Session>> {
+        session.validate()?;
+        let sessions = self.calendar()?.file(session);
+        self.db.insert_sessions(&sessions)?;
+        Ok(sessions)
+    }
+
+    pub fn session(&self, id: &str) -> Result<Session> {
+        self.db
+            .session(id)?
+            .ok_or_else(|| Error::NotFound(format!("session {id}")))
+    }
+
+    /// The stored ones of `ids`, whatever day they are on
+    pub fn find_sessions(&self, ids: &[&str]) -> Result<Vec<Session>> {
+        let mut sessions = Vec::new();
+        for id in ids {
+            sessions.extend(self.db.session(id)?);
+        }
+        Ok(sessions)
+    }
+
+    /// Replaces the notes and tags of a session. Tags are parsed as typed
+    /// (see `model::parse_tags`); blank notes clear them.
+    pub fn annotate_session(
+        &mut self,
+        id: &str,
+        notes: Option<&str>,
+        tags: &[String],
+    ) -> Result<Session> {
+        let mut session = self.session(id)?;
+        session.notes = notes
+            .map(str::trim)
+            .filter(|notes| !notes.is_empty())
+            .map(String::from);
+        session.tags = model::parse_tags(tags)?;
+        session.modified_at = Utc::now();
+        session.validate()?;
+        self.db.update_session(&session)?;
+        Ok(session)
+    }
+
+    /// Up to `limit` sessions matching `query` whose notes, tags or project
+    /// name match `text`, best matches first
+    pub fn search_sessions(
+        &mut self,
+        text: &str,
+        query: &SessionQuery,
+        limit: u32,
+    ) -> Result<Vec<Session>> {
+        // Renames may have come from the CLI or a sync since the last search
+        self.db.set_project_names(&self.projects()?)?;
+        self.db.search(text, query, limit)
+    }
+
+    pub fn delete_session(&mut self, id: &str) -> Result<()> {
+        let now = Utc::now();
+        let session = self.session(id)?;
+        let edit = SessionEdit::new(EditAction::Delete, Some(&session), None, now);
+        self.db.apply_edit(&[], &[id], &[edit], now)
+    }
+
+    // ============================================
+    // MANUAL EDITS
+    // ============================================
+
+    /// Adds a session entered by hand, split where it runs past the start
+    /// of a day; returns the sessions saved
+    pub fn create_session(&mut self, entry: &SessionEntry) -> Result<Vec<Session>> {
+        let now = Utc::now();
+        let parts = self.new_session(entry, now)?;
+        let edits: Vec<SessionEdit> = parts
+            .iter()
+            .map(|part| SessionEdit::new(EditAction::Create, None, Some(part), now))
+            .collect();
+        self.save_edit(&parts, &[], &[], &edits, now)?;
+        Ok(parts)
+    }
+
+    /// Fails if `entry` would be refused as a new session, without saving
+    /// anything
+    pub fn check_new_session(&self, entry: &SessionEntry) -> Result<()> {
+        self.new_session(entry, Utc::now()).map(drop)
+    }
+
+    /// The sessions `entry` adds, checked but not saved
+    fn new_session(&self, entry: &SessionEntry, now: DateTime<Utc>) -> Result<Vec<Session>> {
+        let calendar = self.calendar()?;
+        let session = editing::build(entry, None, calendar.timezone, now)?;
+        self.check_session_project(&session)?;
+        let parts = calendar.file(session);
+        self.check_overlaps(&parts, &[])?;
+        Ok(parts)
+    }
+
+    /// Replaces a session with `entry`; returns the sessions saved, more
+    /// than one if it now runs past the start of a day
+    pub fn edit_session(&mut self, id: &str, entry: &SessionEntry) -> Result<Vec<Session>> {
+        let now = Utc::now();
+        let before = self.session(id)?;
+        let calendar = self.calendar()?;
+        let session = editing::build(entry, Some(&before), calendar.timezone, now)?;
+        self.check_session_project(&session)?;
+        let parts = calendar.file(session);
+        let edits: Vec<SessionEdit> = parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| match i {
+                0 => SessionEdit::new(EditAction::Edit, Some(&before), Some(part), now),
+                _ => SessionEdit::new(EditAction::Split, None, Some(part), now),
+            })
+            .collect();
+        self.save_edit(&parts, &[id], &[], &edits, now)?;
+        Ok(parts)
+    }
+
+    /// Cuts a session in two at `at`; returns both parts
+    pub fn split_session(&mut self, id: &str, at: DateTime<Utc>) -> Result<Vec<Session>> {
+        let now = Utc::now();
+        let before = self.session(id)?;
+        let parts = editing::split(&before, at, now)?;
+        let edits = [
+            SessionEdit::new(EditAction::Split, Some(&before), Some(&parts[0]), now),
+            SessionEdit::new(EditAction::Split, None, Some(&parts[1]), now),
+        ];
+        self.save_edit(&parts, &[id], &[], &edits, now)?;
+        Ok(parts.to_vec())
+    }
+
+    /// Combines sessions of one project and day into the earliest of them;
+    /// the others are deleted
+    pub fn merge_sessions(&mut self, ids: &[String]) -> Result<Session> {
+        let now = Utc::now();
+        let mut ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let sessions = ids
+            .iter()
+            .map(|id| self.session(id))
+            .collect::<Result<Vec<_>>>()?;
+        let merged = editing::merge(sessions.clone(), now)?;
+        let mut edits = Vec::new();
+        let mut removed = Vec::new();
+        for session in &sessions {
+            if session.id == merged.id {
+                edits.push(SessionEdit::new(
+                    EditAction::Merge,
+                    Some(session),
+                    Some(&merged),
+                    now,
+                ));
+            } else {
+                edits.push(SessionEdit::new(
+                    EditAction::Merge,
+                    Some(session),
+                    None,
+                    now,
+                ));
+                removed.push(session.id.as_str());
+            }
+        }
+        self.save_edit(std::slice::from_ref(&merged), &ids, &removed, &edits, now)?;
+        Ok(merged)
+    }
+
+    /// Manual changes, newest first: of one session, or of all of them
+    pub fn session_edits(&self, session_id: Option<&str>, limit: u32) -> Result<Vec<SessionEdit>> {
+        self.db.session_edits(session_id, limit)
+    }
+
+    /// Work can only be logged on projects that exist and are not in the
+    /// trash; archived ones are fine for past work
+    fn check_session_project(&self, session: &Session) -> Result<()> {
+        if session.kind == SessionKind::Break {
+            return Ok(());
+        }
+        let project = self
+            .projects()?
+            .into_iter()
+            .find(|p| p.id == session.project_id)
+            .ok_or_else(|| Error::NotFound(format!("project {}", session.project_id)))?;
+        match project.trashed_at {
+            Some(_) => Err(Error::Invalid(format!("{} is in the trash", project.name))),
+            None => Ok(()),
+        }
+    }
+
+    /// Saves `saved` unless it overlaps a session other than the
+    /// `replaced` ones, deletes `deleted` and records `edits`
+    fn save_edit(
+        &mut self,
+        saved: &[Session],
+        replaced: &[&str],
+        deleted: &[&str],
+        edits: &[SessionEdit],
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        self.check_overlaps(saved, replaced)?;
+        self.db.apply_edit(saved, deleted, edits, now)
+    }
+
+    fn check_overlaps(&self, sessions: &[Session], replaced: &[&str]) -> Result<()> {
+        let start = sessions.iter().map(|s| s.start_time).min();
+        let end = sessions.iter().map(|s| s.end_time).max();
+        if let (Some(start), Some(end)) = (start, end) {
+            let others = self.db.overlapping(start, end)?;
+            editing::check_overlaps(sessions, &others, replaced)?;
+        }
+        Ok(())
+    }
+
+    // ============================================
+    // IMPORT
+    // ============================================
+
+    /// What importing `file` would add, without adding it
+    pub fn plan_import(&self, file: &ImportFile) -> Result<ImportPlan> {
+        let ids: Vec<&str> = file.entries.iter().map(|e| e.id.as_str()).collect();
+        Ok(import::plan(
+            file,
+            &self.projects()?,
+            &self.db.known_ids(&ids)?,
+            &self.calendar()?,
+            Utc::now(),
+        ))
+    }
+
+    /// Adds the projects and sessions of `file` that are not here yet.
+    /// Planned again under the lock, so it matches the store as it is now.
+    pub fn import_file(&mut self, file: &ImportFile) -> Result<ImportPreview> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let plan = self.plan_import(file)?;
+        if !plan.projects.is_empty() {
+            let mut list = self.project_list()?;
+            list.projects.extend(plan.projects);
+            self.save_projects(&list)?;
+            self.projects_changed()?;
+        }
+        self.db.insert_sessions(&plan.sessions)?;
+        Ok(plan.preview)
+    }
+
+    // ============================================
+    // DAYS
+    // ============================================
+
+    pub fn day_settings(&self) -> Result<DaySettings> {
+        Ok(self.settings()?.day)
+    }
+
+    /// Applies to sessions recorded from now on; days already filed stay
+    pub fn set_day_settings(&self, settings: &DaySettings) -> Result<()> {
+        settings.validate()?;
+        self.update_settings(|all| all.day = settings.clone())
+    }
+
+    pub fn calendar(&self) -> Result<Calendar> {
+        Ok(self.day_settings()?.calendar())
+    }
+
+    // ============================================
+    // SETTINGS
+    // ============================================
+
+    pub fn idle_settings(&self) -> Result<IdleSettings> {
+        Ok(self.settings()?.idle)
+    }
+
+    pub fn set_idle_settings(&self, settings: &IdleSettings) -> Result<()> {
+        settings.validate()?;
+        self.update_settings(|all| all.idle = settings.clone())
+    }
+
+    pub fn pomodoro_settings(&self) -> Result<PomodoroSettings> {
+        Ok(self.settings()?.pomodoro)
+    }
+
+    pub fn set_pomodoro_settings(&self, settings: &PomodoroSettings) -> Result<()> {
+        settings.validate()?;
+        self.update_settings(|all| all.pomodoro = settings.clone())
+    }
+
+    pub fn notification_settings(&self) -> Result<NotificationSettings> {
+        Ok(self.settings()?.notifications)
+    }
+
+    pub fn set_notification_settings(&self, settings: &NotificationSettings) -> Result<()> {
+        settings.validate()?;
+        self.update_settings(|all| all.notifications = settings.clone())
+    }
+
+    pub fn hotkey_settings(&self) -> Result<HotkeySettings> {
+        Ok(self.settings()?.hotkeys)
+    }
+
+    pub fn set_hotkey_settings(&self, settings: &HotkeySettings) -> Result<()> {
+        settings.validate()?;
+        self.update_settings(|all| all.hotkeys = settings.clone())
+    }
+
+    fn settings(&self) -> Result<Settings> {
+        Ok(read_json(&self.dir.join(SETTINGS_FILE))?.unwrap_or_default())
+    }
+
+    fn update_settings(&self, update: impl FnOnce(&mut Settings)) -> Result<()> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let mut settings = self.settings()?;
+        update(&mut settings);
+        write_json_atomic(&self.dir.join(SETTINGS_FILE), &settings)
+    }
+
+    // ============================================
+    // SYNC OUTBOX
+    // ============================================
+
+    /// Days waiting to be uploaded; only those due by `due` if given
+    pub fn sync_outbox(&self, due: Option<DateTime<Utc>>) -> Result<Vec<OutboxEntry>> {
+        self.db.outbox(due)
+    }
+
+    pub fn sync_revision(&self, date: NaiveDate) -> Result<Option<i64>> {
+        self.db.outbox_revision(date)
+    }
+
+    pub fn mark_synced(&self, date: NaiveDate, revision: i64) -> Result<bool> {
+        self.db.mark_synced(date, revision)
+    }
+
+    /// Queues `date` for upload even though it did not change here
+    pub fn requeue(&self, date: NaiveDate) -> Result<()> {
+        self.db.mark_dirty(date)
+    }
+
+    /// Queues every day holding sessions for upload
+    pub fn requeue_all(&self) -> Result<()> {
+        self.db.mark_all_dirty()
+    }
+
+    pub fn mark_sync_failed(
+        &self,
+        date: NaiveDate,
+        error: &str,
+        next_attempt: DateTime<Utc>,
+    ) -> Result<()> {
+        self.db.mark_failed(date, error, next_attempt)
+    }
+
+    /// Everything a sync of the days `from`..=`to` exchanges: all projects
+    /// plus the sessions of those days, with tombstones
+    pub fn sync_data(&self, from: NaiveDate, to: NaiveDate) -> Result<SyncData> {
+        let list = self.project_list()?;
+        let sessions = self.db.sessions(&SessionQuery {
+            from: Some(from.to_string()),
+            to: Some(to.to_string()),
+            ..Default::default()
+        })?;
+        Ok(SyncData {
+            projects: list.projects,
+            sessions,
+            deleted_projects: list.deleted,
+            deleted_sessions: self.db.session_tombstones(from, to)?,
+        })
+    }
+
+    /// Stores a merged result from `merge::merge`. Records in `data`
+    /// replace local ones with the same id and tombstones remove them.
+    pub fn apply_sync(&mut self, data: &SyncData) -> Result<()> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let mut list = self.project_list()?;
+        let before = list.clone();
+        for project in &data.projects {
+            list.deleted.retain(|t| t.id != project.id);
+            match list.projects.iter_mut().find(|p| p.id == project.id) {
+                Some(existing) => *existing = project.clone(),
+                None => list.projects.push(project.clone()),
+            }
+        }
+        for tombstone in &data.deleted_projects {
+            list.projects.retain(|p| p.id != tombstone.id);
+            list.deleted.retain(|t| t.id != tombstone.id);
+            list.deleted.push(tombstone.clone());
+        }
+        if list != before {
+            self.save_projects(&list)?;
+        }
+        self.db
+            .apply_sessions(&data.sessions, &data.deleted_sessions)
+    }
+
+    /// Every uploaded day carries the project list, so a project change
+    /// is published with today's log
+    fn projects_changed(&self) -> Result<()> {
+        self.db.mark_dirty(self.calendar()?.today())
+    }
+
+    fn project_list(&self) -> Result<ProjectList> {
+        Ok(self
+            .read_projects()?
+            .map(|(list, _)| list)
+            .unwrap_or_default())
+    }
+
+    fn read_projects(&self) -> Result<Option<(ProjectList, u32)>> {
+        match fs::read(self.dir.join(PROJECTS_FILE)) {
+            Ok(bytes) => model::parse_projects(&bytes)
+                .map(Some)
+                .map_err(|e| malformed(PROJECTS_FILE, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save_projects(&self, list: &ProjectList) -> Result<()> {
+        write_json_atomic(
+            &self.dir.join(PROJECTS_FILE),
+            &model::versioned_projects(list),
+        )
+    }
+
+    /// Rewrites projects.json in the current layout if an older version
+    /// wrote it. Called from `open`, which already holds the lock.
+    fn migrate_projects_file(&self) -> Result<()> {
+        match self.read_projects()? {
+            Some((list, version)) if version < SCHEMA_VERSION => self.save_projects(&list),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Exclusive advisory lock on the data directory, held while reading and
+/// rewriting its JSON files. Released when dropped.
+pub struct DirLock(File);
+
+impl DirLock {
+    pub fn acquire(dir: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(LOCK_FILE))?;
+        file.lock()?;
+        Ok(Self(file))
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        let _ = self.0.unlock();
+    }
+}
+
+/// Short random id, like the ones the frontend generates
+pub fn new_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..9].to_string()
+}
+
+/// Moves sessions from a pre-database `sessions.json` into the history
+/// database, keeping the old file around as `sessions.json.bak`.
+fn migrate_sessions_file(dir: &Path, db: &mut Database) -> Result<()> {
+    let path = dir.join(LEGACY_SESSIONS_FILE);
+    let sessions = match read_json::<Vec<Session>>(&path) {
+        Ok(Some(sessions)) => sessions,
+        Ok(None) => return Ok(()),
+        Err(e) => return Err(malformed(LEGACY_SESSIONS_FILE, e)),
+    };
+    if db.is_empty()? {
+        db.insert_sessions(&sessions)?;
+    }
+    fs::rename(&path, path.with_extension("json.bak"))?;
+    Ok(())
+}
+
+fn malformed(file: &'static str, source: Error) -> Error {
+    Error::Malformed {
+        file,
+        source: Box::new(source),
+    }
+}
+
+pub(crate) fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Writes to a temp file in the same directory, syncs it, then renames it
+/// over the target so readers never observe a half-written file.
+pub(crate) fn write_json_atomic<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    {
+        let mut file = File::create(&tmp)?;
+        serde_json::to_writer_pretty(&mut file, value)?;
+        file.write_all(b"\n")?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TRASH_RETENTION_DAYS;
+    use crate::testing::{self, project, session, TempDir};
+    use chrono::Duration;
+
+    fn project_sessions(store: &Store, id: &str) -> Vec<Session> {
+        let query = SessionQuery {
+            project_id: Some(id.into()),
+            ..Default::default()
+        };
+        store.sessions(&query).unwrap()
+    }
+
+    #[test]
+    fn keeps_its_data_across_reopens() {
+        let (dir, mut store) = testing::temp_store("store-reopen");
+        assert!(store
+            .import(
+                vec![project("web", "Website")],
+                vec![session("s1", "web", "2025-03-03T09:00:00Z", 25)],
+            )
+            .unwrap());
+        // Seeding only ever fills an empty store
+        assert!(!store
+            .import(vec![project("docs", "Docs")], Vec::new())
+            .unwrap());
+        store
+            .add_session(session("s2", "web", "2025-03-04T09:00:00Z", 50))
+            .unwrap();
+        drop(store);
+
+        let store = Store::open(dir.path()).unwrap();
+        assert_eq!(store.projects().unwrap().len(), 1);
+        assert_eq!(store.active_project("web").unwrap().name, "Website");
+        let sessions: Vec<_> = project_sessions(&store, "web")
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(sessions, ["s1", "s2"]);
+        // Written in place, with no temp file left over
+        assert!(dir.path().join(PROJECTS_FILE).exists());
+        assert!(!dir.path().join("projects.json.tmp").exists());
+    }
+
+    #[test]
+    fn migrates_files_of_older_versions() {
+        let dir = TempDir::new("store-legacy");
+        write_json_atomic(
+            &dir.path().join(PROJECTS_FILE),
+            &[serde_json::json!({ "id": "web", "name": "Website", "color": "#00ff88" })],
+        )
+        .unwrap();
+        write_json_atomic(
+            &dir.path().join(LEGACY_SESSIONS_FILE),
+            &[session("s1", "web", "2025-03-03T09:00:00Z", 25)],
+        )
+        .unwrap();
+
+        let store = Store::open(dir.path()).unwrap();
+        assert_eq!(store.active_project("web").unwrap().name, "Website");
+        assert_eq!(project_sessions(&store, "web").len(), 1);
+        let (_, version) = store.read_projects().unwrap().unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        assert!(!dir.path().join(LEGACY_SESSIONS_FILE).exists());
+        assert!(dir.path().join("sessions.json.bak").exists());
+    }
+
+    #[test]
+    fn trash_keeps_sessions_until_the_retention_is_over() {
+        let (_dir, mut store) = testing::temp_store("store-trash");
+        store.add_project(project("web", "Website")).unwrap();
+        store
+            .add_session(session("s1", "web", "2025-03-03T09:00:00Z", 25))
+            .unwrap();
+
+        store.trash_project("web").unwrap();
+        assert!(store.active_project("web").is_err());
+        assert_eq!(project_sessions(&store, "web").len(), 1);
+        // Trashing again keeps the original date
+        let trashed_at = store.projects().unwrap()[0].trashed_at;
+        let again = store.trash_project("web").unwrap();
+        assert_eq!(again.trashed_at, trashed_at);
+
+        store.restore_project("web").unwrap();
+        assert!(store.active_project("web").is_ok());
+
+        // When its time is up it is purged, leaving tombstones
+        let trashed_at = store.trash_project("web").unwrap().trashed_at.unwrap();
+        let expiry = trashed_at + Duration::days(TRASH_RETENTION_DAYS);
+        assert!(store
+            .purge_trash(expiry - Duration::minutes(1))
+            .unwrap()
+            .is_empty());
+        assert_eq!(project_sessions(&store, "web").len(), 1);
+        let purged = store.purge_trash(expiry).unwrap();
+        assert_eq!(purged[0].id, "web");
+        assert!(store.projects().unwrap().is_empty());
+        assert!(project_sessions(&store, "web").is_empty());
+        let day = "2025-03-03".parse().unwrap();
+        let data = store.sync_data(day, day).unwrap();
+        assert_eq!(data.deleted_projects[0].id, "web");
+        assert_eq!(data.deleted_sessions[0].id, "s1");
+        assert!(store.purge_trash(expiry).unwrap().is_empty());
+    }
+
+    #[test]
+    fn purges_by_hand() {
+        let (_dir, mut store) = testing::temp_store("store-purge");
+        for (id, start) in [
+            ("web", "2025-03-03T09:00:00Z"),
+            ("docs", "2025-03-03T10:00:00Z"),
+        ] {
+            store.add_project(project(id, id)).unwrap();
+            store.add_session(session(id, id, start, 25)).unwrap();
+        }
+        assert!(store.purge_project("web").is_err());
+
+        store.trash_project("web").unwrap();
+        store.purge_project("web").unwrap();
+        let ids: Vec<String> = store
+            .projects()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(ids, ["docs"]);
+        assert!(project_sessions(&store, "web").is_empty());
+        assert_eq!(project_sessions(&store, "docs").len(), 1);
+
+        // Tombstones carry the deletion to other devices
+        let day = "2025-03-03".parse().unwrap();
+        let data = store.sync_data(day, day).unwrap();
+        assert_eq!(data.deleted_projects[0].id, "web");
+        assert_eq!(data.deleted_sessions[0].id, "web");
+        assert!(store.purge_project("web").is_err());
+    }
+
+    #[test]
+    fn stores_on_one_directory_share_it() {
+        let (dir, mut app) = testing::temp_store("store-shared");
+        let mut cli = Store::open(dir.path()).unwrap();
+        app.add_project(project("web", "Website")).unwrap();
+        cli.add_session(session("s1", "web", "2025-03-03T09:00:00Z", 25))
+            .unwrap();
+        assert_eq!(cli.active_project("web").unwrap().name, "Website");
+        assert_eq!(project_sessions(&app, "web").len(), 1);
+        assert!(cli.add_project(project("web", "Again")).is_err());
+    }
+
+    #[test]
+    fn dir_lock_excludes_other_holders() {
+        let dir = TempDir::new("store-lock");
+        let held = DirLock::acquire(dir.path()).unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let waiter = {
+            let dir = dir.path().to_path_buf();
+            std::thread::spawn(move || {
+                let _lock = DirLock::acquire(&dir).unwrap();
+                tx.send(()).unwrap();
+            })
+        };
+        let wait = std::time::Duration::from_millis(200);
+        assert!(rx.recv_timeout(wait).is_err());
+        drop(held);
+        rx.recv_timeout(wait * 10).unwrap();
+        waiter.join().unwrap();
+    }
+
+    #[test]
+    fn concurrent_writers_keep_each_others_changes() {
+        let (dir, store) = testing::temp_store("store-writers");
+        // Without the directory lock one writer's projects.json would
+        // overwrite the other's
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let dir = dir.path().to_path_buf();
+                std::thread::spawn(move || {
+                    let mut store = Store::open(dir).unwrap();
+                    for n in 0..10 {
+                        let id = format!("p{writer}-{n}");
+                        store.add_project(project(&id, &id)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(store.projects().unwrap().len(), 40);
+    }
+
+    #[test]
+    fn totals_follow_the_query() {
+        let (_dir, mut store) = testing::temp_store("store-totals");
+        let utc = DaySettings {
+            timezone: Some(chrono_tz::UTC),
+            day_start_hour: 0,
+        };
+        store.set_day_settings(&utc).unwrap();
+        for (id, project, start, minutes) in [
+            ("s1", "web", "2025-03-03T09:00:00Z", 25),
+            ("s2", "web", "2025-03-04T09:00:00Z", 50),
+            ("s3", "docs", "2025-03-04T11:00:00Z", 30),
+        ] {
+            store
+                .add_session(session(id, project, start, minutes))
+                .unwrap();
+        }
+        let march_4 = SessionQuery {
+            from: Some("2025-03-04".into()),
+            to: Some("2025-03-04".into()),
+            ..Default::default()
+        };
+        let totals = store.session_totals(&march_4, GroupBy::Project).unwrap();
+        let totals: Vec<_> = totals
+            .iter()
+            .map(|t| (t.key.as_str(), t.seconds, t.count))
+            .collect();
+        assert_eq!(totals, [("docs", 1800, 1), ("web", 3000, 1)]);
+    }
+}
@@ -0,0 +1,722 @@
+// Productivity Tracker - Cloud sync
+// Exchanges changed days with a sync backend (see backend/). Each day is
+// fetched, merged with the local copy (see merge.rs) and uploaded again, so
+// edits made on other devices are kept. Days waiting for upload are queued
+// in the history database (see db.rs), so nothing is lost while offline;
+// failed uploads are retried with exponential backoff.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::SyncBackend;
+use crate::crypto::{self, KdfParams, Keyring, Sealed, Vault};
+use crate::db::OutboxEntry;
+use crate::error::{Error, Result};
+use crate::merge::{merge, SyncData};
+use crate::store::Store;
+
+/// Delay before the first retry (seconds), doubled after each failure
+const RETRY_BASE: i64 = 30;
+const RETRY_MAX: i64 = 60 * 60;
+
+/// Days fetched by `pull`, counting back from today
+pub const PULL_DAYS: i64 = 7;
+
+/// One day's data, as stored in `daily-logs/YYYY-MM-DD.json`. With sync
+/// encryption on, `data` is left empty and the day travels in `encrypted`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DayLog {
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub data: SyncData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted: Option<Sealed>,
+}
+
+impl DayLog {
+    pub fn load(store: &Store, date: NaiveDate) -> Result<Self> {
+        Ok(Self {
+            date,
+            data: store.sync_data(date, date)?,
+            encrypted: None,
+        })
+    }
+
+    /// Data key the log was sealed with, `None` for plain text
+    fn key_id(&self) -> Option<&str> {
+        self.encrypted.as_ref().map(|sealed| sealed.key_id.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DayState {
+    Pending,
+    Syncing,
+    Synced,
+    Failed,
+}
+
+/// Sync progress of one day, reported to the UI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayStatus {
+    pub date: NaiveDate,
+    pub state: DayState,
+    /// Failed uploads since the day was last synced
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub next_attempt: Option<DateTime<Utc>>,
+}
+
+impl DayStatus {
+    /// Status of a day waiting in the outbox
+    pub fn queued(entry: &OutboxEntry) -> Self {
+        let state = if entry.attempts > 0 {
+            DayState::Failed
+        } else {
+            DayState::Pending
+        };
+        Self::new(entry, state)
+    }
+
+    fn new(entry: &OutboxEntry, state: DayState) -> Self {
+        Self {
+            date: entry.date,
+            state,
+            attempts: entry.attempts,
+            error: entry.last_error.clone(),
+            next_attempt: Some(entry.next_attempt),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SyncSummary {
+    pub synced: usize,
+    pub failed: usize,
+    /// Days left in the outbox: changed during upload or skipped while offline
+    pub pending: usize,
+    /// Whether changes from other devices were merged into the store
+    pub updated: bool,
+    /// Set when the run itself could not complete
+    pub error: Option<String>,
+}
+
+/// Delay before retrying a day that has failed `attempts` times
+pub fn backoff(attempts: u32) -> Duration {
+    let factor = 1i64 << attempts.saturating_sub(1).min(16);
+    Duration::seconds((RETRY_BASE * factor).min(RETRY_MAX))
+}
+
+/// When the next queued day becomes due, if any
+pub fn next_attempt(store: &Store) -> Result<Option<DateTime<Utc>>> {
+    Ok(store
+        .sync_outbox(None)?
+        .iter()
+        .map(|entry| entry.next_attempt)
+        .min())
+}
+
+/// Syncs queued days: every one of them when `force` is set, otherwise
+/// those due by `now`. `report` is called whenever a day changes state.
+/// Days are sealed with `vault` when encryption is on.
+pub fn run(
+    store: &Mutex<Store>,
+    backend: &dyn SyncBackend,
+    vault: Option<&Vault>,
+    now: DateTime<Utc>,
+    force: bool,
+    mut report: impl FnMut(&DayStatus),
+) -> Result<SyncSummary> {
+    let due = if force { None } else { Some(now) };
+    let entries = store.lock().unwrap().sync_outbox(due)?;
+    let mut summary = SyncSummary::default();
+
+    for (i, entry) in entries.iter().enumerate() {
+        report(&DayStatus::new(entry, DayState::Syncing));
+
+        match sync_day(store, backend, vault, entry.date) {
+            Ok((revision, updated)) => {
+                summary.updated |= updated;
+                let clean = match revision {
+                    Some(revision) => store.lock().unwrap().mark_synced(entry.date, revision)?,
+                    None => true,
+                };
+                if clean {
+                    summary.synced += 1;
+                    report(&DayStatus {
+                        attempts: 0,
+                        error: None,
+                        next_attempt: None,
+                        ..DayStatus::new(entry, DayState::Synced)
+                    });
+                } else {
+                    summary.pending += 1;
+                    report(&DayStatus::new(entry, DayState::Pending));
+                }
+            }
+            Err(e) => {
+                let next = now + backoff(entry.attempts + 1);
+                store
+                    .lock()
+                    .unwrap()
+                    .mark_sync_failed(entry.date, &e.to_string(), next)?;
+                summary.failed += 1;
+                report(&DayStatus {
+                    attempts: entry.attempts + 1,
+                    error: Some(e.to_string()),
+                    next_attempt: Some(next),
+                    ..DayStatus::new(entry, DayState::Failed)
+                });
+                // Unreachable server: the remaining days would fail the same way
+                if matches!(e, Error::Http(_)) {
+                    summary.pending += entries.len() - i - 1;
+                    break;
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Merges one day with the server's copy, stores the result and uploads it
+/// if the server lacks any of it or holds it under another key. Returns the
+/// day's outbox revision as of the merge and whether local data changed.
+fn sync_day(
+    store: &Mutex<Store>,
+    backend: &dyn SyncBackend,
+    vault: Option<&Vault>,
+    date: NaiveDate,
+) -> Result<(Option<i64>, bool)> {
+    let (remote, stale) = match backend.fetch_day(date)? {
+        Some(log) => {
+            let stale = log.key_id() != vault.map(Vault::current_key_id);
+            (open(vault, log)?.data, stale)
+        }
+        None => (SyncData::default(), false),
+    };
+    let (merged, revision, updated) = {
+        let mut store = store.lock().unwrap();
+        let local = local_data(&store, date, date, &remote)?;
+        let mut merged = merge(&local, &remote);
+        let changes = merged.changes_from(&local);
+        if !changes.is_empty() {
+            store.apply_sync(&changes)?;
+        }
+        // Sessions since moved to another day are uploaded with that day
+        merged.sessions.retain(|session| session.date == date);
+        (merged, store.sync_revision(date)?, !changes.is_empty())
+    };
+    let moved = remote.sessions.iter().any(|session| {
+        !merged.sessions.iter().any(|s| s.id == session.id)
+            && !merged.deleted_sessions.iter().any(|t| t.id == session.id)
+    });
+    if stale || moved || !merged.changes_from(&remote).is_empty() {
+        let log = DayLog {
+            date,
+            data: merged,
+            encrypted: None,
+        };
+        backend.push(&seal(vault, log)?)?;
+    }
+    Ok((revision, updated))
+}
+
+/// Merges the last `PULL_DAYS` days from the server into the store, so
+/// changes made on other devices show up without local edits to those days.
+/// Days the server holds in plain text or under an older key are queued to
+/// be sealed again. Returns whether anything changed.
+pub fn pull(
+    store: &Mutex<Store>,
+    backend: &dyn SyncBackend,
+    vault: Option<&Vault>,
+    today: NaiveDate,
+) -> Result<bool> {
+    let from = today - Duration::days(PULL_DAYS - 1);
+    let mut remote = SyncData::default();
+    let mut stale = Vec::new();
+    for log in backend.fetch_range(from, today)? {
+        if log.key_id() != vault.map(Vault::current_key_id) {
+            stale.push(log.date);
+        }
+        remote = merge(&remote, &open(vault, log)?.data);
+    }
+
+    let mut store = store.lock().unwrap();
+    for date in stale {
+        store.requeue(date)?;
+    }
+    let local = local_data(&store, from, today, &remote)?;
+    let changes = merge(&local, &remote).changes_from(&local);
+    if changes.is_empty() {
+        return Ok(false);
+    }
+    let queued: BTreeSet<NaiveDate> = store
+        .sync_outbox(None)?
+        .iter()
+        .map(|entry| entry.date)
+        .collect();
+    store.apply_sync(&changes)?;
+
+    // Days that only took in the server's changes now match it
+    let days: BTreeSet<NaiveDate> = changes
+        .sessions
+        .iter()
+        .map(|session| session.date)
+        .chain(changes.deleted_sessions.iter().filter_map(|t| t.date))
+        .collect();
+    for &date in days.difference(&queued) {
+        if let Some(revision) = store.sync_revision(date)? {
+            store.mark_synced(date, revision)?;
+        }
+    }
+    Ok(true)
+}
+
+/// Local data of `from..=to`, plus the local version of sessions `remote`
+/// files under those days that were since edited onto another day, so the
+/// edit wins over the server's older copy
+fn local_data(
+    store: &Store,
+    from: NaiveDate,
+    to: NaiveDate,
+    remote: &SyncData,
+) -> Result<SyncData> {
+    let mut local = store.sync_data(from, to)?;
+    let moved: Vec<&str> = remote
+        .sessions
+        .iter()
+        .map(|session| session.id.as_str())
+        .filter(|id| !local.sessions.iter().any(|s| s.id == *id))
+        .collect();
+    local.sessions.extend(store.find_sessions(&moved)?);
+    Ok(local)
+}
+
+/// Decrypts a downloaded log; sealed logs need the vault
+fn open(vault: Option<&Vault>, log: DayLog) -> Result<DayLog> {
+    match vault {
+        Some(vault) => vault.open(log),
+        None if log.encrypted.is_some() => Err(crypto::locked()),
+        None => Ok(log),
+    }
+}
+
+fn seal(vault: Option<&Vault>, log: DayLog) -> Result<DayLog> {
+    match vault {
+        Some(vault) => vault.seal(&log),
+        None => Ok(log),
+    }
+}
+
+/// Every day from `from` to `to` on the server, decrypted and merged into
+/// one snapshot; nothing is stored
+pub fn history(
+    store: &Mutex<Store>,
+    backend: &dyn SyncBackend,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<SyncData> {
+    let vault = vault(&data_dir(store), backend)?;
+    let mut data = SyncData::default();
+    for log in backend.fetch_range(from, to)? {
+        data = merge(&data, &open(vault.as_ref(), log)?.data);
+    }
+    Ok(data.normalized())
+}
+
+// ============================================
+// ENCRYPTION
+// ============================================
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    /// Whether this device holds the keys; false until the passphrase is
+    /// entered when encryption was turned on elsewhere
+    pub unlocked: bool,
+    /// Data keys in the keyring, one per rotation
+    pub keys: usize,
+    pub key_created_at: Option<DateTime<Utc>>,
+}
+
+pub fn encryption_status(store: &Mutex<Store>) -> Result<EncryptionStatus> {
+    let dir = data_dir(store);
+    let keyring = crypto::read_keyring(&dir)?;
+    let current = keyring.as_ref().and_then(|keyring| keyring.keys.last());
+    Ok(EncryptionStatus {
+        enabled: keyring.is_some(),
+        unlocked: matches!(Vault::load(&dir), Ok(Some(_))),
+        keys: keyring.as_ref().map_or(0, |keyring| keyring.keys.len()),
+        key_created_at: current.map(|key| key.created_at),
+    })
+}
+
+/// The vault to sync with, after exchanging keyrings with the backend so
+/// keys rotated and passphrases changed on other devices are picked up.
+/// `None` while encryption is off.
+pub fn vault(dir: &Path, backend: &dyn SyncBackend) -> Result<Option<Vault>> {
+    let remote = backend.fetch_keyring()?;
+    let mut vault = match (Vault::load(dir), remote.as_ref()) {
+        (Ok(Some(vault)), _) => vault,
+        (Ok(None), None) => return Ok(None),
+        // Turned on by another device: remember that until unlocked
+        (Ok(None), Some(remote)) | (Err(Error::Crypto(_)), Some(remote)) => {
+            crypto::save_keyring(dir, remote)?;
+            return Err(crypto::locked());
+        }
+        (Err(e), _) => return Err(e),
+    };
+    let adopted = match remote {
+        Some(remote) if remote == vault.keyring => return Ok(Some(vault)),
+        Some(remote) => vault.adopt_keyring(remote)?,
+        None => false,
+    };
+    if adopted {
+        vault.save(dir)?;
+    } else {
+        // Missing (e.g. a new backend) or behind ours. Keyring changes are
+        // uploaded as soon as they are made, so this rarely overwrites one
+        // from another device.
+        backend.push_keyring(&vault.keyring)?;
+    }
+    Ok(Some(vault))
+}
+
+/// Turns encryption on for the backend and every device syncing with it.
+/// Returns the recovery code, to be shown once.
+pub fn enable_encryption(
+    store: &Mutex<Store>,
+    backend: &dyn SyncBackend,
+    passphrase: &str,
+) -> Result<String> {
+    if backend.fetch_keyring()?.is_some() {
+        return Err(Error::Crypto(
+            "already turned on for this sync backend: unlock it with your passphrase".into(),
+        ));
+    }
+    let (vault, code) = Vault::create(passphrase, KdfParams::default())?;
+    backend.push_keyring(&vault.keyring)?;
+    vault.save(&data_dir(store))?;
+    store.lock().unwrap().requeue_all()?;
+    Ok(code)
+}
+
+pub fn unlock_encryption(
+    store: &Mutex<Store>,
+    backend: &dyn SyncBackend,
+    passphrase: &str,
+) -> Result<()> {
+    let dir = data_dir(store);
+    Vault::unlock(keyring(&dir, backend)?, passphrase)?.save(&dir)
+}
+
+/// Unlocks with the recovery code and replaces the forgotten passphrase
+pub fn recover_encryption(
+    store: &Mutex<Store>,
+    backend: &dyn SyncBackend,
+    code: &str,
+    new_passphrase: &str,
+) -> Result<()> {
+    let dir = data_dir(store);
+    let vault = Vault::recover(keyring(&dir, backend)?, code, new_passphrase)?;
+    backend.push_keyring(&vault.keyring)?;
+    vault.save(&dir)
+}
+
+pub fn change_passphrase(
+    store: &Mutex<Store>,
+    backend: &dyn SyncBackend,
+    passphrase: &str,
+) -> Result<()> {
+    update_vault(store, backend, |vault| vault.change_passphrase(passphrase))
+}
+
+/// Replaces the recovery code and returns the new one
+pub fn new_recovery_code(store: &Mutex<Store>, backend: &dyn SyncBackend) -> Result<String> {
+    update_vault(store, backend, Vault::new_recovery_code)
+}
+
+/// Starts sealing with a new data key and queues every day to be sealed
+/// with it. Older keys stay in the keyring until then.
+pub fn rotate_encryption_key(store: &Mutex<Store>, backend: &dyn SyncBackend) -> Result<()> {
+    update_vault(store, backend, Vault::rotate)?;
+    store.lock().unwrap().requeue_all()
+}
+
+/// Applies `change` to the latest keyring, uploads it, then saves it here
+fn update_vault<T>(
+    store: &Mutex<Store>,
+    backend: &dyn SyncBackend,
+    change: impl FnOnce(&mut Vault) -> Result<T>,
+) -> Result<T> {
+    let dir = data_dir(store);
+    let mut vault =
+        vault(&dir, backend)?.ok_or_else(|| Error::Crypto("encryption is off".into()))?;
+    let result = change(&mut vault)?;
+    backend.push_keyring(&vault.keyring)?;
+    vault.save(&dir)?;
+    Ok(result)
+}
+
+/// The backend's keyring, or the one saved here when the backend has none
+fn keyring(dir: &Path, backend: &dyn SyncBackend) -> Result<Keyring> {
+    match backend.fetch_keyring()? {
+        Some(keyring) => Ok(keyring),
+        None => crypto::read_keyring(dir)?.ok_or_else(|| Error::Crypto("encryption is off".into())),
+    }
+}
+
+/// Keys live next to the store's files; the lock is not held while the
+/// backend is contacted
+fn data_dir(store: &Mutex<Store>) -> PathBuf {
+    store.lock().unwrap().dir().to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::mock_server;
+    use crate::backend::{FolderBackend, LambdaBackend};
+    use crate::editing::SessionEntry;
+    use crate::model::{Session, SessionKind};
+    use crate::testing::{self, TempDir};
+    use chrono_tz::Tz;
+    use std::net::TcpListener;
+
+    fn temp_store(name: &str) -> (TempDir, Mutex<Store>) {
+        let (dir, store) = testing::temp_store(&format!("sync-{name}"));
+        (dir, Mutex::new(store))
+    }
+
+    fn add_session(store: &Mutex<Store>, id: &str, start: &str) {
+        let session = Session {
+            kind: SessionKind::Pomodoro,
+            timezone: Some(Tz::UTC),
+            ..testing::session(id, "p1", start, 25)
+        };
+        store.lock().unwrap().add_session(session).unwrap();
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_an_hour() {
+        assert_eq!(backoff(1), Duration::seconds(30));
+        assert_eq!(backoff(2), Duration::seconds(60));
+        assert_eq!(backoff(4), Duration::seconds(240));
+        assert_eq!(backoff(30), Duration::hours(1));
+    }
+
+    #[test]
+    fn failed_days_are_retried_after_backoff() {
+        let (_dir, store) = temp_store("retry");
+        add_session(&store, "s1", "2025-01-01T10:00:00Z");
+        let now = Utc::now();
+
+        let (url, _server) = mock_server(vec![(503, "{}")]);
+        let backend = LambdaBackend::new(&url).unwrap();
+        let mut states = Vec::new();
+        let summary = run(&store, &backend, None, now, false, |s| states.push(s.state)).unwrap();
+        assert_eq!(summary.failed, 1);
+        assert_eq!(states, [DayState::Syncing, DayState::Failed]);
+
+        let queued = store.lock().unwrap().sync_outbox(None).unwrap();
+        assert_eq!(queued[0].attempts, 1);
+        assert_eq!(
+            queued[0].last_error.as_deref(),
+            Some("sync failed: HTTP 503 Service Unavailable")
+        );
+        assert!(store
+            .lock()
+            .unwrap()
+            .sync_outbox(Some(now))
+            .unwrap()
+            .is_empty());
+
+        let (url, server) = mock_server(vec![(404, "{}"), (200, r#"{"success":true}"#)]);
+        let backend = LambdaBackend::new(&url).unwrap();
+        let later = now + backoff(1);
+        let summary = run(&store, &backend, None, later, false, |_| {}).unwrap();
+        assert_eq!(summary.synced, 1);
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /sync?date=2025-01-01 "));
+        assert!(requests[1].contains(r#""id":"s1""#));
+        assert!(store.lock().unwrap().sync_outbox(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn offline_run_stops_at_first_day() {
+        let (_dir, store) = temp_store("offline");
+        add_session(&store, "s1", "2025-01-01T10:00:00Z");
+        add_session(&store, "s2", "2025-01-02T10:00:00Z");
+
+        // Nothing listens on a port once its listener is dropped
+        let url = format!(
+            "http://{}",
+            TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+        );
+        let backend = LambdaBackend::new(&url).unwrap();
+        let summary = run(&store, &backend, None, Utc::now(), true, |_| {}).unwrap();
+        assert_eq!((summary.failed, summary.pending), (1, 1));
+
+        let queued = store.lock().unwrap().sync_outbox(None).unwrap();
+        assert_eq!(queued.len(), 2);
+        assert_eq!((queued[0].attempts, queued[1].attempts), (1, 0));
+    }
+
+    #[test]
+    fn run_keeps_edits_from_other_devices() {
+        let (_dir, store) = temp_store("merge");
+        add_session(&store, "s1", "2025-01-01T10:00:00Z");
+        add_session(&store, "s2", "2025-01-01T11:00:00Z");
+
+        // Another device deleted s2 and added s3
+        let remote = r#"{"date":"2025-01-01","projects":[],"sessions":[{"id":"s3",
+            "projectId":"p1","startTime":"2025-01-01T12:00:00.000Z",
+            "endTime":"2025-01-01T12:25:00.000Z","duration":1500,"type":"pomodoro",
+            "modifiedAt":"2025-01-01T12:25:00.000Z"}],"deletedSessions":[{"id":"s2",
+            "deletedAt":"2025-01-02T09:00:00.000Z","date":"2025-01-01"}]}"#;
+        let (url, server) = mock_server(vec![(200, remote), (200, r#"{"success":true}"#)]);
+        let backend = LambdaBackend::new(&url).unwrap();
+        let summary = run(&store, &backend, None, Utc::now(), true, |_| {}).unwrap();
+        assert_eq!(summary.synced, 1);
+        assert!(summary.updated);
+
+        let ids: Vec<_> = store
+            .lock()
+            .unwrap()
+            .sessions(&Default::default())
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, ["s1", "s3"]);
+        let pushed = &server.join().unwrap()[1];
+        assert!(pushed.contains(r#""id":"s1""#) && pushed.contains(r#""id":"s3""#));
+        assert!(store.lock().unwrap().sync_outbox(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sessions_edited_onto_another_day_move_on_the_server() {
+        let (_dir, store) = temp_store("moved");
+        let shared = TempDir::new("sync-moved-shared");
+        let backend = FolderBackend::new(shared.path());
+        store
+            .lock()
+            .unwrap()
+            .add_project(testing::project("p1", "Docs"))
+            .unwrap();
+        add_session(&store, "s1", "2025-01-01T10:00:00Z");
+        run(&store, &backend, None, Utc::now(), true, |_| {}).unwrap();
+
+        let start = "2025-01-02T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let entry = SessionEntry {
+            project_id: "p1".into(),
+            start_time: start,
+            end_time: start + Duration::minutes(25),
+            kind: SessionKind::Pomodoro,
+            duration: None,
+            notes: None,
+            tags: Vec::new(),
+        };
+        store.lock().unwrap().edit_session("s1", &entry).unwrap();
+        run(&store, &backend, None, Utc::now(), true, |_| {}).unwrap();
+        pull(&store, &backend, None, date("2025-01-03")).unwrap();
+
+        let session = store.lock().unwrap().session("s1").unwrap();
+        assert_eq!(session.date, date("2025-01-02"));
+        let ids = |day: &str| -> Vec<String> {
+            let log = backend.fetch_day(date(day)).unwrap().unwrap();
+            log.data.sessions.into_iter().map(|s| s.id).collect()
+        };
+        assert!(ids("2025-01-01").is_empty());
+        assert_eq!(ids("2025-01-02"), ["s1"]);
+    }
+
+    #[test]
+    fn pull_does_not_queue_downloaded_days() {
+        let (_dir, store) = temp_store("pull");
+        let remote = r#"{"sessions":[{"id":"s1","projectId":"p1",
+            "startTime":"2025-01-01T10:00:00.000Z","endTime":"2025-01-01T10:25:00.000Z",
+            "duration":1500,"type":"work"}],"filesLoaded":1}"#;
+        let (url, server) = mock_server(vec![(200, remote)]);
+        let backend = LambdaBackend::new(&url).unwrap();
+        assert!(pull(&store, &backend, None, date("2025-01-03")).unwrap());
+
+        let request = &server.join().unwrap()[0];
+        assert!(request.starts_with("GET /sync?from=2024-12-28&to=2025-01-03 "));
+        let store = store.lock().unwrap();
+        assert_eq!(store.sessions(&Default::default()).unwrap().len(), 1);
+        assert!(store.sync_outbox(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn encrypted_days_sync_between_devices() {
+        let (laptop_dir, laptop) = temp_store("sealed-laptop");
+        let (desktop_dir, desktop) = temp_store("sealed-desktop");
+        let shared = TempDir::new("sync-sealed-shared");
+        let backend = FolderBackend::new(shared.path());
+        add_session(&laptop, "s1", "2025-01-01T10:00:00Z");
+
+        let fast = KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let (vault, _code) = Vault::create("correct horse", fast).unwrap();
+        backend.push_keyring(&vault.keyring).unwrap();
+        vault.save(laptop_dir.path()).unwrap();
+        let vault = super::vault(laptop_dir.path(), &backend).unwrap();
+        run(&laptop, &backend, vault.as_ref(), Utc::now(), true, |_| {}).unwrap();
+        let stored =
+            std::fs::read_to_string(shared.path().join("daily-logs/2025-01-01.json")).unwrap();
+        assert!(stored.contains(r#""encrypted""#) && !stored.contains("startTime"));
+
+        let err = super::vault(desktop_dir.path(), &backend).err().unwrap();
+        assert_eq!(err.to_string(), crypto::locked().to_string());
+        assert!(encryption_status(&desktop).unwrap().enabled);
+        unlock_encryption(&desktop, &backend, "correct horse").unwrap();
+        let vault = super::vault(desktop_dir.path(), &backend).unwrap();
+        assert!(pull(&desktop, &backend, vault.as_ref(), date("2025-01-03")).unwrap());
+        assert_eq!(
+            desktop
+                .lock()
+                .unwrap()
+                .sessions(&Default::default())
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let history = history(&desktop, &backend, date("2025-01-01"), date("2025-01-01"));
+        assert_eq!(history.unwrap().sessions[0].id, "s1");
+    }
+
+    #[test]
+    fn edits_mark_days_dirty_again() {
+        let (_dir, store) = temp_store("dirty");
+        add_session(&store, "s1", "2025-01-01T10:00:00Z");
+        let entry = store.lock().unwrap().sync_outbox(None).unwrap().remove(0);
+
+        add_session(&store, "s2", "2025-01-01T12:00:00Z");
+        let mut store = store.into_inner().unwrap();
+        assert!(!store.mark_synced(entry.date, entry.revision).unwrap());
+        store.delete_session("s1").unwrap();
+        assert_eq!(
+            store.sync_outbox(None).unwrap()[0].revision,
+            entry.revision + 2
+        );
+    }
+}
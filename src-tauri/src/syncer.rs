@@ -0,0 +1,158 @@
+// Productivity Tracker - Background sync
+// Syncs the outbox off the UI thread and forwards per-day progress to the
+// webview
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::backend::{BackendConfig, SyncBackend};
+use crate::commands::StoreState;
+use crate::error::{Error, Result};
+use crate::sync::{self, SyncSummary};
+
+pub const SYNC_STATUS_EVENT: &str = "sync-status";
+pub const SYNC_FINISHED_EVENT: &str = "sync-finished";
+
+pub struct SyncConfig {
+    pub backend: BackendConfig,
+    /// How often queued days are uploaded and recent days pulled; `None`
+    /// syncs only on demand
+    pub interval: Option<Duration>,
+}
+
+enum Wake {
+    Configure(Option<SyncConfig>),
+    Now,
+}
+
+/// Handle to the sync worker, kept in Tauri state
+pub struct Syncer {
+    tx: Sender<Wake>,
+    /// The worker's backend, for commands that talk to it directly
+    backend: Mutex<Option<BackendConfig>>,
+}
+
+impl Syncer {
+    /// Points the worker at a new backend, or disables sync with `None`.
+    /// Recent days are pulled and due days uploaded right away.
+    pub fn configure(&self, config: Option<SyncConfig>) {
+        *self.backend.lock().unwrap() = config.as_ref().map(|config| config.backend.clone());
+        let _ = self.tx.send(Wake::Configure(config));
+    }
+
+    /// Pulls recent days and syncs every queued day now, ignoring retry
+    /// delays
+    pub fn sync_now(&self) {
+        let _ = self.tx.send(Wake::Now);
+    }
+
+    /// A connection of its own to the configured backend
+    pub fn backend(&self) -> Result<Box<dyn SyncBackend>> {
+        match self.backend.lock().unwrap().clone() {
+            Some(config) => config.connect(),
+            None => Err(Error::Sync("no sync backend configured".into())),
+        }
+    }
+}
+
+pub fn spawn(app: AppHandle) -> Syncer {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || worker(app, rx));
+    Syncer {
+        tx,
+        backend: Mutex::new(None),
+    }
+}
+
+fn worker(app: AppHandle, rx: Receiver<Wake>) {
+    let mut backend = None;
+    let mut interval: Option<Duration> = None;
+    let mut pulled_at = Instant::now();
+    loop {
+        let wake = match (&backend, interval) {
+            (Some(_), Some(interval)) => {
+                let next_pull = interval.saturating_sub(pulled_at.elapsed());
+                rx.recv_timeout(wait_time(&app, next_pull.max(Duration::from_secs(1))))
+            }
+            _ => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        let (force, pull) = match wake {
+            Ok(Wake::Configure(config)) => {
+                (backend, interval) = connect(config);
+                (false, true)
+            }
+            Ok(Wake::Now) => (true, true),
+            // Retries may wake the worker sooner; those only upload
+            Err(RecvTimeoutError::Timeout) => {
+                (false, interval.is_some_and(|i| pulled_at.elapsed() >= i))
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        if let Some(backend) = &backend {
+            if pull {
+                pulled_at = Instant::now();
+            }
+            run(&app, backend.as_ref(), force, pull);
+        }
+    }
+}
+
+fn connect(config: Option<SyncConfig>) -> (Option<Box<dyn SyncBackend>>, Option<Duration>) {
+    let Some(config) = config else {
+        return (None, None);
+    };
+    match config.backend.connect() {
+        Ok(backend) => (Some(backend), config.interval),
+        Err(e) => {
+            log::error!("invalid sync configuration: {e}");
+            (None, None)
+        }
+    }
+}
+
+/// Sleeps until the next retry is due, but no longer than `limit`
+fn wait_time(app: &AppHandle, limit: Duration) -> Duration {
+    let next = sync::next_attempt(&app.state::<StoreState>().lock().unwrap());
+    match next {
+        Ok(Some(next)) => (next - Utc::now())
+            .to_std()
+            .unwrap_or_default()
+            .clamp(Duration::from_secs(1), limit),
+        _ => limit,
+    }
+}
+
+fn run(app: &AppHandle, backend: &dyn SyncBackend, force: bool, pull: bool) {
+    let store = app.state::<StoreState>();
+    let dir = store.lock().unwrap().dir().to_path_buf();
+    let result = sync::vault(&dir, backend).and_then(|vault| {
+        let mut summary = sync::run(
+            &store,
+            backend,
+            vault.as_ref(),
+            Utc::now(),
+            force,
+            |status| {
+                let _ = app.emit(SYNC_STATUS_EVENT, status);
+            },
+        )?;
+        if pull {
+            let today = store.lock().unwrap().calendar()?.today();
+            summary.updated |= sync::pull(&store, backend, vault.as_ref(), today)?;
+        }
+        Ok(summary)
+    });
+    let summary = result.unwrap_or_else(|e| {
+        log::warn!("sync failed: {e}");
+        SyncSummary {
+            error: Some(e.to_string()),
+            ..Default::default()
+        }
+    });
+    let _ = app.emit(SYNC_FINISHED_EVENT, summary);
+}
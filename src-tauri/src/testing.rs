@@ -0,0 +1,98 @@
+// Productivity Tracker - Test fixtures
+// Shared by the unit tests: scratch directories that clean up after
+// themselves, and sessions and projects built from the few fields a test
+// cares about.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::model::{Project, Session, SessionKind};
+use crate::store::Store;
+
+/// A fresh directory under the system temp dir, removed when dropped, so
+/// a failing test leaves nothing behind
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        // Unique per test even when several share a name
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("pt-{name}-{}-{n}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A store in its own scratch directory. Bind the directory first, so the
+/// store is closed before the directory goes.
+pub fn temp_store(name: &str) -> (TempDir, Store) {
+    let dir = TempDir::new(name);
+    let store = Store::open(dir.path()).unwrap();
+    (dir, store)
+}
+
+pub fn at(time: &str) -> DateTime<Utc> {
+    time.parse().unwrap()
+}
+
+/// `time` (hh:mm) on `date`, in UTC
+pub fn time_on(date: &str, time: &str) -> DateTime<Utc> {
+    at(&format!("{date}T{time}:00Z"))
+}
+
+/// `minutes` of work on `project` from `start`, filed under its UTC date
+pub fn session(id: &str, project: &str, start: &str, minutes: i64) -> Session {
+    let start = at(start);
+    Session {
+        id: id.into(),
+        project_id: project.into(),
+        start_time: start,
+        end_time: start + Duration::minutes(minutes),
+        duration: minutes as u64 * 60,
+        kind: SessionKind::Work,
+        modified_at: start,
+        date: start.date_naive(),
+        timezone: None,
+        notes: None,
+        tags: Vec::new(),
+    }
+}
+
+/// `minutes` of `kind` on `project` from 09:00 UTC on `date`
+pub fn day_session(
+    id: &str,
+    project: &str,
+    date: &str,
+    minutes: i64,
+    kind: SessionKind,
+) -> Session {
+    Session {
+        kind,
+        ..session(id, project, &format!("{date}T09:00:00Z"), minutes)
+    }
+}
+
+pub fn project(id: &str, name: &str) -> Project {
+    Project {
+        id: id.into(),
+        name: name.into(),
+        color: "#00ff88".into(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,159 @@
+// Productivity Tracker - Background ticker
+// Drives the timer engine once per second and forwards its state to the webview
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands::StoreState;
+use crate::error::{Error, Result};
+use crate::idle::{IdleEvent, IdleMonitor};
+use crate::model::Session;
+use crate::notifier;
+use crate::pomodoro::{self, PomodoroStatus};
+use crate::timer::{Tick, TimerEngine, TimerStatus};
+use crate::tray;
+
+pub type TimerState = Mutex<TimerEngine>;
+pub type IdleState = Mutex<IdleMonitor>;
+
+pub const TICK_EVENT: &str = "timer-tick";
+pub const STATUS_EVENT: &str = "timer-status";
+pub const FINISHED_EVENT: &str = "timer-finished";
+pub const SESSION_SAVED_EVENT: &str = "session-saved";
+pub const IDLE_EVENT: &str = "idle-returned";
+pub const POMODORO_EVENT: &str = "pomodoro-cycle";
+pub const ERROR_EVENT: &str = "timer-error";
+
+pub fn spawn(app: AppHandle) {
+    thread::spawn(move || {
+        let mut last = None;
+        let mut failing = false;
+        loop {
+            thread::sleep(Duration::from_secs(1));
+            match tick(&app, &last) {
+                Ok(seen) => (last, failing) = (seen, false),
+                // A session that can't be saved fails every second until
+                // it can; one report is enough
+                Err(e) if !failing => {
+                    report_error(&app, "saving the timer", &e);
+                    failing = true;
+                }
+                Err(_) => {}
+            }
+        }
+    });
+}
+
+/// Project and paused flag seen on the previous tick, used to notice
+/// changes made outside the app (e.g. by the `pt` CLI)
+type Seen = Option<(String, bool)>;
+
+fn tick(app: &AppHandle, last: &Seen) -> Result<Seen> {
+    let result = app
+        .state::<TimerState>()
+        .lock()
+        .unwrap()
+        .tick(Utc::now(), |session| {
+            app.state::<StoreState>()
+                .lock()
+                .unwrap()
+                .add_session(session)
+        })?;
+    Ok(match result {
+        Tick::Idle => {
+            if last.is_some() {
+                publish_status(app, None);
+            }
+            None
+        }
+        Tick::Running(status) => {
+            let seen = Some((status.project_id.clone(), status.paused));
+            if seen != *last {
+                publish_status(app, Some(&status));
+            } else {
+                tray::update(app, Some(&status));
+            }
+            let _ = app.emit(TICK_EVENT, &status);
+            check_idle(app, &status);
+            seen
+        }
+        Tick::Finished { session, saved } => {
+            announce_saved(app, &saved);
+            let _ = app.emit(FINISHED_EVENT, &session);
+            let (cycle, status) = next_phase(app, &session);
+            if let Some(cycle) = cycle {
+                notifier::finished(app, &session, &cycle, status.is_some());
+                let _ = app.emit(POMODORO_EVENT, cycle);
+            }
+            publish_status(app, status.as_ref());
+            status.map(|s| (s.project_id, s.paused))
+        }
+    })
+}
+
+/// Pauses the timer where the user went away if so configured, and tells
+/// the webview when they are back. A pause shows up on the next tick.
+fn check_idle(app: &AppHandle, status: &TimerStatus) {
+    let event = app
+        .state::<IdleState>()
+        .lock()
+        .unwrap()
+        .check(Some(status), Utc::now());
+    match event {
+        Some(IdleEvent::Away(span)) if span.paused => {
+            if let Err(e) = app.state::<TimerState>().lock().unwrap().pause(span.from) {
+                log::warn!("pausing the idle timer failed: {e}");
+            }
+        }
+        Some(IdleEvent::Back(span)) => {
+            let _ = app.emit(IDLE_EVENT, span);
+        }
+        _ => {}
+    }
+}
+
+/// Moves the pomodoro cycle on after a countdown ran out, starting the
+/// next phase if it follows on its own. Returns the cycle now, and the
+/// timer so started.
+fn next_phase(app: &AppHandle, session: &Session) -> (Option<PomodoroStatus>, Option<TimerStatus>) {
+    let now = Utc::now();
+    let (timer, store) = (app.state::<TimerState>(), app.state::<StoreState>());
+    let (mut timer, mut store) = (timer.lock().unwrap(), store.lock().unwrap());
+    let started = pomodoro::finished(&store, session, now)
+        .and_then(|cycle| pomodoro::auto_start(&mut store, &mut timer, &cycle, now))
+        .unwrap_or_else(|e| {
+            log::error!("moving the pomodoro cycle on failed: {e}");
+            None
+        });
+    let cycle = pomodoro::status(&store, now)
+        .inspect_err(|e| log::warn!("reading the pomodoro cycle failed: {e}"))
+        .ok();
+    (cycle, started)
+}
+
+/// Broadcasts a timer state change (start, pause, stop...) to the webview
+/// and the tray, whoever triggered it
+pub fn publish_status(app: &AppHandle, status: Option<&TimerStatus>) {
+    tray::update(app, status);
+    let _ = app.emit(STATUS_EVENT, status);
+}
+
+/// Tells the webview about sessions the timer saved, one per day a
+/// session was split into
+pub fn announce_saved(app: &AppHandle, saved: &[Session]) {
+    for session in saved {
+        let _ = app.emit(SESSION_SAVED_EVENT, session);
+    }
+}
+
+/// Logs a failure of the timer outside the window's own commands, e.g. a
+/// session the ticker could not save or a stop from the tray, and shows it
+/// in the window, where the user would otherwise never learn of it
+pub fn report_error(app: &AppHandle, what: &str, e: &Error) {
+    log::error!("{what} failed: {e}");
+    let _ = app.emit(ERROR_EVENT, format!("{what} failed: {e}"));
+}
@@ -0,0 +1,580 @@
+// Productivity Tracker - Timer engine
+// Owns the active timer, pause accounting and pomodoro countdown.
+// State lives in timer.json so a crash or reload picks the running
+// session back up.
+
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::model::{Session, SessionKind};
+use crate::store::{new_id, read_json, write_json_atomic, DirLock};
+
+const TIMER_FILE: &str = "timer.json";
+
+/// Project id used for break timers
+pub const BREAK_ID: &str = "break";
+
+/// Sessions shorter than this are discarded on stop (seconds)
+pub const DEFAULT_MIN_DURATION: u64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActiveTimer {
+    project_id: String,
+    started_at: DateTime<Utc>,
+    /// Seconds accumulated before the last resume
+    accumulated: u64,
+    /// Set while running, cleared while paused
+    resumed_at: Option<DateTime<Utc>>,
+    /// Pomodoro / break length in seconds, `None` in free mode
+    countdown: Option<u64>,
+}
+
+impl ActiveTimer {
+    fn elapsed(&self, now: DateTime<Utc>) -> u64 {
+        let running = self
+            .resumed_at
+            .map(|t| (now - t).num_seconds().max(0) as u64)
+            .unwrap_or(0);
+        self.accumulated + running
+    }
+
+    fn is_break(&self) -> bool {
+        self.project_id == BREAK_ID
+    }
+}
+
+/// Snapshot of the active timer sent to the webview
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerStatus {
+    pub project_id: String,
+    pub started_at: DateTime<Utc>,
+    pub elapsed: u64,
+    pub paused: bool,
+    pub countdown: Option<u64>,
+    pub remaining: Option<u64>,
+}
+
+pub enum Tick {
+    Idle,
+    Running(TimerStatus),
+    /// The countdown reached zero; the session has been recorded, `saved`
+    /// as split into days, and the timer cleared.
+    Finished {
+        session: Session,
+        saved: Vec<Session>,
+    },
+}
+
+pub struct TimerEngine {
+    dir: PathBuf,
+}
+
+// Like the store, the engine keeps no state in memory: timer.json is read
+// and rewritten under the data directory lock, so the `pt` CLI can drive
+// the same timer as the desktop app.
+//
+// Methods that end a timer take `record`, which saves the session and
+// returns what was saved (e.g. `Store::add_session`). It runs before
+// timer.json changes, so if saving fails the timer is kept and the error
+// returned: a running session is never lost.
+impl TimerEngine {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn status(&self, now: DateTime<Utc>) -> Result<Option<TimerStatus>> {
+        Ok(self.load()?.map(|timer| status(&timer, now)))
+    }
+
+    /// Starts timing `project_id`. Resumes instead if that project is
+    /// paused; any other active timer is discarded.
+    pub fn start(
+        &mut self,
+        project_id: &str,
+        countdown: Option<u64>,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        if let Some(mut timer) = self.load()? {
+            if timer.project_id == project_id && timer.resumed_at.is_none() {
+                timer.resumed_at = Some(now);
+                return self.save(Some(&timer));
+            }
+        }
+
+        self.save(Some(&ActiveTimer {
+            project_id: project_id.to_string(),
+            started_at: now,
+            accumulated: 0,
+            resumed_at: Some(now),
+            countdown: countdown.filter(|&c| c > 0),
+        }))
+    }
+
+    /// Starts a break countdown. A running work timer is stopped and saved.
+    pub fn start_break(
+        &mut self,
+        duration: u64,
+        now: DateTime<Utc>,
+        record: impl FnOnce(Session) -> Result<Vec<Session>>,
+    ) -> Result<Vec<Session>> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let saved = match self
+            .load()?
+            .filter(|timer| !timer.is_break())
+            .and_then(|timer| finish(&timer, true, DEFAULT_MIN_DURATION, now))
+        {
+            Some(session) => record(session)?,
+            None => Vec::new(),
+        };
+        self.save(Some(&ActiveTimer {
+            project_id: BREAK_ID.to_string(),
+            started_at: now,
+            accumulated: 0,
+            resumed_at: Some(now),
+            countdown: Some(duration).filter(|&d| d > 0),
+        }))?;
+        Ok(saved)
+    }
+
+    pub fn pause(&mut self, now: DateTime<Utc>) -> Result<()> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let Some(mut timer) = self.load()? else {
+            return Ok(());
+        };
+        if timer.resumed_at.is_none() {
+            return Ok(());
+        }
+        timer.accumulated = timer.elapsed(now);
+        timer.resumed_at = None;
+        self.save(Some(&timer))
+    }
+
+    pub fn resume(&mut self, now: DateTime<Utc>) -> Result<()> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let Some(mut timer) = self.load()? else {
+            return Ok(());
+        };
+        if timer.resumed_at.is_some() {
+            return Ok(());
+        }
+        timer.resumed_at = Some(now);
+        self.save(Some(&timer))
+    }
+
+    /// Stops the active timer. Its session is recorded when `save` is set
+    /// and at least `min_duration` seconds have elapsed.
+    pub fn stop(
+        &mut self,
+        save: bool,
+        min_duration: u64,
+        now: DateTime<Utc>,
+        record: impl FnOnce(Session) -> Result<Vec<Session>>,
+    ) -> Result<Vec<Session>> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let Some(timer) = self.load()? else {
+            return Ok(Vec::new());
+        };
+        let saved = match finish(&timer, save, min_duration, now) {
+            Some(session) => record(session)?,
+            None => Vec::new(),
+        };
+        self.save(None)?;
+        Ok(saved)
+    }
+
+    /// Leaves `seconds` (e.g. idle time) out of the timer started at
+    /// `started_at`; a pomodoro gets them back on its countdown
+    pub fn remove_time(
+        &mut self,
+        started_at: DateTime<Utc>,
+        seconds: u64,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let mut timer = self.load_started_at(started_at)?;
+        timer.accumulated = timer.elapsed(now).saturating_sub(seconds);
+        if timer.resumed_at.is_some() {
+            timer.resumed_at = Some(now);
+        }
+        self.save(Some(&timer))
+    }
+
+    /// Cuts `from..to` out of the timer started at `started_at`: records
+    /// the session up to `from` (unless too short to keep), and the timer
+    /// goes on from `to` with what was timed since. A pomodoro keeps the
+    /// time it had left at `from`.
+    pub fn cut(
+        &mut self,
+        started_at: DateTime<Utc>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        now: DateTime<Utc>,
+        record: impl FnOnce(Session) -> Result<Vec<Session>>,
+    ) -> Result<Vec<Session>> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let timer = self.load_started_at(started_at)?;
+        let gap = (to - from).num_seconds().max(0) as u64;
+        let total = timer.elapsed(now);
+        let before = timer.elapsed(from).min(total.saturating_sub(gap));
+        let saved = if before < DEFAULT_MIN_DURATION {
+            Vec::new()
+        } else {
+            record(session(&timer, before, SessionKind::Work, from))?
+        };
+        self.save(Some(&ActiveTimer {
+            project_id: timer.project_id.clone(),
+            started_at: to,
+            accumulated: total.saturating_sub(before + gap),
+            resumed_at: timer.resumed_at.map(|_| now),
+            countdown: timer.countdown.map(|c| c.saturating_sub(before)),
+        }))?;
+        Ok(saved)
+    }
+
+    /// Advances the countdown, finishing the timer once it reaches zero
+    pub fn tick(
+        &mut self,
+        now: DateTime<Utc>,
+        record: impl FnOnce(Session) -> Result<Vec<Session>>,
+    ) -> Result<Tick> {
+        let _lock = DirLock::acquire(&self.dir)?;
+        let Some(timer) = self.load()? else {
+            return Ok(Tick::Idle);
+        };
+        match timer.countdown {
+            Some(countdown) if timer.elapsed(now) >= countdown => {
+                let kind = if timer.is_break() {
+                    SessionKind::Break
+                } else {
+                    SessionKind::Pomodoro
+                };
+                let session = session(&timer, countdown, kind, now);
+                let saved = record(session.clone())?;
+                self.save(None)?;
+                Ok(Tick::Finished { session, saved })
+            }
+            _ => Ok(Tick::Running(status(&timer, now))),
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(TIMER_FILE)
+    }
+
+    fn load(&self) -> Result<Option<ActiveTimer>> {
+        read_json(&self.path())
+    }
+
+    /// The active timer, if it is still the one started at `started_at`
+    fn load_started_at(&self, started_at: DateTime<Utc>) -> Result<ActiveTimer> {
+        self.load()?
+            .filter(|timer| timer.started_at == started_at)
+            .ok_or_else(|| Error::Invalid("the timer has changed since".into()))
+    }
+
+    fn save(&self, timer: Option<&ActiveTimer>) -> Result<()> {
+        match timer {
+            Some(timer) => write_json_atomic(&self.path(), timer),
+            None => match fs::remove_file(self.path()) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+fn status(timer: &ActiveTimer, now: DateTime<Utc>) -> TimerStatus {
+    let elapsed = timer.elapsed(now);
+    TimerStatus {
+        project_id: timer.project_id.clone(),
+        started_at: timer.started_at,
+        elapsed,
+        paused: timer.resumed_at.is_none(),
+        countdown: timer.countdown,
+        remaining: timer.countdown.map(|c| c.saturating_sub(elapsed)),
+    }
+}
+
+/// The session recorded when a timer is stopped by hand
+fn finish(
+    timer: &ActiveTimer,
+    save: bool,
+    min_duration: u64,
+    now: DateTime<Utc>,
+) -> Option<Session> {
+    let elapsed = timer.elapsed(now);
+    if !save || elapsed < min_duration {
+        return None;
+    }
+    let kind = if timer.is_break() {
+        SessionKind::Break
+    } else {
+        SessionKind::Work
+    };
+    Some(session(timer, elapsed, kind, now))
+}
+
+fn session(timer: &ActiveTimer, duration: u64, kind: SessionKind, now: DateTime<Utc>) -> Session {
+    Session {
+        id: new_id(),
+        project_id: timer.project_id.clone(),
+        start_time: timer.started_at,
+        end_time: now,
+        duration,
+        kind,
+        modified_at: now,
+        // Filed under its local day, and split at midnight, by the store
+        date: timer.started_at.date_naive(),
+        timezone: None,
+        notes: None,
+        tags: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{time_on, TempDir};
+
+    const DAY: &str = "2025-03-03";
+
+    fn engine(name: &str) -> (TempDir, TimerEngine) {
+        let dir = TempDir::new(name);
+        let timer = TimerEngine::new(dir.path());
+        (dir, timer)
+    }
+
+    /// Records a session as saved in one piece
+    fn keep(session: Session) -> Result<Vec<Session>> {
+        Ok(vec![session])
+    }
+
+    fn fail(_: Session) -> Result<Vec<Session>> {
+        Err(Error::Invalid("disk full".into()))
+    }
+
+    fn unsaved(_: Session) -> Result<Vec<Session>> {
+        panic!("nothing should be saved")
+    }
+
+    #[test]
+    fn pauses_and_resumes() {
+        let (_dir, mut timer) = engine("timer-pause");
+        assert_eq!(timer.status(time_on(DAY, "09:00")).unwrap(), None);
+        timer.start("web", None, time_on(DAY, "09:00")).unwrap();
+        timer.pause(time_on(DAY, "09:10")).unwrap();
+        timer.pause(time_on(DAY, "09:20")).unwrap();
+        let status = timer.status(time_on(DAY, "09:30")).unwrap().unwrap();
+        assert!(status.paused);
+        assert_eq!((status.elapsed, status.remaining), (600, None));
+
+        // Starting the paused project resumes it
+        timer.start("web", None, time_on(DAY, "09:30")).unwrap();
+        timer.resume(time_on(DAY, "09:35")).unwrap();
+        let status = timer.status(time_on(DAY, "09:40")).unwrap().unwrap();
+        assert_eq!(
+            (status.started_at, status.elapsed),
+            (time_on(DAY, "09:00"), 1200)
+        );
+
+        let [session] = &timer.stop(true, 60, time_on(DAY, "09:40"), keep).unwrap()[..] else {
+            panic!("expected one session");
+        };
+        assert_eq!(
+            (session.start_time, session.end_time),
+            (time_on(DAY, "09:00"), time_on(DAY, "09:40"))
+        );
+        assert_eq!((session.duration, session.kind), (1200, SessionKind::Work));
+        assert_eq!(timer.status(time_on(DAY, "09:40")).unwrap(), None);
+        assert!(timer
+            .stop(true, 60, time_on(DAY, "09:40"), unsaved)
+            .unwrap()
+            .is_empty());
+
+        // Too short, or not to be saved
+        timer.start("web", None, time_on(DAY, "10:00")).unwrap();
+        assert!(timer
+            .stop(true, 3600, time_on(DAY, "10:30"), unsaved)
+            .unwrap()
+            .is_empty());
+        timer.start("web", None, time_on(DAY, "10:00")).unwrap();
+        assert!(timer
+            .stop(false, 60, time_on(DAY, "10:30"), unsaved)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn survives_a_reload() {
+        let (dir, mut timer) = engine("timer-reload");
+        timer
+            .start("web", Some(1500), time_on(DAY, "09:00"))
+            .unwrap();
+        timer.pause(time_on(DAY, "09:05")).unwrap();
+        assert!(dir.path().join(TIMER_FILE).exists());
+
+        // Another engine on the same directory, like `pt` next to the app
+        let mut other = TimerEngine::new(dir.path());
+        let status = other.status(time_on(DAY, "09:30")).unwrap().unwrap();
+        assert!(status.paused);
+        assert_eq!((status.elapsed, status.remaining), (300, Some(1200)));
+        other.resume(time_on(DAY, "09:30")).unwrap();
+        let status = timer.status(time_on(DAY, "09:35")).unwrap().unwrap();
+        assert!(!status.paused);
+        assert_eq!(status.elapsed, 600);
+
+        // Starting another project discards the timer
+        other.start("docs", None, time_on(DAY, "09:40")).unwrap();
+        let status = timer.status(time_on(DAY, "09:40")).unwrap().unwrap();
+        assert_eq!((status.project_id.as_str(), status.elapsed), ("docs", 0));
+        timer
+            .stop(false, 0, time_on(DAY, "09:40"), unsaved)
+            .unwrap();
+        assert!(!dir.path().join(TIMER_FILE).exists());
+    }
+
+    #[test]
+    fn countdowns_finish_on_tick() {
+        let (_dir, mut timer) = engine("timer-tick");
+        assert!(matches!(
+            timer.tick(time_on(DAY, "09:00"), unsaved).unwrap(),
+            Tick::Idle
+        ));
+        timer
+            .start("web", Some(1500), time_on(DAY, "09:00"))
+            .unwrap();
+        match timer.tick(time_on(DAY, "09:10"), unsaved).unwrap() {
+            Tick::Running(status) => assert_eq!(status.remaining, Some(900)),
+            _ => panic!("expected the pomodoro to run"),
+        }
+
+        // Paused time doesn't count down
+        timer.pause(time_on(DAY, "09:10")).unwrap();
+        assert!(matches!(
+            timer.tick(time_on(DAY, "10:00"), unsaved).unwrap(),
+            Tick::Running(_)
+        ));
+        timer.resume(time_on(DAY, "10:00")).unwrap();
+        let Tick::Finished {
+            session: pomodoro,
+            saved,
+        } = timer.tick(time_on(DAY, "10:16"), keep).unwrap()
+        else {
+            panic!("expected the pomodoro to finish");
+        };
+        assert_eq!(
+            (pomodoro.kind, pomodoro.duration),
+            (SessionKind::Pomodoro, 1500)
+        );
+        assert_eq!(pomodoro.end_time, time_on(DAY, "10:16"));
+        assert_eq!(saved, [pomodoro]);
+        assert!(matches!(
+            timer.tick(time_on(DAY, "10:16"), unsaved).unwrap(),
+            Tick::Idle
+        ));
+
+        // A break saves the work it interrupts
+        timer.start("web", None, time_on(DAY, "11:00")).unwrap();
+        let work = timer.start_break(300, time_on(DAY, "11:20"), keep).unwrap();
+        assert_eq!((work[0].kind, work[0].duration), (SessionKind::Work, 1200));
+        let Tick::Finished { session: rest, .. } = timer.tick(time_on(DAY, "11:25"), keep).unwrap()
+        else {
+            panic!("expected the break to finish");
+        };
+        assert_eq!(
+            (rest.project_id.as_str(), rest.kind, rest.duration),
+            (BREAK_ID, SessionKind::Break, 300)
+        );
+    }
+
+    #[test]
+    fn removes_and_cuts_out_time() {
+        let (_dir, mut timer) = engine("timer-cut");
+        timer
+            .start("web", Some(3600), time_on(DAY, "09:00"))
+            .unwrap();
+        timer
+            .remove_time(time_on(DAY, "09:00"), 300, time_on(DAY, "09:20"))
+            .unwrap();
+        let status = timer.status(time_on(DAY, "09:20")).unwrap().unwrap();
+        assert_eq!((status.elapsed, status.remaining), (900, Some(2700)));
+        assert!(timer
+            .remove_time(time_on(DAY, "08:00"), 60, time_on(DAY, "09:20"))
+            .is_err());
+
+        // 09:25-09:45 goes elsewhere: what came before is saved, the rest
+        // goes on with the time the pomodoro had left at 09:25
+        let before = timer
+            .cut(
+                time_on(DAY, "09:00"),
+                time_on(DAY, "09:25"),
+                time_on(DAY, "09:45"),
+                time_on(DAY, "09:50"),
+                keep,
+            )
+            .unwrap();
+        assert_eq!(
+            (before[0].end_time, before[0].duration),
+            (time_on(DAY, "09:25"), 1200)
+        );
+        let status = timer.status(time_on(DAY, "09:50")).unwrap().unwrap();
+        assert_eq!(status.started_at, time_on(DAY, "09:45"));
+        assert_eq!((status.elapsed, status.remaining), (300, Some(2100)));
+
+        // Too little before the cut to keep
+        assert!(timer
+            .cut(
+                time_on(DAY, "09:45"),
+                time_on(DAY, "09:45"),
+                time_on(DAY, "09:55"),
+                time_on(DAY, "09:55"),
+                unsaved
+            )
+            .unwrap()
+            .is_empty());
+        assert!(timer
+            .cut(
+                time_on(DAY, "09:45"),
+                time_on(DAY, "09:50"),
+                time_on(DAY, "09:55"),
+                time_on(DAY, "09:55"),
+                unsaved
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn keeps_the_timer_when_saving_fails() {
+        let (_dir, mut timer) = engine("timer-unsaved");
+        timer
+            .start("web", Some(1500), time_on(DAY, "09:00"))
+            .unwrap();
+        let running = timer.status(time_on(DAY, "09:20")).unwrap();
+        assert!(timer.stop(true, 60, time_on(DAY, "09:20"), fail).is_err());
+        assert!(timer.start_break(300, time_on(DAY, "09:20"), fail).is_err());
+        assert!(timer
+            .cut(
+                time_on(DAY, "09:00"),
+                time_on(DAY, "09:10"),
+                time_on(DAY, "09:15"),
+                time_on(DAY, "09:20"),
+                fail
+            )
+            .is_err());
+        assert_eq!(timer.status(time_on(DAY, "09:20")).unwrap(), running);
+
+        // A finished countdown is tried again on the next tick
+        assert!(timer.tick(time_on(DAY, "09:30"), fail).is_err());
+        assert!(matches!(
+            timer.tick(time_on(DAY, "09:30"), keep).unwrap(),
+            Tick::Finished { .. }
+        ));
+    }
+}
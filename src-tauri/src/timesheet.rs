@@ -0,0 +1,981 @@
+// Productivity Tracker - Timesheets
+// Printable summaries of the work done over a date range, for billing
+// clients: time per project, then per day and project, with optional
+// hourly rates and rounding. Rendered as a self-contained HTML page or as
+// a PDF. Breaks are left out, and so are the rates of projects that are
+// not billable. Amounts are never converted, so a timesheet has one
+// currency.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::db::SessionQuery;
+use crate::error::{Error, Result};
+use crate::model::{Project, Session, SessionKind};
+use crate::pdf::{self, Document, Font, BLACK, PAGE_HEIGHT, PAGE_WIDTH};
+use crate::store::Store;
+
+const DEFAULT_TITLE: &str = "Timesheet";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimesheetFormat {
+    Html,
+    Pdf,
+}
+
+impl TimesheetFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TimesheetFormat::Html => "html",
+            TimesheetFormat::Pdf => "pdf",
+        }
+    }
+
+    /// Name for the save dialog's file type filter
+    pub fn description(self) -> &'static str {
+        match self {
+            TimesheetFormat::Html => "HTML",
+            TimesheetFormat::Pdf => "PDF",
+        }
+    }
+}
+
+impl FromStr for TimesheetFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "html" => Ok(TimesheetFormat::Html),
+            "pdf" => Ok(TimesheetFormat::Pdf),
+            other => Err(Error::Invalid(format!(
+                "unknown timesheet format {other:?} (html or pdf)"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    #[default]
+    Up,
+    Nearest,
+    Down,
+}
+
+impl FromStr for RoundingMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "up" => Ok(RoundingMode::Up),
+            "nearest" => Ok(RoundingMode::Nearest),
+            "down" => Ok(RoundingMode::Down),
+            other => Err(Error::Invalid(format!(
+                "unknown rounding {other:?} (up, nearest or down)"
+            ))),
+        }
+    }
+}
+
+/// Applied to each session on its own, as billing tools do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rounding {
+    /// Step in minutes; 0 keeps the time as tracked
+    #[serde(default)]
+    pub minutes: u64,
+    #[serde(default)]
+    pub mode: RoundingMode,
+}
+
+impl Rounding {
+    pub fn apply(&self, seconds: u64) -> u64 {
+        let step = self.minutes * 60;
+        if step == 0 {
+            return seconds;
+        }
+        let steps = match self.mode {
+            RoundingMode::Up => seconds.div_ceil(step),
+            RoundingMode::Nearest => (seconds + step / 2) / step,
+            RoundingMode::Down => seconds / step,
+        };
+        steps * step
+    }
+
+    /// e.g. "Each session rounded up to 15 minutes"
+    fn describe(&self) -> Option<String> {
+        if self.minutes == 0 {
+            return None;
+        }
+        let mode = match self.mode {
+            RoundingMode::Up => "up",
+            RoundingMode::Nearest => "to the nearest",
+            RoundingMode::Down => "down",
+        };
+        let to = if self.mode == RoundingMode::Nearest {
+            ""
+        } else {
+            " to"
+        };
+        Some(format!(
+            "Each session rounded {mode}{to} {} minutes.",
+            self.minutes
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimesheetOptions {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Project ids to include; all when empty
+    #[serde(default)]
+    pub projects: Vec<String>,
+    #[serde(default)]
+    pub rounding: Rounding,
+    /// Hourly rate by project id, in `currency`; billable projects without
+    /// one are billed at their own rate, others show no amount
+    #[serde(default)]
+    pub rates: HashMap<String, f64>,
+    /// Written after amounts, e.g. "EUR"; the projects' own if omitted
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// Heading, e.g. the client's name
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+impl TimesheetOptions {
+    pub fn validate(&self) -> Result<()> {
+        if self.to < self.from {
+            return Err(Error::Invalid("the range ends before it starts".into()));
+        }
+        if let Some((id, _)) = self
+            .rates
+            .iter()
+            .find(|(_, rate)| !rate.is_finite() || **rate < 0.0)
+        {
+            return Err(Error::Invalid(format!("invalid rate for project {id}")));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timesheet {
+    pub title: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub rounding: Rounding,
+    pub currency: Option<String>,
+    /// Most time first
+    pub projects: Vec<ProjectTotal>,
+    /// By date, then project name
+    pub days: Vec<DayLine>,
+    /// Time as tracked, before rounding
+    pub tracked: u64,
+    /// Time after rounding, what is billed
+    pub seconds: u64,
+    /// Sum of the amounts; `None` if no project has a rate
+    pub amount: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectTotal {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    pub sessions: usize,
+    pub tracked: u64,
+    pub seconds: u64,
+    pub rate: Option<f64>,
+    pub amount: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayLine {
+    pub date: NaiveDate,
+    pub project_id: String,
+    pub project: String,
+    pub sessions: usize,
+    pub seconds: u64,
+    pub amount: Option<f64>,
+}
+
+/// Builds the timesheet of `options` from the store's sessions
+pub fn timesheet(store: &Store, options: &TimesheetOptions) -> Result<Timesheet> {
+    options.validate()?;
+    let projects = store.projects()?;
+    let options = billed_rates(&projects, options)?;
+    let sessions = store.sessions(&SessionQuery {
+        from: Some(options.from.to_string()),
+        to: Some(options.to.to_string()),
+        ..Default::default()
+    })?;
+    Ok(build(&sessions, &projects, &options))
+}
+
+/// `options` with the rate of every included billable project, the one
+/// given or else the project's own, and the currency they are in. Fails
+/// if the rates are in more than one currency.
+fn billed_rates(projects: &[Project], options: &TimesheetOptions) -> Result<TimesheetOptions> {
+    let currency = options
+        .currency
+        .as_deref()
+        .map(|c| c.trim().to_uppercase())
+        .filter(|c| !c.is_empty());
+    let mut rates = HashMap::new();
+    let mut currencies: BTreeSet<String> = currency.iter().cloned().collect();
+    let billed = projects.iter().filter(|p| {
+        p.billable && (options.projects.is_empty() || options.projects.contains(&p.id))
+    });
+    for project in billed {
+        let (rate, rate_currency) = match (options.rates.get(&project.id), project.hourly_rate) {
+            (Some(&rate), _) => (rate, currency.clone().or(project.currency.clone())),
+            (None, Some(rate)) => (rate, project.currency.clone()),
+            (None, None) => continue,
+        };
+        rates.insert(project.id.clone(), rate);
+        currencies.extend(rate_currency);
+    }
+    if currencies.len() > 1 {
+        let names: Vec<_> = currencies.into_iter().collect();
+        return Err(Error::Invalid(format!(
+            "the rates are in {}; choose projects billed in one currency",
+            names.join(" and ")
+        )));
+    }
+    Ok(TimesheetOptions {
+        rates,
+        currency: currencies.pop_first(),
+        ..options.clone()
+    })
+}
+
+pub fn build(sessions: &[Session], projects: &[Project], options: &TimesheetOptions) -> Timesheet {
+    let amount = |rate: Option<f64>, seconds: u64| rate.map(|rate| rate * seconds as f64 / 3600.0);
+    let mut totals: Vec<ProjectTotal> = Vec::new();
+    let mut days: Vec<DayLine> = Vec::new();
+
+    let included = sessions.iter().filter(|s| {
+        s.kind != SessionKind::Break
+            && (s.date >= options.from && s.date <= options.to)
+            && (options.projects.is_empty() || options.projects.contains(&s.project_id))
+    });
+    for session in included {
+        let seconds = options.rounding.apply(session.duration);
+        let rate = options.rates.get(&session.project_id).copied();
+
+        let total = match totals.iter().position(|t| t.id == session.project_id) {
+            Some(i) => &mut totals[i],
+            None => {
+                let project = projects.iter().find(|p| p.id == session.project_id);
+                totals.push(ProjectTotal {
+                    id: session.project_id.clone(),
+                    name: project.map_or(session.project_id.clone(), |p| p.name.clone()),
+                    color: project.map_or_else(String::new, |p| p.color.clone()),
+                    sessions: 0,
+                    tracked: 0,
+                    seconds: 0,
+                    rate,
+                    amount: None,
+                });
+                totals.last_mut().unwrap()
+            }
+        };
+        total.sessions += 1;
+        total.tracked += session.duration;
+        total.seconds += seconds;
+
+        let name = total.name.clone();
+        match days
+            .iter_mut()
+            .find(|d| d.date == session.date && d.project_id == session.project_id)
+        {
+            Some(day) => {
+                day.sessions += 1;
+                day.seconds += seconds;
+            }
+            None => days.push(DayLine {
+                date: session.date,
+                project_id: session.project_id.clone(),
+                project: name,
+                sessions: 1,
+                seconds,
+                amount: None,
+            }),
+        }
+    }
+
+    for day in &mut days {
+        day.amount = amount(options.rates.get(&day.project_id).copied(), day.seconds);
+    }
+    for total in &mut totals {
+        total.amount = amount(total.rate, total.seconds);
+    }
+    days.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.project.cmp(&b.project)));
+    totals.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.name.cmp(&b.name)));
+
+    Timesheet {
+        title: options
+            .title
+            .clone()
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_TITLE.to_string()),
+        from: options.from,
+        to: options.to,
+        rounding: options.rounding,
+        currency: options.currency.clone().filter(|c| !c.trim().is_empty()),
+        tracked: totals.iter().map(|t| t.tracked).sum(),
+        seconds: totals.iter().map(|t| t.seconds).sum(),
+        amount: totals.iter().filter_map(|t| t.amount).reduce(|a, b| a + b),
+        projects: totals,
+        days,
+    }
+}
+
+impl Timesheet {
+    pub fn render(&self, format: TimesheetFormat) -> Vec<u8> {
+        match format {
+            TimesheetFormat::Html => self.html().into_bytes(),
+            TimesheetFormat::Pdf => self.pdf(),
+        }
+    }
+
+    fn has_rates(&self) -> bool {
+        self.projects.iter().any(|p| p.rate.is_some())
+    }
+
+    fn money(&self, amount: Option<f64>) -> String {
+        match (amount, &self.currency) {
+            (Some(amount), Some(currency)) => format!("{amount:.2} {currency}"),
+            (Some(amount), None) => format!("{amount:.2}"),
+            (None, _) => String::new(),
+        }
+    }
+
+    fn period(&self) -> String {
+        format!("{} to {}", self.from, self.to)
+    }
+
+    fn notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        if let Some(rounding) = self.rounding.describe() {
+            notes.push(rounding);
+            notes.push(format!(
+                "Tracked {}, billed {}.",
+                hours_minutes(self.tracked),
+                hours_minutes(self.seconds)
+            ));
+        }
+        notes
+    }
+
+    // ============================================
+    // HTML
+    // ============================================
+
+    /// One page with its styles inline, ready to print or attach
+    pub fn html(&self) -> String {
+        let rates = self.has_rates();
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title} {period}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n\
+             <header>\n<h1>{title}</h1>\n<p>{period}</p>\n</header>\n",
+            title = escape(&self.title),
+            period = escape(&self.period()),
+        );
+
+        html.push_str("<h2>Projects</h2>\n<table>\n<thead><tr><th>Project</th>");
+        html.push_str("<th class=\"num\">Sessions</th><th class=\"num\">Time</th><th class=\"num\">Hours</th>");
+        if rates {
+            html.push_str("<th class=\"num\">Rate</th><th class=\"num\">Amount</th>");
+        }
+        html.push_str("</tr></thead>\n<tbody>\n");
+        for project in &self.projects {
+            let swatch = match pdf::parse_color(&project.color) {
+                Some(_) => format!(
+                    "<span class=\"swatch\" style=\"background:{}\"></span>",
+                    project.color
+                ),
+                None => String::new(),
+            };
+            let _ = write!(
+                html,
+                "<tr><td>{swatch}{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>\
+                 <td class=\"num\">{}</td>",
+                escape(&project.name),
+                project.sessions,
+                hours_minutes(project.seconds),
+                decimal_hours(project.seconds),
+            );
+            if rates {
+                let _ = write!(
+                    html,
+                    "<td class=\"num\">{}</td><td class=\"num\">{}</td>",
+                    escape(&self.money(project.rate)),
+                    escape(&self.money(project.amount)),
+                );
+            }
+            html.push_str("</tr>\n");
+        }
+        let _ = write!(
+            html,
+            "</tbody>\n<tfoot><tr><th>Total</th><td class=\"num\">{}</td><td class=\"num\">{}</td>\
+             <td class=\"num\">{}</td>",
+            self.projects.iter().map(|p| p.sessions).sum::<usize>(),
+            hours_minutes(self.seconds),
+            decimal_hours(self.seconds),
+        );
+        if rates {
+            let _ = write!(
+                html,
+                "<td></td><td class=\"num\">{}</td>",
+                escape(&self.money(self.amount))
+            );
+        }
+        html.push_str("</tr></tfoot>\n</table>\n");
+
+        html.push_str(
+            "<h2>Daily breakdown</h2>\n<table>\n<thead><tr><th>Date</th><th>Project</th>",
+        );
+        html.push_str("<th class=\"num\">Sessions</th><th class=\"num\">Time</th>");
+        if rates {
+            html.push_str("<th class=\"num\">Amount</th>");
+        }
+        html.push_str("</tr></thead>\n<tbody>\n");
+        let mut last_date = None;
+        for day in &self.days {
+            let date = if last_date == Some(day.date) {
+                String::new()
+            } else {
+                day_label(day.date)
+            };
+            last_date = Some(day.date);
+            let _ = write!(
+                html,
+                "<tr><td>{date}</td><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>",
+                escape(&day.project),
+                day.sessions,
+                hours_minutes(day.seconds),
+            );
+            if rates {
+                let _ = write!(
+                    html,
+                    "<td class=\"num\">{}</td>",
+                    escape(&self.money(day.amount))
+                );
+            }
+            html.push_str("</tr>\n");
+        }
+        if self.days.is_empty() {
+            html.push_str("<tr><td colspan=\"5\">No sessions in this period.</td></tr>\n");
+        }
+        html.push_str("</tbody>\n</table>\n");
+
+        for note in self.notes() {
+            let _ = writeln!(html, "<p class=\"note\">{}</p>", escape(&note));
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    // ============================================
+    // PDF
+    // ============================================
+
+    /// A4 pages, tables continuing on new pages with their headings
+    pub fn pdf(&self) -> Vec<u8> {
+        let rates = self.has_rates();
+        let mut page = PdfPage::new(&format!("{} {}", self.title, self.period()));
+        page.doc
+            .text(MARGIN, page.y, Font::Bold, 20.0, BLACK, &self.title);
+        page.y -= 18.0;
+        page.doc
+            .text(MARGIN, page.y, Font::Regular, 11.0, GRAY, &self.period());
+        page.y -= 32.0;
+
+        // Project, sessions, time, hours, rate, amount
+        let mut columns = vec![
+            Column::left("Project", MARGIN),
+            Column::right("Sessions", 290.0),
+            Column::right("Time", 345.0),
+            Column::right("Hours", 400.0),
+        ];
+        if rates {
+            columns.push(Column::right("Rate", 475.0));
+            columns.push(Column::right("Amount", RIGHT));
+        }
+        page.heading("Projects");
+        page.header(&columns);
+        for project in &self.projects {
+            let mut cells = vec![
+                project.name.clone(),
+                project.sessions.to_string(),
+                hours_minutes(project.seconds),
+                decimal_hours(project.seconds),
+            ];
+            if rates {
+                cells.push(self.money(project.rate));
+                cells.push(self.money(project.amount));
+            }
+            page.row(
+                &columns,
+                &cells,
+                Font::Regular,
+                pdf::parse_color(&project.color),
+            );
+        }
+        let mut cells = vec![
+            "Total".to_string(),
+            self.projects
+                .iter()
+                .map(|p| p.sessions)
+                .sum::<usize>()
+                .to_string(),
+            hours_minutes(self.seconds),
+            decimal_hours(self.seconds),
+        ];
+        if rates {
+            cells.push(String::new());
+            cells.push(self.money(self.amount));
+        }
+        page.rule();
+        page.row(&columns, &cells, Font::Bold, None);
+        page.y -= 24.0;
+
+        // Date, project, sessions, time, amount
+        let mut columns = vec![
+            Column::left("Date", MARGIN),
+            Column::left("Project", 150.0),
+            Column::right("Sessions", 375.0),
+            Column::right("Time", 440.0),
+        ];
+        if rates {
+            columns.push(Column::right("Amount", RIGHT));
+        }
+        page.heading("Daily breakdown");
+        page.header(&columns);
+        let mut last_date = None;
+        for day in &self.days {
+            let date = if last_date == Some(day.date) {
+                String::new()
+            } else {
+                day_label(day.date)
+            };
+            last_date = Some(day.date);
+            let mut cells = vec![
+                date,
+                day.project.clone(),
+                day.sessions.to_string(),
+                hours_minutes(day.seconds),
+            ];
+            if rates {
+                cells.push(self.money(day.amount));
+            }
+            page.row(&columns, &cells, Font::Regular, None);
+        }
+        if self.days.is_empty() {
+            page.row(
+                &columns,
+                &["No sessions in this period.".to_string()],
+                Font::Regular,
+                None,
+            );
+        }
+
+        page.y -= 12.0;
+        for note in self.notes() {
+            page.room(LINE);
+            page.doc
+                .text(MARGIN, page.y, Font::Regular, 9.0, GRAY, &note);
+            page.y -= LINE;
+        }
+        page.doc.finish()
+    }
+}
+
+const HTML_STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; color: #1a1a1a;
+       max-width: 780px; margin: 40px auto; padding: 0 24px; font-size: 14px; }
+header { border-bottom: 2px solid #1a1a1a; margin-bottom: 24px; }
+h1 { margin: 0 0 4px; font-size: 26px; }
+header p { margin: 0 0 12px; color: #666; }
+h2 { font-size: 16px; margin: 28px 0 8px; }
+table { width: 100%; border-collapse: collapse; }
+th, td { text-align: left; padding: 6px 8px; border-bottom: 1px solid #ddd; }
+thead th { font-size: 12px; text-transform: uppercase; color: #666; }
+tfoot th, tfoot td { font-weight: bold; border-top: 2px solid #1a1a1a; }
+.num { text-align: right; font-variant-numeric: tabular-nums; }
+.swatch { display: inline-block; width: 10px; height: 10px; border-radius: 2px; margin-right: 8px; }
+.note { color: #666; font-size: 12px; }
+@page { size: A4; margin: 20mm; }
+@media print { body { margin: 0; max-width: none; } }
+";
+
+const MARGIN: f32 = 50.0;
+const RIGHT: f32 = PAGE_WIDTH - MARGIN;
+const LINE: f32 = 16.0;
+const TEXT_SIZE: f32 = 10.0;
+const GRAY: pdf::Color = [0.4, 0.4, 0.4];
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Right,
+}
+
+/// A table column: left edge for left aligned text, right edge otherwise
+#[derive(Clone, Copy)]
+struct Column {
+    title: &'static str,
+    x: f32,
+    align: Align,
+}
+
+impl Column {
+    fn left(title: &'static str, x: f32) -> Self {
+        Column {
+            title,
+            x,
+            align: Align::Left,
+        }
+    }
+
+    fn right(title: &'static str, x: f32) -> Self {
+        Column {
+            title,
+            x,
+            align: Align::Right,
+        }
+    }
+}
+
+/// Writing position in a PDF, starting new pages as they fill up
+struct PdfPage {
+    doc: Document,
+    y: f32,
+    /// Columns of the table being written, repeated on a new page
+    header: Option<Vec<Column>>,
+}
+
+impl PdfPage {
+    fn new(title: &str) -> Self {
+        PdfPage {
+            doc: Document::new(title),
+            y: PAGE_HEIGHT - MARGIN,
+            header: None,
+        }
+    }
+
+    /// Starts a new page unless `height` still fits on this one
+    fn room(&mut self, height: f32) {
+        if self.y - height >= MARGIN {
+            return;
+        }
+        self.doc.new_page();
+        self.y = PAGE_HEIGHT - MARGIN;
+        if let Some(header) = self.header.take() {
+            self.draw_header(&header);
+            self.header = Some(header);
+        }
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.header = None;
+        self.room(LINE * 4.0);
+        self.doc.text(MARGIN, self.y, Font::Bold, 13.0, BLACK, text);
+        self.y -= LINE * 1.25;
+    }
+
+    fn header(&mut self, columns: &[Column]) {
+        self.draw_header(columns);
+        self.header = Some(columns.to_vec());
+    }
+
+    fn draw_header(&mut self, columns: &[Column]) {
+        for column in columns {
+            let title = column.title.to_uppercase();
+            let x = match column.align {
+                Align::Left => column.x,
+                Align::Right => column.x - Font::Bold.width(&title, 8.0),
+            };
+            self.doc.text(x, self.y, Font::Bold, 8.0, GRAY, &title);
+        }
+        self.y -= 6.0;
+        self.doc.line(MARGIN, RIGHT, self.y, 0.5, 0.6);
+        self.y -= LINE - 4.0;
+    }
+
+    fn rule(&mut self) {
+        self.doc.line(MARGIN, RIGHT, self.y + LINE - 5.0, 1.0, 0.0);
+    }
+
+    /// One row; a `swatch` color is drawn before the first cell
+    fn row(
+        &mut self,
+        columns: &[Column],
+        cells: &[String],
+        font: Font,
+        swatch: Option<pdf::Color>,
+    ) {
+        self.room(LINE);
+        for (i, (column, cell)) in columns.iter().zip(cells).enumerate() {
+            match column.align {
+                Align::Left => {
+                    let mut x = column.x;
+                    if let (0, Some(color)) = (i, swatch) {
+                        self.doc.rect(x, self.y, 7.0, 7.0, color);
+                        x += 12.0;
+                    }
+                    // Cut what would run into the next column
+                    let limit = columns.get(i + 1).map_or(RIGHT, |next| match next.align {
+                        Align::Left => next.x,
+                        Align::Right => next.x - 60.0,
+                    }) - x
+                        - 8.0;
+                    self.doc
+                        .text(x, self.y, font, TEXT_SIZE, BLACK, &fit(cell, font, limit));
+                }
+                Align::Right => self.doc.text_right(column.x, self.y, font, TEXT_SIZE, cell),
+            }
+        }
+        self.y -= LINE;
+    }
+}
+
+/// `text` cut with an ellipsis to fit `width` points
+fn fit(text: &str, font: Font, width: f32) -> String {
+    if font.width(text, TEXT_SIZE) <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.to_string();
+    while !cut.is_empty() && font.width(&format!("{cut}…"), TEXT_SIZE) > width {
+        cut.pop();
+    }
+    format!("{}…", cut.trim_end())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// e.g. "Wed 2025-01-15"
+fn day_label(date: NaiveDate) -> String {
+    date.format("%a %Y-%m-%d").to_string()
+}
+
+/// e.g. "1:30"
+fn hours_minutes(seconds: u64) -> String {
+    let minutes = seconds / 60;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// e.g. "1.50"
+fn decimal_hours(seconds: u64) -> String {
+    format!("{:.2}", seconds as f64 / 3600.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{day_session, project};
+
+    fn options() -> TimesheetOptions {
+        TimesheetOptions {
+            from: "2025-01-13".parse().unwrap(),
+            to: "2025-01-19".parse().unwrap(),
+            projects: Vec::new(),
+            rounding: Rounding::default(),
+            rates: HashMap::new(),
+            currency: None,
+            title: None,
+        }
+    }
+
+    fn sessions() -> Vec<Session> {
+        vec![
+            day_session("s1", "web", "2025-01-13", 50, SessionKind::Work),
+            day_session("s2", "web", "2025-01-13", 25, SessionKind::Pomodoro),
+            day_session("s3", "docs", "2025-01-14", 61, SessionKind::Work),
+            day_session("s4", "break", "2025-01-14", 5, SessionKind::Break),
+            day_session("s5", "web", "2025-01-20", 60, SessionKind::Work),
+        ]
+    }
+
+    #[test]
+    fn rounds_each_session() {
+        let up = Rounding {
+            minutes: 15,
+            mode: RoundingMode::Up,
+        };
+        assert_eq!(up.apply(1), 900);
+        assert_eq!(up.apply(900), 900);
+        assert_eq!(up.apply(0), 0);
+        let nearest = Rounding {
+            mode: RoundingMode::Nearest,
+            ..up
+        };
+        assert_eq!((nearest.apply(449), nearest.apply(450)), (0, 900));
+        let down = Rounding {
+            mode: RoundingMode::Down,
+            ..up
+        };
+        assert_eq!(down.apply(1799), 900);
+        assert_eq!(Rounding::default().apply(1234), 1234);
+    }
+
+    #[test]
+    fn totals_with_rates_and_rounding() {
+        let mut options = options();
+        options.rounding = Rounding {
+            minutes: 15,
+            mode: RoundingMode::Up,
+        };
+        options.rates.insert("web".into(), 80.0);
+        options.currency = Some("EUR".into());
+        let sheet = build(
+            &sessions(),
+            &[project("web", "Website"), project("docs", "Docs")],
+            &options,
+        );
+
+        // 50 -> 60 and 25 -> 30 minutes; 61 -> 75; the break and the
+        // session after the range are left out
+        assert_eq!(sheet.tracked, (50 + 25 + 61) * 60);
+        assert_eq!(sheet.seconds, (60 + 30 + 75) * 60);
+        let web = &sheet.projects[0];
+        assert_eq!(
+            (web.name.as_str(), web.sessions, web.seconds),
+            ("Website", 2, 5400)
+        );
+        assert_eq!(web.amount, Some(120.0));
+        assert_eq!(sheet.projects[1].amount, None);
+        assert_eq!(sheet.amount, Some(120.0));
+
+        let days: Vec<_> = sheet
+            .days
+            .iter()
+            .map(|d| (d.date.to_string(), d.project.as_str(), d.seconds))
+            .collect();
+        assert_eq!(
+            days,
+            [
+                ("2025-01-13".to_string(), "Website", 5400),
+                ("2025-01-14".to_string(), "Docs", 4500)
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_projects() {
+        let mut options = options();
+        options.projects = vec!["docs".into()];
+        let sheet = build(&sessions(), &[project("docs", "Docs")], &options);
+        assert_eq!(sheet.projects.len(), 1);
+        assert_eq!(sheet.amount, None);
+    }
+
+    #[test]
+    fn bills_in_one_currency() {
+        let billable = |id: &str, rate: Option<f64>, currency: &str| Project {
+            billable: true,
+            hourly_rate: rate,
+            currency: Some(currency.into()),
+            ..project(id, id)
+        };
+        let mut projects = vec![
+            billable("web", Some(80.0), "EUR"),
+            billable("docs", None, "EUR"),
+            Project {
+                hourly_rate: Some(50.0),
+                currency: Some("USD".into()),
+                ..project("chores", "Chores")
+            },
+        ];
+        let mut options = options();
+        options.rates.insert("docs".into(), 60.0);
+        options.rates.insert("chores".into(), 50.0);
+
+        // Projects that are not billable show no amount, given a rate or not
+        let billed = billed_rates(&projects, &options).unwrap();
+        assert_eq!(billed.currency.as_deref(), Some("EUR"));
+        let mut rates: Vec<_> = billed.rates.into_iter().collect();
+        rates.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(rates, [("docs".into(), 60.0), ("web".into(), 80.0)]);
+
+        projects.push(billable("app", Some(90.0), "USD"));
+        let error = billed_rates(&projects, &options).unwrap_err();
+        assert!(error.to_string().contains("EUR and USD"));
+        options.projects = vec!["web".into(), "docs".into()];
+        assert!(billed_rates(&projects, &options).is_ok());
+        // Given rates are in the given currency, the projects' own are not
+        options.projects.clear();
+        options.currency = Some("usd".into());
+        assert!(billed_rates(&projects, &options).is_err());
+        options.rates.insert("web".into(), 85.0);
+        let billed = billed_rates(&projects, &options).unwrap();
+        assert_eq!(billed.currency.as_deref(), Some("USD"));
+        assert_eq!(billed.rates["app"], 90.0);
+    }
+
+    #[test]
+    fn renders_html() {
+        let mut options = options();
+        options.rates.insert("web".into(), 80.0);
+        options.title = Some("Acme <Ltd>".into());
+        options.rounding.minutes = 15;
+        let html = build(&sessions(), &[project("web", "Website")], &options).html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h1>Acme &lt;Ltd&gt;</h1>"));
+        assert!(html.contains("<td class=\"num\">120.00</td>"));
+        assert!(html.contains("<td>Mon 2025-01-13</td><td>Website</td>"));
+        assert!(html.contains("Each session rounded up to 15 minutes."));
+        assert!(!html.contains("<script"));
+    }
+
+    #[test]
+    fn renders_pdf_over_pages() {
+        let days: Vec<Session> = (0..60)
+            .map(|i| {
+                let date = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap() + chrono::Days::new(i);
+                day_session(
+                    &i.to_string(),
+                    "web",
+                    &date.to_string(),
+                    30,
+                    SessionKind::Work,
+                )
+            })
+            .collect();
+        let mut options = options();
+        options.from = "2025-01-01".parse().unwrap();
+        options.to = "2025-12-31".parse().unwrap();
+        let pdf = build(&days, &[project("web", "Website")], &options).pdf();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("/Count 2"));
+        // The daily table's header is repeated on the second page
+        assert_eq!(text.matches("(SESSIONS) Tj").count(), 3);
+    }
+
+    #[test]
+    fn fits_long_names() {
+        let name = "A very long project name that will not fit the column";
+        let cut = fit(name, Font::Regular, 100.0);
+        assert!(cut.ends_with('…') && Font::Regular.width(&cut, TEXT_SIZE) <= 100.0);
+        assert_eq!(fit("Short", Font::Regular, 100.0), "Short");
+    }
+}
@@ -0,0 +1,232 @@
+// Productivity Tracker - System tray
+// Shows the active project and elapsed time, and controls the timer
+// while the main window is hidden
+
+use std::sync::Mutex;
+
+use tauri::menu::{IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::commands::{self, StoreState};
+use crate::error::Result;
+use crate::pomodoro::{self, Phase};
+use crate::ticker;
+use crate::timer::{TimerStatus, BREAK_ID};
+
+const TRAY_ID: &str = "main";
+const START_PREFIX: &str = "start:";
+
+/// Items whose label or enabled state follow the timer
+struct TrayItems {
+    status: MenuItem<Wry>,
+    pause: MenuItem<Wry>,
+    resume: MenuItem<Wry>,
+    stop: MenuItem<Wry>,
+}
+
+type TrayState = Mutex<Option<TrayItems>>;
+
+pub fn init(app: &AppHandle) -> Result<()> {
+    app.manage::<TrayState>(Mutex::new(None));
+
+    let menu = build_menu(app)?;
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Productivity Tracker")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| handle_menu(app, event.id().as_ref()))
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+
+    let status = app
+        .state::<ticker::TimerState>()
+        .lock()
+        .unwrap()
+        .status(chrono::Utc::now())?;
+    update(app, status.as_ref());
+    Ok(())
+}
+
+/// Rebuilds the menu after the project list changed
+pub fn rebuild_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_menu(app) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => log::warn!("failed to rebuild tray menu: {e}"),
+    }
+}
+
+/// Reflects the timer status in the tray tooltip, title and menu
+pub fn update(app: &AppHandle, status: Option<&TimerStatus>) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    let label = match status {
+        Some(status) => {
+            let name = project_name(app, &status.project_id);
+            let time = format_time(status.remaining.unwrap_or(status.elapsed));
+            let paused = if status.paused { " (paused)" } else { "" };
+            format!("{name} - {time}{paused}")
+        }
+        None => "No active timer".to_string(),
+    };
+
+    let _ = tray.set_tooltip(Some(format!("Productivity Tracker\n{label}")));
+    #[cfg(target_os = "macos")]
+    let _ = tray.set_title(status.map(|s| format_time(s.remaining.unwrap_or(s.elapsed))));
+
+    let state = app.state::<TrayState>();
+    let items = state.lock().unwrap();
+    if let Some(items) = items.as_ref() {
+        let running = status.is_some_and(|s| !s.paused);
+        let paused = status.is_some_and(|s| s.paused);
+        let _ = items.status.set_text(&label);
+        let _ = items.pause.set_enabled(running);
+        let _ = items.resume.set_enabled(paused);
+        let _ = items.stop.set_enabled(status.is_some());
+    }
+}
+
+fn build_menu(app: &AppHandle) -> Result<Menu<Wry>> {
+    let status = MenuItem::with_id(app, "status", "No active timer", false, None::<&str>)?;
+
+    let mut projects = app.state::<StoreState>().lock().unwrap().projects()?;
+    projects.retain(|p| p.is_active());
+    let project_items = projects
+        .iter()
+        .map(|p| {
+            let id = format!("{START_PREFIX}{}", p.id);
+            MenuItem::with_id(app, id, &p.name, true, None::<&str>)
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let project_refs: Vec<&dyn IsMenuItem<Wry>> = project_items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<Wry>)
+        .collect();
+    let start =
+        Submenu::with_id_and_items(app, "start", "Start", !projects.is_empty(), &project_refs)?;
+
+    let pause = MenuItem::with_id(app, "pause", "Pause", false, None::<&str>)?;
+    let resume = MenuItem::with_id(app, "resume", "Resume", false, None::<&str>)?;
+    let stop = MenuItem::with_id(app, "stop", "Stop", false, None::<&str>)?;
+    let break_ = MenuItem::with_id(app, "break", "Break", true, None::<&str>)?;
+    let show = MenuItem::with_id(app, "show", "Show window", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+
+    let menu = Menu::with_items(
+        app,
+        &[
+            &status,
+            &PredefinedMenuItem::separator(app)?,
+            &start,
+            &pause,
+            &resume,
+            &stop,
+            &break_,
+            &PredefinedMenuItem::separator(app)?,
+            &show,
+            &quit,
+        ],
+    )?;
+
+    *app.state::<TrayState>().lock().unwrap() = Some(TrayItems {
+        status,
+        pause,
+        resume,
+        stop,
+    });
+    Ok(menu)
+}
+
+fn handle_menu(app: &AppHandle, id: &str) {
+    let result = match id {
+        "pause" => commands::pause_timer(app.clone(), app.state()).map(drop),
+        "resume" => commands::resume_timer(app.clone(), app.state()).map(drop),
+        "stop" => commands::stop_timer(app.clone(), app.state(), app.state(), None, None),
+        "break" => pomodoro_length(app, None).and_then(|duration| {
+            commands::start_break(app.clone(), app.state(), app.state(), duration).map(drop)
+        }),
+        "show" => {
+            show_main_window(app);
+            Ok(())
+        }
+        "quit" => {
+            app.exit(0);
+            Ok(())
+        }
+        _ => match id.strip_prefix(START_PREFIX) {
+            Some(project_id) => pomodoro_length(app, Some(Phase::Work)).and_then(|countdown| {
+                commands::start_timer(
+                    app.clone(),
+                    app.state(),
+                    app.state(),
+                    project_id.to_string(),
+                    Some(countdown),
+                )
+                .map(drop)
+            }),
+            None => Ok(()),
+        },
+    };
+    if let Err(e) = result {
+        ticker::report_error(app, &format!("tray action {id}"), &e);
+    }
+}
+
+/// Countdown for a timer started from the tray or a shortcut, in seconds:
+/// `phase`, or the break the pomodoro cycle is due
+pub fn pomodoro_length(app: &AppHandle, phase: Option<Phase>) -> Result<u64> {
+    let status = pomodoro::status(
+        &app.state::<StoreState>().lock().unwrap(),
+        chrono::Utc::now(),
+    )?;
+    let phase = phase.unwrap_or_else(|| status.cycle.due_break(&status.settings));
+    Ok(status.settings.length(phase))
+}
+
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+fn project_name(app: &AppHandle, project_id: &str) -> String {
+    if project_id == BREAK_ID {
+        return "Break".to_string();
+    }
+    let projects = app.state::<StoreState>().lock().unwrap().projects();
+    projects
+        .ok()
+        .and_then(|projects| projects.into_iter().find(|p| p.id == project_id))
+        .map(|p| p.name)
+        .unwrap_or_else(|| project_id.to_string())
+}
+
+fn format_time(seconds: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
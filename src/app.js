@@ -11,6 +11,9 @@
 // ============================================
 
 const API_URL = (typeof CONFIG !== 'undefined' && CONFIG.API_URL) || '';
+// An explicit SYNC_BACKEND wins; otherwise API_URL selects the Lambda API
+const SYNC_BACKEND = (typeof CONFIG !== 'undefined' && CONFIG.SYNC_BACKEND)
+  || (API_URL ? { kind: 'lambda', apiUrl: API_URL } : null);
 const DEBUG = (typeof CONFIG !== 'undefined' && CONFIG.DEBUG) || false;
 
 function log(...args) {
@@ -27,17 +30,49 @@ const state = {
   activeTimer: null,
   startTime: null,
   elapsedSeconds: 0,
-  timerInterval: null,
   isPaused: false,
-  pausedElapsed: 0,
+  // Countdown of the running timer (seconds), null in free mode
+  timerCountdown: null,
   // Pomodoro
   pomodoroMode: true,
   pomodoroDuration: 25 * 60,
   pomodoroRemaining: 0,
+  // Lengths and where the cycle is, as reported by Rust
+  pomodoro: null,
+  // Notification and sound settings, as saved in Rust
+  notifications: null,
+  // Global shortcuts, as registered by Rust
+  hotkeys: null,
   // AI Config
   aiProvider: 'anthropic',
   aiModel: '',
-  aiApiKey: '',
+  // Timezone and day start hour, replaced by the store's in loadData
+  calendar: {
+    timezone: Intl.DateTimeFormat().resolvedOptions().timeZone,
+    dayStartHour: 0
+  },
+  // Whether the provider's API key is saved (the key stays in Rust)
+  hasApiKey: false,
+  // Id of the AI reply being streamed, to stop it
+  aiRequestId: null,
+  // Project whose client and billing details are being edited
+  editingProjectId: null,
+  // Hides the undo bar of the last project moved to the trash
+  undoTimeout: null,
+  // Session being edited in the session form, null when adding one
+  editingSessionId: null,
+  // Day shown in the sessions log, null for today
+  sessionsDate: null,
+  // Sessions of sessionsDate when it is outside the loaded week
+  daySessions: [],
+  // Sessions matching the search box, best first
+  searchResults: [],
+  // Seconds and sessions of the last week per day and per project, as
+  // summed by Rust
+  totals: {
+    byDate: [],
+    byProject: []
+  },
   // Charts
   charts: {
     hourly: null,
@@ -46,6 +81,9 @@ const state = {
   }
 };
 
+// Must match TRASH_RETENTION_DAYS in model.rs
+const TRASH_RETENTION_DAYS = 30;
+
 // Default projects
 const DEFAULT_PROJECTS = [
   { id: 'proj1', name: 'Work Project', color: '#00ff88' },
@@ -57,57 +95,6 @@ const DEFAULT_PROJECTS = [
 // UTILITIES
 // ============================================
 
-function saveRecoveryState() {
-  if (!state.activeTimer) {
-    localStorage.removeItem('pt_recovery');
-    return;
-  }
-  
-  const recoveryData = {
-    activeTimer: state.activeTimer,
-    startTime: state.startTime,
-    isPaused: state.isPaused,
-    pausedElapsed: state.pausedElapsed,
-    pomodoroMode: state.pomodoroMode,
-    pomodoroDuration: state.pomodoroDuration,
-    lastSeen: Date.now() 
-  };
-  
-  localStorage.setItem('pt_recovery', JSON.stringify(recoveryData));
-}
-
-function checkRecoveryState() {
-  try {
-    const saved = localStorage.getItem('pt_recovery');
-    if (!saved) return;
-
-    const data = JSON.parse(saved);
-    if (Date.now() - data.lastSeen > 24 * 60 * 60 * 1000) {
-      localStorage.removeItem('pt_recovery');
-      return;
-    }
-
-    console.log('♻️ Recovering crashed session...');
-    state.activeTimer = data.activeTimer;
-    state.startTime = data.startTime;
-    state.isPaused = data.isPaused;
-    state.pausedElapsed = data.pausedElapsed;
-    state.pomodoroMode = data.pomodoroMode;
-    state.pomodoroDuration = data.pomodoroDuration;
-
-    if (state.isPaused) {
-      state.elapsedSeconds = state.pausedElapsed;
-      updateTimerDisplay();
-      renderProjects();
-    } else {
-      resumeTimer(); 
-    }
-  } catch (e) {
-    console.error('Recovery failed:', e);
-    localStorage.removeItem('pt_recovery');
-  }
-}
-
 const generateId = () => Math.random().toString(36).substr(2, 9);
 
 const formatTime = (seconds) => {
@@ -124,20 +111,39 @@ const formatTimeShort = (seconds) => {
   return `${m}m`;
 };
 
-const getToday = () => new Date().toISOString().split('T')[0];
+// Day a moment is filed under, as the Rust store does (see calendar.rs):
+// the date on the wall clock of the configured timezone, hours before the
+// day start counting for the day before
+const getDayOf = (time) => {
+  const parts = Object.fromEntries(new Intl.DateTimeFormat('en-CA', {
+    timeZone: state.calendar.timezone,
+    hourCycle: 'h23',
+    year: 'numeric',
+    month: '2-digit',
+    day: '2-digit',
+    hour: '2-digit'
+  }).formatToParts(new Date(time)).map(part => [part.type, part.value]));
+  // Wall-clock date and hour, shifted on a calendar without DST
+  const shifted = Date.UTC(parts.year, parts.month - 1, parts.day, parts.hour - state.calendar.dayStartHour);
+  return new Date(shifted).toISOString().split('T')[0];
+};
+
+const getToday = () => getDayOf(Date.now());
 
-const getWeekAgo = () => {
-  const d = new Date();
-  d.setDate(d.getDate() - 7);
+const getDaysAgo = (days) => {
+  const d = new Date(`${getToday()}T00:00:00Z`);
+  d.setUTCDate(d.getUTCDate() - days);
   return d.toISOString().split('T')[0];
 };
 
+const getWeekAgo = () => getDaysAgo(7);
+
 const formatHour = (date) => {
   return new Date(date).toLocaleTimeString('en-US', { hour: '2-digit', minute: '2-digit', hour12: false });
 };
 
 const formatDay = (dateStr) => {
-  return new Date(dateStr).toLocaleDateString('en-US', { weekday: 'short', day: 'numeric' });
+  return new Date(dateStr).toLocaleDateString('en-US', { weekday: 'short', day: 'numeric', timeZone: 'UTC' });
 };
 
 function escapeHtml(text) {
@@ -150,22 +156,58 @@ function escapeHtml(text) {
 // STORAGE
 // ============================================
 
+const { invoke } = window.__TAURI__.core;
+
+// Projects and sessions are persisted by the Rust backend
+// (projects.json and the history.db SQLite database in the app data directory)
 const Storage = {
-  async save(key, data) {
-    try {
-      localStorage.setItem(`pt_${key}`, JSON.stringify(data));
-    } catch (e) {
-      console.warn('Storage save error:', e);
-    }
-  },
+  listProjects: () => invoke('list_projects'),
+  addProject: (project) => invoke('add_project', { project }),
+  updateProject: (project) => invoke('update_project', { project }),
+  setProjectArchived: (id, archived) => invoke('set_project_archived', { id, archived }),
+  // Trashed projects keep their sessions until purged, by hand or after 30 days
+  trashProject: (id) => invoke('trash_project', { id }),
+  restoreProject: (id) => invoke('restore_project', { id }),
+  purgeProject: (id) => invoke('purge_project', { id }),
+
+  // query: { from, to, projectId, type } - all optional, dates inclusive
+  listSessions: (query = {}) => invoke('list_sessions', { query }),
+  sessionTotals: (groupBy, query = {}) => invoke('session_totals', { groupBy, query }),
+  addSession: (session) => invoke('add_session', { session }),
+  // entry: { projectId, startTime, endTime, type, notes, tags } with tags as
+  // typed, e.g. ["#client, review"]; Rust splits and checks them. Entries
+  // crossing the day start come back as one session per day
+  createSession: (entry) => invoke('create_session', { entry }),
+  editSession: (id, entry) => invoke('edit_session', { id, entry }),
+  splitSession: (id, at) => invoke('split_session', { id, at }),
+  // Sessions of the same project, type and day; the earliest one is kept
+  mergeSessions: (ids) => invoke('merge_sessions', { ids }),
+  deleteSession: (id) => invoke('delete_session', { id }),
+  // Audit trail of manual changes, newest first, of one session or all
+  sessionEdits: (sessionId = null, limit = null) => invoke('session_edits', { sessionId, limit }),
+  // Matches notes, tags (#tag) and project names; query as for listSessions
+  searchSessions: (text, query = {}) => invoke('search_sessions', { text, query }),
+
+  // One-off migration of data saved by older versions in localStorage
+  async migrateLegacy() {
+    const read = (key) => {
+      try {
+        return JSON.parse(localStorage.getItem(`pt_${key}`));
+      } catch (e) {
+        return null;
+      }
+    };
 
-  async load(key, defaultValue = null) {
-    try {
-      const data = localStorage.getItem(`pt_${key}`);
-      return data ? JSON.parse(data) : defaultValue;
-    } catch (e) {
-      return defaultValue;
+    const projects = read('projects') || DEFAULT_PROJECTS;
+    const sessions = read('sessions') || [];
+
+    const imported = await invoke('import_legacy_data', { projects, sessions });
+    if (imported) {
+      log('Migrated localStorage data:', projects.length, 'projects,', sessions.length, 'sessions');
     }
+    localStorage.removeItem('pt_projects');
+    localStorage.removeItem('pt_sessions');
+    localStorage.removeItem('pt_recovery');
   }
 };
 
@@ -174,279 +216,168 @@ const Storage = {
 // ============================================
 
 async function loadData() {
-  state.projects = await Storage.load('projects', DEFAULT_PROJECTS);
-  state.sessions = await Storage.load('sessions', []);
-  state.apiKey = await Storage.load('apiKey', '');
-  
-  if (state.apiKey) {
-    document.getElementById('apiKeyInput').value = '••••••••••••••••';
+  try {
+    await Storage.migrateLegacy();
+    state.calendar = await invoke('get_calendar');
+    state.projects = await Storage.listProjects();
+    // Only the window rendered by the dashboard is kept in memory
+    state.sessions = await Storage.listSessions({ from: getWeekAgo() });
+  } catch (e) {
+    console.error('Failed to load data:', e);
   }
 }
 
-async function saveProjects() {
-  localStorage.setItem('pt_projects', JSON.stringify(state.projects));
-  await Storage.save('projects', state.projects);
-  log('Projects saved, count:', state.projects.length);
-}
-
-async function saveSessions() {
-  localStorage.setItem('pt_sessions', JSON.stringify(state.sessions));
-  await Storage.save('sessions', state.sessions);
-  log('Sessions saved, count:', state.sessions.length);
-}
-
-async function saveApiKey(key) {
-  state.apiKey = key;
-  await Storage.save('apiKey', key);
-}
-
 // ============================================
 // TIMER FUNCTIONS
 // ============================================
 
-function startTimer(projectId) {
-  if (state.isPaused && state.activeTimer === projectId) {
-    resumeTimer();
-    return;
-  }
-  
-  if (state.activeTimer) {
-    stopTimer(false);
+// The timer runs in the Rust backend, which keeps counting and saves
+// finished sessions on its own. These functions send commands and mirror
+// the status it reports through events.
+
+function applyTimerStatus(status) {
+  if (status) {
+    state.activeTimer = status.projectId;
+    state.startTime = new Date(status.startedAt).getTime();
+    state.elapsedSeconds = status.elapsed;
+    state.isPaused = status.paused;
+    state.timerCountdown = status.countdown;
+    state.pomodoroRemaining = status.remaining || 0;
+  } else {
+    state.activeTimer = null;
+    state.startTime = null;
+    state.elapsedSeconds = 0;
+    state.isPaused = false;
+    state.timerCountdown = null;
+    state.pomodoroRemaining = 0;
   }
+}
 
-  state.activeTimer = projectId;
-  state.startTime = Date.now();
-  state.elapsedSeconds = 0;
-  state.isPaused = false;
-  state.pausedElapsed = 0;
-  
-  if (state.pomodoroMode && state.pomodoroDuration > 0) {
-    state.pomodoroRemaining = state.pomodoroDuration;
+async function timerCommand(command, args = {}) {
+  try {
+    applyTimerStatus(await invoke(command, args));
+  } catch (e) {
+    console.error(`${command} failed:`, e);
   }
 
-  state.timerInterval = setInterval(() => {
-    state.elapsedSeconds = Math.floor((Date.now() - state.startTime) / 1000);
-    
-    if (state.pomodoroMode && state.pomodoroDuration > 0) {
-      state.pomodoroRemaining = state.pomodoroDuration - state.elapsedSeconds;
-      
-      if (state.pomodoroRemaining <= 0) {
-        pomodoroFinished();
-        return;
-      }
-    }
-    
-    updateTimerDisplay();
-    if (state.elapsedSeconds % 2 === 0) saveRecoveryState(); 
-  }, 1000);
-
-  saveRecoveryState();
   updateTimerDisplay();
   renderProjects();
 }
 
+function startTimer(projectId) {
+  const countdown = state.pomodoroMode && state.pomodoroDuration > 0 ? state.pomodoroDuration : null;
+  return timerCommand('start_timer', { projectId, countdown });
+}
+
 function pauseTimer() {
   if (!state.activeTimer || state.isPaused) return;
-  
-  clearInterval(state.timerInterval);
-  state.isPaused = true;
-  state.pausedElapsed = state.elapsedSeconds;
-  
-  updateTimerDisplay();
-  renderProjects();
+  return timerCommand('pause_timer');
 }
 
 function resumeTimer() {
   if (!state.activeTimer || !state.isPaused) return;
-  
-  state.isPaused = false;
-  state.startTime = Date.now() - (state.pausedElapsed * 1000);
-  
-  state.timerInterval = setInterval(() => {
-    state.elapsedSeconds = Math.floor((Date.now() - state.startTime) / 1000);
-    
-    if (state.pomodoroMode && state.pomodoroDuration > 0) {
-      state.pomodoroRemaining = state.pomodoroDuration - state.elapsedSeconds;
-      if (state.pomodoroRemaining <= 0) {
-        pomodoroFinished();
-        return;
-      }
-    }
-    
-    updateTimerDisplay();
-    if (state.elapsedSeconds % 2 === 0) saveRecoveryState(); 
-  }, 1000);
-
-  saveRecoveryState();
-  updateTimerDisplay();
-  renderProjects();
+  return timerCommand('resume_timer');
 }
 
-function stopTimer(save = true) {
+async function stopTimer(save = true) {
   if (!state.activeTimer) return;
 
-  localStorage.removeItem('pt_recovery');
-  clearInterval(state.timerInterval);
-
   const minDuration = (typeof CONFIG !== 'undefined' && CONFIG.MIN_SESSION_DURATION) || 60;
 
-  if (save && state.elapsedSeconds >= minDuration) {
-    const isBreak = state.activeTimer === 'break';
-    const session = {
-      id: generateId(),
-      projectId: state.activeTimer,
-      startTime: new Date(state.startTime).toISOString(),
-      endTime: new Date().toISOString(),
-      duration: state.elapsedSeconds,
-      date: getToday(),
-      type: isBreak ? 'break' : 'work'
-    };
-    log('Saving session:', session);
-    state.sessions.push(session);
-    saveSessions();
+  try {
+    await invoke('stop_timer', { save, minDuration });
+  } catch (e) {
+    // The timer keeps running when its session could not be saved
+    console.error('stop_timer failed:', e);
+    showTimerError(`Saving the session failed: ${e}`);
+    return;
   }
 
-  state.activeTimer = null;
-  state.startTime = null;
-  state.elapsedSeconds = 0;
-  state.isPaused = false;
-  state.pausedElapsed = 0;
-  state.pomodoroRemaining = 0;
-
+  applyTimerStatus(null);
   updateTimerDisplay();
   updateStats();
   renderProjects();
   renderSessions();
 }
 
+// The selected work length stays for the pomodoro after the break
 function startBreak(duration) {
-  if (state.activeTimer) {
-    stopTimer(state.activeTimer !== 'break');
+  return timerCommand('start_break', { duration });
+}
+
+async function restoreTimer() {
+  try {
+    applyTimerStatus(await invoke('get_timer'));
+    if (state.activeTimer) log('Restored running timer:', state.activeTimer);
+  } catch (e) {
+    console.error('Timer restore failed:', e);
   }
+}
 
-  state.activeTimer = 'break';
-  state.startTime = Date.now();
-  state.elapsedSeconds = 0;
-  state.isPaused = false;
-  state.pausedElapsed = 0;
-  state.pomodoroMode = true;
-  state.pomodoroDuration = duration;
-  state.pomodoroRemaining = duration;
+async function setupTimerEvents() {
+  const { listen } = window.__TAURI__.event;
 
-  state.timerInterval = setInterval(() => {
-    state.elapsedSeconds = Math.floor((Date.now() - state.startTime) / 1000);
-    state.pomodoroRemaining = state.pomodoroDuration - state.elapsedSeconds;
-    
-    if (state.pomodoroRemaining <= 0) {
-      breakFinished();
-      return;
+  await listen('timer-tick', (e) => {
+    applyTimerStatus(e.payload);
+    updateTimerDisplay();
+  });
+
+  // Start/pause/stop from anywhere, including the tray menu and the `pt` CLI
+  await listen('timer-status', async (e) => {
+    applyTimerStatus(e.payload);
+    if (!e.payload) {
+      // The session may have been saved by another process
+      state.sessions = await Storage.listSessions({ from: getWeekAgo() });
+      updateStats();
+      renderSessions();
     }
-    
     updateTimerDisplay();
-  }, 1000);
+    renderProjects();
+  });
 
-  updateTimerDisplay();
-  renderProjects();
-}
+  await listen('timer-finished', (e) => countdownFinished(e.payload));
 
-function pomodoroFinished() {
-  clearInterval(state.timerInterval);
-  
-  const session = {
-    id: generateId(),
-    projectId: state.activeTimer,
-    startTime: new Date(state.startTime).toISOString(),
-    endTime: new Date().toISOString(),
-    duration: state.pomodoroDuration,
-    date: getToday(),
-    type: 'pomodoro'
-  };
-  state.sessions.push(session);
-  saveSessions();
-  
-  if ('Notification' in window && Notification.permission === 'granted') {
-    new Notification('🍅 Pomodoro completed!', {
-      body: `${state.pomodoroDuration / 60} minute session finished`,
-      icon: 'icons/icon.png'
-    });
-  }
-  
-  try {
-    const audio = new AudioContext();
-    const oscillator = audio.createOscillator();
-    const gain = audio.createGain();
-    oscillator.connect(gain);
-    gain.connect(audio.destination);
-    oscillator.frequency.value = 800;
-    gain.gain.value = 0.3;
-    oscillator.start();
-    setTimeout(() => oscillator.stop(), 200);
-  } catch (e) {}
-  
-  const display = document.getElementById('timerDisplay');
-  display.classList.add('finished');
-  
-  setTimeout(() => {
-    display.classList.remove('finished');
-    state.activeTimer = null;
-    state.startTime = null;
-    state.elapsedSeconds = 0;
-    state.pomodoroRemaining = 0;
-    updateTimerDisplay();
+  // The cycle moves on when a countdown runs out, here or in the `pt` CLI
+  await listen('pomodoro-cycle', (e) => applyPomodoroStatus(e.payload));
+
+  // Failures of the ticker, tray, shortcuts and notification buttons
+  await listen('timer-error', (e) => showTimerError(e.payload));
+
+  await listen('session-saved', (e) => {
+    const session = e.payload;
+    log('Session saved:', session);
+    hideTimerError();
+    if (!state.sessions.some(s => s.id === session.id)) {
+      state.sessions.push(session);
+    }
     updateStats();
     renderProjects();
     renderSessions();
-  }, 3000);
+  });
 }
 
-function breakFinished() {
-  clearInterval(state.timerInterval);
-  
-  const session = {
-    id: generateId(),
-    projectId: 'break',
-    startTime: new Date(state.startTime).toISOString(),
-    endTime: new Date().toISOString(),
-    duration: state.pomodoroDuration,
-    date: getToday(),
-    type: 'break'
-  };
-  state.sessions.push(session);
-  saveSessions();
-  log('Break saved:', session);
-  
-  if ('Notification' in window && Notification.permission === 'granted') {
-    new Notification('☕ Break finished!', {
-      body: 'Ready to get back to work?'
-    });
-  }
-  
-  try {
-    const audio = new AudioContext();
-    const oscillator = audio.createOscillator();
-    const gain = audio.createGain();
-    oscillator.connect(gain);
-    gain.connect(audio.destination);
-    oscillator.frequency.value = 600;
-    gain.gain.value = 0.3;
-    oscillator.start();
-    setTimeout(() => oscillator.stop(), 300);
-  } catch (e) {}
-  
+// Stays until dismissed or a session is saved, as the timer keeps the
+// session that failed to save
+function showTimerError(message) {
+  console.error('Timer error:', message);
+  document.getElementById('timerErrorMessage').textContent = message;
+  document.getElementById('timerErrorBar').classList.remove('hidden');
+}
+
+function hideTimerError() {
+  document.getElementById('timerErrorBar').classList.add('hidden');
+}
+
+// The notification and its sound come from Rust, also while the window is
+// hidden; the timer display only flashes
+function countdownFinished(session) {
+  log('Countdown finished:', session);
+
   const display = document.getElementById('timerDisplay');
   display.classList.add('finished');
-  
+
   setTimeout(() => {
     display.classList.remove('finished');
-    state.activeTimer = null;
-    state.startTime = null;
-    state.elapsedSeconds = 0;
-    state.pomodoroRemaining = 0;
-    
-    state.pomodoroDuration = 25 * 60;
-    document.querySelectorAll('.pomo-btn').forEach(b => b.classList.remove('active'));
-    document.querySelector('.pomo-btn[data-duration="1500"]').classList.add('active');
-    
     updateTimerDisplay();
     updateStats();
     renderProjects();
@@ -459,7 +390,7 @@ function updateTimerDisplay() {
   const projectLabel = document.getElementById('timerProject');
   const stopBtn = document.getElementById('stopBtn');
 
-  if (state.pomodoroMode && state.pomodoroDuration > 0 && state.activeTimer) {
+  if (state.activeTimer && state.timerCountdown) {
     display.textContent = formatTime(Math.max(0, state.pomodoroRemaining));
     display.classList.add('countdown');
   } else {
@@ -519,15 +450,29 @@ function getMinutesInHour(session, hourStart, hourEnd) {
   return Math.min(60, Math.round((overlapEnd - overlapStart) / 60000));
 }
 
+// Refreshes state.totals; the stats below read them
+async function loadTotals() {
+  const query = { from: getWeekAgo() };
+  try {
+    const [byDate, byProject] = await Promise.all([
+      Storage.sessionTotals('date', query),
+      Storage.sessionTotals('project', query)
+    ]);
+    state.totals = { byDate, byProject };
+  } catch (e) {
+    console.error('Failed to load totals:', e);
+  }
+}
+
 function getStats() {
   const today = getToday();
-  const weekAgo = getWeekAgo();
 
   const todaySessions = state.sessions.filter(s => s.date === today);
-  const weekSessions = state.sessions.filter(s => s.date >= weekAgo);
+  const dayTotal = (date) => state.totals.byDate.find(t => t.key === date) || { seconds: 0, count: 0 };
 
-  const todayTotal = todaySessions.reduce((acc, s) => acc + s.duration, 0);
-  const weekTotal = weekSessions.reduce((acc, s) => acc + s.duration, 0);
+  const todayTotal = dayTotal(today).seconds;
+  const todayCount = dayTotal(today).count;
+  const weekTotal = state.totals.byDate.reduce((acc, t) => acc + t.seconds, 0);
 
   // Hourly data (last 8 hours) - FIXED CALCULATION
   const hourlyData = [];
@@ -553,13 +498,8 @@ function getStats() {
 
   const dailyData = [];
   for (let i = 6; i >= 0; i--) {
-    const date = new Date(Date.now() - i * 24 * 60 * 60 * 1000);
-    const dateStr = date.toISOString().split('T')[0];
-    const hours = Math.round(
-      state.sessions
-        .filter(s => s.date === dateStr)
-        .reduce((acc, s) => acc + s.duration, 0) / 3600 * 10
-    ) / 10;
+    const dateStr = getDaysAgo(i);
+    const hours = Math.round(dayTotal(dateStr).seconds / 3600 * 10) / 10;
 
     dailyData.push({
       label: formatDay(dateStr),
@@ -568,9 +508,8 @@ function getStats() {
   }
 
   const projectData = state.projects.map(p => {
-    const totalSeconds = weekSessions
-      .filter(s => s.projectId === p.id)
-      .reduce((acc, s) => acc + s.duration, 0);
+    const total = state.totals.byProject.find(t => t.key === p.id);
+    const totalSeconds = total ? total.seconds : 0;
     
     const value = totalSeconds >= 3600 
       ? Math.round(totalSeconds / 3600 * 10) / 10
@@ -587,41 +526,108 @@ function getStats() {
     };
   }).filter(p => p.seconds > 0);
 
-  return { todayTotal, weekTotal, todaySessions, weekSessions, hourlyData, dailyData, projectData };
+  return { todayTotal, todayCount, weekTotal, todaySessions, hourlyData, dailyData, projectData };
 }
 
-function updateStats() {
+// The totals come from Rust, so the charts are redrawn once they are in
+async function updateStats() {
+  await loadTotals();
   const stats = getStats();
 
   document.getElementById('todayTotal').textContent = formatTime(stats.todayTotal);
   document.getElementById('weekTotal').textContent = formatTime(stats.weekTotal);
-  document.getElementById('sessionCount').textContent = stats.todaySessions.length;
-  document.getElementById('sessionsBadge').textContent = stats.todaySessions.length;
+  document.getElementById('sessionCount').textContent = stats.todayCount;
+  document.getElementById('sessionsBadge').textContent = stats.todayCount;
+  updateCharts();
 }
 
 // ============================================
 // PROJECT MANAGEMENT
 // ============================================
 
-function addProject(name, color) {
+async function addProject(name, color) {
   const project = {
     id: generateId(),
     name: name.trim(),
     color: color
   };
+
+  try {
+    await Storage.addProject(project);
+  } catch (e) {
+    console.error('Add project error:', e);
+    return;
+  }
+
   state.projects.push(project);
-  saveProjects();
   renderProjects();
 }
 
-function deleteProject(id) {
+// Replaces the project with the copy Rust saved
+function projectChanged(project) {
+  state.projects = state.projects.map(p => p.id === project.id ? project : p);
+  renderProjects();
+}
+
+async function archiveProject(id, archived) {
+  if (archived && state.activeTimer === id) {
+    stopTimer();
+  }
+  try {
+    projectChanged(await Storage.setProjectArchived(id, archived));
+  } catch (e) {
+    console.error('Archive project error:', e);
+  }
+}
+
+// Moves the project to the trash; its sessions stay until it is purged
+async function deleteProject(id) {
   if (state.activeTimer === id) {
-    stopTimer(false);
+    stopTimer();
+  }
+
+  let project;
+  try {
+    project = await Storage.trashProject(id);
+  } catch (e) {
+    console.error('Delete project error:', e);
+    return;
+  }
+  projectChanged(project);
+
+  document.getElementById('undoMessage').textContent = `${project.name} moved to the trash`;
+  document.getElementById('undoTrash').dataset.projectId = id;
+  document.getElementById('undoBar').classList.remove('hidden');
+  clearTimeout(state.undoTimeout);
+  state.undoTimeout = setTimeout(hideUndo, 10000);
+}
+
+function hideUndo() {
+  document.getElementById('undoBar').classList.add('hidden');
+}
+
+async function restoreProject(id) {
+  hideUndo();
+  try {
+    projectChanged(await Storage.restoreProject(id));
+  } catch (e) {
+    console.error('Restore project error:', e);
+  }
+}
+
+async function purgeProject(id) {
+  const project = state.projects.find(p => p.id === id);
+  if (!project || !confirm(`Delete ${project.name} and all of its sessions? This can't be undone.`)) {
+    return;
+  }
+  try {
+    await Storage.purgeProject(id);
+  } catch (e) {
+    console.error('Purge project error:', e);
+    return;
   }
   state.projects = state.projects.filter(p => p.id !== id);
   state.sessions = state.sessions.filter(s => s.projectId !== id);
-  saveProjects();
-  saveSessions();
   renderProjects();
   updateStats();
 }
@@ -634,18 +640,18 @@ function renderProjects() {
   const container = document.getElementById('projectsList');
   const stats = getStats();
 
-  container.innerHTML = state.projects.map(project => {
+  container.innerHTML = state.projects.filter(p => !p.trashedAt).map(project => {
     const projectTime = stats.todaySessions
       .filter(s => s.projectId === project.id)
       .reduce((acc, s) => acc + s.duration, 0);
     const isActive = state.activeTimer === project.id;
 
     return `
-      <div class="project-item ${isActive ? 'active' : ''}" style="color: ${project.color}" data-project-id="${project.id}">
+      <div class="project-item ${isActive ? 'active' : ''} ${project.archived ? 'archived' : ''}" style="color: ${project.color}" data-project-id="${project.id}">
         <div class="project-color" style="background: ${project.color}"></div>
         <div class="project-info">
           <div class="project-name">${escapeHtml(project.name)}</div>
-          <div class="project-time">Today: ${formatTime(projectTime)}</div>
+          <div class="project-time">Today: ${formatTime(projectTime)}${renderProjectDetails(project)}</div>
         </div>
         <div class="project-actions">
           ${isActive ? `
@@ -670,6 +676,10 @@ function renderProjects() {
               </svg>
               Stop
             </button>
+          ` : project.archived ? `
+            <button class="project-btn start" data-action="unarchive" data-project-id="${project.id}" style="color: ${project.color}">
+              Restore
+            </button>
           ` : `
             <button class="project-btn start" data-action="start" data-project-id="${project.id}" style="color: ${project.color}">
               <svg width="14" height="14" viewBox="0 0 24 24" fill="currentColor">
@@ -678,6 +688,18 @@ function renderProjects() {
               Start
             </button>
           `}
+          <button class="project-btn edit" data-action="edit" data-project-id="${project.id}" title="Client and billing">
+            <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+              <path d="M12 20h9"/><path d="M16.5 3.5a2.12 2.12 0 0 1 3 3L7 19l-4 1 1-4z"/>
+            </svg>
+          </button>
+          ${project.archived ? '' : `
+            <button class="project-btn edit" data-action="archive" data-project-id="${project.id}" title="Archive">
+              <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+                <polyline points="21 8 21 21 3 21 3 8"/><rect x="1" y="3" width="22" height="5"/><line x1="10" y1="12" x2="14" y2="12"/>
+              </svg>
+            </button>
+          `}
           <button class="project-btn delete" data-action="delete" data-project-id="${project.id}">
             <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
               <polyline points="3 6 5 6 21 6"/><path d="M19 6v14a2 2 0 0 1-2 2H7a2 2 0 0 1-2-2V6m3 0V4a2 2 0 0 1 2-2h4a2 2 0 0 1 2 2v2"/>
@@ -691,6 +713,89 @@ function renderProjects() {
   container.querySelectorAll('button[data-action]').forEach(btn => {
     btn.addEventListener('click', handleProjectAction);
   });
+  renderTrash();
+  renderExportProjects();
+  renderTimesheetProjects();
+  renderBillingClients();
+}
+
+function renderProjectDetails(project) {
+  const details = [];
+  if (project.client) details.push(escapeHtml(project.client));
+  if (project.hourlyRate != null) details.push(`${formatMoney(project.hourlyRate, project.currency)}/h`);
+  if (project.archived) details.push('archived');
+  return details.length ? ` · ${details.join(' · ')}` : '';
+}
+
+function editProjectDetails(id) {
+  const project = state.projects.find(p => p.id === id);
+  if (!project) return;
+  state.editingProjectId = id;
+  document.getElementById('projectDetailsName').textContent = project.name;
+  document.getElementById('projectClient').value = project.client || '';
+  document.getElementById('projectRate').value = project.hourlyRate ?? '';
+  document.getElementById('projectCurrency').value = project.currency || '';
+  document.getElementById('projectBillable').checked = !!project.billable;
+  document.getElementById('projectDetailsError').textContent = '';
+  document.getElementById('projectDetailsForm').classList.remove('hidden');
+}
+
+// Rust validates the details; its message is shown when they are rejected
+async function saveProjectDetails() {
+  const project = state.projects.find(p => p.id === state.editingProjectId);
+  if (!project) return;
+  const value = (id) => document.getElementById(id).value.trim();
+  const rate = value('projectRate');
+  const updated = {
+    ...project,
+    client: value('projectClient') || null,
+    hourlyRate: rate === '' ? null : parseFloat(rate),
+    currency: value('projectCurrency').toUpperCase() || null,
+    billable: document.getElementById('projectBillable').checked
+  };
+
+  try {
+    await Storage.updateProject(updated);
+  } catch (e) {
+    document.getElementById('projectDetailsError').textContent = e;
+    return;
+  }
+  Object.assign(project, updated);
+  closeProjectDetails();
+  renderProjects();
+}
+
+function closeProjectDetails() {
+  state.editingProjectId = null;
+  document.getElementById('projectDetailsForm').classList.add('hidden');
+}
+
+function renderTrash() {
+  const trashed = state.projects.filter(p => p.trashedAt);
+  document.getElementById('trashSection').classList.toggle('hidden', trashed.length === 0);
+  document.getElementById('trashBadge').textContent = trashed.length;
+
+  const list = document.getElementById('trashList');
+  list.innerHTML = trashed.map(project => {
+    const purgeAt = new Date(project.trashedAt).getTime() + TRASH_RETENTION_DAYS * 86400000;
+    const days = Math.max(0, Math.ceil((purgeAt - Date.now()) / 86400000));
+    return `
+      <div class="trash-item">
+        <div class="project-color" style="background: ${project.color}"></div>
+        <div class="project-info">
+          <div class="project-name">${escapeHtml(project.name)}</div>
+          <div class="project-time">Deleted with its sessions in ${days} days</div>
+        </div>
+        <div class="project-actions">
+          <button class="project-btn start" data-action="restore" data-project-id="${project.id}" style="color: ${project.color}">Restore</button>
+          <button class="project-btn delete" data-action="purge" data-project-id="${project.id}">Delete forever</button>
+        </div>
+      </div>
+    `;
+  }).join('');
+  list.querySelectorAll('button[data-action]').forEach(btn => {
+    btn.addEventListener('click', handleProjectAction);
+  });
 }
 
 function handleProjectAction(e) {
@@ -711,6 +816,21 @@ function handleProjectAction(e) {
     case 'stop':
       stopTimer();
       break;
+    case 'edit':
+      editProjectDetails(projectId);
+      break;
+    case 'archive':
+      archiveProject(projectId, true);
+      break;
+    case 'unarchive':
+      archiveProject(projectId, false);
+      break;
+    case 'restore':
+      restoreProject(projectId);
+      break;
+    case 'purge':
+      purgeProject(projectId);
+      break;
     case 'delete':
       deleteProject(projectId);
       break;
@@ -719,54 +839,346 @@ function handleProjectAction(e) {
 
 function renderSessions() {
   const container = document.getElementById('sessionsList');
-  const todaySessions = state.sessions
-    .filter(s => s.date === getToday())
+  const date = state.sessionsDate || getToday();
+  const daySessions = (state.sessionsDate ? state.daySessions : state.sessions)
+    .filter(s => s.date === date)
     .sort((a, b) => new Date(b.startTime) - new Date(a.startTime));
 
-  if (todaySessions.length === 0) {
-    container.innerHTML = '<div class="no-sessions">No sessions today</div>';
+  if (daySessions.length === 0) {
+    container.innerHTML = `<div class="no-sessions">No sessions ${state.sessionsDate ? 'that day' : 'today'}</div>`;
     return;
   }
 
-  container.innerHTML = todaySessions.map(session => {
-    const isBreak = session.projectId === 'break' || session.type === 'break';
-    
-    if (isBreak) {
-      return `
-        <div class="session-item">
-          <div class="session-color" style="background: var(--accent-cyan)"></div>
-          <div class="session-info">
-            <div class="session-project">☕ Break</div>
-            <div class="session-time">${formatHour(session.startTime)} - ${formatHour(session.endTime)}</div>
-          </div>
-          <div class="session-duration" style="color: var(--accent-cyan)">${formatTimeShort(session.duration)}</div>
-        </div>
-      `;
-    }
-    
-    const project = state.projects.find(p => p.id === session.projectId);
-    if (!project) return '';
+  container.innerHTML = daySessions.map(session => renderSessionItem(session)).join('');
+  bindSessionEdits(container);
+}
 
+function renderSessionItem(session, showDate = false) {
+  const isBreak = session.projectId === 'break' || session.type === 'break';
+  const when = `${showDate ? session.date + ' ' : ''}${formatHour(session.startTime)} - ${formatHour(session.endTime)}`;
+  const editButton = `
+      <button class="project-btn edit" data-session-id="${session.id}" title="Edit session">
+        <svg width="12" height="12" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
+          <path d="M12 20h9"/><path d="M16.5 3.5a2.12 2.12 0 0 1 3 3L7 19l-4 1 1-4z"/>
+        </svg>
+      </button>`;
+
+  if (isBreak) {
     return `
       <div class="session-item">
-        <div class="session-color" style="background: ${project.color}"></div>
+        <div class="session-color" style="background: var(--accent-cyan)"></div>
         <div class="session-info">
-          <div class="session-project">${escapeHtml(project.name)}</div>
-          <div class="session-time">${formatHour(session.startTime)} - ${formatHour(session.endTime)}</div>
+          <div class="session-project">☕ Break</div>
+          <div class="session-time">${when}</div>
         </div>
-        <div class="session-duration">${formatTimeShort(session.duration)}</div>
+        ${editButton}
+        <div class="session-duration" style="color: var(--accent-cyan)">${formatTimeShort(session.duration)}</div>
       </div>
     `;
-  }).join('');
+  }
+
+  const project = state.projects.find(p => p.id === session.projectId);
+  if (!project) return '';
+  const tags = (session.tags || []).map(tag => `<span class="session-tag">#${escapeHtml(tag)}</span>`).join('');
+
+  return `
+    <div class="session-item">
+      <div class="session-color" style="background: ${project.color}"></div>
+      <div class="session-info">
+        <div class="session-project">${escapeHtml(project.name)}${tags}</div>
+        <div class="session-time">${when}</div>
+        ${session.notes ? `<div class="session-notes">${escapeHtml(session.notes)}</div>` : ''}
+      </div>
+      ${editButton}
+      <div class="session-duration">${formatTimeShort(session.duration)}</div>
+    </div>
+  `;
+}
+
+function bindSessionEdits(container) {
+  container.querySelectorAll('button[data-session-id]').forEach(btn => {
+    btn.addEventListener('click', () => editSession(btn.dataset.sessionId));
+  });
 }
 
 // ============================================
-// CHARTS
+// SESSION EDITING & SEARCH
 // ============================================
 
-function initCharts() {
-  const stats = getStats();
-
+async function showSessionsOf(date) {
+  state.sessionsDate = date && date !== getToday() ? date : null;
+  if (state.sessionsDate) {
+    try {
+      state.daySessions = await Storage.listSessions({ from: date, to: date });
+    } catch (e) {
+      console.error('Failed to load sessions:', e);
+      state.daySessions = [];
+    }
+  }
+  renderSessions();
+}
+
+function findSession(id) {
+  return [state.sessions, state.daySessions, state.searchResults]
+    .map(list => list.find(s => s.id === id))
+    .find(Boolean);
+}
+
+// Date and HH:MM of a moment in the calendar's timezone, for the form
+function toCalendarTime(time) {
+  const timeZone = state.calendar.timezone;
+  const date = new Date(time);
+  return {
+    date: date.toLocaleDateString('en-CA', { timeZone }),
+    time: date.toLocaleTimeString('en-GB', { timeZone, hour: '2-digit', minute: '2-digit', hourCycle: 'h23' })
+  };
+}
+
+// Moment a date and HH:MM in the calendar's timezone stand for
+function fromCalendarTime(date, time) {
+  const wall = new Date(`${date}T${time}:00Z`).getTime();
+  const offsetAt = (moment) => {
+    const local = toCalendarTime(moment);
+    return new Date(`${local.date}T${local.time}:00Z`).getTime() - moment;
+  };
+  // The offset at the guess can differ from the one at the result
+  // around DST changes, so it is looked up twice
+  let moment = wall - offsetAt(wall);
+  moment = wall - offsetAt(moment);
+  return new Date(moment);
+}
+
+function renderSessionProjectOptions(selected) {
+  const select = document.getElementById('sessionProject');
+  const projects = state.projects.filter(p => (!p.archived && !p.trashedAt) || p.id === selected);
+  select.innerHTML = projects
+    .map(p => `<option value="${p.id}"${p.id === selected ? ' selected' : ''}>${escapeHtml(p.name)}</option>`)
+    .join('');
+}
+
+function openSessionForm(session) {
+  state.editingSessionId = session ? session.id : null;
+  const type = session ? session.type : 'work';
+  renderSessionProjectOptions(session && type !== 'break' ? session.projectId : state.activeTimer);
+
+  let start, end;
+  if (session) {
+    start = toCalendarTime(session.startTime);
+    end = toCalendarTime(session.endTime);
+  } else {
+    // An hour up to now, or a working hour of the day shown
+    const now = Date.now();
+    start = toCalendarTime(now - 3600000);
+    end = toCalendarTime(now);
+    if (state.sessionsDate) {
+      start = { date: state.sessionsDate, time: '09:00' };
+      end = { date: state.sessionsDate, time: '10:00' };
+    }
+  }
+
+  document.getElementById('sessionFormName').textContent = session
+    ? `${session.type === 'break' ? 'Break' : (state.projects.find(p => p.id === session.projectId) || {}).name || session.projectId} · ${session.date}`
+    : 'New session';
+  document.getElementById('sessionType').value = type;
+  document.getElementById('sessionDay').value = start.date;
+  document.getElementById('sessionStart').value = start.time;
+  document.getElementById('sessionEnd').value = end.time;
+  document.getElementById('sessionNotes').value = session ? session.notes || '' : '';
+  document.getElementById('sessionTags').value = session ? (session.tags || []).join(' ') : '';
+  document.getElementById('sessionFormError').textContent = '';
+  document.getElementById('sessionFormHistory').innerHTML = '';
+  document.querySelectorAll('.session-edit-only').forEach(el => el.classList.toggle('hidden', !session));
+  updateSessionProjectField();
+  document.getElementById('sessionForm').classList.remove('hidden');
+
+  if (session) renderSessionHistory(session.id);
+}
+
+function editSession(id) {
+  const session = findSession(id);
+  if (session) openSessionForm(session);
+}
+
+function updateSessionProjectField() {
+  const isBreak = document.getElementById('sessionType').value === 'break';
+  document.getElementById('sessionProject').disabled = isBreak;
+}
+
+// Start and end of the form; an end before the start is on the next day
+function sessionFormSpan() {
+  const day = document.getElementById('sessionDay').value;
+  const start = document.getElementById('sessionStart').value;
+  const end = document.getElementById('sessionEnd').value;
+  if (!day || !start || !end) throw 'Enter a day, start and end';
+  const startTime = fromCalendarTime(day, start);
+  let endTime = fromCalendarTime(day, end);
+  if (endTime <= startTime) {
+    const next = new Date(`${day}T00:00:00Z`);
+    next.setUTCDate(next.getUTCDate() + 1);
+    endTime = fromCalendarTime(next.toISOString().split('T')[0], end);
+  }
+  return { startTime, endTime };
+}
+
+async function saveSession() {
+  const error = document.getElementById('sessionFormError');
+  const type = document.getElementById('sessionType').value;
+  try {
+    const { startTime, endTime } = sessionFormSpan();
+    const entry = {
+      projectId: type === 'break' ? 'break' : document.getElementById('sessionProject').value,
+      startTime: startTime.toISOString(),
+      endTime: endTime.toISOString(),
+      type,
+      notes: document.getElementById('sessionNotes').value,
+      tags: [document.getElementById('sessionTags').value]
+    };
+    if (state.editingSessionId) {
+      await Storage.editSession(state.editingSessionId, entry);
+    } else {
+      await Storage.createSession(entry);
+    }
+  } catch (e) {
+    error.textContent = e;
+    return;
+  }
+  closeSessionForm();
+  await reloadSessions();
+}
+
+// Splits the session being edited at the form's start time
+async function splitEditedSession() {
+  const session = findSession(state.editingSessionId);
+  if (!session) return;
+  const at = window.prompt('Split at (HH:MM)', toCalendarTime(
+    (new Date(session.startTime).getTime() + new Date(session.endTime).getTime()) / 2
+  ).time);
+  if (!at) return;
+  try {
+    let moment = fromCalendarTime(toCalendarTime(session.startTime).date, at);
+    if (moment <= new Date(session.startTime)) {
+      moment = new Date(moment.getTime() + 86400000);
+    }
+    await Storage.splitSession(session.id, moment.toISOString());
+  } catch (e) {
+    document.getElementById('sessionFormError').textContent = e;
+    return;
+  }
+  closeSessionForm();
+  await reloadSessions();
+}
+
+// Merges the session being edited with the one before it of the same
+// project, type and day
+async function mergeWithPrevious() {
+  const session = findSession(state.editingSessionId);
+  if (!session) return;
+  const error = document.getElementById('sessionFormError');
+  const previous = [state.sessions, state.daySessions]
+    .flat()
+    .filter(s => s.id !== session.id && s.date === session.date && s.projectId === session.projectId &&
+      s.type === session.type && new Date(s.startTime) < new Date(session.startTime))
+    .sort((a, b) => new Date(b.startTime) - new Date(a.startTime))[0];
+  if (!previous) {
+    error.textContent = 'No earlier session of this project that day';
+    return;
+  }
+  try {
+    await Storage.mergeSessions([previous.id, session.id]);
+  } catch (e) {
+    error.textContent = e;
+    return;
+  }
+  closeSessionForm();
+  await reloadSessions();
+}
+
+async function deleteEditedSession() {
+  const id = state.editingSessionId;
+  if (!id || !confirm('Delete this session?')) return;
+  try {
+    await Storage.deleteSession(id);
+  } catch (e) {
+    document.getElementById('sessionFormError').textContent = e;
+    return;
+  }
+  closeSessionForm();
+  await reloadSessions();
+}
+
+async function renderSessionHistory(id) {
+  let edits;
+  try {
+    edits = await Storage.sessionEdits(id, 10);
+  } catch (e) {
+    console.error('Failed to load session history:', e);
+    return;
+  }
+  if (state.editingSessionId !== id) return;
+  const describe = (s) => s ? `${formatHour(s.startTime)} - ${formatHour(s.endTime)}` : '';
+  document.getElementById('sessionFormHistory').innerHTML = edits.map(edit => `
+    <div class="session-edit">
+      <span>${new Date(edit.at).toLocaleString()}</span>
+      <span>${edit.action}</span>
+      <span>${[describe(edit.before), describe(edit.after)].filter(Boolean).join(' → ')}</span>
+    </div>
+  `).join('');
+}
+
+function closeSessionForm() {
+  state.editingSessionId = null;
+  document.getElementById('sessionForm').classList.add('hidden');
+}
+
+// Manual changes can touch any day, so everything shown is reloaded
+async function reloadSessions() {
+  try {
+    state.sessions = await Storage.listSessions({ from: getWeekAgo() });
+  } catch (e) {
+    console.error('Failed to reload sessions:', e);
+  }
+  await showSessionsOf(state.sessionsDate);
+  if (document.getElementById('sessionSearch').value.trim()) {
+    await searchSessions();
+  }
+  renderProjects();
+  await updateStats();
+}
+
+async function searchSessions() {
+  const text = document.getElementById('sessionSearch').value.trim();
+  if (!text) {
+    state.searchResults = [];
+    document.getElementById('searchResults').classList.add('hidden');
+    return;
+  }
+  try {
+    state.searchResults = await Storage.searchSessions(text);
+  } catch (e) {
+    console.error('Search failed:', e);
+    state.searchResults = [];
+  }
+  document.getElementById('searchResults').classList.remove('hidden');
+  renderSearchResults();
+}
+
+function renderSearchResults() {
+  const container = document.getElementById('searchResults');
+  if (state.searchResults.length === 0) {
+    container.innerHTML = '<div class="no-sessions">No matching sessions</div>';
+    return;
+  }
+  container.innerHTML = state.searchResults.map(session => renderSessionItem(session, true)).join('');
+  bindSessionEdits(container);
+}
+
+// ============================================
+// CHARTS
+// ============================================
+
+function initCharts() {
+  const stats = getStats();
+
   const hourlyCtx = document.getElementById('hourlyChart').getContext('2d');
   state.charts.hourly = new Chart(hourlyCtx, {
     type: 'bar',
@@ -910,184 +1322,764 @@ function renderProjectsLegend(data) {
 // AWS SYNC
 // ============================================
 
+// Uploads run in the Rust sync worker: changed days are queued locally and
+// retried with backoff until they reach the sync backend, even after a restart.
+
+function setSyncButton(label, className = null) {
+  const syncBtn = document.getElementById('syncBtn');
+  syncBtn.classList.remove('syncing', 'synced');
+  if (className) syncBtn.classList.add(className);
+  syncBtn.querySelector('span').textContent = label;
+}
+
 async function syncToAWS() {
-  if (!API_URL) {
-    console.error('API_URL not configured in config.js');
+  if (!SYNC_BACKEND) {
+    console.error('No sync backend configured in config.js (API_URL or SYNC_BACKEND)');
     return;
   }
 
-  const syncBtn = document.getElementById('syncBtn');
-  syncBtn.classList.add('syncing');
-  syncBtn.querySelector('span').textContent = 'Sync...';
+  setSyncButton('Sync...', 'syncing');
+  try {
+    await invoke('sync_now');
+  } catch (error) {
+    console.error('Sync error:', error);
+    setSyncButton('Error!');
+  }
+}
 
-  const payload = {
-    date: getToday(),
-    projects: state.projects,
-    sessions: state.sessions.filter(s => s.date === getToday())
-  };
+async function updateSyncTooltip() {
+  const pending = await invoke('sync_status');
+  const failed = pending.filter(d => d.state === 'failed');
+  document.getElementById('syncBtn').title = failed.length
+    ? failed.map(d => `${d.date}: ${d.error} (retry ${new Date(d.nextAttempt).toLocaleTimeString()})`).join('\n')
+    : pending.length ? `${pending.length} day(s) waiting to sync` : 'All days synced';
+}
 
+// The sync worker merges changes from other devices into the store;
+// reload what the dashboard shows when it did
+async function reloadSyncedData() {
   try {
-    log('Syncing to AWS:', payload);
-    
-    const response = await fetch(`${API_URL}/sync`, {
-      method: 'POST',
-      headers: { 'Content-Type': 'application/json' },
-      body: JSON.stringify(payload)
-    });
+    state.projects = await Storage.listProjects();
+    state.sessions = await Storage.listSessions({ from: getWeekAgo() });
+  } catch (e) {
+    console.error('Failed to reload synced data:', e);
+    return;
+  }
+  renderProjects();
+  renderSessions();
+  updateStats();
+}
+
+async function setupAutoSync() {
+  const { listen } = window.__TAURI__.event;
 
-    const result = await response.json();
-    log('Sync result:', result);
+  await listen('sync-status', (e) => {
+    log('Sync status:', e.payload);
+    if (e.payload.state === 'syncing') setSyncButton('Sync...', 'syncing');
+  });
 
-    if (result.success) {
-      syncBtn.classList.remove('syncing');
-      syncBtn.classList.add('synced');
-      syncBtn.querySelector('span').textContent = 'Synced!';
+  await listen('sync-finished', async (e) => {
+    const summary = e.payload;
+    log('Sync result:', summary);
+    if (summary.updated) await reloadSyncedData();
+    // e.g. encryption turned on by another device
+    if (summary.error) await refreshEncryptionStatus();
+    const syncBtn = document.getElementById('syncBtn');
+
+    if (summary.error || summary.failed > 0) {
+      console.error('Sync error:', summary.error || `${summary.failed} day(s) failed`);
+      setSyncButton('Error!');
+    } else if (summary.synced > 0 || syncBtn.classList.contains('syncing')) {
+      setSyncButton('Synced!', 'synced');
     } else {
-      throw new Error(result.error || 'Sync failed');
+      return;
     }
+    await updateSyncTooltip();
+    setTimeout(() => setSyncButton('Sync'), 3000);
+  });
+
+  if (!SYNC_BACKEND) return;
+
+  const interval = (typeof CONFIG !== 'undefined' && CONFIG.AUTO_SYNC_INTERVAL) || 0;
+  log('Sync backend:', SYNC_BACKEND.kind, '- auto-sync interval:', interval, 'minutes');
+  await invoke('configure_sync', { backend: SYNC_BACKEND, intervalMinutes: interval });
+  await updateSyncTooltip();
+}
 
+// ============================================
+// SYNC ENCRYPTION
+// ============================================
+// Day logs are sealed in Rust before upload (see crypto.rs). Passphrases
+// and recovery codes are only passed through to the commands.
+
+const RECOVERY_CODE_COMMANDS = ['enable_encryption', 'new_recovery_code'];
+
+async function refreshEncryptionStatus() {
+  const status = await invoke('encryption_status');
+  const states = new Set([
+    status.enabled ? 'enabled' : 'disabled',
+    status.enabled && (status.unlocked ? 'unlocked' : 'locked')
+  ]);
+
+  document.getElementById('encryptionStatus').textContent = !status.enabled
+    ? 'Off: day logs are uploaded as plain JSON. Set a passphrase to encrypt them on this device before upload.'
+    : !status.unlocked
+      ? 'Locked: sync data is encrypted. Enter your passphrase, or your recovery code and a new passphrase.'
+      : `On: day logs are encrypted before upload (${status.keys} key(s), current one since ${new Date(status.keyCreatedAt).toLocaleDateString()}).`;
+
+  document.querySelectorAll('.encryption-section [data-when]').forEach(el => {
+    el.classList.toggle('hidden', !states.has(el.dataset.when));
+  });
+}
+
+async function encryptionAction(command) {
+  const passphraseInput = document.getElementById('encryptionPassphrase');
+  const codeInput = document.getElementById('recoveryCodeInput');
+  const args = {
+    enable_encryption: { passphrase: passphraseInput.value },
+    unlock_encryption: { passphrase: passphraseInput.value },
+    change_passphrase: { passphrase: passphraseInput.value },
+    recover_encryption: { recoveryCode: codeInput.value, newPassphrase: passphraseInput.value }
+  }[command] || {};
+
+  if (command === 'rotate_encryption_key' &&
+      !confirm('Encrypt every day again with a new key? Other devices pick it up on their next sync.')) {
+    return;
+  }
+
+  const codeEl = document.getElementById('recoveryCode');
+  try {
+    const result = await invoke(command, args);
+    passphraseInput.value = '';
+    codeInput.value = '';
+    codeEl.classList.toggle('hidden', !RECOVERY_CODE_COMMANDS.includes(command));
+    if (RECOVERY_CODE_COMMANDS.includes(command)) {
+      codeEl.textContent = `Recovery code: ${result}. Write it down: it is shown only once, and it is ` +
+        'the only way back in if you forget your passphrase.';
+    }
+    log('Encryption:', command, 'done');
   } catch (error) {
-    console.error('Sync error:', error);
-    syncBtn.classList.remove('syncing');
-    syncBtn.querySelector('span').textContent = 'Error!';
+    codeEl.classList.remove('hidden');
+    codeEl.textContent = `Error: ${error}`;
   }
+  await refreshEncryptionStatus();
+}
 
-  setTimeout(() => {
-    syncBtn.classList.remove('synced');
-    syncBtn.querySelector('span').textContent = 'Sync';
-  }, 3000);
+// ============================================
+// EXPORT
+// ============================================
+
+function renderExportProjects() {
+  const select = document.getElementById('exportProject');
+  const selected = select.value;
+  select.innerHTML = '<option value="">All</option>' + state.projects
+    .map(p => `<option value="${p.id}">${escapeHtml(p.name)}</option>`)
+    .join('');
+  select.value = state.projects.some(p => p.id === selected) ? selected : '';
 }
 
-async function loadFromAWS(date = getToday()) {
-  if (!API_URL) {
-    log('API_URL not configured, skip load from AWS');
-    return null;
+// Rust shows the save dialog and writes the file
+async function exportSessions(format) {
+  const value = (id) => document.getElementById(id).value || null;
+  const query = {
+    from: value('exportFrom'),
+    to: value('exportTo'),
+    projectId: value('exportProject'),
+    type: value('exportType')
+  };
+  const status = document.getElementById('exportStatus');
+  try {
+    const path = await invoke('export_sessions', { query, format });
+    if (path) status.textContent = `Exported to ${path}`;
+  } catch (error) {
+    status.textContent = `Export failed: ${error}`;
   }
+}
+
+// ============================================
+// BILLING
+// ============================================
+
+function renderBillingClients() {
+  const select = document.getElementById('billingClient');
+  const selected = select.value;
+  const clients = [...new Set(state.projects.map(p => p.client).filter(Boolean))].sort();
+  select.innerHTML = '<option value="">All</option>' + clients
+    .map(c => `<option value="${escapeHtml(c)}">${escapeHtml(c)}</option>`)
+    .join('');
+  select.value = clients.includes(selected) ? selected : '';
+}
 
+async function showBilling() {
+  const value = (id) => document.getElementById(id).value || null;
+  const query = {
+    from: value('billingFrom'),
+    to: value('billingTo'),
+    period: value('billingPeriod'),
+    client: value('billingClient')
+  };
+  const container = document.getElementById('billingResults');
   try {
-    log('Loading from AWS, date:', date);
-    
-    const response = await fetch(`${API_URL}/sync?date=${date}`, {
-      method: 'GET',
-      headers: { 'Content-Type': 'application/json' }
-    });
+    renderBilling(await invoke('billing_summary', { query }));
+  } catch (error) {
+    container.textContent = `Billing failed: ${error}`;
+  }
+}
 
-    if (response.status === 404) {
-      log('No data on AWS for date:', date);
-      return null;
-    }
+function renderBilling(summary) {
+  const container = document.getElementById('billingResults');
+  if (summary.lines.length === 0) {
+    container.innerHTML = '<div class="no-sessions">No billable time in this range</div>';
+    return;
+  }
+  const rows = summary.lines.map(line => `
+    <tr>
+      <td>${line.period}</td>
+      <td>${line.client ? escapeHtml(line.client) : '<em>No client</em>'}</td>
+      <td>${line.projects.map(escapeHtml).join(', ')}</td>
+      <td class="num">${formatTimeShort(line.seconds)}</td>
+      <td class="num">${formatMoney(line.amount, line.currency)}</td>
+    </tr>
+  `).join('');
+  const totals = summary.totals.map(total => `
+    <tr class="total">
+      <td colspan="3">Total</td>
+      <td class="num">${formatTimeShort(total.seconds)}</td>
+      <td class="num">${formatMoney(total.amount, total.currency)}</td>
+    </tr>
+  `).join('');
+  const unbilled = summary.unbilledSeconds
+    ? `<div>Not billable: ${formatTimeShort(summary.unbilledSeconds)}</div>`
+    : '';
+
+  container.innerHTML = `
+    <table class="billing-table">
+      <thead><tr><th>Period</th><th>Client</th><th>Projects</th><th class="num">Time</th><th class="num">Amount</th></tr></thead>
+      <tbody>${rows}${totals}</tbody>
+    </table>
+    ${unbilled}
+  `;
+}
 
-    if (!response.ok) {
-      throw new Error(`HTTP ${response.status}`);
-    }
+// Amounts without a currency come from billable projects without a rate
+function formatMoney(amount, currency) {
+  return amount != null && currency ? `${amount.toFixed(2)} ${escapeHtml(currency)}` : 'No rate';
+}
+
+// ============================================
+// TIMESHEET
+// ============================================
+
+// Keeps the checkboxes and rates already entered when projects re-render
+function renderTimesheetProjects() {
+  const container = document.getElementById('timesheetProjects');
+  const previous = {};
+  container.querySelectorAll('[data-project-id]').forEach(row => {
+    previous[row.dataset.projectId] = {
+      included: row.querySelector('input[type="checkbox"]').checked,
+      rate: row.querySelector('input[type="number"]').value
+    };
+  });
+
+  container.innerHTML = state.projects.map(project => {
+    const { included = true, rate = project.hourlyRate ?? '' } = previous[project.id] || {};
+    // Only billable projects are billed, at their own rate unless one is given
+    const rateInput = project.billable
+      ? `<input type="number" min="0" step="0.01" placeholder="Hourly rate" value="${escapeHtml(rate)}">`
+      : '<input type="number" placeholder="Not billable" disabled>';
+    return `
+      <div class="config-row" data-project-id="${project.id}">
+        <label>
+          <input type="checkbox" ${included ? 'checked' : ''}>
+          <span style="color: ${project.color}">${escapeHtml(project.name)}</span>
+        </label>
+        ${rateInput}
+      </div>
+    `;
+  }).join('');
+}
 
-    const data = await response.json();
-    log('Loaded from AWS:', data);
-    return data;
+// Rust builds the timesheet, shows the save dialog and writes the file;
+// it refuses rates in more than one currency
+async function saveTimesheet(format) {
+  const value = (id) => document.getElementById(id).value.trim();
+  const projects = [];
+  const rates = {};
+  document.querySelectorAll('#timesheetProjects [data-project-id]').forEach(row => {
+    if (!row.querySelector('input[type="checkbox"]').checked) return;
+    projects.push(row.dataset.projectId);
+    // Rates left as the project's own stay in the project's currency
+    const project = state.projects.find(p => p.id === row.dataset.projectId);
+    const rate = parseFloat(row.querySelector('input[type="number"]').value);
+    if (rate >= 0 && rate !== project?.hourlyRate) rates[row.dataset.projectId] = rate;
+  });
 
+  const status = document.getElementById('timesheetStatus');
+  if (projects.length === 0) {
+    status.textContent = 'Select at least one project';
+    return;
+  }
+  const options = {
+    from: value('timesheetFrom'),
+    to: value('timesheetTo'),
+    // All projects ticked means all of them, including ones added later
+    projects: projects.length === state.projects.length ? [] : projects,
+    rounding: {
+      minutes: parseInt(value('timesheetRounding')),
+      mode: value('timesheetRoundingMode')
+    },
+    rates,
+    currency: value('timesheetCurrency') || null,
+    title: value('timesheetTitle') || null
+  };
+  try {
+    const path = await invoke('save_timesheet', { options, format });
+    if (path) status.textContent = `Saved to ${path}`;
   } catch (error) {
-    console.error('Load from AWS error:', error);
-    return null;
+    status.textContent = `Timesheet failed: ${error}`;
   }
 }
 
-async function loadHistoryFromAWS(days = 30) {
-  if (!API_URL) return null;
+// ============================================
+// IMPORT
+// ============================================
 
-  const to = getToday();
-  const fromDate = new Date();
-  fromDate.setDate(fromDate.getDate() - days);
-  const from = fromDate.toISOString().split('T')[0];
+function importOptions() {
+  const columns = {};
+  document.querySelectorAll('#importColumns [data-column]').forEach(input => {
+    columns[input.dataset.column] = input.value.trim() || null;
+  });
+  return {
+    dayFirst: document.getElementById('importDateOrder').value === 'dayFirst',
+    columns
+  };
+}
 
+// Rust shows the open dialog and keeps the file until it is committed
+async function previewImport() {
+  const source = document.getElementById('importSource').value;
+  const status = document.getElementById('importStatus');
+  status.textContent = '';
   try {
-    log('Loading history from AWS:', from, 'to', to);
-    
-    const response = await fetch(`${API_URL}/sync?from=${from}&to=${to}`, {
-      method: 'GET',
-      headers: { 'Content-Type': 'application/json' }
-    });
-
-    if (!response.ok) {
-      throw new Error(`HTTP ${response.status}`);
-    }
+    const preview = await invoke('preview_import', { source, options: importOptions() });
+    if (preview) renderImportPreview(preview);
+  } catch (error) {
+    status.textContent = `Import failed: ${error}`;
+  }
+}
 
-    const data = await response.json();
-    log('History loaded:', data.filesLoaded, 'files,', data.sessions.length, 'sessions');
-    return data;
+function renderImportPreview(preview) {
+  const range = preview.from ? `, ${preview.from} to ${preview.to}` : '';
+  const projects = preview.projects.map(p => `
+    <li>${escapeHtml(p.name)}${p.new ? ' (new project)' : ''}: ${p.entries} entries, ${formatTimeShort(p.seconds)}</li>
+  `).join('');
+  const issues = preview.issues.slice(0, 5)
+    .map(i => `<li>Row ${i.row}: ${escapeHtml(i.message)}</li>`)
+    .join('');
+  const more = preview.issues.length > 5 ? `<li>...and ${preview.issues.length - 5} more</li>` : '';
+
+  const container = document.getElementById('importPreview');
+  container.innerHTML = `
+    <div>${preview.entries} entries to import, ${formatTimeShort(preview.seconds)}${range}</div>
+    ${projects ? `<ul>${projects}</ul>` : ''}
+    ${preview.duplicates ? `<div>${preview.duplicates} already imported, skipped</div>` : ''}
+    ${issues ? `<div>${preview.issues.length} rows could not be read:</div><ul>${issues}${more}</ul>` : ''}
+    <div class="config-row">
+      <button class="save-key-btn" id="importCommit" ${preview.entries ? '' : 'disabled'}>Import</button>
+      <button class="save-key-btn" id="importCancel">Cancel</button>
+    </div>
+  `;
+  container.classList.remove('hidden');
+  document.getElementById('importCommit').addEventListener('click', commitImport);
+  document.getElementById('importCancel').addEventListener('click', cancelImport);
+}
 
+async function commitImport() {
+  const status = document.getElementById('importStatus');
+  try {
+    const imported = await invoke('commit_import');
+    status.textContent = `Imported ${imported.entries} entries`;
   } catch (error) {
-    console.error('Load history error:', error);
-    return null;
+    status.textContent = `Import failed: ${error}`;
   }
+  document.getElementById('importPreview').classList.add('hidden');
+  await reloadSyncedData();
 }
 
-async function mergeFromAWS() {
-  const remoteData = await loadFromAWS();
-  
-  if (!remoteData) return;
+async function cancelImport() {
+  await invoke('cancel_import');
+  document.getElementById('importPreview').classList.add('hidden');
+}
 
-  let projectsChanged = false;
-  if (remoteData.projects) {
-    remoteData.projects.forEach(remoteProj => {
-      const localIndex = state.projects.findIndex(p => p.id === remoteProj.id);
-      
-      if (localIndex === -1) {
-        state.projects.push(remoteProj);
-        projectsChanged = true;
-        log('Added project from AWS:', remoteProj.name);
-      } else {
-        const localProj = state.projects[localIndex];
-        if (localProj.name !== remoteProj.name || localProj.color !== remoteProj.color) {
-          state.projects[localIndex] = remoteProj; 
-          projectsChanged = true;
-          log('Updated project from AWS:', remoteProj.name);
-        }
-      }
-    });
-    
-    if (projectsChanged) saveProjects();
+// ============================================
+// DAYS
+// ============================================
+
+async function initDaySettings() {
+  const timezoneSelect = document.getElementById('dayTimezone');
+  const startSelect = document.getElementById('dayStartHour');
+  const zones = Intl.supportedValuesOf ? Intl.supportedValuesOf('timeZone') : [];
+
+  timezoneSelect.innerHTML = `<option value="">System (${escapeHtml(state.calendar.timezone)})</option>` +
+    zones.map(zone => `<option value="${zone}">${zone}</option>`).join('');
+  startSelect.innerHTML = Array.from({ length: 13 }, (_, h) =>
+    `<option value="${h}">${h.toString().padStart(2, '0')}:00${h === 0 ? ' (midnight)' : ''}</option>`
+  ).join('');
+
+  try {
+    const settings = await invoke('get_day_settings');
+    timezoneSelect.value = settings.timezone || '';
+    startSelect.value = settings.dayStartHour;
+  } catch (e) {
+    console.error('Failed to load day settings:', e);
   }
+}
 
-  if (remoteData.sessions) {
-    let added = 0;
-    remoteData.sessions.forEach(remoteSess => {
-      const exists = state.sessions.find(s => s.id === remoteSess.id);
-      if (!exists) {
-        state.sessions.push(remoteSess);
-        added++;
-      }
-    });
-    if (added > 0) {
-      saveSessions();
-      log('Added sessions from AWS:', added);
-    }
+async function saveDaySettings() {
+  const settings = {
+    timezone: document.getElementById('dayTimezone').value || null,
+    dayStartHour: parseInt(document.getElementById('dayStartHour').value, 10)
+  };
+  const status = document.getElementById('dayStatus');
+  try {
+    state.calendar = await invoke('set_day_settings', { settings });
+    status.textContent = `Today is ${getToday()} (${state.calendar.timezone}). Sessions recorded from now on use these settings.`;
+    updateStats();
+  } catch (error) {
+    status.textContent = `Error: ${error}`;
+  }
+}
+
+// ============================================
+// POMODORO CYCLES
+// ============================================
+
+// Rust counts the pomodoros and decides which break comes next (see
+// pomodoro.rs); the selector buttons take their lengths from its settings.
+async function initPomodoro() {
+  try {
+    applyPomodoroStatus(await invoke('pomodoro_status'));
+  } catch (e) {
+    console.error('Failed to load pomodoro settings:', e);
   }
+}
+
+function applyPomodoroStatus(status) {
+  state.pomodoro = status;
+  const { settings, cycle } = status;
+  const lengths = {
+    work: settings.workMinutes,
+    shortBreak: settings.shortBreakMinutes,
+    longBreak: settings.longBreakMinutes
+  };
+  document.querySelectorAll('.pomo-btn').forEach(btn => {
+    const minutes = lengths[btn.dataset.phase];
+    if (minutes) btn.dataset.duration = minutes * 60;
+  });
+  document.querySelector('.pomo-btn[data-phase="work"]').textContent = `${settings.workMinutes} min`;
+  document.querySelector('.pomo-btn[data-phase="shortBreak"]').textContent = `${settings.shortBreakMinutes} min ☕`;
+  document.querySelector('.pomo-btn[data-phase="longBreak"]').textContent = `${settings.longBreakMinutes} min 🌴`;
+  if (state.pomodoroMode) selectPomodoroPhase('work');
+
+  const next = {
+    work: 'pomodoro',
+    shortBreak: 'short break',
+    longBreak: 'long break'
+  }[cycle.next];
+  document.getElementById('pomodoroCycleText').textContent =
+    `🍅 ${cycle.inSet} of ${settings.longBreakEvery} · ${cycle.completed} today · next: ${next} (${status.nextLength / 60} min)`;
+
+  document.getElementById('pomoWork').value = settings.workMinutes;
+  document.getElementById('pomoShortBreak').value = settings.shortBreakMinutes;
+  document.getElementById('pomoLongBreak').value = settings.longBreakMinutes;
+  document.getElementById('pomoEvery').value = settings.longBreakEvery;
+  document.getElementById('pomoAutoBreaks').checked = settings.autoStartBreaks;
+  document.getElementById('pomoAutoWork').checked = settings.autoStartWork;
+}
+
+// Highlights a selector button; work and free set the mode of the next start
+function selectPomodoroPhase(phase) {
+  const btn = document.querySelector(`.pomo-btn[data-phase="${phase}"]`);
+  document.querySelectorAll('.pomo-btn').forEach(b => b.classList.remove('active'));
+  btn.classList.add('active');
+  state.pomodoroDuration = parseInt(btn.dataset.duration, 10);
+  state.pomodoroMode = state.pomodoroDuration > 0;
+}
 
+// Starts the break due, or a pomodoro on the last one's project
+async function startNextPomodoro() {
+  try {
+    applyTimerStatus(await invoke('start_pomodoro', { projectId: null }));
+  } catch (e) {
+    document.getElementById('pomodoroCycleText').textContent = e;
+    return;
+  }
+  if (state.pomodoro.cycle.next === 'work') selectPomodoroPhase('work');
+  updateTimerDisplay();
   renderProjects();
-  renderSessions();
-  updateStats();
 }
 
-function setupAutoSync() {
-  const interval = (typeof CONFIG !== 'undefined' && CONFIG.AUTO_SYNC_INTERVAL) || 0;
-  
-  if (interval > 0) {
-    log('Auto-sync enabled, interval:', interval, 'minutes');
-    
-    setInterval(() => {
-      if (state.sessions.length > 0) {
-        log('Auto-sync triggered');
-        syncToAWS();
-      }
-    }, interval * 60 * 1000);
+async function resetPomodoro() {
+  try {
+    applyPomodoroStatus(await invoke('reset_pomodoro'));
+  } catch (e) {
+    console.error('reset_pomodoro failed:', e);
+  }
+}
+
+async function savePomodoroSettings() {
+  const number = (id) => parseInt(document.getElementById(id).value, 10) || 0;
+  const settings = {
+    workMinutes: number('pomoWork'),
+    shortBreakMinutes: number('pomoShortBreak'),
+    longBreakMinutes: number('pomoLongBreak'),
+    longBreakEvery: number('pomoEvery'),
+    autoStartBreaks: document.getElementById('pomoAutoBreaks').checked,
+    autoStartWork: document.getElementById('pomoAutoWork').checked
+  };
+  const status = document.getElementById('pomodoroStatus');
+  try {
+    applyPomodoroStatus(await invoke('set_pomodoro_settings', { settings }));
+    status.textContent = `A long break comes after every ${settings.longBreakEvery} pomodoros.`;
+  } catch (error) {
+    status.textContent = `Error: ${error}`;
+  }
+}
+
+// ============================================
+// NOTIFICATIONS
+// ============================================
+
+async function initNotifications() {
+  try {
+    applyNotificationSettings(await invoke('get_notification_settings'));
+  } catch (e) {
+    console.error('Failed to load notification settings:', e);
+  }
+}
+
+function applyNotificationSettings(settings) {
+  state.notifications = settings;
+  document.getElementById('notifyEnabled').checked = settings.enabled;
+  document.getElementById('notifySound').checked = settings.sound;
+  document.getElementById('pomodoroSound').textContent = settings.pomodoroSound || 'System sound';
+  document.getElementById('breakSound').textContent = settings.breakSound || 'System sound';
+}
+
+async function saveNotificationSettings(changes = {}) {
+  const settings = {
+    ...state.notifications,
+    enabled: document.getElementById('notifyEnabled').checked,
+    sound: document.getElementById('notifySound').checked,
+    ...changes
+  };
+  const status = document.getElementById('notificationStatus');
+  try {
+    applyNotificationSettings(await invoke('set_notification_settings', { settings }));
+    status.textContent = settings.enabled ? 'Notifications are on.' : 'Notifications are off.';
+  } catch (error) {
+    status.textContent = `Error: ${error}`;
+  }
+}
+
+// key: pomodoroSound or breakSound
+async function chooseSound(key, action) {
+  if (action === 'clear') return saveNotificationSettings({ [key]: null });
+  try {
+    const path = await invoke('choose_sound_file');
+    if (path) await saveNotificationSettings({ [key]: path });
+  } catch (error) {
+    document.getElementById('notificationStatus').textContent = `Error: ${error}`;
+  }
+}
+
+// ============================================
+// GLOBAL SHORTCUTS
+// ============================================
+
+async function initHotkeys() {
+  try {
+    applyHotkeySettings(await invoke('get_hotkey_settings'));
+  } catch (e) {
+    console.error('Failed to load shortcut settings:', e);
   }
 }
 
+function applyHotkeySettings(settings) {
+  state.hotkeys = settings;
+  document.getElementById('hotkeysEnabled').checked = settings.enabled;
+  document.querySelectorAll('[data-hotkey]').forEach(input => {
+    input.value = settings[input.dataset.hotkey] || '';
+  });
+}
+
+async function saveHotkeySettings(changes = {}) {
+  const settings = {
+    ...state.hotkeys,
+    enabled: document.getElementById('hotkeysEnabled').checked,
+    ...changes
+  };
+  const status = document.getElementById('hotkeyStatus');
+  try {
+    applyHotkeySettings(await invoke('set_hotkey_settings', { settings }));
+    status.textContent = settings.enabled ? 'Global shortcuts are on.' : 'Global shortcuts are off.';
+  } catch (error) {
+    // The shortcuts that were kept
+    applyHotkeySettings(state.hotkeys);
+    status.textContent = `Error: ${error}`;
+  }
+}
+
+// Accelerator string for a key press, e.g. "CommandOrControl+Alt+P";
+// null while only modifiers are down or none is
+function hotkeyFromEvent(e) {
+  if (['Control', 'Meta', 'Alt', 'Shift'].includes(e.key)) return null;
+  const mac = navigator.platform.startsWith('Mac');
+  const modifiers = [];
+  if (mac ? e.metaKey : e.ctrlKey) modifiers.push('CommandOrControl');
+  if (mac && e.ctrlKey) modifiers.push('Control');
+  if (!mac && e.metaKey) modifiers.push('Super');
+  if (e.altKey) modifiers.push('Alt');
+  if (e.shiftKey) modifiers.push('Shift');
+  if (!modifiers.length) return null;
+  return [...modifiers, e.code.replace(/^(Key|Digit)/, '')].join('+');
+}
+
+function recordHotkey(e) {
+  e.preventDefault();
+  // Keeps Escape and Ctrl+S from reaching the app shortcuts
+  e.stopPropagation();
+  if (e.key === 'Escape') return e.target.blur();
+  const keys = hotkeyFromEvent(e);
+  if (keys) saveHotkeySettings({ [e.target.dataset.hotkey]: keys });
+}
+
+// ============================================
+// IDLE
+// ============================================
+
+async function initIdleDetection() {
+  const { listen } = window.__TAURI__.event;
+  await listen('idle-returned', (e) => showIdlePrompt(e.payload));
+
+  let status;
+  try {
+    status = await invoke('idle_status');
+  } catch (e) {
+    console.error('Failed to load idle settings:', e);
+    return;
+  }
+  applyIdleSettings(status);
+  if (status.pending) showIdlePrompt(status.pending);
+}
+
+function applyIdleSettings(status) {
+  document.getElementById('idleEnabled').checked = status.settings.enabled;
+  document.getElementById('idleThreshold').value = status.settings.thresholdMinutes;
+  document.getElementById('idleAction').value = status.settings.action;
+  if (!status.available) {
+    document.getElementById('idleStatus').textContent =
+      'Idle time can\'t be detected here. It needs Linux with X11, or a Wayland compositor supporting ' +
+      'ext-idle-notify (e.g. Sway, KDE Plasma, Hyprland).';
+  }
+}
+
+async function saveIdleSettings() {
+  const settings = {
+    enabled: document.getElementById('idleEnabled').checked,
+    thresholdMinutes: parseInt(document.getElementById('idleThreshold').value, 10) || 0,
+    action: document.getElementById('idleAction').value
+  };
+  const status = document.getElementById('idleStatus');
+  try {
+    applyIdleSettings(await invoke('set_idle_settings', { settings }));
+    if (settings.enabled) status.textContent = `You count as away after ${settings.thresholdMinutes} minutes without input.`;
+    else status.textContent = 'Idle detection is off.';
+  } catch (error) {
+    status.textContent = `Error: ${error}`;
+  }
+}
+
+// span: { projectId, from, to, paused }, as sent by the `idle-returned` event
+function showIdlePrompt(span) {
+  const project = state.projects.find(p => p.id === span.projectId);
+  const name = project ? project.name : span.projectId;
+  const minutes = Math.max(1, Math.round((new Date(span.to) - new Date(span.from)) / 60000));
+  const when = `${formatHour(span.from)} - ${formatHour(span.to)}`;
+  const actions = document.getElementById('idleActions');
+
+  if (span.paused) {
+    document.getElementById('idleMessage').textContent = `${name} paused: you were away ${minutes}m (${when})`;
+    actions.innerHTML = `
+      <button class="confirm-btn" data-idle="resume">Resume</button>
+      <button class="cancel-btn" data-idle="dismiss">Dismiss</button>
+    `;
+  } else {
+    const others = state.projects.filter(p => p.id !== span.projectId && !p.archived && !p.trashedAt);
+    document.getElementById('idleMessage').textContent = `Away ${minutes}m during ${name} (${when})`;
+    actions.innerHTML = `
+      <button class="cancel-btn" data-idle="keep">Keep</button>
+      <button class="confirm-btn" data-idle="discard">Discard</button>
+      ${others.length ? `
+        <select id="idleProject">
+          ${others.map(p => `<option value="${p.id}">${escapeHtml(p.name)}</option>`).join('')}
+        </select>
+        <button class="cancel-btn" data-idle="reassign">Reassign</button>
+      ` : ''}
+    `;
+  }
+  actions.querySelectorAll('button[data-idle]').forEach(btn => {
+    btn.addEventListener('click', () => resolveIdle(btn.dataset.idle));
+  });
+  document.getElementById('idleBar').classList.remove('hidden');
+}
+
+// The timer status and any session recorded come back through events
+async function resolveIdle(action) {
+  let resolution;
+  if (action === 'reassign') {
+    resolution = { action, projectId: document.getElementById('idleProject').value };
+  } else {
+    // A paused timer already left the idle time out
+    resolution = { action: action === 'keep' ? 'keep' : 'discard' };
+  }
+  try {
+    await invoke('resolve_idle', { resolution });
+    if (action === 'resume') await resumeTimer();
+  } catch (e) {
+    document.getElementById('idleMessage').textContent = e;
+    return;
+  }
+  document.getElementById('idleBar').classList.add('hidden');
+}
+
 // ============================================
 // AI MULTI-PROVIDER
 // ============================================
 
-function initAIConfig() {
+// API keys live in the OS keychain, handled by Rust (see secrets.rs).
+// Keys saved in localStorage by older versions are moved there once.
+async function migrateApiKeys() {
+  const legacy = localStorage.getItem('pt_apiKey');
+  if (legacy && !localStorage.getItem('pt_ai_key_anthropic')) {
+    localStorage.setItem('pt_ai_key_anthropic', legacy);
+  }
+  localStorage.removeItem('pt_apiKey');
+
+  for (const providerKey of Object.keys(CONFIG.AI_PROVIDERS)) {
+    const key = localStorage.getItem(`pt_ai_key_${providerKey}`);
+    if (!key) continue;
+    try {
+      await invoke('set_api_key', { provider: providerKey, key });
+      localStorage.removeItem(`pt_ai_key_${providerKey}`);
+      log('Moved API key to the keychain for', providerKey);
+    } catch (e) {
+      console.error('Failed to move API key for', providerKey, e);
+    }
+  }
+}
+
+async function initAIConfig() {
   const providerSelect = document.getElementById('aiProvider');
   const modelSelect = document.getElementById('aiModel');
   
@@ -1108,7 +2100,8 @@ function initAIConfig() {
     state.aiModel = savedModel;
   }
   
-  loadApiKeyForProvider(savedProvider);
+  await migrateApiKeys();
+  await loadApiKeyForProvider(savedProvider);
   
   providerSelect.addEventListener('change', (e) => {
     state.aiProvider = e.target.value;
@@ -1148,123 +2141,79 @@ function updateModelSelect() {
   }
 }
 
-function loadApiKeyForProvider(providerKey) {
-  const savedKey = localStorage.getItem(`pt_ai_key_${providerKey}`) || '';
+async function loadApiKeyForProvider(providerKey) {
   const apiKeyInput = document.getElementById('apiKeyInput');
-  
-  if (savedKey) {
-    apiKeyInput.value = '••••••••••••••••';
-    state.aiApiKey = savedKey;
-  } else {
-    apiKeyInput.value = '';
-    state.aiApiKey = '';
+  try {
+    state.hasApiKey = await invoke('has_api_key', { provider: providerKey });
+  } catch (e) {
+    console.error('Failed to read API key:', e);
+    state.hasApiKey = false;
   }
+  apiKeyInput.value = state.hasApiKey ? '••••••••••••••••' : '';
 }
 
-function saveApiKeyHandler() {
+async function saveApiKeyHandler() {
   const apiKeyInput = document.getElementById('apiKeyInput');
   const key = apiKeyInput.value;
   
   if (key && !key.startsWith('••')) {
-    state.aiApiKey = key;
-    localStorage.setItem(`pt_ai_key_${state.aiProvider}`, key);
-    apiKeyInput.value = '••••••••••••••••';
-    log('API key saved for', state.aiProvider);
+    try {
+      await invoke('set_api_key', { provider: state.aiProvider, key });
+      state.hasApiKey = true;
+      apiKeyInput.value = '••••••••••••••••';
+      log('API key saved for', state.aiProvider);
+    } catch (e) {
+      console.error('Failed to save API key:', e);
+    }
   }
 }
 
-async function callAI(prompt) {
-  const provider = CONFIG.AI_PROVIDERS[state.aiProvider];
-  
-  if (!provider.local && !state.aiApiKey) {
-    throw new Error('API Key not configured');
-  }
+// Runs in the Rust insights module, which holds the API key; the reply
+// streams back as ai-text events, tagged with the id we pick here
+async function callAI(prompt, onText) {
+  const { listen } = window.__TAURI__.event;
+  const id = generateId();
   
   log('Calling AI:', state.aiProvider, state.aiModel);
   
-  switch (state.aiProvider) {
-    case 'anthropic':
-      return callAnthropic(prompt);
-    case 'openai':
-    case 'groq':
-      return callOpenAICompatible(prompt);
-    case 'ollama':
-      return callOllama(prompt);
-    default:
-      throw new Error('Provider not supported');
-  }
-}
-
-async function callAnthropic(prompt) {
-  const response = await fetch(CONFIG.AI_PROVIDERS.anthropic.endpoint, {
-    method: 'POST',
-    headers: {
-      'Content-Type': 'application/json',
-      'x-api-key': state.aiApiKey,
-      'anthropic-version': '2023-06-01',
-      'anthropic-dangerous-direct-browser-access': 'true'
-    },
-    body: JSON.stringify({
-      model: state.aiModel,
-      max_tokens: 1024,
-      messages: [{ role: 'user', content: prompt }]
-    })
-  });
-
-  const data = await response.json();
-  
-  if (data.error) {
-    throw new Error(data.error.message);
-  }
-  
-  return data.content?.map(c => c.text || '').join('\n') || '';
-}
-
-async function callOpenAICompatible(prompt) {
-  const provider = CONFIG.AI_PROVIDERS[state.aiProvider];
-  
-  const response = await fetch(provider.endpoint, {
-    method: 'POST',
-    headers: {
-      'Content-Type': 'application/json',
-      'Authorization': `Bearer ${state.aiApiKey}`
-    },
-    body: JSON.stringify({
-      model: state.aiModel,
-      max_tokens: 1024,
-      messages: [{ role: 'user', content: prompt }]
+  let finish;
+  const finished = new Promise(resolve => { finish = resolve; });
+  const unlisten = await Promise.all([
+    listen('ai-text', (e) => {
+      if (e.payload.id === id) onText?.(e.payload.text);
+    }),
+    listen('ai-finished', (e) => {
+      if (e.payload.id === id) finish(e.payload);
     })
-  });
-
-  const data = await response.json();
+  ]);
   
-  if (data.error) {
-    throw new Error(data.error.message || data.error);
+  state.aiRequestId = id;
+  try {
+    await invoke('ai_generate', {
+      id,
+      request: {
+        provider: state.aiProvider,
+        model: state.aiModel,
+        prompt
+      }
+    });
+    const result = await finished;
+    if (result.error) {
+      const error = new Error(result.error.message);
+      error.kind = result.error.kind;
+      throw error;
+    }
+    return result.text;
+  } finally {
+    state.aiRequestId = null;
+    unlisten.forEach(stop => stop());
   }
-  
-  return data.choices?.[0]?.message?.content || '';
 }
 
-async function callOllama(prompt) {
-  const response = await fetch(CONFIG.AI_PROVIDERS.ollama.endpoint, {
-    method: 'POST',
-    headers: {
-      'Content-Type': 'application/json'
-    },
-    body: JSON.stringify({
-      model: state.aiModel,
-      messages: [{ role: 'user', content: prompt }],
-      stream: false
-    })
-  });
-
-  const data = await response.json();
-  
-  if (data.error) {
-    throw new Error(data.error);
+function cancelAI() {
+  if (state.aiRequestId) {
+    invoke('ai_cancel', { id: state.aiRequestId });
   }
-  
-  return data.message?.content || '';
 }
 
 async function generateAISuggestions() {
@@ -1273,7 +2222,7 @@ async function generateAISuggestions() {
 
   const provider = CONFIG.AI_PROVIDERS[state.aiProvider];
   
-  if (!provider.local && !state.aiApiKey) {
+  if (!provider.local && !state.hasApiKey) {
     results.innerHTML = `<div class="ai-content" style="border-color: var(--accent-red)">⚠️ Please enter your API Key for ${provider.name}</div>`;
     return;
   }
@@ -1283,69 +2232,19 @@ async function generateAISuggestions() {
     <svg width="18" height="18" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" style="animation: spin 1s linear infinite;">
       <path d="M21 12a9 9 0 1 1-6.219-8.56"/>
     </svg>
-    Loading history...
-  `;
-
-  const history = await loadHistoryFromAWS(30);
-  
-  btn.innerHTML = `
-    <svg width="18" height="18" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" style="animation: spin 1s linear infinite;">
-      <path d="M21 12a9 9 0 1 1-6.219-8.56"/>
-    </svg>
-    Analyzing...
+    Computing statistics...
   `;
 
-  const allSessions = history?.sessions || state.sessions;
-  const stats = getStats();
-  
-  const workSessions = allSessions.filter(s => s.type !== 'break');
-  const breakSessions = allSessions.filter(s => s.type === 'break');
-  const totalWorkTime = workSessions.reduce((acc, s) => acc + s.duration, 0);
-  const totalBreakTime = breakSessions.reduce((acc, s) => acc + s.duration, 0);
-  
-  const projectStats = {};
-  workSessions.forEach(s => {
-    if (!projectStats[s.projectId]) {
-      projectStats[s.projectId] = { sessions: 0, duration: 0 };
-    }
-    projectStats[s.projectId].sessions++;
-    projectStats[s.projectId].duration += s.duration;
-  });
-  
-  const projectNames = {};
-  state.projects.forEach(p => projectNames[p.id] = p.name);
-  
-  const projectSummary = Object.entries(projectStats)
-    .map(([id, data]) => ({
-      name: projectNames[id] || id,
-      hours: Math.round(data.duration / 3600 * 10) / 10,
-      sessions: data.sessions
-    }))
-    .sort((a, b) => b.hours - a.hours);
-
-  const hourlyPattern = {};
-  workSessions.forEach(s => {
-    const hour = new Date(s.startTime).getHours();
-    hourlyPattern[hour] = (hourlyPattern[hour] || 0) + s.duration;
-  });
-  
-  const bestHours = Object.entries(hourlyPattern)
-    .sort((a, b) => b[1] - a[1])
-    .slice(0, 3)
-    .map(([h]) => `${h}:00`);
-
-  const dayPattern = { 0: 0, 1: 0, 2: 0, 3: 0, 4: 0, 5: 0, 6: 0 };
-  const dayNames = ['Sunday', 'Monday', 'Tuesday', 'Wednesday', 'Thursday', 'Friday', 'Saturday'];
-  workSessions.forEach(s => {
-    const day = new Date(s.startTime).getDay();
-    dayPattern[day] += s.duration;
-  });
+  let stats;
+  try {
+    stats = await invoke('get_analytics', { days: 30 });
+  } catch (error) {
+    console.error('Analytics error:', error);
+    results.innerHTML = `<div class="ai-content" style="border-color: var(--accent-red)">❌ Error: ${escapeHtml(String(error))}</div>`;
+    resetAIButton(btn);
+    return;
+  }
   
-  const bestDays = Object.entries(dayPattern)
-    .sort((a, b) => b[1] - a[1])
-    .slice(0, 3)
-    .map(([d]) => dayNames[d]);
-
   const prompt = `
 Act as an expert Productivity Coach and Behavioral Analyst specializing in "Deep Work" and time management.
 Your goal is to analyze the user's raw data and transform it into strategic insights, not just descriptive text.
@@ -1353,21 +2252,22 @@ Your goal is to analyze the user's raw data and transform it into strategic insi
 USER DATA:
 ----------------
 [GENERAL METRICS - 30 DAYS]
-- Total Work Time: ${formatTime(totalWorkTime)}
-- Focus Sessions: ${workSessions.length}
-- Total Break Time: ${formatTime(totalBreakTime)} (${breakSessions.length} recorded breaks)
-- Focus/Rest Ratio: ${totalBreakTime > 0 ? (totalWorkTime / totalBreakTime).toFixed(1) : '∞'}:1 (Ideal target between 3:1 and 6:1)
-- Consistency: Data available for ${history?.filesLoaded || '1'} days.
+- Total Work Time: ${formatTime(stats.workSeconds)}
+- Focus Sessions: ${stats.workSessions} (average ${formatTime(stats.averageSession)})
+- Total Break Time: ${formatTime(stats.breakSeconds)} (${stats.breakSessions} recorded breaks)
+- Focus/Rest Ratio: ${stats.focusRatio !== null ? stats.focusRatio.toFixed(1) : '∞'}:1 (Ideal target between 3:1 and 6:1)
+- Consistency: Data available for ${stats.activeDays || 1} days. Current streak ${stats.currentStreak} days, longest ${stats.longestStreak}.
+- Fragmentation: ${stats.shortSessions} sessions under 15 minutes (${Math.round(stats.fragmentation * 100)}% of sessions), ${stats.contextSwitches} project switches.
 
 [PROJECT ALLOCATION]
-${projectSummary.map(p => `- ${p.name}: ${p.hours}h (${p.sessions} sess.) [${Math.round((p.hours / (totalWorkTime/60 || 1)) * 100)}% of total]`).join('\n')}
+${stats.projects.map(p => `- ${p.name}: ${(p.seconds / 3600).toFixed(1)}h (${p.sessions} sess.) [${Math.round(p.share * 100)}% of total]`).join('\n')}
 
 [CHRONOTYPE & PATTERNS]
-- Peak Hours (Flow State): ${bestHours.length > 0 ? bestHours.join(', ') : 'Insufficient data'}
-- Best Days: ${bestDays.length > 0 ? bestDays.join(', ') : 'Insufficient data'}
+- Peak Hours (Flow State): ${stats.bestHours.length > 0 ? stats.bestHours.map(h => `${h}:00`).join(', ') : 'Insufficient data'}
+- Best Days: ${stats.bestDays.length > 0 ? stats.bestDays.join(', ') : 'Insufficient data'}
 
 [TODAY]
-- Work done: ${formatTime(stats.todayTotal)} across ${stats.todaySessions.length} sessions.
+- Work done: ${formatTime(stats.lastDay.seconds)} across ${stats.lastDay.sessions} sessions.
 ----------------
 
 ANALYSIS INSTRUCTIONS:
@@ -1393,15 +2293,35 @@ REQUIRED OUTPUT (Use Markdown formatting):
 Tone of voice: Professional, direct, motivating but data-driven. Do not be verbose.
 `;
 
+  btn.disabled = false;
+  btn.innerHTML = `
+    <svg width="18" height="18" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" style="animation: spin 1s linear infinite;">
+      <path d="M21 12a9 9 0 1 1-6.219-8.56"/>
+    </svg>
+    Analyzing... (click to stop)
+  `;
+
+  results.innerHTML = '<div class="ai-content"></div>';
+  const content = results.querySelector('.ai-content');
+
   try {
-    const text = await callAI(prompt);
-    results.innerHTML = `<div class="ai-content">${escapeHtml(text)}</div>`;
+    await callAI(prompt, (text) => {
+      content.textContent += text;
+    });
 
   } catch (error) {
-    console.error('AI Error:', error);
-    results.innerHTML = `<div class="ai-content" style="border-color: var(--accent-red)">❌ Error: ${escapeHtml(error.message)}</div>`;
+    if (error.kind === 'cancelled') {
+      content.textContent += '\n\n(stopped)';
+    } else {
+      console.error('AI Error:', error);
+      results.innerHTML = `<div class="ai-content" style="border-color: var(--accent-red)">❌ Error: ${escapeHtml(error.message || String(error))}</div>`;
+    }
   }
 
+  resetAIButton(btn);
+}
+
+function resetAIButton(btn) {
   btn.disabled = false;
   btn.innerHTML = `
     <svg width="18" height="18" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
@@ -1449,6 +2369,17 @@ function setupEventHandlers() {
     }
   });
 
+  document.getElementById('dismissTimerError').addEventListener('click', hideTimerError);
+  document.getElementById('undoTrash').addEventListener('click', (e) => {
+    restoreProject(e.currentTarget.dataset.projectId);
+  });
+  document.getElementById('trashToggle').addEventListener('click', () => {
+    document.getElementById('trashList').classList.toggle('hidden');
+  });
+
+  document.getElementById('confirmProjectDetails').addEventListener('click', saveProjectDetails);
+  document.getElementById('cancelProjectDetails').addEventListener('click', closeProjectDetails);
+
   document.getElementById('cancelAddProject').addEventListener('click', () => {
     document.getElementById('newProjectName').value = '';
     document.getElementById('addProjectForm').classList.add('hidden');
@@ -1463,8 +2394,23 @@ function setupEventHandlers() {
   document.getElementById('syncBtn').addEventListener('click', syncToAWS);
 
   document.getElementById('sessionsToggle').addEventListener('click', () => {
-    document.getElementById('sessionsList').classList.toggle('hidden');
+    document.getElementById('sessionsBody').classList.toggle('hidden');
+  });
+  document.getElementById('sessionsDate').addEventListener('change', (e) => showSessionsOf(e.target.value));
+  document.getElementById('addSessionBtn').addEventListener('click', () => openSessionForm(null));
+
+  // Searches once typing pauses
+  let searchTimeout = null;
+  document.getElementById('sessionSearch').addEventListener('input', () => {
+    clearTimeout(searchTimeout);
+    searchTimeout = setTimeout(searchSessions, 300);
   });
+  document.getElementById('sessionType').addEventListener('change', updateSessionProjectField);
+  document.getElementById('confirmSession').addEventListener('click', saveSession);
+  document.getElementById('splitSession').addEventListener('click', splitEditedSession);
+  document.getElementById('mergeSession').addEventListener('click', mergeWithPrevious);
+  document.getElementById('deleteSession').addEventListener('click', deleteEditedSession);
+  document.getElementById('cancelSession').addEventListener('click', closeSessionForm);
 
   document.getElementById('saveApiKey').addEventListener('click', saveApiKeyHandler);
 
@@ -1472,28 +2418,71 @@ function setupEventHandlers() {
     if (e.key === 'Enter') saveApiKeyHandler();
   });
 
-  document.getElementById('generateAI').addEventListener('click', generateAISuggestions);
+  document.getElementById('generateAI').addEventListener('click', () => {
+    state.aiRequestId ? cancelAI() : generateAISuggestions();
+  });
+
+  document.querySelectorAll('[data-export]').forEach(btn => {
+    btn.addEventListener('click', () => exportSessions(btn.dataset.export));
+  });
+  document.getElementById('exportFrom').value = getDaysAgo(30);
+  document.getElementById('exportTo').value = getToday();
+  document.getElementById('sessionsDate').value = getToday();
+
+  document.getElementById('billingShow').addEventListener('click', showBilling);
+  document.getElementById('billingFrom').value = getToday().slice(0, 5) + '01-01';
+  document.getElementById('billingTo').value = getToday();
+
+  document.querySelectorAll('[data-timesheet]').forEach(btn => {
+    btn.addEventListener('click', () => saveTimesheet(btn.dataset.timesheet));
+  });
+  document.getElementById('timesheetFrom').value = getToday().slice(0, 8) + '01';
+  document.getElementById('timesheetTo').value = getToday();
+
+  document.getElementById('importSource').addEventListener('change', (e) => {
+    document.getElementById('importColumns').classList.toggle('hidden', e.target.value !== 'csv');
+  });
+  document.getElementById('importChoose').addEventListener('click', previewImport);
+
+  document.getElementById('dayTimezone').addEventListener('change', saveDaySettings);
+  document.getElementById('dayStartHour').addEventListener('change', saveDaySettings);
+  ['idleEnabled', 'idleThreshold', 'idleAction'].forEach(id => {
+    document.getElementById(id).addEventListener('change', saveIdleSettings);
+  });
+
+  document.querySelectorAll('.encryption-section [data-action]').forEach(btn => {
+    btn.addEventListener('click', () => encryptionAction(btn.dataset.action));
+  });
 
   document.querySelectorAll('.pomo-btn').forEach(btn => {
     btn.addEventListener('click', () => {
-      const duration = parseInt(btn.dataset.duration);
-      const type = btn.dataset.type;
-      
-      document.querySelectorAll('.pomo-btn').forEach(b => b.classList.remove('active'));
-      btn.classList.add('active');
-      
-      state.pomodoroDuration = duration;
-      state.pomodoroMode = duration > 0;
-      
-      if (type === 'break') {
-        startBreak(duration);
+      const phase = btn.dataset.phase;
+      if (phase === 'work' || phase === 'free') {
+        selectPomodoroPhase(phase);
+      } else {
+        document.querySelectorAll('.pomo-btn').forEach(b => b.classList.remove('active'));
+        btn.classList.add('active');
+        startBreak(parseInt(btn.dataset.duration, 10));
       }
     });
   });
-
-  if ('Notification' in window && Notification.permission === 'default') {
-    Notification.requestPermission();
-  }
+  document.getElementById('notifyEnabled').addEventListener('change', () => saveNotificationSettings());
+  document.getElementById('notifySound').addEventListener('change', () => saveNotificationSettings());
+  document.querySelectorAll('[data-sound]').forEach(btn => {
+    btn.addEventListener('click', () => chooseSound(btn.dataset.sound, btn.dataset.soundAction));
+  });
+  document.getElementById('hotkeysEnabled').addEventListener('change', () => saveHotkeySettings());
+  document.querySelectorAll('[data-hotkey]').forEach(input => {
+    input.addEventListener('keydown', recordHotkey);
+  });
+  document.querySelectorAll('[data-hotkey-clear]').forEach(btn => {
+    btn.addEventListener('click', () => saveHotkeySettings({ [btn.dataset.hotkeyClear]: null }));
+  });
+  document.getElementById('pomodoroNext').addEventListener('click', startNextPomodoro);
+  document.getElementById('pomodoroReset').addEventListener('click', resetPomodoro);
+  ['pomoWork', 'pomoShortBreak', 'pomoLongBreak', 'pomoEvery', 'pomoAutoBreaks', 'pomoAutoWork'].forEach(id => {
+    document.getElementById(id).addEventListener('change', savePomodoroSettings);
+  });
 
   document.addEventListener('keydown', (e) => {
     if ((e.ctrlKey || e.metaKey) && e.key === 's') {
@@ -1512,17 +2501,23 @@ function setupEventHandlers() {
 
 async function init() {
   await loadData();
-  await mergeFromAWS();
 
-  checkRecoveryState();
+  await restoreTimer();
+  await setupTimerEvents();
   
   renderProjects();
   renderSessions();
   updateStats();
   initCharts();
   setupEventHandlers();
-  initAIConfig();
-  setupAutoSync();
+  await initAIConfig();
+  await initDaySettings();
+  await initPomodoro();
+  await initNotifications();
+  await initHotkeys();
+  await initIdleDetection();
+  await setupAutoSync();
+  await refreshEncryptionStatus();
   
   console.log('🚀 Productivity Tracker initialized');
 }
@@ -1537,4 +2532,5 @@ window.startTimer = startTimer;
 window.stopTimer = stopTimer;
 window.pauseTimer = pauseTimer;
 window.resumeTimer = resumeTimer;
-window.deleteProject = deleteProject;
\ No newline at end of file
+window.deleteProject = deleteProject;
+window.archiveProject = archiveProject;
\ No newline at end of file
@@ -11,6 +11,14 @@ const CONFIG = {
   // AWS API Gateway endpoint (get this after deploying your Lambda)
   // Example: 'https://abc123xyz.execute-api.eu-central-1.amazonaws.com/prod'
   API_URL: '',
+
+  // Sync without the Lambda (leave null to use API_URL). One of:
+  //   { kind: 's3', endpoint: 'http://localhost:9000', bucket: 'tracker',
+  //     region: 'us-east-1', accessKey: '...', secretKey: '...' }   // AWS S3, MinIO...
+  //   { kind: 'webdav', url: 'https://cloud.example.com/remote.php/dav/files/me/tracker',
+  //     username: 'me', password: '...' }
+  //   { kind: 'folder', path: '/home/me/Sync/productivity-tracker' } // Syncthing, Dropbox...
+  SYNC_BACKEND: null,
   
   // Auto sync interval (minutes, 0 = disabled)
   AUTO_SYNC_INTERVAL: 5,
@@ -28,11 +36,11 @@ const CONFIG = {
   DEBUG: false,
 
   // AI Providers Configuration
-  // API keys are stored locally in the browser, not here
+  // API keys are stored in the OS keychain, not here, and are sent only to
+  // the provider's own endpoint, which is set in the app
   AI_PROVIDERS: {
     anthropic: {
       name: 'Anthropic (Claude)',
-      endpoint: 'https://api.anthropic.com/v1/messages',
       models: ['claude-sonnet-4-5-20250929', 'claude-haiku-4-5-20251001'],
       defaultModel: 'claude-haiku-4-5-20251001',
       keyPlaceholder: 'sk-ant-...',
@@ -40,7 +48,6 @@ const CONFIG = {
     },
     openai: {
       name: 'OpenAI (GPT)',
-      endpoint: 'https://api.openai.com/v1/chat/completions',
       models: ['gpt-5.2-2025-12-11', 'gpt-5-mini-2025-08-07'],
       defaultModel: 'gpt-5-mini-2025-08-07',
       keyPlaceholder: 'sk-...',
@@ -48,7 +55,6 @@ const CONFIG = {
     },
     groq: {
       name: 'Groq (Fast & Free tier)',
-      endpoint: 'https://api.groq.com/openai/v1/chat/completions',
       models: ['llama-3.3-70b-versatile', 'llama-3.1-8b-instant', 'qwen/qwen3-32b'],
       defaultModel: 'llama-3.1-8b-instant',
       keyPlaceholder: 'gsk_...',
@@ -56,7 +62,6 @@ const CONFIG = {
     },
     ollama: {
       name: 'Ollama (Local)',
-      endpoint: 'http://localhost:11434/api/chat',
       models: ['llama3.2', 'mistral', 'codellama', 'phi3'],
       defaultModel: 'llama3.2',
       keyPlaceholder: '(not required)',